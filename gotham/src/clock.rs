@@ -0,0 +1,159 @@
+//! Defines the `Clock` through which Gotham reads the current time, so that time-dependent
+//! behavior can be tested deterministically.
+
+use std::fmt;
+use std::panic::RefUnwindSafe;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+
+use state::{FromState, State, StateData};
+
+/// A source of the current time.
+///
+/// Gotham reads the time through the `Clock` configured via `ServerConfig::with_clock` when
+/// limiting the rate at which connections are accepted and when writing a `Retry-After` date,
+/// and `MemoryBackend::with_clock` accepts one for expiring sessions. Applications can read the
+/// configured `Clock` via `clock`. `SystemClock` is used by default, and `MockClock` allows tests
+/// to control the passing of time.
+///
+/// The `Date` header is written by Hyper, and always uses the system time.
+pub trait Clock: fmt::Debug + Send + Sync + RefUnwindSafe {
+    /// The current time, as a monotonic `Instant`, for measuring durations.
+    fn instant(&self) -> Instant;
+
+    /// The current time, as a `SystemTime`, for communicating dates.
+    fn system_time(&self) -> SystemTime;
+}
+
+/// A `Clock` which reads the time from the operating system. This is the default.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn instant(&self) -> Instant {
+        Instant::now()
+    }
+
+    fn system_time(&self) -> SystemTime {
+        SystemTime::now()
+    }
+}
+
+/// A `Clock` which stands still until it is advanced, for use in tests.
+///
+/// It starts at the time it was created, and clones share the same time, so a clone can be
+/// configured while another is kept by the test to advance.
+///
+/// # Examples
+///
+/// ```rust
+/// # extern crate gotham;
+/// #
+/// # use std::time::Duration;
+/// # use gotham::clock::{Clock, MockClock};
+/// #
+/// # fn main() {
+/// let clock = MockClock::new();
+/// let start = clock.instant();
+///
+/// clock.advance(Duration::from_secs(60));
+/// assert_eq!(clock.instant() - start, Duration::from_secs(60));
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct MockClock {
+    instant: Instant,
+    system_time: SystemTime,
+    elapsed: Arc<Mutex<Duration>>,
+}
+
+impl MockClock {
+    /// Creates a `MockClock` which reads the time at which it was created.
+    pub fn new() -> MockClock {
+        MockClock {
+            instant: Instant::now(),
+            system_time: SystemTime::now(),
+            elapsed: Arc::new(Mutex::new(Duration::from_secs(0))),
+        }
+    }
+
+    /// Moves the time read from this `MockClock`, and every clone of it, forward by `duration`.
+    pub fn advance(&self, duration: Duration) {
+        *self.elapsed.lock().expect("mock clock mutex poisoned") += duration;
+    }
+
+    fn elapsed(&self) -> Duration {
+        *self.elapsed.lock().expect("mock clock mutex poisoned")
+    }
+}
+
+impl Default for MockClock {
+    fn default() -> MockClock {
+        MockClock::new()
+    }
+}
+
+impl Clock for MockClock {
+    fn instant(&self) -> Instant {
+        self.instant + self.elapsed()
+    }
+
+    fn system_time(&self) -> SystemTime {
+        self.system_time + self.elapsed()
+    }
+}
+
+struct ConfiguredClock {
+    clock: Arc<dyn Clock>,
+}
+
+impl StateData for ConfiguredClock {}
+
+pub(crate) fn put_clock(state: &mut State, clock: Arc<dyn Clock>) {
+    state.put(ConfiguredClock { clock })
+}
+
+/// Returns the `Clock` configured via `ServerConfig::with_clock`, or a `SystemClock` when the
+/// `State` was not created by Gotham for a `Request`.
+///
+/// # Examples
+///
+/// ```rust
+/// # extern crate gotham;
+/// # extern crate hyper;
+/// #
+/// # use std::time::{Duration, UNIX_EPOCH};
+/// # use hyper::{Response, StatusCode};
+/// # use gotham::clock::{clock, MockClock};
+/// # use gotham::config::ServerConfig;
+/// # use gotham::state::State;
+/// # use gotham::test::TestServer;
+/// #
+/// fn my_handler(state: State) -> (State, Response) {
+///     let now = clock(&state).system_time();
+///     let secs = now.duration_since(UNIX_EPOCH).unwrap().as_secs();
+///     let response = Response::new().with_status(StatusCode::Ok).with_body(secs.to_string());
+///     (state, response)
+/// }
+/// #
+/// # fn main() {
+/// #   let mock = MockClock::new();
+/// #   let config = ServerConfig::default().with_clock(mock.clone());
+/// #   let test_server = TestServer::with_config(|| Ok(my_handler), config).unwrap();
+/// #
+/// #   let read = || {
+/// #       let response = test_server.client().get("http://localhost/").perform().unwrap();
+/// #       response.read_utf8_body().unwrap().parse::<u64>().unwrap()
+/// #   };
+/// #
+/// #   let before = read();
+/// #   mock.advance(Duration::from_secs(3600));
+/// #   assert_eq!(read(), before + 3600);
+/// # }
+/// ```
+pub fn clock(state: &State) -> Arc<dyn Clock> {
+    match ConfiguredClock::try_borrow_from(state) {
+        Some(configured) => configured.clock.clone(),
+        None => Arc::new(SystemClock),
+    }
+}
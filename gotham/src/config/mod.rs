@@ -0,0 +1,1181 @@
+//! Defines configuration for the server which runs a Gotham application.
+
+use std::fmt;
+use std::io;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+use futures::{Future, IntoFuture};
+use hyper::{HttpVersion, Response};
+use hyper::server::Http;
+use num_cpus;
+use tokio_core::reactor::{Core, Handle};
+
+use clock::{Clock, SystemClock};
+use handler::ErrorFormat;
+use http::request::deserialize_error::DeserializeErrorMapper;
+use http::request::extractor_failure::ExtractorFailureHook;
+use http::request::forwarded::ForwardedPrecedence;
+use http::request::json::UnknownFields;
+use http::response::redirect::LocationStyle;
+use http::response::retry_after::RetryAfterFormat;
+use http::response::stream::StreamingLimit;
+use router::strategy::{AllowScope, OptionsAsterisk};
+use shutdown::{DrainBehavior, Shutdown};
+use state::{State, StateData, TransferHook};
+
+/// Configuration for the server which runs a Gotham application, and for the request handling
+/// performed by Gotham before control is passed to the application.
+///
+/// # Examples
+///
+/// ```rust
+/// # extern crate gotham;
+/// #
+/// # use gotham::config::ServerConfig;
+/// #
+/// # fn main() {
+/// let config = ServerConfig::default()
+///     .with_num_threads(4)
+///     .with_max_headers(32);
+///
+/// assert_eq!(config.num_threads(), 4);
+/// assert_eq!(config.max_headers(), Some(32));
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct ServerConfig {
+    num_threads: usize,
+    max_headers: Option<usize>,
+    max_decoded_path_length: Option<usize>,
+    encoded_slashes: EncodedSlashes,
+    dot_segments: DotSegments,
+    reject_unknown_expectations: bool,
+    host_requirement: HostRequirement,
+    trace_behavior: TraceBehavior,
+    response_validation: ResponseValidation,
+    reuse_port: bool,
+    bind_interface: Option<String>,
+    proxy_protocol: bool,
+    shutdown: Shutdown,
+    shutdown_timeout: Duration,
+    drain_behavior: DrainBehavior,
+    retry_after: Option<Duration>,
+    retry_after_format: RetryAfterFormat,
+    error_format: ErrorFormat,
+    read_timeout: Option<Duration>,
+    trusted_proxy_headers: Option<ForwardedPrecedence>,
+    server_timing: bool,
+    max_connections_per_worker: Option<usize>,
+    max_accept_rate: Option<u32>,
+    accept_backoff: Duration,
+    connection_metrics: ConnectionMetrics,
+    unknown_fields: UnknownFields,
+    deserialize_error_mapper: Option<DeserializeErrorMapper>,
+    extractor_failure_hook: Option<ExtractorFailureHook>,
+    transfer_accounting: bool,
+    transfer_hook: Option<TransferHook>,
+    location_style: LocationStyle,
+    allow_scope: AllowScope,
+    options_asterisk: OptionsAsterisk,
+    pre_routing_hook: Option<PreRoutingHook>,
+    worker_init: Option<WorkerInit>,
+    clock: Arc<dyn Clock>,
+    streaming_limit: Option<StreamingLimit>,
+    app_data: Vec<AppData>,
+}
+
+impl Default for ServerConfig {
+    fn default() -> ServerConfig {
+        ServerConfig {
+            num_threads: num_cpus::get(),
+            max_headers: None,
+            max_decoded_path_length: None,
+            encoded_slashes: EncodedSlashes::default(),
+            dot_segments: DotSegments::default(),
+            reject_unknown_expectations: true,
+            host_requirement: HostRequirement::default(),
+            trace_behavior: TraceBehavior::default(),
+            response_validation: ResponseValidation::default(),
+            reuse_port: false,
+            bind_interface: None,
+            proxy_protocol: false,
+            shutdown: Shutdown::new(),
+            shutdown_timeout: Duration::from_secs(30),
+            drain_behavior: DrainBehavior::default(),
+            retry_after: None,
+            retry_after_format: RetryAfterFormat::default(),
+            error_format: ErrorFormat::default(),
+            read_timeout: None,
+            trusted_proxy_headers: None,
+            server_timing: false,
+            max_connections_per_worker: None,
+            max_accept_rate: None,
+            accept_backoff: Duration::from_millis(100),
+            connection_metrics: ConnectionMetrics::new(),
+            unknown_fields: UnknownFields::default(),
+            deserialize_error_mapper: None,
+            extractor_failure_hook: None,
+            transfer_accounting: false,
+            transfer_hook: None,
+            location_style: LocationStyle::default(),
+            allow_scope: AllowScope::default(),
+            options_asterisk: OptionsAsterisk::default(),
+            pre_routing_hook: None,
+            worker_init: None,
+            clock: Arc::new(SystemClock),
+            streaming_limit: None,
+            app_data: Vec::new(),
+        }
+    }
+}
+
+impl ServerConfig {
+    /// Sets the number of threads used to accept connections and handle requests. Defaults to
+    /// the number of CPUs.
+    ///
+    /// ## Windows
+    ///
+    /// An additional thread is used on Windows to accept connections.
+    pub fn with_num_threads(self, num_threads: usize) -> ServerConfig {
+        ServerConfig {
+            num_threads,
+            ..self
+        }
+    }
+
+    /// Sets the maximum number of header lines accepted in a single request. Requests which
+    /// exceed this are answered with `431 Request Header Fields Too Large` without invoking the
+    /// application.
+    ///
+    /// This is independent of the total size of the request headers. Hyper rejects any request
+    /// with more than 100 headers, regardless of this setting.
+    pub fn with_max_headers(self, max_headers: usize) -> ServerConfig {
+        ServerConfig {
+            max_headers: Some(max_headers),
+            ..self
+        }
+    }
+
+    /// Sets the maximum length, in bytes, of the percent-decoded request path which is matched
+    /// by the router. Requests which exceed this are answered with `414 URI Too Long` without
+    /// invoking the application.
+    ///
+    /// This bounds the work and allocation performed by routing and path extraction, regardless
+    /// of how the path is encoded. No limit is applied by default.
+    pub fn with_max_decoded_path_length(self, max_decoded_path_length: usize) -> ServerConfig {
+        ServerConfig {
+            max_decoded_path_length: Some(max_decoded_path_length),
+            ..self
+        }
+    }
+
+    /// Sets how percent-encoded slashes in the request path are treated. Defaults to
+    /// `EncodedSlashes::Decode`.
+    pub fn with_encoded_slashes(self, encoded_slashes: EncodedSlashes) -> ServerConfig {
+        ServerConfig {
+            encoded_slashes,
+            ..self
+        }
+    }
+
+    /// Sets how `.` and `..` segments in the request path are treated, once percent-decoded.
+    /// Defaults to `DotSegments::Normalize`.
+    pub fn with_dot_segments(self, dot_segments: DotSegments) -> ServerConfig {
+        ServerConfig {
+            dot_segments,
+            ..self
+        }
+    }
+
+    /// Sets whether requests with an `Expect` header holding any expectation other than
+    /// `100-continue` are answered with `417 Expectation Failed` without invoking the application,
+    /// as required by RFC 7231. When disabled, such expectations are ignored and the request is
+    /// handled as usual. Defaults to `true`.
+    pub fn with_reject_unknown_expectations(
+        self,
+        reject_unknown_expectations: bool,
+    ) -> ServerConfig {
+        ServerConfig {
+            reject_unknown_expectations,
+            ..self
+        }
+    }
+
+    /// Sets which requests must carry a `Host` header. Requests which lack one are answered with
+    /// `400 Bad Request` without invoking the application. Defaults to
+    /// `HostRequirement::Http11`, as required by RFC 7230.
+    pub fn with_host_requirement(self, host_requirement: HostRequirement) -> ServerConfig {
+        ServerConfig {
+            host_requirement,
+            ..self
+        }
+    }
+
+    /// Sets how `TRACE` requests are answered. Defaults to `TraceBehavior::Reject`.
+    pub fn with_trace_behavior(self, trace_behavior: TraceBehavior) -> ServerConfig {
+        ServerConfig {
+            trace_behavior,
+            ..self
+        }
+    }
+
+    /// Sets how responses from the application are checked for contradictions, such as a body
+    /// which differs in length from its `Content-Length` header. Defaults to
+    /// `ResponseValidation::Log` in debug builds, and `ResponseValidation::Off` in release builds.
+    pub fn with_response_validation(self, response_validation: ResponseValidation) -> ServerConfig {
+        ServerConfig {
+            response_validation,
+            ..self
+        }
+    }
+
+    /// Sets `SO_REUSEPORT` on the listening socket, allowing several processes to accept
+    /// connections on the same address. Used with `with_shutdown` to hand off from an old process
+    /// to a new one without dropping connections. Defaults to `false`.
+    ///
+    /// ## Windows
+    ///
+    /// `SO_REUSEPORT` is not available on Windows, and this setting is ignored.
+    pub fn with_reuse_port(self, reuse_port: bool) -> ServerConfig {
+        ServerConfig { reuse_port, ..self }
+    }
+
+    /// Binds the listening socket to the address of the named network interface, e.g. `eth0`,
+    /// rather than the IP address given to `gotham::start_with_config`. The port of the given
+    /// address is kept, and its address family determines whether the IPv4 or IPv6 address of
+    /// the interface is used. The interface is resolved when the server starts, which panics if
+    /// the interface does not exist or has no address of that family.
+    ///
+    /// ## Windows
+    ///
+    /// Interfaces cannot be resolved by name on Windows, and this setting is ignored.
+    pub fn with_bind_interface<S>(self, bind_interface: S) -> ServerConfig
+    where
+        S: Into<String>,
+    {
+        ServerConfig {
+            bind_interface: Some(bind_interface.into()),
+            ..self
+        }
+    }
+
+    /// Expects every connection to begin with a PROXY protocol header, in either the text (v1) or
+    /// binary (v2) form, as sent by TCP load balancers such as HAProxy and AWS Network Load
+    /// Balancers. The client address reported by `gotham::state::client_addr` is then the one
+    /// carried by the header, rather than the address of the load balancer. Defaults to `false`.
+    ///
+    /// Connections which do not begin with a valid header are closed, so this must only be
+    /// enabled when every connection arrives through such a load balancer.
+    pub fn with_proxy_protocol(self, proxy_protocol: bool) -> ServerConfig {
+        ServerConfig {
+            proxy_protocol,
+            ..self
+        }
+    }
+
+    /// Sets the `Shutdown` handle used to tell the server to drain. Once draining has completed,
+    /// `gotham::start_with_config` returns.
+    pub fn with_shutdown(self, shutdown: Shutdown) -> ServerConfig {
+        ServerConfig { shutdown, ..self }
+    }
+
+    /// Sets how long a draining server waits for in-flight connections to complete before
+    /// closing them. Defaults to 30 seconds.
+    pub fn with_shutdown_timeout(self, shutdown_timeout: Duration) -> ServerConfig {
+        ServerConfig {
+            shutdown_timeout,
+            ..self
+        }
+    }
+
+    /// Sets what the server does with new connections while draining. Defaults to
+    /// `DrainBehavior::CloseListener`.
+    pub fn with_drain_behavior(self, drain_behavior: DrainBehavior) -> ServerConfig {
+        ServerConfig {
+            drain_behavior,
+            ..self
+        }
+    }
+
+    /// Sets the delay advertised via the `Retry-After` header of the `503 Service Unavailable`
+    /// responses generated by Gotham, such as those sent while draining with
+    /// `DrainBehavior::RespondUnavailable`. No `Retry-After` header is sent by default.
+    pub fn with_retry_after(self, retry_after: Duration) -> ServerConfig {
+        ServerConfig {
+            retry_after: Some(retry_after),
+            ..self
+        }
+    }
+
+    /// Sets how the `Retry-After` header is written, both by Gotham and by `set_retry_after`.
+    /// Defaults to `RetryAfterFormat::DeltaSeconds`.
+    pub fn with_retry_after_format(self, retry_after_format: RetryAfterFormat) -> ServerConfig {
+        ServerConfig {
+            retry_after_format,
+            ..self
+        }
+    }
+
+    /// Sets the format of the body of responses generated from a `HandlerError`. Defaults to
+    /// `ErrorFormat::Empty`.
+    pub fn with_error_format(self, error_format: ErrorFormat) -> ServerConfig {
+        ServerConfig {
+            error_format,
+            ..self
+        }
+    }
+
+    /// Sets how long a read from a connection may wait for data before the connection is closed.
+    /// This applies to every read, so it guards against clients which stall part way through
+    /// sending a request body, as well as closing idle keep-alive connections. No timeout is
+    /// applied by default.
+    pub fn with_read_timeout(self, read_timeout: Duration) -> ServerConfig {
+        ServerConfig {
+            read_timeout: Some(read_timeout),
+            ..self
+        }
+    }
+
+    /// Trusts the proxy headers of every request when determining whether it arrived over a
+    /// secure connection, as reported by `gotham::state::is_secure`. `precedence` chooses between
+    /// the `Forwarded` and `X-Forwarded-Proto` headers when both are present.
+    ///
+    /// Clients can set these headers themselves, so this must only be enabled when the
+    /// application is reachable solely through a proxy which overwrites them. Only the element
+    /// appended by that proxy, which is the last, is used, as any before it may have been sent by
+    /// the client. Proxy headers are not trusted by default.
+    pub fn with_trusted_proxy_headers(self, precedence: ForwardedPrecedence) -> ServerConfig {
+        ServerConfig {
+            trusted_proxy_headers: Some(precedence),
+            ..self
+        }
+    }
+
+    /// Adds a `Server-Timing` header to every response, reporting the time taken by the
+    /// application as the `total` metric along with any metrics recorded by middleware and
+    /// handlers via `gotham::state::record_server_timing`. Defaults to `false`.
+    ///
+    /// Timing information can help an attacker distinguish between code paths, so this is best
+    /// enabled only in development or for trusted clients.
+    pub fn with_server_timing(self, server_timing: bool) -> ServerConfig {
+        ServerConfig {
+            server_timing,
+            ..self
+        }
+    }
+
+    /// Sets the maximum number of connections each worker thread holds at once. Connections
+    /// accepted by a worker beyond this are closed immediately, before any request is read, and
+    /// counted by `ConnectionMetrics::refused`. No limit is applied by default.
+    ///
+    /// This bounds the memory used by a single worker under load, independent of how connections
+    /// are spread between workers.
+    pub fn with_max_connections_per_worker(self, max_connections: usize) -> ServerConfig {
+        ServerConfig {
+            max_connections_per_worker: Some(max_connections),
+            ..self
+        }
+    }
+
+    /// Sets the maximum number of connections accepted per second from each client IP address,
+    /// shared between all worker threads. Connections beyond this rate are closed immediately,
+    /// before any request is read, and counted by `ConnectionMetrics::rate_limited`. No limit is
+    /// applied by default.
+    ///
+    /// Short bursts of up to `max_accept_rate` connections are permitted, so this mitigates
+    /// connection floods from a single address without affecting clients which open several
+    /// connections at once. The address is that of the connecting socket, so every client behind
+    /// a proxy shares the same limit.
+    pub fn with_max_accept_rate(self, max_accept_rate: u32) -> ServerConfig {
+        ServerConfig {
+            max_accept_rate: Some(max_accept_rate),
+            ..self
+        }
+    }
+
+    /// Sets how long a worker thread waits before accepting connections again, after failing to
+    /// accept one as the process or system has run out of file descriptors (`EMFILE` or
+    /// `ENFILE`). Defaults to 100 milliseconds.
+    ///
+    /// Retrying immediately would fail again until another connection is closed, spinning the
+    /// worker, so it instead gives its open connections time to finish.
+    pub fn with_accept_backoff(self, accept_backoff: Duration) -> ServerConfig {
+        ServerConfig {
+            accept_backoff,
+            ..self
+        }
+    }
+
+    /// Sets the `ConnectionMetrics` handle which the server updates as it accepts connections.
+    pub fn with_connection_metrics(self, connection_metrics: ConnectionMetrics) -> ServerConfig {
+        ServerConfig {
+            connection_metrics,
+            ..self
+        }
+    }
+
+    /// Sets what body extractors do with members of the body which are not fields of the struct
+    /// being deserialized, unless overridden by the extractor. Defaults to
+    /// `UnknownFields::Ignore`.
+    pub fn with_unknown_fields(self, unknown_fields: UnknownFields) -> ServerConfig {
+        ServerConfig {
+            unknown_fields,
+            ..self
+        }
+    }
+
+    /// Sets the `DeserializeErrorMapper` applied when a body extractor is unable to deserialize a
+    /// body. No mapping is applied by default.
+    pub fn with_deserialize_error_mapper(self, mapper: DeserializeErrorMapper) -> ServerConfig {
+        ServerConfig {
+            deserialize_error_mapper: Some(mapper),
+            ..self
+        }
+    }
+
+    /// Sets the `ExtractorFailureHook` told of each request which an extractor fails to extract.
+    /// No hook is called by default.
+    pub fn with_extractor_failure_hook(self, hook: ExtractorFailureHook) -> ServerConfig {
+        ServerConfig {
+            extractor_failure_hook: Some(hook),
+            ..self
+        }
+    }
+
+    /// Sets whether the bytes transferred in the request and response bodies are counted, and
+    /// exposed to the application via `gotham::state::transfer_sizes`. Defaults to `false`, as
+    /// each body is then forwarded through a channel as it is counted.
+    pub fn with_transfer_accounting(self, transfer_accounting: bool) -> ServerConfig {
+        ServerConfig {
+            transfer_accounting,
+            ..self
+        }
+    }
+
+    /// Sets the `TransferHook` told of the bytes transferred in the bodies of each request
+    /// passed to the application, enabling transfer accounting. No hook is called by default.
+    pub fn with_transfer_hook(self, hook: TransferHook) -> ServerConfig {
+        ServerConfig {
+            transfer_hook: Some(hook),
+            ..self
+        }
+    }
+
+    /// Sets how the `Location` header of redirects created by `create_redirect` is written.
+    /// Defaults to `LocationStyle::Relative`.
+    pub fn with_location_style(self, location_style: LocationStyle) -> ServerConfig {
+        ServerConfig {
+            location_style,
+            ..self
+        }
+    }
+
+    /// Sets which methods are listed by the `Allow` header of `405 Method Not Allowed` responses
+    /// sent by the `Router`. Defaults to `AllowScope::Path`.
+    pub fn with_allow_scope(self, allow_scope: AllowScope) -> ServerConfig {
+        ServerConfig {
+            allow_scope,
+            ..self
+        }
+    }
+
+    /// Sets how `OPTIONS *` requests, which ask about the capabilities of the server as a whole,
+    /// are answered. Defaults to `OptionsAsterisk::Capabilities`.
+    pub fn with_options_asterisk(self, options_asterisk: OptionsAsterisk) -> ServerConfig {
+        ServerConfig {
+            options_asterisk,
+            ..self
+        }
+    }
+
+    /// Sets the `PreRoutingHook` which is given each request before the application. No hook is
+    /// installed by default.
+    pub fn with_pre_routing_hook(self, pre_routing_hook: PreRoutingHook) -> ServerConfig {
+        ServerConfig {
+            pre_routing_hook: Some(pre_routing_hook),
+            ..self
+        }
+    }
+
+    /// Sets the `WorkerInit` which is run on each worker thread before it begins serving
+    /// requests. No initialization is run by default.
+    pub fn with_worker_init(self, worker_init: WorkerInit) -> ServerConfig {
+        ServerConfig {
+            worker_init: Some(worker_init),
+            ..self
+        }
+    }
+
+    /// Adds `data` to the `State` of every request, before the `PreRoutingHook` and the
+    /// application are invoked, so that data shared by the whole application, such as a
+    /// database pool or application settings, is available to every `Middleware` and `Handler`
+    /// without being passed to each `NewHandler`.
+    ///
+    /// Each request is given its own clone of `data`, so data which is modified by handlers
+    /// should be shared behind an `Arc`. Adding data of a type which has already been added
+    /// replaces it.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # extern crate gotham;
+    /// # extern crate hyper;
+    /// #
+    /// # use std::collections::HashMap;
+    /// # use std::sync::Arc;
+    /// # use hyper::{Response, StatusCode};
+    /// # use gotham::config::ServerConfig;
+    /// # use gotham::state::{FromState, State, StateData};
+    /// # use gotham::test::TestServer;
+    /// #
+    /// #[derive(Clone)]
+    /// struct Settings {
+    ///     greetings: Arc<HashMap<String, String>>,
+    /// }
+    ///
+    /// impl StateData for Settings {}
+    ///
+    /// fn greet(state: State) -> (State, Response) {
+    ///     let greeting = Settings::borrow_from(&state).greetings["en"].clone();
+    ///     (state, Response::new().with_status(StatusCode::Ok).with_body(greeting))
+    /// }
+    /// #
+    /// # fn main() {
+    /// let mut greetings = HashMap::new();
+    /// greetings.insert("en".to_owned(), "Hello".to_owned());
+    ///
+    /// let settings = Settings { greetings: Arc::new(greetings) };
+    /// let config = ServerConfig::default().with_app_data(settings);
+    /// #
+    /// # let test_server = TestServer::with_config(|| Ok(greet), config).unwrap();
+    /// # let response = test_server.client().get("http://localhost/").perform().unwrap();
+    /// # assert_eq!(response.read_utf8_body().unwrap(), "Hello");
+    /// # }
+    /// ```
+    pub fn with_app_data<T>(mut self, data: T) -> ServerConfig
+    where
+        T: StateData + Clone + Send + Sync,
+    {
+        self.app_data.push(AppData::new(data));
+        self
+    }
+
+    /// Sets the `Clock` through which Gotham reads the current time, and which is available to
+    /// the application via `gotham::clock::clock`. Defaults to `SystemClock`.
+    pub fn with_clock<C>(self, clock: C) -> ServerConfig
+    where
+        C: Clock + 'static,
+    {
+        ServerConfig {
+            clock: Arc::new(clock),
+            ..self
+        }
+    }
+
+    /// Sets the maximum number of responses streamed at once via
+    /// `gotham::http::response::stream::create_channel_response`, shared between all worker
+    /// threads. Once reached, further streaming responses are replaced with
+    /// `503 Service Unavailable` until an existing stream ends. No limit is applied by default.
+    ///
+    /// Streamed responses may be held open for as long as the client remains connected, so this
+    /// bounds the resources held by long-lived streams without affecting other requests.
+    pub fn with_max_streaming_responses(self, max_streaming_responses: usize) -> ServerConfig {
+        ServerConfig {
+            streaming_limit: Some(StreamingLimit::new(max_streaming_responses)),
+            ..self
+        }
+    }
+
+    /// The number of threads used to accept connections and handle requests.
+    pub fn num_threads(&self) -> usize {
+        self.num_threads
+    }
+
+    /// The maximum number of header lines accepted in a single request, if configured.
+    pub fn max_headers(&self) -> Option<usize> {
+        self.max_headers
+    }
+
+    /// The maximum length of the percent-decoded request path, if configured.
+    pub fn max_decoded_path_length(&self) -> Option<usize> {
+        self.max_decoded_path_length
+    }
+
+    /// How percent-encoded slashes in the request path are treated.
+    pub fn encoded_slashes(&self) -> EncodedSlashes {
+        self.encoded_slashes
+    }
+
+    /// How `.` and `..` segments in the request path are treated.
+    pub fn dot_segments(&self) -> DotSegments {
+        self.dot_segments
+    }
+
+    /// Whether requests with an unknown expectation are answered with `417 Expectation Failed`.
+    pub fn reject_unknown_expectations(&self) -> bool {
+        self.reject_unknown_expectations
+    }
+
+    /// Which requests must carry a `Host` header.
+    pub fn host_requirement(&self) -> HostRequirement {
+        self.host_requirement
+    }
+
+    /// How `TRACE` requests are answered.
+    pub fn trace_behavior(&self) -> TraceBehavior {
+        self.trace_behavior
+    }
+
+    /// How responses from the application are checked for contradictions.
+    pub fn response_validation(&self) -> ResponseValidation {
+        self.response_validation
+    }
+
+    /// Whether `SO_REUSEPORT` is set on the listening socket.
+    pub fn reuse_port(&self) -> bool {
+        self.reuse_port
+    }
+
+    /// The name of the network interface whose address the listening socket is bound to, if
+    /// configured.
+    pub fn bind_interface(&self) -> Option<&str> {
+        self.bind_interface.as_deref()
+    }
+
+    /// Whether connections begin with a PROXY protocol header.
+    pub fn proxy_protocol(&self) -> bool {
+        self.proxy_protocol
+    }
+
+    /// The `Shutdown` handle used to tell the server to drain.
+    pub fn shutdown(&self) -> &Shutdown {
+        &self.shutdown
+    }
+
+    /// How long a draining server waits for in-flight connections to complete.
+    pub fn shutdown_timeout(&self) -> Duration {
+        self.shutdown_timeout
+    }
+
+    /// What the server does with new connections while draining.
+    pub fn drain_behavior(&self) -> DrainBehavior {
+        self.drain_behavior
+    }
+
+    /// The delay advertised via the `Retry-After` header of `503 Service Unavailable` responses
+    /// generated by Gotham, if configured.
+    pub fn retry_after(&self) -> Option<Duration> {
+        self.retry_after
+    }
+
+    /// How the `Retry-After` header is written.
+    pub fn retry_after_format(&self) -> RetryAfterFormat {
+        self.retry_after_format
+    }
+
+    /// The format of the body of responses generated from a `HandlerError`.
+    pub fn error_format(&self) -> ErrorFormat {
+        self.error_format
+    }
+
+    /// How long a read from a connection may wait for data, if configured.
+    pub fn read_timeout(&self) -> Option<Duration> {
+        self.read_timeout
+    }
+
+    /// How proxy headers are read when they are trusted, if configured.
+    pub fn trusted_proxy_headers(&self) -> Option<ForwardedPrecedence> {
+        self.trusted_proxy_headers
+    }
+
+    /// Whether a `Server-Timing` header is added to responses.
+    pub fn server_timing(&self) -> bool {
+        self.server_timing
+    }
+
+    /// The maximum number of connections each worker thread holds at once, if configured.
+    pub fn max_connections_per_worker(&self) -> Option<usize> {
+        self.max_connections_per_worker
+    }
+
+    /// The maximum number of connections accepted per second from each client IP address, if
+    /// configured.
+    pub fn max_accept_rate(&self) -> Option<u32> {
+        self.max_accept_rate
+    }
+
+    /// How long a worker thread waits before accepting connections again, after running out of
+    /// file descriptors.
+    pub fn accept_backoff(&self) -> Duration {
+        self.accept_backoff
+    }
+
+    /// The `ConnectionMetrics` handle which the server updates as it accepts connections.
+    pub fn connection_metrics(&self) -> &ConnectionMetrics {
+        &self.connection_metrics
+    }
+
+    /// What body extractors do with members of the body which are not fields of the struct being
+    /// deserialized.
+    pub fn unknown_fields(&self) -> UnknownFields {
+        self.unknown_fields
+    }
+
+    /// The `DeserializeErrorMapper` applied when a body cannot be deserialized, if configured.
+    pub fn deserialize_error_mapper(&self) -> Option<&DeserializeErrorMapper> {
+        self.deserialize_error_mapper.as_ref()
+    }
+
+    /// The `ExtractorFailureHook` told of each extractor failure, if configured.
+    pub fn extractor_failure_hook(&self) -> Option<&ExtractorFailureHook> {
+        self.extractor_failure_hook.as_ref()
+    }
+
+    /// Whether the bytes transferred in the request and response bodies are counted, either as
+    /// enabled directly or by configuring a `TransferHook`.
+    pub fn transfer_accounting(&self) -> bool {
+        self.transfer_accounting || self.transfer_hook.is_some()
+    }
+
+    /// The `TransferHook` told of the bytes transferred for each request, if configured.
+    pub fn transfer_hook(&self) -> Option<&TransferHook> {
+        self.transfer_hook.as_ref()
+    }
+
+    /// How the `Location` header of redirects is written.
+    pub fn location_style(&self) -> LocationStyle {
+        self.location_style
+    }
+
+    /// Which methods are listed by the `Allow` header of `405 Method Not Allowed` responses.
+    pub fn allow_scope(&self) -> AllowScope {
+        self.allow_scope
+    }
+
+    /// How `OPTIONS *` requests are answered.
+    pub fn options_asterisk(&self) -> OptionsAsterisk {
+        self.options_asterisk
+    }
+
+    /// The `PreRoutingHook` which is given each request before the application, if configured.
+    pub fn pre_routing_hook(&self) -> Option<&PreRoutingHook> {
+        self.pre_routing_hook.as_ref()
+    }
+
+    /// The `WorkerInit` run on each worker thread, if configured.
+    pub fn worker_init(&self) -> Option<&WorkerInit> {
+        self.worker_init.as_ref()
+    }
+
+    /// The `Clock` through which Gotham reads the current time.
+    pub fn clock(&self) -> &Arc<dyn Clock> {
+        &self.clock
+    }
+
+    /// The maximum number of responses streamed at once, if configured.
+    pub fn max_streaming_responses(&self) -> Option<usize> {
+        self.streaming_limit.as_ref().map(StreamingLimit::max)
+    }
+
+    pub(crate) fn streaming_limit(&self) -> Option<&StreamingLimit> {
+        self.streaming_limit.as_ref()
+    }
+
+    pub(crate) fn app_data(&self) -> &[AppData] {
+        &self.app_data
+    }
+
+    /// Builds the Hyper protocol which serves connections.
+    pub(crate) fn protocol(&self) -> Http {
+        Http::new()
+    }
+}
+
+/// Determines which requests must carry a `Host` header, configured via
+/// `ServerConfig::with_host_requirement`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum HostRequirement {
+    /// HTTP/1.1 requests must carry a `Host` header, while HTTP/1.0 requests, which predate it,
+    /// need not. This is the default.
+    #[default]
+    Http11,
+
+    /// Every request must carry a `Host` header.
+    Always,
+
+    /// No request need carry a `Host` header.
+    Never,
+}
+
+impl HostRequirement {
+    /// Returns `true` if a request using `version` must carry a `Host` header.
+    pub(crate) fn requires(self, version: HttpVersion) -> bool {
+        match self {
+            HostRequirement::Http11 => version == HttpVersion::Http11,
+            HostRequirement::Always => true,
+            HostRequirement::Never => false,
+        }
+    }
+}
+
+/// Determines how percent-encoded slashes in the request path are treated, configured via
+/// `ServerConfig::with_encoded_slashes`.
+///
+/// The request path is split into segments before each segment is percent-decoded exactly once,
+/// so `%2F` never acts as a path separator, and the double-encoded `%252F` is always captured as
+/// the literal text `%2F`. An application which decodes a captured segment again, or which
+/// builds a filesystem path or an upstream URL from one, can still be confused by either, so
+/// they can be rejected before the request reaches the application.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum EncodedSlashes {
+    /// `%2F` is decoded to `/` within the captured segment, and `%252F` is decoded to `%2F`.
+    /// This is the default.
+    #[default]
+    Decode,
+
+    /// Requests whose path holds a segment containing `/` once decoded, or which would contain
+    /// `/` if decoded a second time, are answered with `400 Bad Request` without invoking the
+    /// application.
+    Reject,
+}
+
+/// Determines how `.` and `..` segments in the request path are treated, configured via
+/// `ServerConfig::with_dot_segments`.
+///
+/// Segments are compared once percent-decoded, so `%2e%2e` is treated as `..`. Clients normalize
+/// dot segments before sending a request, so they only arrive from clients crafting a path to
+/// reach a resource other than the one named, such as a file outside a served directory, or to
+/// confuse rules applied to the raw path by a proxy. Only the path used for routing and path
+/// extraction is affected: the `Uri` in `State` is left as received.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DotSegments {
+    /// `.` segments are removed, and each `..` segment is removed along with the segment before
+    /// it, as described by RFC 3986, so `/a/%2e%2e/b` is routed as `/b`. A `..` segment at the
+    /// root is removed alone. This is the default.
+    #[default]
+    Normalize,
+
+    /// Requests whose path holds a `.` or `..` segment are answered with `400 Bad Request`
+    /// without invoking the application.
+    Reject,
+}
+
+/// Determines how `TRACE` requests are answered, configured via
+/// `ServerConfig::with_trace_behavior`.
+///
+/// `TRACE` echoes the request back to the client, and can be abused by cross-site tracing (XST)
+/// to read credentials which are otherwise hidden from scripts, such as `HttpOnly` cookies. It is
+/// therefore rejected unless enabled.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TraceBehavior {
+    /// `TRACE` requests are answered with `405 Method Not Allowed` without invoking the
+    /// application. This is the default.
+    #[default]
+    Reject,
+
+    /// `TRACE` requests are answered with a `message/http` body echoing the request line and
+    /// headers, without invoking the application. The `Cookie`, `Authorization` and
+    /// `Proxy-Authorization` headers are omitted, so that credentials are never reflected.
+    Echo,
+
+    /// `TRACE` requests are passed to the application like any other request.
+    Application,
+}
+
+/// Determines how responses from the application are checked for contradictions, configured via
+/// `ServerConfig::with_response_validation`.
+///
+/// A response is invalid when:
+///
+/// * It carries a body, but its status or the request method forbids one, e.g. `204 No Content`
+///   or a response to `HEAD`;
+/// * It carries both `Content-Length` and `Transfer-Encoding` headers;
+/// * Its `Content-Length` header differs from the length of its body.
+///
+/// Hyper sends such responses as given, leaving the client to misread the body or the next
+/// response on the connection, so validation catches these mistakes early in development.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ResponseValidation {
+    /// Responses are not checked. This is the default in release builds.
+    Off,
+
+    /// Invalid responses are logged, and sent as given. This is the default in debug builds.
+    ///
+    /// The body is compared with the `Content-Length` header as it streams, without buffering.
+    /// Once it differs, the response is cut off and the connection closed, rather than being
+    /// kept alive for further requests.
+    Log,
+
+    /// Invalid responses are logged, and replaced with `500 Internal Server Error`.
+    ///
+    /// To compare the `Content-Length` header with the body before anything is sent, the body of
+    /// a response which carries both is buffered in memory.
+    Reject,
+}
+
+impl Default for ResponseValidation {
+    fn default() -> ResponseValidation {
+        if cfg!(debug_assertions) {
+            ResponseValidation::Log
+        } else {
+            ResponseValidation::Off
+        }
+    }
+}
+
+impl StateData for ResponseValidation {}
+
+/// A function given each request before the application, and so before routing and any
+/// middleware. Configured via `ServerConfig::with_pre_routing_hook`.
+///
+/// The hook may rewrite the `Method`, `Uri` or `Headers` in `State`, and the request is routed
+/// using the rewritten values. This suits concerns which apply to every request regardless of
+/// route, such as method override, path normalization and canonicalization. Returning a
+/// `Response` sends it to the client immediately, without invoking the application.
+///
+/// A path rewritten by the hook is checked again against the limits set by
+/// `ServerConfig::with_max_decoded_path_length`, `with_encoded_slashes` and `with_dot_segments`.
+///
+/// # Examples
+///
+/// ```rust
+/// # extern crate gotham;
+/// # extern crate hyper;
+/// # extern crate mime;
+/// #
+/// # use hyper::{Response, StatusCode, Uri};
+/// # use gotham::config::{PreRoutingHook, ServerConfig};
+/// # use gotham::http::response::create_response;
+/// # use gotham::router::Router;
+/// # use gotham::router::builder::*;
+/// # use gotham::state::{FromState, State};
+/// # use gotham::test::TestServer;
+/// #
+/// # fn products(state: State) -> (State, Response) {
+/// #     let body = Some((b"products".to_vec(), mime::TEXT_PLAIN));
+/// #     let res = create_response(&state, StatusCode::Ok, body);
+/// #     (state, res)
+/// # }
+/// #
+/// # fn router() -> Router {
+/// #     build_simple_router(|route| {
+/// #         route.get("/products").to(products);
+/// #     })
+/// # }
+/// #
+/// # fn main() {
+/// // Serves the unversioned routes under the legacy `/v1` prefix as well.
+/// let hook = PreRoutingHook::new(|state| {
+///     let path = Uri::borrow_from(state).path().to_owned();
+///     if path.starts_with("/v1/") {
+///         state.put::<Uri>(path[3..].parse().unwrap());
+///     }
+///     None
+/// });
+///
+/// let config = ServerConfig::default().with_pre_routing_hook(hook);
+/// #
+/// # let test_server = TestServer::with_config(router(), config).unwrap();
+/// # let response = test_server
+/// #     .client()
+/// #     .get("http://localhost/v1/products")
+/// #     .perform()
+/// #     .unwrap();
+/// # assert_eq!(response.read_body().unwrap(), b"products");
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct PreRoutingHook {
+    f: Arc<HookFn>,
+}
+
+type HookFn = dyn Fn(&mut State) -> Option<Response> + Send + Sync;
+
+impl PreRoutingHook {
+    /// Creates a `PreRoutingHook` which calls `f` for each request.
+    pub fn new<F>(f: F) -> PreRoutingHook
+    where
+        F: Fn(&mut State) -> Option<Response> + Send + Sync + 'static,
+    {
+        PreRoutingHook { f: Arc::new(f) }
+    }
+
+    pub(crate) fn call(&self, state: &mut State) -> Option<Response> {
+        (self.f)(state)
+    }
+}
+
+impl fmt::Debug for PreRoutingHook {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("PreRoutingHook")
+    }
+}
+
+/// A value added to the `State` of every request, configured via `ServerConfig::with_app_data`.
+#[derive(Clone)]
+pub(crate) struct AppData {
+    put: Arc<dyn Fn(&mut State) + Send + Sync>,
+}
+
+impl AppData {
+    fn new<T>(data: T) -> AppData
+    where
+        T: StateData + Clone + Send + Sync,
+    {
+        AppData {
+            put: Arc::new(move |state| state.put(data.clone())),
+        }
+    }
+
+    pub(crate) fn put(&self, state: &mut State) {
+        (self.put)(state)
+    }
+}
+
+impl fmt::Debug for AppData {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("AppData")
+    }
+}
+
+/// A function run once on each worker thread, after the thread's reactor is created and before
+/// it begins serving requests. Configured via `ServerConfig::with_worker_init`.
+///
+/// The function is given the `Handle` of the worker's reactor, and returns a future which is run
+/// to completion on that reactor before the worker accepts any connections. This suits setting up
+/// per-thread resources, such as thread-local database connections and caches, including those
+/// which are established asynchronously. The worker panics if the future fails.
+///
+/// A `TestServer` runs the function once, on the thread which creates it.
+///
+/// # Examples
+///
+/// ```rust
+/// # extern crate gotham;
+/// # extern crate hyper;
+/// # extern crate mime;
+/// #
+/// # use std::cell::RefCell;
+/// # use std::io;
+/// # use hyper::{Response, StatusCode};
+/// # use gotham::config::{ServerConfig, WorkerInit};
+/// # use gotham::http::response::create_response;
+/// # use gotham::state::State;
+/// # use gotham::test::TestServer;
+/// #
+/// thread_local!(static GREETING: RefCell<Option<String>> = const { RefCell::new(None) });
+///
+/// fn handler(state: State) -> (State, Response) {
+///     let greeting = GREETING.with(|greeting| greeting.borrow().clone()).unwrap();
+///     let body = Some((greeting.into_bytes(), mime::TEXT_PLAIN));
+///     let res = create_response(&state, StatusCode::Ok, body);
+///     (state, res)
+/// }
+///
+/// # fn main() {
+/// let init = WorkerInit::new(|_handle| -> io::Result<()> {
+///     GREETING.with(|greeting| *greeting.borrow_mut() = Some("hello".to_owned()));
+///     Ok(())
+/// });
+///
+/// let config = ServerConfig::default().with_worker_init(init);
+/// #
+/// # let test_server = TestServer::with_config(|| Ok(handler), config).unwrap();
+/// # let response = test_server
+/// #     .client()
+/// #     .get("http://localhost/")
+/// #     .perform()
+/// #     .unwrap();
+/// # assert_eq!(response.read_body().unwrap(), b"hello");
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct WorkerInit {
+    f: Arc<InitFn>,
+}
+
+type InitFn = dyn Fn(&Handle) -> Box<dyn Future<Item = (), Error = io::Error>> + Send + Sync;
+
+impl WorkerInit {
+    /// Creates a `WorkerInit` which calls `f` on each worker thread.
+    pub fn new<F, R>(f: F) -> WorkerInit
+    where
+        F: Fn(&Handle) -> R + Send + Sync + 'static,
+        R: IntoFuture<Item = (), Error = io::Error>,
+        R::Future: 'static,
+    {
+        WorkerInit {
+            f: Arc::new(move |handle| Box::new(f(handle).into_future())),
+        }
+    }
+
+    /// Runs the initialization to completion on `core`.
+    pub(crate) fn run(&self, core: &mut Core) -> io::Result<()> {
+        let f = (self.f)(&core.handle());
+        core.run(f)
+    }
+}
+
+impl fmt::Debug for WorkerInit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("WorkerInit")
+    }
+}
+
+/// Counters describing the connections handled by a running Gotham application.
+///
+/// `ConnectionMetrics` is cheaply cloneable, and all clones refer to the same counters. Pass a
+/// clone to the server via `ServerConfig::with_connection_metrics` and keep another to read them.
+///
+/// # Examples
+///
+/// ```rust
+/// # extern crate gotham;
+/// #
+/// # use gotham::config::{ConnectionMetrics, ServerConfig};
+/// #
+/// # fn main() {
+/// let metrics = ConnectionMetrics::new();
+/// let config = ServerConfig::default()
+///     .with_max_connections_per_worker(64)
+///     .with_connection_metrics(metrics.clone());
+/// # drop(config);
+///
+/// // Later, from any thread:
+/// assert_eq!(metrics.refused(), 0);
+/// # }
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct ConnectionMetrics {
+    refused: Arc<AtomicUsize>,
+    rate_limited: Arc<AtomicUsize>,
+}
+
+impl ConnectionMetrics {
+    /// Creates a new `ConnectionMetrics` handle, with every counter at zero.
+    pub fn new() -> ConnectionMetrics {
+        ConnectionMetrics::default()
+    }
+
+    /// The number of connections closed because a worker had reached the limit set via
+    /// `ServerConfig::with_max_connections_per_worker`.
+    pub fn refused(&self) -> usize {
+        self.refused.load(Ordering::SeqCst)
+    }
+
+    /// The number of connections closed because their client exceeded the rate set via
+    /// `ServerConfig::with_max_accept_rate`.
+    pub fn rate_limited(&self) -> usize {
+        self.rate_limited.load(Ordering::SeqCst)
+    }
+
+    pub(crate) fn record_refused(&self) {
+        self.refused.fetch_add(1, Ordering::SeqCst);
+    }
+
+    pub(crate) fn record_rate_limited(&self) {
+        self.rate_limited.fetch_add(1, Ordering::SeqCst);
+    }
+}
@@ -0,0 +1,832 @@
+//! Defines a `Handler` which serves files from a directory on disk.
+
+use std::ffi::OsString;
+use std::fs;
+use std::io::{self, ErrorKind};
+use std::path::{Component, Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use futures::future;
+use hyper::{Headers, Method, Response, StatusCode, Uri};
+use hyper::header::{q, AcceptEncoding, AcceptRanges, ContentEncoding, ContentRange,
+                    ContentRangeSpec, Encoding, HttpDate, IfRange, LastModified, Quality, Range,
+                    RangeUnit, Vary};
+use mime::{self, Mime};
+
+use handler::{Handler, HandlerFuture, IntoHandlerError, NewHandler};
+use http::PercentDecoded;
+use http::response::create_response;
+use http::response::redirect::create_redirect;
+use http::response::sniff::sniff_content_type;
+use router::response::extender::StaticResponseExtender;
+use router::request::path::PathExtractor;
+use router::tree::SegmentMapping;
+use state::{request_id, FromState, State, StateData};
+
+/// Extracts the segments matched by a glob (`*`) into `State`, for use by `DirHandler`.
+pub struct FilePathExtractor {
+    parts: Vec<String>,
+}
+
+impl StateData for FilePathExtractor {}
+
+impl PathExtractor for FilePathExtractor {
+    fn extract(state: &mut State, segment_mapping: SegmentMapping) -> Result<(), String> {
+        let parts = segment_mapping
+            .get("*")
+            .map(|segments| segments.iter().map(|s| s.val().to_owned()).collect())
+            .unwrap_or_default();
+
+        state.put(FilePathExtractor { parts });
+        Ok(())
+    }
+}
+
+impl StaticResponseExtender for FilePathExtractor {
+    fn extend(_state: &mut State, _res: &mut Response) {}
+}
+
+/// A `Handler` which serves the files beneath a directory, using the segments captured by a glob
+/// route as the path of the file.
+///
+/// When a directory is requested, `DirHandler` behaves as conventional web servers do:
+///
+/// * A request without a trailing slash is redirected with `301 Moved Permanently` to the
+///   slashed form, so that relative links in the index resolve against the directory. This can be
+///   disabled with `with_directory_redirect`, in which case the index is served directly.
+/// * The index file (`index.html` by default) is served when present. This can be changed with
+///   `with_index_file`, or disabled with `with_directory_index`.
+///
+/// Directories without an index, and paths which do not exist, are answered with
+/// `404 Not Found`.
+///
+/// The `Content-Type` of a file is determined by its extension, falling back to
+/// `application/octet-stream`. When enabled with `with_content_sniffing`, files with an unknown
+/// or missing extension are labelled by `sniff_content_type` before that fallback.
+///
+/// A single byte range requested via the `Range` header is answered with `206 Partial Content`.
+/// When the request also carries `If-Range`, the range is served only if the file has not been
+/// modified since the given date, and the whole file is served otherwise.
+///
+/// When enabled with `with_precompressed`, a precompressed sibling of the requested file is served
+/// in its place if the client accepts its encoding, such as `app.js.br` for a request to `app.js`
+/// with `Accept-Encoding: br`. Siblings ending `.br` and `.gz` are considered, and the encoding
+/// the client prefers is chosen, favouring Brotli when the preference is equal. The response
+/// carries the `Content-Type` of the requested file, along with `Content-Encoding`, and
+/// `Vary: Accept-Encoding` is set on every file served so that caches keep the variants apart.
+///
+/// # Examples
+///
+/// ```rust
+/// # extern crate gotham;
+/// #
+/// # use gotham::handler::{DirHandler, FilePathExtractor};
+/// # use gotham::router::builder::*;
+/// #
+/// # fn main() {
+/// let router = build_simple_router(|route| {
+///     route
+///         .get("/static/*")
+///         .with_path_extractor::<FilePathExtractor>()
+///         .to_new_handler(DirHandler::new("public").with_index_file("default.htm"));
+/// });
+/// # drop(router);
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct DirHandler {
+    root: PathBuf,
+    directory_redirect: bool,
+    directory_index: bool,
+    index_file: String,
+    content_sniffing: bool,
+    precompressed: bool,
+}
+
+impl DirHandler {
+    /// Creates a new `DirHandler` serving the files beneath `root`.
+    pub fn new<P>(root: P) -> DirHandler
+    where
+        P: AsRef<Path>,
+    {
+        DirHandler {
+            root: root.as_ref().to_path_buf(),
+            directory_redirect: true,
+            directory_index: true,
+            index_file: String::from("index.html"),
+            content_sniffing: false,
+            precompressed: false,
+        }
+    }
+
+    /// Sets whether a directory requested without a trailing slash is redirected to the slashed
+    /// form. Defaults to `true`.
+    pub fn with_directory_redirect(self, directory_redirect: bool) -> DirHandler {
+        DirHandler {
+            directory_redirect,
+            ..self
+        }
+    }
+
+    /// Sets whether the index file is served when a directory is requested. Defaults to `true`.
+    pub fn with_directory_index(self, directory_index: bool) -> DirHandler {
+        DirHandler {
+            directory_index,
+            ..self
+        }
+    }
+
+    /// Sets the name of the file served when a directory is requested. Defaults to
+    /// `index.html`.
+    pub fn with_index_file(self, index_file: &str) -> DirHandler {
+        DirHandler {
+            index_file: index_file.to_owned(),
+            ..self
+        }
+    }
+
+    /// Sets whether the content type of a file with an unknown or missing extension is sniffed
+    /// from its contents. Defaults to `false`.
+    pub fn with_content_sniffing(self, content_sniffing: bool) -> DirHandler {
+        DirHandler {
+            content_sniffing,
+            ..self
+        }
+    }
+
+    /// Sets whether a precompressed `.br` or `.gz` sibling of a file is served in its place, when
+    /// the client accepts that encoding. Defaults to `false`.
+    pub fn with_precompressed(self, precompressed: bool) -> DirHandler {
+        DirHandler {
+            precompressed,
+            ..self
+        }
+    }
+
+    /// Resolves the requested path beneath `root`, refusing any segment which could escape it.
+    fn resolve(&self, parts: &[String]) -> Option<PathBuf> {
+        let mut path = self.root.clone();
+        for part in parts {
+            // Only a plain name is pushed, as a root, a prefix such as a Windows drive, or a
+            // parent directory would take the path outside of `root`.
+            let mut components = Path::new(part).components();
+            let normal = matches!(
+                (components.next(), components.next()),
+                (Some(Component::Normal(_)), None)
+            );
+
+            if !normal || part.contains('/') || part.contains('\\') {
+                return None;
+            }
+            path.push(part);
+        }
+        Some(path)
+    }
+
+    fn respond(&self, state: &State) -> io::Result<Response> {
+        let path = match FilePathExtractor::try_borrow_from(state)
+            .and_then(|extracted| self.resolve(&extracted.parts))
+        {
+            Some(path) => path,
+            None => return Ok(create_response(state, StatusCode::NotFound, None)),
+        };
+
+        let path = if path.is_dir() {
+            let uri = Uri::borrow_from(state);
+
+            if !uri.path().ends_with('/') && self.directory_redirect {
+                trace!("[{}] redirecting to directory", request_id(state));
+                let location = directory_location(uri);
+
+                return Ok(create_redirect(
+                    state,
+                    StatusCode::MovedPermanently,
+                    &location,
+                ));
+            }
+
+            if !self.directory_index {
+                return Ok(create_response(state, StatusCode::NotFound, None));
+            }
+
+            path.join(&self.index_file)
+        } else {
+            path
+        };
+
+        let sibling = if self.precompressed {
+            precompressed_sibling(state, &path)
+        } else {
+            None
+        };
+        let (served, encoding) = match sibling {
+            Some((sibling, encoding)) => (sibling, Some(encoding)),
+            None => (path.clone(), None),
+        };
+
+        match fs::read(&served) {
+            Ok(contents) => {
+                let mime = mime_for(&path)
+                    .or_else(|| {
+                        if self.content_sniffing && encoding.is_none() {
+                            sniff_content_type(&contents)
+                        } else {
+                            None
+                        }
+                    })
+                    .unwrap_or(mime::APPLICATION_OCTET_STREAM);
+                let modified = fs::metadata(&served).and_then(|m| m.modified()).ok();
+                let mut res = file_response(state, contents, mime, modified);
+
+                if let Some(encoding) = encoding {
+                    trace!("[{}] serving precompressed {}", request_id(state), encoding);
+                    res.headers_mut().set(ContentEncoding(vec![encoding]));
+                }
+                if self.precompressed {
+                    let accept_encoding = "Accept-Encoding".parse().unwrap();
+                    res.headers_mut().set(Vary::Items(vec![accept_encoding]));
+                }
+                Ok(res)
+            }
+            Err(ref e) if e.kind() == ErrorKind::NotFound || path.is_dir() => {
+                trace!("[{}] file not found", request_id(state));
+                Ok(create_response(state, StatusCode::NotFound, None))
+            }
+            Err(e) => Err(e),
+        }
+    }
+}
+
+impl NewHandler for DirHandler {
+    type Instance = DirHandler;
+
+    fn new_handler(&self) -> io::Result<Self::Instance> {
+        Ok(self.clone())
+    }
+}
+
+impl Handler for DirHandler {
+    fn handle(self, state: State) -> Box<HandlerFuture> {
+        match self.respond(&state) {
+            Ok(res) => Box::new(future::ok((state, res))),
+            Err(e) => Box::new(future::err((state, e.into_handler_error()))),
+        }
+    }
+}
+
+/// Creates the response for a file, serving the single byte range requested via the `Range`
+/// header if any.
+///
+/// When the request carries an `If-Range` header, the range is served only if the validator
+/// matches the file, and the whole file is served otherwise, so that a client never combines
+/// parts of different versions of the file. Files are validated by their modification time, so an
+/// entity tag given via `If-Range` never matches.
+fn file_response(
+    state: &State,
+    contents: Vec<u8>,
+    mime: Mime,
+    modified: Option<SystemTime>,
+) -> Response {
+    let len = contents.len() as u64;
+
+    let mut res = match requested_range(state, len, modified) {
+        RequestedRange::Full => create_response(state, StatusCode::Ok, Some((contents, mime))),
+        RequestedRange::Partial(start, end) => {
+            trace!("[{}] serving bytes {}-{}", request_id(state), start, end);
+            let part = contents[start as usize..=end as usize].to_vec();
+            let mut res = create_response(state, StatusCode::PartialContent, Some((part, mime)));
+            res.headers_mut().set(ContentRange(ContentRangeSpec::Bytes {
+                range: Some((start, end)),
+                instance_length: Some(len),
+            }));
+            res
+        }
+        RequestedRange::Unsatisfiable => {
+            trace!("[{}] range not satisfiable", request_id(state));
+            let mut res = create_response(state, StatusCode::RangeNotSatisfiable, None);
+            res.headers_mut().set(ContentRange(ContentRangeSpec::Bytes {
+                range: None,
+                instance_length: Some(len),
+            }));
+            res
+        }
+    };
+
+    res.headers_mut().set(AcceptRanges(vec![RangeUnit::Bytes]));
+    if let Some(modified) = modified {
+        res.headers_mut().set(LastModified(HttpDate::from(modified)));
+    }
+    res
+}
+
+/// The part of a file requested via the `Range` header.
+enum RequestedRange {
+    Full,
+    Partial(u64, u64),
+    Unsatisfiable,
+}
+
+/// Determines the part of a file of `len` bytes, last modified at `modified`, which is requested.
+///
+/// Only a single byte range is served. Requests for several ranges, or in other units, are served
+/// the whole file.
+fn requested_range(state: &State, len: u64, modified: Option<SystemTime>) -> RequestedRange {
+    if *Method::borrow_from(state) != Method::Get {
+        return RequestedRange::Full;
+    }
+
+    let headers = Headers::borrow_from(state);
+    let spec = match headers.get::<Range>() {
+        Some(Range::Bytes(specs)) if specs.len() == 1 => &specs[0],
+        _ => return RequestedRange::Full,
+    };
+
+    let current = match headers.get::<IfRange>() {
+        None => true,
+        Some(IfRange::Date(date)) => match modified {
+            Some(modified) => unix_secs((*date).into()) == unix_secs(modified),
+            None => false,
+        },
+        Some(IfRange::EntityTag(_)) => false,
+    };
+
+    if !current {
+        trace!("[{}] If-Range does not match, ignoring range", request_id(state));
+        return RequestedRange::Full;
+    }
+
+    match spec.to_satisfiable_range(len) {
+        Some((start, end)) => RequestedRange::Partial(start, end),
+        None => RequestedRange::Unsatisfiable,
+    }
+}
+
+/// Builds the location of the directory requested via `uri`, ending with `/`. The location is
+/// built from the normalized segments of the path, as a path such as `//static/docs` is routed
+/// as `/static/docs`, but would otherwise become a protocol-relative redirect.
+fn directory_location(uri: &Uri) -> String {
+    let mut segments = Vec::new();
+    for segment in uri.path().split('/').filter(|segment| !segment.is_empty()) {
+        match PercentDecoded::new(segment).as_ref().map(|decoded| decoded.val()) {
+            Some(".") => (),
+            Some("..") => {
+                segments.pop();
+            }
+            _ => segments.push(segment),
+        }
+    }
+
+    let mut location = String::new();
+    for segment in segments {
+        location.push('/');
+        location.push_str(segment);
+    }
+    location.push('/');
+
+    if let Some(query) = uri.query() {
+        location.push('?');
+        location.push_str(query);
+    }
+    location
+}
+
+/// Finds the precompressed sibling of `path` to serve, and its encoding, preferring the encoding
+/// the client gives the highest quality via `Accept-Encoding`.
+fn precompressed_sibling(state: &State, path: &Path) -> Option<(PathBuf, Encoding)> {
+    let accepted = Headers::borrow_from(state).get::<AcceptEncoding>()?;
+
+    let mut chosen: Option<(PathBuf, Encoding, Quality)> = None;
+    for (encoding, extension) in [(Encoding::Brotli, "br"), (Encoding::Gzip, "gz")] {
+        let quality = match accepted_quality(accepted, &encoding) {
+            Some(quality) if quality > q(0) => quality,
+            _ => continue,
+        };
+        if chosen.as_ref().is_some_and(|&(_, _, chosen)| chosen >= quality) {
+            continue;
+        }
+
+        let mut sibling = OsString::from(path.as_os_str());
+        sibling.push(".");
+        sibling.push(extension);
+        let sibling = PathBuf::from(sibling);
+
+        if sibling.is_file() {
+            chosen = Some((sibling, encoding, quality));
+        }
+    }
+
+    chosen.map(|(sibling, encoding, _)| (sibling, encoding))
+}
+
+/// The quality given to `encoding` via `Accept-Encoding`, either by name or by the `*` wildcard.
+fn accepted_quality(accepted: &AcceptEncoding, encoding: &Encoding) -> Option<Quality> {
+    let wildcard = Encoding::EncodingExt("*".to_owned());
+
+    accepted
+        .iter()
+        .find(|item| item.item == *encoding)
+        .or_else(|| accepted.iter().find(|item| item.item == wildcard))
+        .map(|item| item.quality)
+}
+
+/// The whole seconds since the Unix epoch, the precision of an HTTP-date.
+fn unix_secs(time: SystemTime) -> Option<u64> {
+    time.duration_since(UNIX_EPOCH).ok().map(|d| d.as_secs())
+}
+
+fn mime_for(path: &Path) -> Option<Mime> {
+    let extension = path
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map(|extension| extension.to_ascii_lowercase());
+
+    let mime = match extension.as_deref() {
+        Some("html") | Some("htm") => mime::TEXT_HTML_UTF_8,
+        Some("css") => mime::TEXT_CSS_UTF_8,
+        Some("js") => mime::APPLICATION_JAVASCRIPT_UTF_8,
+        Some("json") => mime::APPLICATION_JSON,
+        Some("txt") => mime::TEXT_PLAIN_UTF_8,
+        Some("png") => mime::IMAGE_PNG,
+        Some("jpg") | Some("jpeg") => mime::IMAGE_JPEG,
+        Some("gif") => mime::IMAGE_GIF,
+        Some("svg") => mime::IMAGE_SVG,
+        _ => return None,
+    };
+
+    Some(mime)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::env;
+
+    use std::time::Duration;
+
+    use hyper::header::{qitem, ContentType, Location, QualityItem};
+    use uuid::Uuid;
+
+    use router::Router;
+    use router::builder::*;
+    use test::TestServer;
+
+    fn fixture() -> PathBuf {
+        let root = env::temp_dir().join(format!("gotham-assets-{}", Uuid::new_v4()));
+        fs::create_dir_all(root.join("docs")).unwrap();
+        fs::create_dir_all(root.join("empty")).unwrap();
+        fs::write(root.join("docs").join("index.html"), "<h1>Docs</h1>").unwrap();
+        fs::write(root.join("style.css"), "h1 {}").unwrap();
+        fs::write(root.join("logo"), b"\x89PNG\r\n\x1a\n\x00\x00\x00\rIHDR").unwrap();
+        root
+    }
+
+    fn router(handler: DirHandler) -> Router {
+        build_simple_router(|route| {
+            route
+                .get("/static/*")
+                .with_path_extractor::<FilePathExtractor>()
+                .to_new_handler(handler);
+        })
+    }
+
+    #[test]
+    fn redirects_directory_then_serves_index() {
+        let root = fixture();
+        let test_server = TestServer::new(router(DirHandler::new(&root))).unwrap();
+
+        let response = test_server
+            .client()
+            .get("http://localhost/static/docs?lang=en")
+            .perform()
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::MovedPermanently);
+        let location = response.headers().get::<Location>().unwrap().to_string();
+        assert_eq!(location, "/static/docs/?lang=en");
+
+        let response = test_server
+            .client()
+            .get(&format!("http://localhost{}", location))
+            .perform()
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::Ok);
+        assert_eq!(
+            response.headers().get::<ContentType>(),
+            Some(&ContentType(mime::TEXT_HTML_UTF_8))
+        );
+        assert_eq!(response.read_body().unwrap(), b"<h1>Docs</h1>");
+
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn redirects_directory_to_normalized_path() {
+        let root = fixture();
+        let test_server = TestServer::new(router(DirHandler::new(&root))).unwrap();
+
+        for path in &["//static/docs", "/static//docs", "/static/./docs"] {
+            let response = test_server
+                .client()
+                .get(&format!("http://localhost{}", path))
+                .perform()
+                .unwrap();
+
+            assert_eq!(response.status(), StatusCode::MovedPermanently, "{}", path);
+            let location = response.headers().get::<Location>().unwrap().to_string();
+            assert_eq!(location, "/static/docs/", "{}", path);
+        }
+
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn refuses_segments_which_escape_root() {
+        let handler = DirHandler::new("/srv/assets");
+        let resolve = |parts: &[&str]| {
+            let parts: Vec<String> = parts.iter().map(|&part| part.to_owned()).collect();
+            handler.resolve(&parts)
+        };
+
+        assert_eq!(
+            resolve(&["css", "site.css"]),
+            Some(PathBuf::from("/srv/assets/css/site.css"))
+        );
+        for part in &["", ".", "..", "/etc", "a/b", "a\\b"] {
+            assert_eq!(resolve(&[part, "passwd"]), None, "{}", part);
+        }
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn refuses_windows_prefix_segments() {
+        let handler = DirHandler::new("C:\\srv\\assets");
+        for part in &["C:", "C:Windows", "\\\\server\\share"] {
+            let parts = vec![part.to_string(), String::from("win.ini")];
+            assert_eq!(handler.resolve(&parts), None, "{}", part);
+        }
+    }
+
+    #[test]
+    fn serves_index_without_redirect_when_disabled() {
+        let root = fixture();
+        let handler = DirHandler::new(&root).with_directory_redirect(false);
+        let test_server = TestServer::new(router(handler)).unwrap();
+
+        let response = test_server
+            .client()
+            .get("http://localhost/static/docs")
+            .perform()
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::Ok);
+        assert_eq!(response.read_body().unwrap(), b"<h1>Docs</h1>");
+
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn does_not_serve_index_when_disabled() {
+        let root = fixture();
+        let handler = DirHandler::new(&root).with_directory_index(false);
+        let test_server = TestServer::new(router(handler)).unwrap();
+
+        let response = test_server
+            .client()
+            .get("http://localhost/static/docs/")
+            .perform()
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NotFound);
+
+        let response = test_server
+            .client()
+            .get("http://localhost/static/style.css")
+            .perform()
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::Ok);
+        assert_eq!(response.read_body().unwrap(), b"h1 {}");
+
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    fn content_type(handler: DirHandler, path: &str) -> Option<Mime> {
+        let test_server = TestServer::new(router(handler)).unwrap();
+        let response = test_server
+            .client()
+            .get(&format!("http://localhost{}", path))
+            .perform()
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::Ok);
+        response.headers().get::<ContentType>().map(|ct| ct.0.clone())
+    }
+
+    #[test]
+    fn sniffs_content_type_when_enabled() {
+        let root = fixture();
+
+        assert_eq!(
+            content_type(DirHandler::new(&root), "/static/logo"),
+            Some(mime::APPLICATION_OCTET_STREAM)
+        );
+        assert_eq!(
+            content_type(DirHandler::new(&root).with_content_sniffing(true), "/static/logo"),
+            Some(mime::IMAGE_PNG)
+        );
+        assert_eq!(
+            content_type(DirHandler::new(&root).with_content_sniffing(true), "/static/style.css"),
+            Some(mime::TEXT_CSS_UTF_8)
+        );
+
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn responds_not_found() {
+        let root = fixture();
+        let test_server = TestServer::new(router(DirHandler::new(&root))).unwrap();
+
+        for path in &["/static/missing.css", "/static/empty/", "/static/%2E%2E/style.css"] {
+            let response = test_server
+                .client()
+                .get(&format!("http://localhost{}", path))
+                .perform()
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::NotFound, "{}", path);
+        }
+
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn serves_requested_range() {
+        let root = fixture();
+        let test_server = TestServer::new(router(DirHandler::new(&root))).unwrap();
+
+        let response = test_server
+            .client()
+            .get("http://localhost/static/style.css")
+            .with_header(Range::bytes(1, 2))
+            .perform()
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::PartialContent);
+        assert_eq!(
+            response.headers().get::<ContentRange>(),
+            Some(&ContentRange(ContentRangeSpec::Bytes {
+                range: Some((1, 2)),
+                instance_length: Some(5),
+            }))
+        );
+        assert_eq!(response.read_body().unwrap(), b"1 ");
+
+        let response = test_server
+            .client()
+            .get("http://localhost/static/style.css")
+            .with_header(Range::bytes(10, 20))
+            .perform()
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::RangeNotSatisfiable);
+
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn serves_full_file_when_if_range_is_stale() {
+        let root = fixture();
+        let test_server = TestServer::new(router(DirHandler::new(&root))).unwrap();
+
+        let response = test_server
+            .client()
+            .get("http://localhost/static/style.css")
+            .perform()
+            .unwrap();
+        let last_modified = response.headers().get::<LastModified>().unwrap().0;
+        let modified: SystemTime = last_modified.into();
+
+        let request = |if_range: IfRange| {
+            test_server
+                .client()
+                .get("http://localhost/static/style.css")
+                .with_header(Range::bytes(1, 2))
+                .with_header(if_range)
+                .perform()
+                .unwrap()
+        };
+
+        let response = request(IfRange::Date(last_modified));
+        assert_eq!(response.status(), StatusCode::PartialContent);
+        assert_eq!(response.read_body().unwrap(), b"1 ");
+
+        let stale = HttpDate::from(modified - Duration::from_secs(60));
+        let response = request(IfRange::Date(stale));
+        assert_eq!(response.status(), StatusCode::Ok);
+        assert!(response.headers().get::<ContentRange>().is_none());
+        assert_eq!(response.read_body().unwrap(), b"h1 {}");
+
+        let etag = ::hyper::header::EntityTag::strong("stale".to_owned());
+        let response = request(IfRange::EntityTag(etag));
+        assert_eq!(response.status(), StatusCode::Ok);
+
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn serves_precompressed_sibling_when_accepted() {
+        let root = fixture();
+        fs::write(root.join("style.css.gz"), b"gzipped").unwrap();
+        fs::write(root.join("style.css.br"), b"brotli").unwrap();
+
+        let handler = DirHandler::new(&root).with_precompressed(true);
+        let test_server = TestServer::new(router(handler)).unwrap();
+
+        let request = |accepted: Vec<QualityItem<Encoding>>| {
+            let response = test_server
+                .client()
+                .get("http://localhost/static/style.css")
+                .with_header(AcceptEncoding(accepted))
+                .perform()
+                .unwrap();
+
+            assert_eq!(response.status(), StatusCode::Ok);
+            assert_eq!(
+                response.headers().get::<ContentType>(),
+                Some(&ContentType(mime::TEXT_CSS_UTF_8))
+            );
+            assert_eq!(
+                response.headers().get::<Vary>(),
+                Some(&Vary::Items(vec!["Accept-Encoding".parse().unwrap()]))
+            );
+
+            let encoding = response.headers().get::<ContentEncoding>().cloned();
+            (encoding, response.read_body().unwrap())
+        };
+
+        let (encoding, body) = request(vec![qitem(Encoding::Gzip)]);
+        assert_eq!(encoding, Some(ContentEncoding(vec![Encoding::Gzip])));
+        assert_eq!(body, b"gzipped");
+
+        let (encoding, body) = request(vec![qitem(Encoding::Gzip), qitem(Encoding::Brotli)]);
+        assert_eq!(encoding, Some(ContentEncoding(vec![Encoding::Brotli])));
+        assert_eq!(body, b"brotli");
+
+        let (encoding, body) = request(vec![
+            qitem(Encoding::Gzip),
+            QualityItem::new(Encoding::Brotli, q(0.5)),
+        ]);
+        assert_eq!(encoding, Some(ContentEncoding(vec![Encoding::Gzip])));
+        assert_eq!(body, b"gzipped");
+
+        let (encoding, body) = request(vec![qitem(Encoding::Deflate)]);
+        assert_eq!(encoding, None);
+        assert_eq!(body, b"h1 {}");
+
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn ignores_precompressed_sibling_unless_enabled() {
+        let root = fixture();
+        fs::write(root.join("style.css.gz"), b"gzipped").unwrap();
+
+        let test_server = TestServer::new(router(DirHandler::new(&root))).unwrap();
+        let response = test_server
+            .client()
+            .get("http://localhost/static/style.css")
+            .with_header(AcceptEncoding(vec![qitem(Encoding::Gzip)]))
+            .perform()
+            .unwrap();
+
+        assert!(response.headers().get::<ContentEncoding>().is_none());
+        assert!(response.headers().get::<Vary>().is_none());
+        assert_eq!(response.read_body().unwrap(), b"h1 {}");
+
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn serves_uncompressed_file_for_malformed_accept_encoding() {
+        let root = fixture();
+        fs::write(root.join("style.css.gz"), b"gzipped").unwrap();
+
+        let handler = DirHandler::new(&root).with_precompressed(true);
+        let test_server = TestServer::new(router(handler)).unwrap();
+
+        // A header which cannot be parsed is treated as accepting no encoding.
+        for accepted in &["gzip;q=banana", "gzip;q=2", "gzip;;q", "\"gzip\"", ",;=,"] {
+            let response = test_server
+                .client()
+                .get("http://localhost/static/style.css")
+                .with_raw_header("Accept-Encoding", *accepted)
+                .perform()
+                .unwrap();
+
+            assert_eq!(response.status(), StatusCode::Ok, "{}", accepted);
+            assert!(response.headers().get::<ContentEncoding>().is_none(), "{}", accepted);
+            assert_eq!(response.read_body().unwrap(), b"h1 {}");
+        }
+
+        fs::remove_dir_all(root).unwrap();
+    }
+}
@@ -0,0 +1,476 @@
+//! Defines a `Handler` which deserializes the `Request` body and serializes the response, in
+//! formats negotiated via `Content-Type` and `Accept`.
+
+use std::any::type_name;
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::marker::PhantomData;
+use std::panic::RefUnwindSafe;
+
+use futures::{future, Future};
+use hyper::StatusCode;
+use hyper::header::{Accept, ContentType, Headers};
+use mime::Mime;
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+
+use handler::{Handler, HandlerError, HandlerFuture, IntoHandlerError, NewHandler};
+use http::format::{negotiate, BodyFormat, FormatError};
+use http::request::body::BodyReader;
+use http::request::charset::{decode_to_utf8, UnsupportedCharset};
+use http::request::deserialize_error::DeserializeErrorMapper;
+use http::request::extractor_failure::{report_extractor_failure, ExtractorFailureReason};
+use http::response::create_response;
+use state::{FromState, State};
+
+/// A `Handler` which wraps a function taking a deserialized request body, and serializes the
+/// value it returns as the response body.
+///
+/// The request body is deserialized according to its `Content-Type`, which must match one of the
+/// enabled formats. Only the media type is compared, ignoring parameters such as `charset`, so
+/// `application/json; charset=utf-8` is read as JSON. The response is serialized in the format
+/// chosen from the enabled formats via the `Accept` header of the request. JSON is enabled by
+/// default, and further formats are enabled via `with_format`.
+///
+/// The request fails with:
+///
+/// * `415 Unsupported Media Type` when the `Content-Type` matches no enabled format, or declares
+///   a `charset` which cannot be transcoded;
+/// * `406 Not Acceptable` when the `Accept` header matches no enabled format, unless a fallback
+///   is set via `with_default_format`;
+/// * `400 Bad Request` when the body cannot be read or deserialized.
+///
+/// # Examples
+///
+/// ```rust
+/// # extern crate gotham;
+/// # extern crate hyper;
+/// # extern crate mime;
+/// # #[macro_use]
+/// # extern crate serde_derive;
+/// #
+/// # use hyper::header::{Accept, qitem};
+/// # use gotham::handler::BodyHandler;
+/// # use gotham::router::Router;
+/// # use gotham::router::builder::*;
+/// # use gotham::state::State;
+/// # use gotham::test::TestServer;
+/// #
+/// #[derive(Deserialize)]
+/// struct NewArticle {
+///     title: String,
+/// }
+///
+/// #[derive(Serialize)]
+/// struct Article {
+///     id: u64,
+///     title: String,
+/// }
+///
+/// fn create_article(state: State, article: NewArticle) -> (State, Article) {
+///     let article = Article {
+///         id: 1,
+///         title: article.title,
+///     };
+///     (state, article)
+/// }
+///
+/// fn router() -> Router {
+///     build_simple_router(|route| {
+///         route
+///             .post("/articles")
+///             .to_new_handler(BodyHandler::new(create_article));
+///     })
+/// }
+/// #
+/// # fn main() {
+/// #   let test_server = TestServer::new(router()).unwrap();
+/// #   let response = test_server
+/// #       .client()
+/// #       .post("http://localhost/articles", r#"{"title": "Gotham"}"#, mime::APPLICATION_JSON)
+/// #       .with_header(Accept(vec![qitem(mime::APPLICATION_JSON)]))
+/// #       .perform()
+/// #       .unwrap();
+/// #   assert_eq!(response.read_utf8_body().unwrap(), r#"{"id":1,"title":"Gotham"}"#);
+/// # }
+/// ```
+pub struct BodyHandler<F, T, R> {
+    f: F,
+    formats: Vec<BodyFormat>,
+    default_format: Option<BodyFormat>,
+    reader: BodyReader,
+    charset_transcoding: bool,
+    phantom: PhantomData<fn(T) -> R>,
+}
+
+impl<F, T, R> BodyHandler<F, T, R>
+where
+    F: Fn(State, T) -> (State, R),
+{
+    /// Creates a `BodyHandler` wrapping `f`, with only JSON enabled.
+    pub fn new(f: F) -> BodyHandler<F, T, R> {
+        BodyHandler {
+            f,
+            formats: vec![BodyFormat::Json],
+            default_format: None,
+            reader: BodyReader::default(),
+            charset_transcoding: false,
+            phantom: PhantomData,
+        }
+    }
+
+    /// Enables an additional format, for both the request and response bodies.
+    ///
+    /// When the request leaves the response format open (e.g. `Accept: */*`, or no `Accept`
+    /// header), the earliest enabled format is preferred.
+    pub fn with_format(self, format: BodyFormat) -> BodyHandler<F, T, R> {
+        let mut formats = self.formats;
+        if !formats.contains(&format) {
+            formats.push(format);
+        }
+
+        BodyHandler { formats, ..self }
+    }
+
+    /// Sets the format of the response when the `Accept` header of the request matches no enabled
+    /// format, in place of failing with `406 Not Acceptable`. Clients which send an overly narrow
+    /// `Accept` header then receive a representation they may still be able to use, as permitted
+    /// by RFC 7231.
+    ///
+    /// No fallback is used by default.
+    pub fn with_default_format(self, format: BodyFormat) -> BodyHandler<F, T, R> {
+        BodyHandler {
+            default_format: Some(format),
+            ..self
+        }
+    }
+
+    /// Sets the `BodyReader` used to buffer the body before it is deserialized.
+    pub fn with_body_reader(self, reader: BodyReader) -> BodyHandler<F, T, R> {
+        BodyHandler { reader, ..self }
+    }
+
+    /// Sets whether request bodies are transcoded to UTF-8 from the `charset` declared in their
+    /// `Content-Type`. Defaults to `false`.
+    pub fn with_charset_transcoding(self, charset_transcoding: bool) -> BodyHandler<F, T, R> {
+        BodyHandler {
+            charset_transcoding,
+            ..self
+        }
+    }
+}
+
+impl<F, T, R> Clone for BodyHandler<F, T, R>
+where
+    F: Clone,
+{
+    fn clone(&self) -> BodyHandler<F, T, R> {
+        BodyHandler {
+            f: self.f.clone(),
+            formats: self.formats.clone(),
+            default_format: self.default_format,
+            reader: self.reader,
+            charset_transcoding: self.charset_transcoding,
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<F, T, R> NewHandler for BodyHandler<F, T, R>
+where
+    F: Fn(State, T) -> (State, R) + Clone + Send + Sync + RefUnwindSafe + 'static,
+    T: DeserializeOwned + 'static,
+    R: Serialize + 'static,
+{
+    type Instance = BodyHandler<F, T, R>;
+
+    fn new_handler(&self) -> io::Result<Self::Instance> {
+        Ok(self.clone())
+    }
+}
+
+impl<F, T, R> Handler for BodyHandler<F, T, R>
+where
+    F: Fn(State, T) -> (State, R) + 'static,
+    T: DeserializeOwned + 'static,
+    R: Serialize + 'static,
+{
+    fn handle(self, mut state: State) -> Box<HandlerFuture> {
+        let (content_type, request_format, response_format) = match self.negotiate(&state) {
+            Ok(negotiated) => negotiated,
+            Err(e) => {
+                if let Some(reason) = e.extractor_failure_reason() {
+                    report_extractor_failure(&state, type_name::<T>(), reason);
+                }
+                return Box::new(future::err((state, e.into())));
+            }
+        };
+
+        let body = self.reader.read(&mut state);
+        let charset_transcoding = self.charset_transcoding;
+        let f = self.f;
+
+        Box::new(body.then(move |result| {
+            let value = result
+                .map_err(BodyError::Body)
+                .and_then(|body| {
+                    if charset_transcoding {
+                        decode_to_utf8(body, &content_type).map_err(BodyError::Charset)
+                    } else {
+                        Ok(body)
+                    }
+                })
+                .and_then(|body| request_format.deserialize(&body).map_err(BodyError::Parse));
+
+            if let Err(ref e) = value {
+                if let Some(reason) = e.extractor_failure_reason() {
+                    report_extractor_failure(&state, type_name::<T>(), reason);
+                }
+            }
+
+            let value = match value {
+                Ok(value) => value,
+                Err(BodyError::Parse(e)) => {
+                    let kind = e.kind();
+                    let error = HandlerError::from(BodyError::Parse(e));
+                    let error = match DeserializeErrorMapper::try_borrow_from(&state) {
+                        Some(mapper) => mapper.map(error, kind, &content_type),
+                        None => error,
+                    };
+                    return future::err((state, error));
+                }
+                Err(e) => return future::err((state, e.into())),
+            };
+
+            let (state, value) = f(state, value);
+
+            match response_format.serialize(&value) {
+                Ok(bytes) => {
+                    let body = Some((bytes, response_format.media_type()));
+                    let res = create_response(&state, StatusCode::Ok, body);
+                    future::ok((state, res))
+                }
+                Err(e) => future::err((state, BodyError::Serialize(e).into())),
+            }
+        }))
+    }
+}
+
+impl<F, T, R> BodyHandler<F, T, R> {
+    /// Chooses the request and response formats from the `Content-Type` and `Accept` headers,
+    /// returning them along with the `Content-Type`.
+    fn negotiate(&self, state: &State) -> Result<(Mime, BodyFormat, BodyFormat), BodyError> {
+        let headers = Headers::borrow_from(state);
+
+        let content_type = headers.get::<ContentType>().map(|ct| ct.0.clone());
+        let request_format = match content_type {
+            Some(ref ct) => self.formats.iter().find(|format| format.matches(ct)).cloned(),
+            None => None,
+        };
+        let (content_type, request_format) = match (content_type, request_format) {
+            (Some(ct), Some(format)) => (ct, format),
+            (content_type, _) => return Err(BodyError::UnsupportedMediaType(content_type)),
+        };
+
+        match negotiate(headers.get::<Accept>(), &self.formats).or(self.default_format) {
+            Some(response_format) => Ok((content_type, request_format, response_format)),
+            None => Err(BodyError::NotAcceptable),
+        }
+    }
+}
+
+/// The reasons a `BodyHandler` can fail, which are carried as the cause of the `HandlerError`.
+#[derive(Debug)]
+enum BodyError {
+    UnsupportedMediaType(Option<Mime>),
+    Charset(UnsupportedCharset),
+    NotAcceptable,
+    Body(::hyper::Error),
+    Parse(FormatError),
+    Serialize(FormatError),
+}
+
+impl BodyError {
+    fn status(&self) -> StatusCode {
+        match *self {
+            BodyError::UnsupportedMediaType(_) | BodyError::Charset(_) => {
+                StatusCode::UnsupportedMediaType
+            }
+            BodyError::NotAcceptable => StatusCode::NotAcceptable,
+            BodyError::Body(_) | BodyError::Parse(_) => StatusCode::BadRequest,
+            BodyError::Serialize(_) => StatusCode::InternalServerError,
+        }
+    }
+
+    /// The reason reported to an `ExtractorFailureHook` for this error, or `None` if the request
+    /// body was extracted successfully.
+    fn extractor_failure_reason(&self) -> Option<ExtractorFailureReason> {
+        match *self {
+            BodyError::UnsupportedMediaType(None) => Some(ExtractorFailureReason::Missing),
+            BodyError::UnsupportedMediaType(Some(_)) | BodyError::Charset(_) => {
+                Some(ExtractorFailureReason::Unsupported)
+            }
+            BodyError::Body(ref e) => Some(ExtractorFailureReason::of_body(e)),
+            BodyError::Parse(_) => Some(ExtractorFailureReason::Parse),
+            BodyError::NotAcceptable | BodyError::Serialize(_) => None,
+        }
+    }
+}
+
+impl From<BodyError> for HandlerError {
+    fn from(e: BodyError) -> HandlerError {
+        let status = e.status();
+        e.into_handler_error().with_status(status)
+    }
+}
+
+impl fmt::Display for BodyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            BodyError::UnsupportedMediaType(Some(ref ct)) => {
+                write!(f, "unsupported media type: {}", ct)
+            }
+            BodyError::UnsupportedMediaType(None) => f.write_str("missing content type"),
+            BodyError::Charset(ref e) => e.fmt(f),
+            BodyError::NotAcceptable => f.write_str("no acceptable response format"),
+            BodyError::Body(ref e) => write!(f, "unable to read body: {}", e),
+            BodyError::Parse(ref e) => write!(f, "invalid body: {}", e),
+            BodyError::Serialize(ref e) => write!(f, "unable to serialize response: {}", e),
+        }
+    }
+}
+
+impl Error for BodyError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match *self {
+            BodyError::UnsupportedMediaType(_) | BodyError::NotAcceptable => None,
+            BodyError::Charset(ref e) => Some(e),
+            BodyError::Body(ref e) => Some(e),
+            BodyError::Parse(ref e) | BodyError::Serialize(ref e) => Some(e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use hyper::header::qitem;
+    use mime;
+
+    use router::Router;
+    use router::builder::*;
+    use test::TestServer;
+
+    #[derive(Deserialize)]
+    struct NewArticle {
+        title: String,
+    }
+
+    #[derive(Serialize)]
+    struct Article {
+        id: u64,
+        title: String,
+    }
+
+    fn create_article(state: State, article: NewArticle) -> (State, Article) {
+        let article = Article {
+            id: 1,
+            title: article.title,
+        };
+        (state, article)
+    }
+
+    fn router(formats: &[BodyFormat]) -> Router {
+        let handler = formats
+            .iter()
+            .fold(BodyHandler::new(create_article), |handler, format| {
+                handler.with_format(*format)
+            });
+
+        build_simple_router(|route| {
+            route.post("/articles").to_new_handler(handler);
+        })
+    }
+
+    fn post(router: Router, content_type: Mime, accept: Mime) -> (StatusCode, Option<Mime>, String) {
+        let test_server = TestServer::new(router).unwrap();
+        let response = test_server
+            .client()
+            .post(
+                "http://localhost/articles",
+                r#"{"title": "Gotham"}"#,
+                content_type,
+            )
+            .with_header(Accept(vec![qitem(accept)]))
+            .perform()
+            .unwrap();
+
+        let status = response.status();
+        let content_type = response.headers().get::<ContentType>().map(|ct| ct.0.clone());
+        (status, content_type, response.read_utf8_body().unwrap())
+    }
+
+    #[test]
+    fn responds_with_json() {
+        let (status, content_type, body) =
+            post(router(&[]), mime::APPLICATION_JSON, mime::APPLICATION_JSON);
+        assert_eq!(status, StatusCode::Ok);
+        assert_eq!(content_type, Some(mime::APPLICATION_JSON));
+        assert_eq!(body, r#"{"id":1,"title":"Gotham"}"#);
+    }
+
+    #[test]
+    fn parses_json_with_media_type_parameters() {
+        let content_type = "application/json; charset=utf-8".parse().unwrap();
+        let (status, _, body) = post(router(&[]), content_type, mime::APPLICATION_JSON);
+        assert_eq!(status, StatusCode::Ok);
+        assert_eq!(body, r#"{"id":1,"title":"Gotham"}"#);
+    }
+
+    #[test]
+    fn rejects_unacceptable_response_format() {
+        let (status, _, _) = post(router(&[]), mime::APPLICATION_JSON, mime::TEXT_HTML);
+        assert_eq!(status, StatusCode::NotAcceptable);
+    }
+
+    #[test]
+    fn falls_back_to_default_format_when_configured() {
+        let xml: Mime = "application/xml".parse().unwrap();
+
+        let (status, _, _) = post(router(&[]), mime::APPLICATION_JSON, xml.clone());
+        assert_eq!(status, StatusCode::NotAcceptable);
+
+        let handler = BodyHandler::new(create_article).with_default_format(BodyFormat::Json);
+        let router = build_simple_router(|route| {
+            route.post("/articles").to_new_handler(handler);
+        });
+
+        let (status, content_type, body) = post(router, mime::APPLICATION_JSON, xml);
+        assert_eq!(status, StatusCode::Ok);
+        assert_eq!(content_type, Some(mime::APPLICATION_JSON));
+        assert_eq!(body, r#"{"id":1,"title":"Gotham"}"#);
+    }
+
+    #[test]
+    fn rejects_unsupported_request_format() {
+        let (status, _, _) = post(router(&[]), mime::TEXT_PLAIN, mime::APPLICATION_JSON);
+        assert_eq!(status, StatusCode::UnsupportedMediaType);
+    }
+
+    #[cfg(feature = "xml")]
+    #[test]
+    fn parses_json_and_responds_with_xml() {
+        let xml: Mime = "application/xml".parse().unwrap();
+        let (status, content_type, body) = post(
+            router(&[BodyFormat::Xml]),
+            mime::APPLICATION_JSON,
+            xml.clone(),
+        );
+        assert_eq!(status, StatusCode::Ok);
+        assert_eq!(content_type, Some(xml));
+        assert!(body.contains("<title>Gotham</title>"));
+        assert!(body.contains("<id>1</id>"));
+    }
+}
@@ -0,0 +1,194 @@
+//! Defines a `Handler` which answers conditional `GET` requests for dynamically produced content.
+
+use std::io;
+use std::panic::RefUnwindSafe;
+use std::sync::Arc;
+
+use futures::future;
+use hyper::{Method, StatusCode};
+use hyper::header::{ETag, EntityTag, Headers, IfNoneMatch};
+use mime::Mime;
+
+use handler::{Handler, HandlerError, HandlerFuture, NewHandler};
+use http::response::create_response;
+use state::{request_id, FromState, State};
+
+/// A `Handler` which produces a response body using a closure, and tags it with an `ETag`
+/// computed from the body.
+///
+/// When a `GET` or `HEAD` request carries an `If-None-Match` header matching the computed tag,
+/// the body is discarded and `304 Not Modified` is sent instead, so the client reuses its cached
+/// copy. The closure is always run, as the tag is only known once the body has been produced,
+/// so this saves bandwidth rather than the work of producing the body.
+///
+/// The tag is a hash of the body, and so is stable across restarts of the application, and
+/// across the servers behind a load balancer.
+///
+/// # Examples
+///
+/// ```rust
+/// # extern crate gotham;
+/// # extern crate hyper;
+/// # extern crate mime;
+/// #
+/// # use hyper::StatusCode;
+/// # use hyper::header::{ETag, IfNoneMatch};
+/// # use gotham::handler::{ConditionalGetHandler, HandlerError};
+/// # use gotham::router::Router;
+/// # use gotham::router::builder::*;
+/// # use gotham::state::State;
+/// # use gotham::test::TestServer;
+/// #
+/// fn report(_state: &mut State) -> Result<(Vec<u8>, mime::Mime), HandlerError> {
+///     Ok((b"{\"total\":42}".to_vec(), mime::APPLICATION_JSON))
+/// }
+///
+/// fn router() -> Router {
+///     build_simple_router(|route| {
+///         route.get("/report").to_new_handler(ConditionalGetHandler::new(report));
+///     })
+/// }
+/// #
+/// # fn main() {
+/// #   let test_server = TestServer::new(router()).unwrap();
+/// #   let response = test_server.client().get("http://localhost/report").perform().unwrap();
+/// #   let etag = response.headers().get::<ETag>().unwrap().0.clone();
+/// #
+/// #   let response = test_server
+/// #       .client()
+/// #       .get("http://localhost/report")
+/// #       .with_header(IfNoneMatch::Items(vec![etag]))
+/// #       .perform()
+/// #       .unwrap();
+/// #   assert_eq!(response.status(), StatusCode::NotModified);
+/// # }
+/// ```
+pub struct ConditionalGetHandler<F> {
+    f: Arc<F>,
+}
+
+impl<F> ConditionalGetHandler<F>
+where
+    F: Fn(&mut State) -> Result<(Vec<u8>, Mime), HandlerError>,
+{
+    /// Creates a `ConditionalGetHandler` which produces the response body using `f`.
+    pub fn new(f: F) -> ConditionalGetHandler<F> {
+        ConditionalGetHandler { f: Arc::new(f) }
+    }
+}
+
+impl<F> Clone for ConditionalGetHandler<F> {
+    fn clone(&self) -> ConditionalGetHandler<F> {
+        ConditionalGetHandler { f: self.f.clone() }
+    }
+}
+
+impl<F> NewHandler for ConditionalGetHandler<F>
+where
+    F: Fn(&mut State) -> Result<(Vec<u8>, Mime), HandlerError> + Send + Sync + RefUnwindSafe,
+{
+    type Instance = ConditionalGetHandler<F>;
+
+    fn new_handler(&self) -> io::Result<Self::Instance> {
+        Ok(self.clone())
+    }
+}
+
+impl<F> Handler for ConditionalGetHandler<F>
+where
+    F: Fn(&mut State) -> Result<(Vec<u8>, Mime), HandlerError>,
+{
+    fn handle(self, mut state: State) -> Box<HandlerFuture> {
+        let (body, mime) = match (self.f)(&mut state) {
+            Ok(content) => content,
+            Err(e) => return Box::new(future::err((state, e))),
+        };
+
+        let etag = entity_tag(&body);
+
+        let mut res = if is_not_modified(&state, &etag) {
+            trace!("[{}] content not modified", request_id(&state));
+            create_response(&state, StatusCode::NotModified, None)
+        } else {
+            create_response(&state, StatusCode::Ok, Some((body, mime)))
+        };
+
+        res.headers_mut().set(ETag(etag));
+        Box::new(future::ok((state, res)))
+    }
+}
+
+/// Returns `true` if the request is a `GET` or `HEAD` whose `If-None-Match` header matches `etag`.
+fn is_not_modified(state: &State, etag: &EntityTag) -> bool {
+    match *Method::borrow_from(state) {
+        Method::Get | Method::Head => (),
+        _ => return false,
+    }
+
+    match Headers::borrow_from(state).get::<IfNoneMatch>() {
+        Some(IfNoneMatch::Any) => true,
+        Some(IfNoneMatch::Items(tags)) => tags.iter().any(|tag| tag.weak_eq(etag)),
+        None => false,
+    }
+}
+
+/// Computes a strong `EntityTag` from the 64-bit FNV-1a hash of `body`.
+fn entity_tag(body: &[u8]) -> EntityTag {
+    let hash = body.iter().fold(0xcbf2_9ce4_8422_2325u64, |hash, &byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    });
+
+    EntityTag::strong(format!("{:016x}", hash))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use mime;
+
+    use test::TestServer;
+
+    fn content(_state: &mut State) -> Result<(Vec<u8>, Mime), HandlerError> {
+        Ok((b"dynamic content".to_vec(), mime::TEXT_PLAIN))
+    }
+
+    #[test]
+    fn responds_not_modified_when_etag_matches() {
+        let test_server = TestServer::new(ConditionalGetHandler::new(content)).unwrap();
+
+        let response = test_server
+            .client()
+            .get("http://localhost/")
+            .perform()
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::Ok);
+        let etag = response.headers().get::<ETag>().unwrap().0.clone();
+        assert_eq!(response.read_body().unwrap(), b"dynamic content");
+
+        let response = test_server
+            .client()
+            .get("http://localhost/")
+            .with_header(IfNoneMatch::Items(vec![etag.clone()]))
+            .perform()
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NotModified);
+        assert_eq!(response.headers().get::<ETag>(), Some(&ETag(etag)));
+        assert!(response.read_body().unwrap().is_empty());
+
+        let response = test_server
+            .client()
+            .get("http://localhost/")
+            .with_header(IfNoneMatch::Items(vec![EntityTag::strong("stale".to_owned())]))
+            .perform()
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::Ok);
+    }
+
+    #[test]
+    fn entity_tag_depends_on_body() {
+        assert_eq!(entity_tag(b"a"), entity_tag(b"a"));
+        assert_ne!(entity_tag(b"a"), entity_tag(b"b"));
+        assert_eq!(entity_tag(b"").tag(), "cbf29ce484222325");
+    }
+}
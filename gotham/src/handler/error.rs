@@ -2,9 +2,12 @@ use std::error::Error;
 use std::fmt::{self, Debug, Display, Formatter};
 
 use hyper::{Response, StatusCode};
+use mime::{self, Mime};
+use serde_json::{self, Map, Value};
 
 use handler::IntoResponse;
-use state::{request_id, State};
+use state::{request_id, FromState, State, StateData};
+use http::request::field_errors::FieldErrors;
 use http::response::create_response;
 
 /// Describes an error which occurred during handler execution, and allows the creation of a HTTP
@@ -12,8 +15,34 @@ use http::response::create_response;
 pub struct HandlerError {
     status_code: StatusCode,
     cause: Box<Error>,
+    problem: Option<Box<Problem>>,
 }
 
+/// The members of an `application/problem+json` body which are set explicitly.
+#[derive(Default)]
+struct Problem {
+    problem_type: Option<String>,
+    title: Option<String>,
+    detail: Option<String>,
+}
+
+/// The format of the body of responses generated from a `HandlerError`, configured for the whole
+/// application via `ServerConfig::with_error_format`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ErrorFormat {
+    /// Responses carry no body, unless the error was caused by `FieldErrors`, in which case the
+    /// body is `application/json` listing them. This is the default.
+    #[default]
+    Empty,
+
+    /// Responses carry an `application/problem+json` body, as described in [RFC
+    /// 7807](https://tools.ietf.org/html/rfc7807). The `instance` member is the request id, and
+    /// an `errors` member lists any `FieldErrors` which caused the error.
+    ProblemJson,
+}
+
+impl StateData for ErrorFormat {}
+
 /// Allows conversion into a HandlerError from an implementing type.
 ///
 /// Futures returned from handlers can resolve to an error type with a value of `(State,
@@ -54,6 +83,7 @@ where
         HandlerError {
             status_code: StatusCode::InternalServerError,
             cause: Box::new(self),
+            problem: None,
         }
     }
 }
@@ -114,6 +144,83 @@ impl HandlerError {
             ..self
         }
     }
+
+    /// Sets the `type` member of the `application/problem+json` body, a URI identifying the
+    /// type of problem. Defaults to `about:blank`.
+    pub fn with_problem_type<S: Into<String>>(mut self, problem_type: S) -> HandlerError {
+        self.problem().problem_type = Some(problem_type.into());
+        self
+    }
+
+    /// Sets the `title` member of the `application/problem+json` body, a short summary of the
+    /// type of problem. Defaults to the canonical reason of the status code.
+    pub fn with_title<S: Into<String>>(mut self, title: S) -> HandlerError {
+        self.problem().title = Some(title.into());
+        self
+    }
+
+    /// Sets the `detail` member of the `application/problem+json` body, an explanation specific
+    /// to this occurrence of the problem. Omitted by default, as the cause of the error may not
+    /// be suitable to show to clients.
+    pub fn with_detail<S: Into<String>>(mut self, detail: S) -> HandlerError {
+        self.problem().detail = Some(detail.into());
+        self
+    }
+
+    /// Returns the HTTP status code which will be used when rendering this error.
+    pub fn status(&self) -> StatusCode {
+        self.status_code
+    }
+
+    fn problem(&mut self) -> &mut Problem {
+        self.problem.get_or_insert_with(Default::default)
+    }
+
+    /// Finds the `FieldErrors` which caused this error, if any, so that they can be included in
+    /// the body.
+    fn field_errors(&self) -> Option<&FieldErrors> {
+        let mut cause: Option<&(dyn Error + 'static)> = Some(&*self.cause);
+        while let Some(e) = cause {
+            if let Some(errors) = e.downcast_ref::<FieldErrors>() {
+                return Some(errors);
+            }
+            cause = e.source();
+        }
+        None
+    }
+
+    /// Renders the `application/problem+json` body for this error.
+    fn problem_body(&self, state: &State) -> Vec<u8> {
+        let problem = match self.problem {
+            Some(ref problem) => &**problem,
+            None => &Problem::default(),
+        };
+
+        let title = match problem.title {
+            Some(ref title) => title.clone(),
+            None => self.status_code
+                .canonical_reason()
+                .unwrap_or("(unregistered)")
+                .to_owned(),
+        };
+
+        let mut body = Map::new();
+        body.insert(
+            "type".to_owned(),
+            Value::from(problem.problem_type.as_deref().unwrap_or("about:blank")),
+        );
+        body.insert("title".to_owned(), Value::from(title));
+        body.insert("status".to_owned(), Value::from(self.status_code.as_u16()));
+        if let Some(ref detail) = problem.detail {
+            body.insert("detail".to_owned(), Value::from(detail.as_str()));
+        }
+        body.insert("instance".to_owned(), Value::from(request_id(state)));
+        if let Some(errors) = self.field_errors() {
+            body.insert("errors".to_owned(), errors.to_json());
+        }
+
+        serde_json::to_vec(&Value::Object(body)).expect("problem details are serializable")
+    }
 }
 
 impl IntoResponse for HandlerError {
@@ -127,6 +234,99 @@ impl IntoResponse for HandlerError {
                 .unwrap_or("(unregistered)",)
         );
 
-        create_response(state, self.status_code, None)
+        match ErrorFormat::try_borrow_from(state) {
+            Some(&ErrorFormat::ProblemJson) => {
+                let mime: Mime = "application/problem+json".parse().unwrap();
+                let body = self.problem_body(state);
+                create_response(state, self.status_code, Some((body, mime)))
+            }
+            _ => match self.field_errors() {
+                Some(errors) => {
+                    let mut body = Map::new();
+                    body.insert("errors".to_owned(), errors.to_json());
+                    let body = serde_json::to_vec(&Value::Object(body))
+                        .expect("field errors are serializable");
+                    create_response(state, self.status_code, Some((body, mime::APPLICATION_JSON)))
+                }
+                None => create_response(state, self.status_code, None),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::io;
+
+    use hyper::header::{ContentType, Headers};
+
+    use config::ServerConfig;
+    use http::header::XRequestId;
+    use state::request_id::set_request_id;
+    use test::TestServer;
+
+    fn handler(state: State) -> (State, HandlerError) {
+        let e = io::Error::other("database unavailable")
+            .into_handler_error()
+            .with_status(StatusCode::ServiceUnavailable)
+            .with_problem_type("https://example.com/problems/unavailable")
+            .with_detail("Try again later");
+        (state, e)
+    }
+
+    fn get(config: ServerConfig) -> (StatusCode, Option<Mime>, Vec<u8>) {
+        let test_server = TestServer::with_config(|| Ok(handler), config).unwrap();
+        let response = test_server
+            .client()
+            .get("http://localhost/")
+            .with_header(XRequestId("abc123".to_owned()))
+            .perform()
+            .unwrap();
+
+        let status = response.status();
+        let content_type = response.headers().get::<ContentType>().map(|ct| ct.0.clone());
+        (status, content_type, response.read_body().unwrap())
+    }
+
+    #[test]
+    fn renders_empty_body_by_default() {
+        let (status, content_type, body) = get(ServerConfig::default());
+        assert_eq!(status, StatusCode::ServiceUnavailable);
+        assert_eq!(content_type, None);
+        assert!(body.is_empty());
+    }
+
+    #[test]
+    fn renders_problem_json() {
+        let config = ServerConfig::default().with_error_format(ErrorFormat::ProblemJson);
+        let (status, content_type, body) = get(config);
+        assert_eq!(status, StatusCode::ServiceUnavailable);
+        assert_eq!(
+            content_type.as_ref().map(|ct| ct.as_ref()),
+            Some("application/problem+json")
+        );
+
+        let problem: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(problem["type"], "https://example.com/problems/unavailable");
+        assert_eq!(problem["title"], "Service Unavailable");
+        assert_eq!(problem["status"], 503);
+        assert_eq!(problem["detail"], "Try again later");
+        assert_eq!(problem["instance"], "abc123");
+    }
+
+    #[test]
+    fn omits_detail_unless_set() {
+        let mut state = State::new();
+        state.put(Headers::new());
+        state.put(ErrorFormat::ProblemJson);
+        set_request_id(&mut state);
+
+        let e = io::Error::other("secret").into_handler_error();
+        let problem: Value = serde_json::from_slice(&e.problem_body(&state)).unwrap();
+        assert_eq!(problem["type"], "about:blank");
+        assert_eq!(problem["title"], "Internal Server Error");
+        assert!(problem.get("detail").is_none());
     }
 }
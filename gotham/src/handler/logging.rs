@@ -0,0 +1,191 @@
+//! Defines a `Handler` which controls how the response to a route is written to the request log.
+
+use std::io;
+
+use log::LogLevel;
+
+use handler::{Handler, HandlerFuture, NewHandler};
+use state::{FromState, State, StateData};
+
+/// How the `[RESPONSE]` line written by Gotham once a `Handler` has responded is logged.
+///
+/// Each response is logged at `LogLevel::Info` by default. Errors are always logged at
+/// `LogLevel::Error`, regardless of this setting, as they are rarely noise.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RequestLogging {
+    /// The response is logged at the given level.
+    Level(LogLevel),
+
+    /// The response is not logged.
+    Off,
+}
+
+impl StateData for RequestLogging {}
+
+impl Default for RequestLogging {
+    fn default() -> RequestLogging {
+        RequestLogging::Level(LogLevel::Info)
+    }
+}
+
+/// The level at which to log the response to the `Request` in `state`, if at all.
+pub(crate) fn response_log_level(state: &State) -> Option<LogLevel> {
+    match RequestLogging::try_borrow_from(state).cloned().unwrap_or_default() {
+        RequestLogging::Level(level) => Some(level),
+        RequestLogging::Off => None,
+    }
+}
+
+/// Configures and creates `RequestLoggingHandler` values, which wrap the `Handler` created by
+/// another `NewHandler` and log its responses as configured by `logging`.
+///
+/// This allows noisy routes, such as health checks polled by a load balancer, to be logged at a
+/// lower level or silenced entirely, without affecting the logging of other routes.
+///
+/// # Examples
+///
+/// ```rust
+/// # extern crate gotham;
+/// # extern crate hyper;
+/// # extern crate log;
+/// #
+/// # use hyper::{Response, StatusCode};
+/// # use log::LogLevel;
+/// # use gotham::handler::{NewRequestLoggingHandler, RequestLogging};
+/// # use gotham::router::Router;
+/// # use gotham::router::builder::*;
+/// # use gotham::state::State;
+/// # use gotham::test::TestServer;
+/// #
+/// fn health(state: State) -> (State, Response) {
+///     (state, Response::new().with_status(StatusCode::Ok))
+/// }
+///
+/// fn router() -> Router {
+///     build_simple_router(|route| {
+///         route
+///             .get("/health")
+///             .to_new_handler(NewRequestLoggingHandler::new(|| Ok(health), RequestLogging::Off));
+///
+///         route.get("/ready").to_new_handler(NewRequestLoggingHandler::new(
+///             || Ok(health),
+///             RequestLogging::Level(LogLevel::Debug),
+///         ));
+///     })
+/// }
+/// #
+/// # fn main() {
+/// #   let test_server = TestServer::new(router()).unwrap();
+/// #   let response = test_server.client().get("http://localhost/health").perform().unwrap();
+/// #   assert_eq!(response.status(), StatusCode::Ok);
+/// # }
+/// ```
+pub struct NewRequestLoggingHandler<NH> {
+    new_handler: NH,
+    logging: RequestLogging,
+}
+
+/// Handles a single request on behalf of a wrapped `Handler`, setting how its response is
+/// logged.
+///
+/// See `NewRequestLoggingHandler` for usage details.
+pub struct RequestLoggingHandler<H> {
+    handler: H,
+    logging: RequestLogging,
+}
+
+impl<NH> NewRequestLoggingHandler<NH>
+where
+    NH: NewHandler,
+{
+    /// Creates a `NewRequestLoggingHandler` which logs the responses of the handler created by
+    /// `new_handler` as configured by `logging`.
+    pub fn new(new_handler: NH, logging: RequestLogging) -> NewRequestLoggingHandler<NH> {
+        NewRequestLoggingHandler {
+            new_handler,
+            logging,
+        }
+    }
+}
+
+impl<NH> NewHandler for NewRequestLoggingHandler<NH>
+where
+    NH: NewHandler,
+{
+    type Instance = RequestLoggingHandler<NH::Instance>;
+
+    fn new_handler(&self) -> io::Result<Self::Instance> {
+        Ok(RequestLoggingHandler {
+            handler: self.new_handler.new_handler()?,
+            logging: self.logging,
+        })
+    }
+}
+
+impl<H> Handler for RequestLoggingHandler<H>
+where
+    H: Handler,
+{
+    fn handle(self, mut state: State) -> Box<HandlerFuture> {
+        state.put(self.logging);
+        self.handler.handle(state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use hyper::{Response, StatusCode};
+
+    use http::header::XRequestId;
+    use router::Router;
+    use router::builder::*;
+    use test::TestServer;
+    use test::logged;
+
+    fn ok(state: State) -> (State, Response) {
+        (state, Response::new().with_status(StatusCode::Ok))
+    }
+
+    fn router() -> Router {
+        build_simple_router(|route| {
+            route
+                .get("/health")
+                .to_new_handler(NewRequestLoggingHandler::new(|| Ok(ok), RequestLogging::Off));
+
+            route.get("/ready").to_new_handler(NewRequestLoggingHandler::new(
+                || Ok(ok),
+                RequestLogging::Level(LogLevel::Debug),
+            ));
+
+            route.get("/status").to(ok);
+        })
+    }
+
+    #[test]
+    fn logs_responses_as_configured_for_route() {
+        logged::capture();
+
+        let test_server = TestServer::new(router()).unwrap();
+        for path in &["health", "ready", "status"] {
+            let response = test_server
+                .client()
+                .get(&format!("http://localhost/{}", path))
+                .with_header(XRequestId(format!("logging-{}", path)))
+                .perform()
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::Ok);
+        }
+
+        let level = |path: &str| {
+            let prefix = format!("[RESPONSE][logging-{}]", path);
+            let lines = logged::lines_starting_with(&prefix);
+            lines.into_iter().map(|(level, _)| level).collect::<Vec<_>>()
+        };
+
+        assert!(level("health").is_empty());
+        assert_eq!(level("ready"), vec![LogLevel::Debug]);
+        assert_eq!(level("status"), vec![LogLevel::Info]);
+    }
+}
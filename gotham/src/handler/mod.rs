@@ -7,14 +7,28 @@
 use std::io;
 use std::panic::RefUnwindSafe;
 
-use hyper::Response;
+use hyper::{Response, StatusCode};
 use futures::{future, Future};
+use mime;
 
+use http::response::create_response;
 use state::State;
 
+mod assets;
+mod body;
+mod conditional;
 mod error;
+pub(crate) mod logging;
+mod size_limit;
+mod timeout;
 
-pub use self::error::{HandlerError, IntoHandlerError};
+pub use self::assets::{DirHandler, FilePathExtractor};
+pub use self::body::BodyHandler;
+pub use self::conditional::ConditionalGetHandler;
+pub use self::error::{ErrorFormat, HandlerError, IntoHandlerError};
+pub use self::logging::{NewRequestLoggingHandler, RequestLogging, RequestLoggingHandler};
+pub use self::size_limit::{NewSizeLimitHandler, SizeLimitHandler};
+pub use self::timeout::{NewTimeoutHandler, TimeoutHandler};
 
 /// A type alias for the trait objects returned by `HandlerService`.
 ///
@@ -68,12 +82,14 @@ pub trait IntoHandlerFuture {
 }
 
 impl<T> IntoHandlerFuture for (State, T)
-    where T: IntoResponse
+    where T: Responder
 {
     fn into_handler_future(self) -> Box<HandlerFuture> {
-        let (state, t) = self;
-        let response = t.into_response(&state);
-        Box::new(future::ok((state, response)))
+        let (mut state, t) = self;
+        match t.respond(&mut state) {
+            Ok(response) => Box::new(future::ok((state, response))),
+            Err(e) => Box::new(future::err((state, e))),
+        }
     }
 }
 
@@ -86,8 +102,10 @@ impl IntoHandlerFuture for Box<HandlerFuture> {
 /// Represents a type which can be converted to a response. This trait is used in converting the
 /// return type of a function into a response.
 ///
-/// The only default implementation is the noop which converts a `hyper::Response` by
-/// returning the value unmodified.
+/// A `hyper::Response` is converted by returning the value unmodified. Owned bodies are sent with
+/// `200 OK`: a `String` as `text/plain; charset=utf-8`, and a `Vec<u8>` as
+/// `application/octet-stream`. In both cases the `Content-Length` header is the length of the body
+/// in bytes, so a `String` containing multibyte characters is framed correctly.
 ///
 /// # Examples
 ///
@@ -169,6 +187,88 @@ impl<T, E> IntoResponse for ::std::result::Result<T, E>
     }
 }
 
+impl IntoResponse for String {
+    fn into_response(self, state: &State) -> Response {
+        let body = (self.into_bytes(), mime::TEXT_PLAIN_UTF_8);
+        create_response(state, StatusCode::Ok, Some(body))
+    }
+}
+
+impl IntoResponse for Vec<u8> {
+    fn into_response(self, state: &State) -> Response {
+        let body = (self, mime::APPLICATION_OCTET_STREAM);
+        create_response(state, StatusCode::Ok, Some(body))
+    }
+}
+
+/// Represents a type which knows how to render itself as a response, with full access to the
+/// `State` of the current request.
+///
+/// `Responder` is intended for library authors who want to provide reusable response types, such
+/// as a type which negotiates its representation from the `Accept` header. Unlike `IntoResponse`,
+/// a `Responder` may mutate `State` (e.g. to take the request body or record data for
+/// middleware), and may fail with a `HandlerError`, which is rendered in the usual way.
+///
+/// Every `IntoResponse` type is also a `Responder`, so handlers may return either.
+///
+/// # Examples
+///
+/// ```rust
+/// # extern crate gotham;
+/// # extern crate hyper;
+/// # extern crate mime;
+/// #
+/// # use std::io;
+/// # use gotham::state::{FromState, State};
+/// # use gotham::handler::{HandlerError, IntoHandlerError, Responder};
+/// # use gotham::http::response::create_response;
+/// # use gotham::test::TestServer;
+/// # use hyper::{Response, StatusCode, Uri};
+/// #
+/// struct Greeting {
+///     name: Option<String>,
+/// }
+///
+/// impl Responder for Greeting {
+///     fn respond(self, state: &mut State) -> Result<Response, HandlerError> {
+///         match self.name {
+///             Some(name) => {
+///                 let body = format!("Hello, {}, from {}", name, Uri::borrow_from(state).path());
+///                 let body = Some((body.into_bytes(), mime::TEXT_PLAIN));
+///                 Ok(create_response(state, StatusCode::Ok, body))
+///             }
+///             None => {
+///                 let e = io::Error::new(io::ErrorKind::InvalidInput, "no name");
+///                 Err(e.into_handler_error().with_status(StatusCode::BadRequest))
+///             }
+///         }
+///     }
+/// }
+///
+/// fn handler(state: State) -> (State, Greeting) {
+///     let name = Some(String::from("world"));
+///     (state, Greeting { name })
+/// }
+///
+/// # fn main() {
+/// #   let test_server = TestServer::new(|| Ok(handler)).unwrap();
+/// #   let response = test_server.client().get("http://localhost/greet").perform().unwrap();
+/// #   assert_eq!(response.read_utf8_body().unwrap(), "Hello, world, from /greet");
+/// # }
+/// ```
+pub trait Responder {
+    /// Renders this value as a `hyper::Response`, or fails with a `HandlerError`.
+    fn respond(self, state: &mut State) -> Result<Response, HandlerError>;
+}
+
+impl<T> Responder for T
+    where T: IntoResponse
+{
+    fn respond(self, state: &mut State) -> Result<Response, HandlerError> {
+        Ok(self.into_response(state))
+    }
+}
+
 impl<F, R> Handler for F
     where F: FnOnce(State) -> R,
           R: IntoHandlerFuture
@@ -177,3 +277,104 @@ impl<F, R> Handler for F
         self(state).into_handler_future()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::io;
+
+    use hyper::header::{ContentLength, ContentType};
+
+    use state::StateData;
+    use test::TestServer;
+
+    struct Counted(u32);
+
+    struct Count(u32);
+
+    impl StateData for Count {}
+
+    impl Responder for Counted {
+        fn respond(self, state: &mut State) -> Result<Response, HandlerError> {
+            if self.0 == 0 {
+                let e = io::Error::other("nothing to count");
+                return Err(e.into_handler_error().with_status(StatusCode::NotFound));
+            }
+
+            state.put(Count(self.0));
+            Ok(Response::new()
+                .with_status(StatusCode::Ok)
+                .with_body(format!("{}", state.borrow::<Count>().0)))
+        }
+    }
+
+    #[test]
+    fn responder_renders_response() {
+        let test_server = TestServer::new(|| Ok(|state| (state, Counted(3)))).unwrap();
+        let response = test_server
+            .client()
+            .get("http://localhost/")
+            .perform()
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::Ok);
+        assert_eq!(response.read_utf8_body().unwrap(), "3");
+    }
+
+    #[test]
+    fn responder_renders_handler_error() {
+        let test_server = TestServer::new(|| Ok(|state| (state, Counted(0)))).unwrap();
+        let response = test_server
+            .client()
+            .get("http://localhost/")
+            .perform()
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::NotFound);
+    }
+
+    #[test]
+    fn string_response_has_byte_length() {
+        let body = "h\u{e9}llo w\u{f6}rld \u{1f30d}";
+        assert_eq!(body.chars().count(), 13);
+        assert_eq!(body.len(), 18);
+
+        let handler = move |state| (state, body.to_owned());
+        let test_server = TestServer::new(move || Ok(handler)).unwrap();
+        let response = test_server
+            .client()
+            .get("http://localhost/")
+            .perform()
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::Ok);
+        assert_eq!(
+            response.headers().get::<ContentLength>(),
+            Some(&ContentLength(body.len() as u64))
+        );
+        assert_eq!(
+            response.headers().get::<ContentType>(),
+            Some(&ContentType(mime::TEXT_PLAIN_UTF_8))
+        );
+        assert_eq!(response.read_utf8_body().unwrap(), body);
+    }
+
+    #[test]
+    fn bytes_response_has_length() {
+        let handler = |state| (state, vec![0u8, 159, 146, 150]);
+        let test_server = TestServer::new(move || Ok(handler)).unwrap();
+        let response = test_server
+            .client()
+            .get("http://localhost/")
+            .perform()
+            .unwrap();
+
+        assert_eq!(response.headers().get::<ContentLength>(), Some(&ContentLength(4)));
+        assert_eq!(
+            response.headers().get::<ContentType>(),
+            Some(&ContentType(mime::APPLICATION_OCTET_STREAM))
+        );
+        assert_eq!(response.read_body().unwrap(), vec![0u8, 159, 146, 150]);
+    }
+}
@@ -0,0 +1,264 @@
+//! Defines a `Handler` which guards against another `Handler` producing an unexpectedly large
+//! response body.
+
+use std::cell::Cell;
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::rc::Rc;
+
+use futures::{future, Future, Sink, Stream};
+use hyper::{self, Body, Response};
+use hyper::header::ContentLength;
+use tokio_core::reactor::Handle;
+
+use handler::{Handler, HandlerFuture, IntoHandlerError, NewHandler};
+use state::{request_id, FromState, State};
+
+/// Configures and creates `SizeLimitHandler` values, which wrap the `Handler` created by another
+/// `NewHandler` and limit the size of the response body it produces to `max_size` bytes.
+///
+/// This is a safety net against runaway handlers, rather than a limit which well-behaved handlers
+/// are expected to reach, and so exceeding it is logged as an error:
+///
+/// * A response whose `Content-Length` exceeds the limit, such as one built by `create_response`,
+///   is replaced by a `500 Internal Server Error`.
+/// * A streamed response without a `Content-Length` is aborted once the limit is passed. The
+///   status and headers have already been sent, so the connection is closed before the body is
+///   complete.
+///
+/// # Examples
+///
+/// ```rust
+/// # extern crate gotham;
+/// # extern crate hyper;
+/// # extern crate mime;
+/// #
+/// # use hyper::{Response, StatusCode};
+/// # use gotham::handler::NewSizeLimitHandler;
+/// # use gotham::http::response::create_response;
+/// # use gotham::router::Router;
+/// # use gotham::router::builder::*;
+/// # use gotham::state::State;
+/// # use gotham::test::TestServer;
+/// #
+/// fn report(state: State) -> (State, Response) {
+///     let body = Some((vec![b'x'; 4096], mime::TEXT_PLAIN));
+///     let res = create_response(&state, StatusCode::Ok, body);
+///     (state, res)
+/// }
+///
+/// fn router() -> Router {
+///     build_simple_router(|route| {
+///         route
+///             .get("/report")
+///             .to_new_handler(NewSizeLimitHandler::new(|| Ok(report), 1024));
+///     })
+/// }
+/// #
+/// # fn main() {
+/// #   let test_server = TestServer::new(router()).unwrap();
+/// #   let response = test_server.client().get("http://localhost/report").perform().unwrap();
+/// #   assert_eq!(response.status(), StatusCode::InternalServerError);
+/// # }
+/// ```
+pub struct NewSizeLimitHandler<NH> {
+    new_handler: NH,
+    max_size: u64,
+}
+
+/// Handles a single request on behalf of a wrapped `Handler`, limiting the size of its response
+/// body.
+///
+/// See `NewSizeLimitHandler` for usage details.
+pub struct SizeLimitHandler<H> {
+    handler: H,
+    max_size: u64,
+}
+
+impl<NH> NewSizeLimitHandler<NH>
+where
+    NH: NewHandler,
+{
+    /// Creates a `NewSizeLimitHandler` which limits the response bodies of the handler created by
+    /// `new_handler` to `max_size` bytes.
+    pub fn new(new_handler: NH, max_size: u64) -> NewSizeLimitHandler<NH> {
+        NewSizeLimitHandler {
+            new_handler,
+            max_size,
+        }
+    }
+}
+
+impl<NH> NewHandler for NewSizeLimitHandler<NH>
+where
+    NH: NewHandler,
+    NH::Instance: 'static,
+{
+    type Instance = SizeLimitHandler<NH::Instance>;
+
+    fn new_handler(&self) -> io::Result<Self::Instance> {
+        Ok(SizeLimitHandler {
+            handler: self.new_handler.new_handler()?,
+            max_size: self.max_size,
+        })
+    }
+}
+
+impl<H> Handler for SizeLimitHandler<H>
+where
+    H: Handler + 'static,
+{
+    fn handle(self, state: State) -> Box<HandlerFuture> {
+        let max_size = self.max_size;
+
+        let f = self.handler.handle(state).and_then(move |(state, res)| {
+            let length = res.headers().get::<ContentLength>().map(|&ContentLength(len)| len);
+
+            match length {
+                Some(length) if length > max_size => {
+                    error!(
+                        "[{}] response body of {} bytes exceeds the limit of {} bytes",
+                        request_id(&state),
+                        length,
+                        max_size
+                    );
+                    let e = ResponseTooLarge { max_size };
+                    future::err((state, e.into_handler_error()))
+                }
+                None if res.body_ref().is_some() => {
+                    let res = limit_stream(&state, res, max_size);
+                    future::ok((state, res))
+                }
+                _ => future::ok((state, res)),
+            }
+        });
+
+        Box::new(f)
+    }
+}
+
+/// Replaces the body of `res` with one which forwards its chunks, failing once more than
+/// `max_size` bytes have been sent.
+fn limit_stream(state: &State, res: Response, max_size: u64) -> Response {
+    let request_id = request_id(state).to_owned();
+    let status = res.status();
+    let headers = res.headers().clone();
+    let (tx, body) = Body::pair();
+
+    let exceeded = Rc::new(Cell::new(false));
+    let mut sent = 0u64;
+
+    let chunks = {
+        let stop = exceeded.clone();
+        res.body()
+            .take_while(move |_| Ok(!stop.get()))
+            .then(move |result| match result {
+                Ok(chunk) => {
+                    sent += chunk.len() as u64;
+                    if sent > max_size {
+                        error!(
+                            "[{}] streamed response body exceeds the limit of {} bytes, aborting",
+                            request_id,
+                            max_size
+                        );
+                        exceeded.set(true);
+                        Ok(Err(hyper::Error::TooLarge))
+                    } else {
+                        Ok(Ok(chunk))
+                    }
+                }
+                Err(e) => Ok(Err(e)),
+            })
+    };
+
+    let forward = tx.sink_map_err(|_| ())
+        .send_all(chunks)
+        .map(|_| ());
+    Handle::borrow_from(state).spawn(forward);
+
+    Response::new()
+        .with_status(status)
+        .with_headers(headers)
+        .with_body(body)
+}
+
+/// The error which causes a response to be replaced when its body exceeds the limit.
+#[derive(Debug)]
+struct ResponseTooLarge {
+    max_size: u64,
+}
+
+impl fmt::Display for ResponseTooLarge {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "response body exceeds the limit of {} bytes", self.max_size)
+    }
+}
+
+impl Error for ResponseTooLarge {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use futures::stream;
+    use hyper::{Chunk, StatusCode};
+    use mime;
+
+    use http::response::create_response;
+    use test::TestServer;
+
+    fn buffered(state: State) -> (State, Response) {
+        let body = Some((vec![b'x'; 100], mime::TEXT_PLAIN));
+        let res = create_response(&state, StatusCode::Ok, body);
+        (state, res)
+    }
+
+    fn streamed(state: State) -> (State, Response) {
+        let (tx, body) = Body::pair();
+        let chunks = stream::iter_ok::<_, ()>((0..10).map(|_| Ok(Chunk::from(vec![b'x'; 10]))));
+        Handle::borrow_from(&state).spawn(tx.sink_map_err(|_| ()).send_all(chunks).map(|_| ()));
+
+        (state, Response::new().with_status(StatusCode::Ok).with_body(body))
+    }
+
+    #[test]
+    fn replaces_buffered_response_over_limit() {
+        let test_server = TestServer::new(NewSizeLimitHandler::new(|| Ok(buffered), 99)).unwrap();
+        let response = test_server
+            .client()
+            .get("http://localhost/")
+            .perform()
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::InternalServerError);
+
+        let test_server = TestServer::new(NewSizeLimitHandler::new(|| Ok(buffered), 100)).unwrap();
+        let response = test_server
+            .client()
+            .get("http://localhost/")
+            .perform()
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::Ok);
+        assert_eq!(response.read_body().unwrap().len(), 100);
+    }
+
+    #[test]
+    fn aborts_streamed_response_over_limit() {
+        let test_server = TestServer::new(NewSizeLimitHandler::new(|| Ok(streamed), 50)).unwrap();
+        let response = test_server
+            .client()
+            .get("http://localhost/")
+            .perform()
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::Ok);
+        assert!(response.read_body().is_err());
+
+        let test_server = TestServer::new(NewSizeLimitHandler::new(|| Ok(streamed), 100)).unwrap();
+        let response = test_server
+            .client()
+            .get("http://localhost/")
+            .perform()
+            .unwrap();
+        assert_eq!(response.read_body().unwrap().len(), 100);
+    }
+}
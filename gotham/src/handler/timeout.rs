@@ -0,0 +1,235 @@
+//! Defines a `Handler` which answers with a fallback response when another `Handler` takes too
+//! long to respond.
+
+use std::io;
+use std::panic::RefUnwindSafe;
+use std::time::Duration;
+
+use futures::{future, Future};
+use hyper::{Headers, HttpVersion, Method, Uri};
+use tokio_core::reactor::{Handle, Timeout};
+
+use handler::{ErrorFormat, Handler, HandlerFuture, IntoHandlerError, NewHandler};
+use state::{request_id, FromState, State};
+use state::request_id::copy_request_id;
+
+/// Configures and creates `TimeoutHandler` values, which wrap the `Handler` created by another
+/// `NewHandler` and respond using `fallback` when it has not responded within `timeout`.
+///
+/// The fallback can return a cached or stale response, allowing the route to stay responsive
+/// while the underlying data is slow to produce. When the timeout elapses, the wrapped handler is
+/// dropped and no longer polled.
+///
+/// The `State` given to `fallback` is not the `State` of the timed out handler, which is owned by
+/// that handler. It holds the `Method`, `Uri`, `HttpVersion`, `Headers` and request id of the
+/// `Request`, but none of the values added by middleware or extractors.
+///
+/// # Examples
+///
+/// ```rust
+/// # extern crate futures;
+/// # extern crate gotham;
+/// # extern crate hyper;
+/// # extern crate mime;
+/// #
+/// # use std::time::Duration;
+/// # use futures::future;
+/// # use hyper::{Response, StatusCode};
+/// # use gotham::handler::{HandlerFuture, NewTimeoutHandler};
+/// # use gotham::http::response::create_response;
+/// # use gotham::router::Router;
+/// # use gotham::router::builder::*;
+/// # use gotham::state::State;
+/// # use gotham::test::TestServer;
+/// #
+/// fn prices(_state: State) -> Box<HandlerFuture> {
+///     // Waits on a slow upstream service, which never responds.
+///     Box::new(future::empty())
+/// }
+///
+/// fn cached_prices(state: State) -> (State, Response) {
+///     let body = Some((b"cached".to_vec(), mime::TEXT_PLAIN));
+///     let res = create_response(&state, StatusCode::Ok, body);
+///     (state, res)
+/// }
+///
+/// fn router() -> Router {
+///     build_simple_router(|route| {
+///         route.get("/prices").to_new_handler(NewTimeoutHandler::new(
+///             || Ok(prices),
+///             Duration::from_millis(50),
+///             cached_prices,
+///         ));
+///     })
+/// }
+/// #
+/// # fn main() {
+/// #   let test_server = TestServer::new(router()).unwrap();
+/// #   let response = test_server.client().get("http://localhost/prices").perform().unwrap();
+/// #   assert_eq!(response.status(), StatusCode::Ok);
+/// #   assert_eq!(response.read_body().unwrap(), b"cached");
+/// # }
+/// ```
+pub struct NewTimeoutHandler<NH, F> {
+    new_handler: NH,
+    timeout: Duration,
+    fallback: F,
+}
+
+/// Handles a single request on behalf of a wrapped `Handler`, responding using the fallback if
+/// it times out.
+///
+/// See `NewTimeoutHandler` for usage details.
+pub struct TimeoutHandler<H, F> {
+    handler: H,
+    timeout: Duration,
+    fallback: F,
+}
+
+impl<NH, F> NewTimeoutHandler<NH, F>
+where
+    NH: NewHandler,
+    F: Handler + RefUnwindSafe + Copy + Send + Sync + 'static,
+{
+    /// Creates a `NewTimeoutHandler` which responds using `fallback` when the handler created by
+    /// `new_handler` takes longer than `timeout`.
+    pub fn new(new_handler: NH, timeout: Duration, fallback: F) -> NewTimeoutHandler<NH, F> {
+        NewTimeoutHandler {
+            new_handler,
+            timeout,
+            fallback,
+        }
+    }
+}
+
+impl<NH, F> NewHandler for NewTimeoutHandler<NH, F>
+where
+    NH: NewHandler,
+    NH::Instance: 'static,
+    F: Handler + RefUnwindSafe + Copy + Send + Sync + 'static,
+{
+    type Instance = TimeoutHandler<NH::Instance, F>;
+
+    fn new_handler(&self) -> io::Result<Self::Instance> {
+        Ok(TimeoutHandler {
+            handler: self.new_handler.new_handler()?,
+            timeout: self.timeout,
+            fallback: self.fallback,
+        })
+    }
+}
+
+impl<H, F> Handler for TimeoutHandler<H, F>
+where
+    H: Handler + 'static,
+    F: Handler + 'static,
+{
+    fn handle(self, state: State) -> Box<HandlerFuture> {
+        let timeout = match Timeout::new(self.timeout, Handle::borrow_from(&state)) {
+            Ok(timeout) => timeout,
+            Err(e) => return Box::new(future::err((state, e.into_handler_error()))),
+        };
+
+        let fallback_state = fallback_state(&state);
+        let fallback = self.fallback;
+
+        let f = self.handler
+            .handle(state)
+            .select2(timeout)
+            .then(move |result| -> Box<HandlerFuture> {
+                match result {
+                    Ok(future::Either::A((response, _))) => Box::new(future::ok(response)),
+                    Err(future::Either::A((e, _))) => Box::new(future::err(e)),
+                    Ok(future::Either::B(_)) => {
+                        trace!(
+                            "[{}] handler timed out, responding with fallback",
+                            request_id(&fallback_state)
+                        );
+                        fallback.handle(fallback_state)
+                    }
+                    Err(future::Either::B((e, _))) => {
+                        Box::new(future::err((fallback_state, e.into_handler_error())))
+                    }
+                }
+            });
+
+        Box::new(f)
+    }
+}
+
+/// Builds the `State` given to the fallback, from the details of the `Request` in `state`.
+fn fallback_state(state: &State) -> State {
+    let mut fallback_state = State::new();
+
+    fallback_state.put(Method::borrow_from(state).clone());
+    fallback_state.put(Uri::borrow_from(state).clone());
+    fallback_state.put(*HttpVersion::borrow_from(state));
+    fallback_state.put(Headers::borrow_from(state).clone());
+    fallback_state.put(Handle::borrow_from(state).clone());
+
+    if let Some(error_format) = ErrorFormat::try_borrow_from(state) {
+        fallback_state.put(*error_format);
+    }
+
+    copy_request_id(state, &mut fallback_state);
+    fallback_state
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use hyper::{Response, StatusCode};
+    use mime;
+
+    use http::response::create_response;
+    use test::TestServer;
+
+    fn respond(state: State, body: &str) -> (State, Response) {
+        let body = Some((body.as_bytes().to_vec(), mime::TEXT_PLAIN));
+        let res = create_response(&state, StatusCode::Ok, body);
+        (state, res)
+    }
+
+    fn slow(state: State) -> Box<HandlerFuture> {
+        let handle = Handle::borrow_from(&state).clone();
+        let delay = Timeout::new(Duration::from_millis(500), &handle).unwrap();
+
+        Box::new(delay.then(move |_| Ok(respond(state, "fresh"))))
+    }
+
+    fn fast(state: State) -> (State, Response) {
+        respond(state, "fresh")
+    }
+
+    fn stale(state: State) -> (State, Response) {
+        assert_eq!(*Method::borrow_from(&state), Method::Get);
+        respond(state, "stale")
+    }
+
+    fn body<NH>(new_handler: NH) -> Vec<u8>
+    where
+        NH: NewHandler + 'static,
+    {
+        let new_handler = NewTimeoutHandler::new(new_handler, Duration::from_millis(50), stale);
+        let test_server = TestServer::new(new_handler).unwrap();
+        let response = test_server
+            .client()
+            .get("http://localhost/")
+            .perform()
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::Ok);
+        response.read_body().unwrap()
+    }
+
+    #[test]
+    fn responds_with_fallback_when_handler_times_out() {
+        assert_eq!(body(|| Ok(slow)), b"stale");
+    }
+
+    #[test]
+    fn responds_with_handler_when_in_time() {
+        assert_eq!(body(|| Ok(fast)), b"fresh");
+    }
+}
@@ -0,0 +1,253 @@
+//! Defines the serialization formats supported for request and response bodies, and content
+//! negotiation between them.
+
+use std::cmp::Reverse;
+use std::error::Error;
+use std::fmt;
+
+use hyper::header::{q, Accept};
+use mime::{self, Mime};
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use serde_json;
+#[cfg(feature = "xml")]
+use serde_xml_rs;
+
+use http::request::deserialize_error::DeserializeErrorKind;
+
+/// A serialization format for request and response bodies.
+///
+/// `BodyFormat::Xml` is only available when the `xml` feature of Gotham is enabled.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BodyFormat {
+    /// JSON, with the media type `application/json`. Also matches media types with a `+json`
+    /// structured syntax suffix.
+    Json,
+
+    /// XML, with the media type `application/xml`. Also matches `text/xml`, and media types with
+    /// a `+xml` structured syntax suffix.
+    #[cfg(feature = "xml")]
+    Xml,
+}
+
+impl BodyFormat {
+    /// The media type used when responding in this format.
+    pub fn media_type(&self) -> Mime {
+        match *self {
+            BodyFormat::Json => mime::APPLICATION_JSON,
+            #[cfg(feature = "xml")]
+            BodyFormat::Xml => "application/xml".parse().unwrap(),
+        }
+    }
+
+    /// Returns `true` if `media_type` denotes this format, ignoring any parameters.
+    pub fn matches(&self, media_type: &Mime) -> bool {
+        match *self {
+            BodyFormat::Json => {
+                media_type.suffix() == Some(mime::JSON)
+                    || (media_type.type_() == mime::APPLICATION && media_type.subtype() == mime::JSON)
+            }
+            #[cfg(feature = "xml")]
+            BodyFormat::Xml => {
+                media_type.suffix() == Some(mime::XML)
+                    || ((media_type.type_() == mime::APPLICATION || media_type.type_() == mime::TEXT)
+                        && media_type.subtype() == mime::XML)
+            }
+        }
+    }
+
+    /// Deserializes a value from `body` in this format.
+    pub fn deserialize<T>(&self, body: &[u8]) -> Result<T, FormatError>
+    where
+        T: DeserializeOwned,
+    {
+        match *self {
+            BodyFormat::Json => serde_json::from_slice(body).map_err(FormatError::Json),
+            #[cfg(feature = "xml")]
+            BodyFormat::Xml => serde_xml_rs::from_reader(body).map_err(FormatError::Xml),
+        }
+    }
+
+    /// Serializes `value` in this format.
+    pub fn serialize<T>(&self, value: &T) -> Result<Vec<u8>, FormatError>
+    where
+        T: Serialize,
+    {
+        match *self {
+            BodyFormat::Json => serde_json::to_vec(value).map_err(FormatError::Json),
+            #[cfg(feature = "xml")]
+            BodyFormat::Xml => serde_xml_rs::to_string(value)
+                .map(String::into_bytes)
+                .map_err(FormatError::Xml),
+        }
+    }
+}
+
+/// Chooses the format to respond with, from those in `formats`, according to the `Accept` header
+/// of the request.
+///
+/// Media ranges are considered in order of their quality value, and ties are broken by the
+/// order of `formats`. When the request has no `Accept` header, the first format is chosen.
+/// Returns `None` when the client accepts none of `formats`.
+///
+/// # Examples
+///
+/// ```rust
+/// # extern crate hyper;
+/// # extern crate gotham;
+/// #
+/// # use hyper::header::{qitem, Accept};
+/// # use gotham::http::format::{negotiate, BodyFormat};
+/// #
+/// # fn main() {
+/// let formats = [BodyFormat::Json];
+///
+/// assert_eq!(negotiate(None, &formats), Some(BodyFormat::Json));
+///
+/// let accept = Accept(vec![qitem("application/*".parse().unwrap())]);
+/// assert_eq!(negotiate(Some(&accept), &formats), Some(BodyFormat::Json));
+///
+/// let accept = Accept(vec![qitem("text/html".parse().unwrap())]);
+/// assert_eq!(negotiate(Some(&accept), &formats), None);
+/// # }
+/// ```
+pub fn negotiate(accept: Option<&Accept>, formats: &[BodyFormat]) -> Option<BodyFormat> {
+    let accept = match accept {
+        Some(accept) if !accept.0.is_empty() => accept,
+        _ => return formats.first().cloned(),
+    };
+
+    let mut ranges: Vec<_> = accept.0.iter().filter(|item| item.quality > q(0)).collect();
+    ranges.sort_by_key(|item| Reverse(item.quality));
+
+    for range in ranges {
+        let range = &range.item;
+        let found = formats.iter().find(|format| {
+            if range.type_() == mime::STAR {
+                true
+            } else if range.subtype() == mime::STAR {
+                range.type_() == format.media_type().type_()
+            } else {
+                format.matches(range)
+            }
+        });
+
+        if let Some(format) = found {
+            return Some(*format);
+        }
+    }
+
+    None
+}
+
+/// An error which occurred while serializing or deserializing a body.
+#[derive(Debug)]
+pub enum FormatError {
+    /// A JSON (de)serialization error.
+    Json(serde_json::Error),
+
+    /// An XML (de)serialization error.
+    #[cfg(feature = "xml")]
+    Xml(serde_xml_rs::Error),
+}
+
+impl FormatError {
+    /// The kind of deserialization error.
+    pub(crate) fn kind(&self) -> DeserializeErrorKind {
+        match *self {
+            FormatError::Json(ref e) => DeserializeErrorKind::of_json(e),
+            #[cfg(feature = "xml")]
+            FormatError::Xml(_) => DeserializeErrorKind::Syntax,
+        }
+    }
+}
+
+impl fmt::Display for FormatError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            FormatError::Json(ref e) => write!(f, "JSON error: {}", e),
+            #[cfg(feature = "xml")]
+            FormatError::Xml(ref e) => write!(f, "XML error: {}", e),
+        }
+    }
+}
+
+impl Error for FormatError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match *self {
+            FormatError::Json(ref e) => Some(e),
+            #[cfg(feature = "xml")]
+            FormatError::Xml(ref e) => Some(e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use hyper::header::QualityItem;
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Article {
+        title: String,
+    }
+
+    #[test]
+    fn json_matches() {
+        assert!(BodyFormat::Json.matches(&mime::APPLICATION_JSON));
+        assert!(BodyFormat::Json.matches(&"application/vnd.api+json".parse().unwrap()));
+        assert!(!BodyFormat::Json.matches(&mime::TEXT_PLAIN));
+    }
+
+    #[test]
+    fn json_round_trip() {
+        let article = Article {
+            title: String::from("Gotham"),
+        };
+        let bytes = BodyFormat::Json.serialize(&article).unwrap();
+        assert_eq!(BodyFormat::Json.deserialize::<Article>(&bytes).unwrap(), article);
+        assert!(BodyFormat::Json.deserialize::<Article>(b"{").is_err());
+    }
+
+    #[test]
+    fn negotiate_skips_unacceptable_ranges() {
+        let accept = Accept(vec![
+            QualityItem::new(mime::APPLICATION_JSON, q(0)),
+            QualityItem::new(mime::STAR_STAR, q(100)),
+        ]);
+        assert_eq!(
+            negotiate(Some(&accept), &[BodyFormat::Json]),
+            Some(BodyFormat::Json)
+        );
+
+        let accept = Accept(vec![QualityItem::new(mime::APPLICATION_JSON, q(0))]);
+        assert_eq!(negotiate(Some(&accept), &[BodyFormat::Json]), None);
+    }
+
+    #[cfg(feature = "xml")]
+    #[test]
+    fn negotiate_prefers_higher_quality() {
+        let formats = [BodyFormat::Json, BodyFormat::Xml];
+        let accept = Accept(vec![
+            QualityItem::new(mime::APPLICATION_JSON, q(500)),
+            QualityItem::new("application/xml".parse().unwrap(), q(900)),
+        ]);
+        assert_eq!(negotiate(Some(&accept), &formats), Some(BodyFormat::Xml));
+
+        let accept = Accept(vec![QualityItem::new(mime::STAR_STAR, q(1000))]);
+        assert_eq!(negotiate(Some(&accept), &formats), Some(BodyFormat::Json));
+    }
+
+    #[cfg(feature = "xml")]
+    #[test]
+    fn xml_round_trip() {
+        let article = Article {
+            title: String::from("Gotham"),
+        };
+        let bytes = BodyFormat::Xml.serialize(&article).unwrap();
+        assert_eq!(BodyFormat::Xml.deserialize::<Article>(&bytes).unwrap(), article);
+        assert!(BodyFormat::Xml.matches(&"text/xml".parse().unwrap()));
+        assert!(BodyFormat::Xml.matches(&"application/atom+xml".parse().unwrap()));
+    }
+}
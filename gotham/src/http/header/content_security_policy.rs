@@ -0,0 +1,24 @@
+//! Defines the Content-Security-Policy header.
+
+header! {
+    /// Defines the Content-Security-Policy header.
+    ///
+    /// Restricts the resources, such as scripts and stylesheets, which a browser will load for the
+    /// response, as described by the [W3C Content Security Policy
+    /// specification](https://www.w3.org/TR/CSP/).
+    ///
+    /// # Example
+    /// ```
+    /// # extern crate hyper;
+    /// # extern crate gotham;
+    ///
+    /// use hyper::header::Headers;
+    /// use gotham::http::header::ContentSecurityPolicy;
+    ///
+    /// # fn main () {
+    /// let mut headers = Headers::new();
+    /// headers.set(ContentSecurityPolicy(String::from("default-src 'self'")));
+    /// # }
+    /// ```
+    (ContentSecurityPolicy, "Content-Security-Policy") => [String]
+}
@@ -0,0 +1,310 @@
+//! Defines the Forwarded header.
+
+use std::fmt;
+use std::str;
+
+use hyper;
+use hyper::header::{Formatter, Header, Raw};
+
+static NAME: &str = "Forwarded";
+
+/// The Forwarded header as defined as part of [RFC
+/// 7239](https://tools.ietf.org/html/rfc7239).
+///
+/// The Forwarded HTTP header field allows proxy components to disclose information lost in the
+/// proxying process, such as the address of the original client and the protocol it used. Each
+/// proxy appends a new element, so the first element describes the hop closest to the client.
+///
+/// # ABNF
+/// ```plain
+/// Forwarded   = 1#forwarded-element
+///
+/// forwarded-element =
+///     [ forwarded-pair ] *( ";" [ forwarded-pair ] )
+///
+/// forwarded-pair = token "=" value
+/// value          = token / quoted-string
+/// ```
+///
+/// # Example values
+/// * `for=192.0.2.60;proto=http;by=203.0.113.43`
+/// * `for="[2001:db8::1]";proto=https, for=198.51.100.17`
+///
+/// # Example
+/// ```
+/// # extern crate hyper;
+/// # extern crate gotham;
+///
+/// use hyper::header::Headers;
+/// use gotham::http::header::{Forwarded, ForwardedElement};
+///
+/// # fn main () {
+/// let mut headers = Headers::new();
+/// headers.set(Forwarded(vec![ForwardedElement {
+///     for_node: Some(String::from("[2001:db8::1]")),
+///     proto: Some(String::from("https")),
+///     ..ForwardedElement::default()
+/// }]));
+/// # }
+/// ```
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Forwarded(pub Vec<ForwardedElement>);
+
+/// A single hop described by the `Forwarded` header.
+///
+/// Values are stored as they were received, with any quoting removed. Parameters not defined by
+/// RFC 7239 are ignored.
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct ForwardedElement {
+    /// The `by` parameter, identifying the interface where the request came into the proxy.
+    pub by: Option<String>,
+
+    /// The `for` parameter, identifying the node making the request to the proxy. IPv6 addresses
+    /// are enclosed in square brackets, and may be followed by a port.
+    pub for_node: Option<String>,
+
+    /// The `host` parameter, holding the `Host` request header as received by the proxy.
+    pub host: Option<String>,
+
+    /// The `proto` parameter, holding the protocol used to make the request to the proxy.
+    pub proto: Option<String>,
+}
+
+impl Header for Forwarded {
+    fn header_name() -> &'static str {
+        NAME
+    }
+
+    fn parse_header(raw: &Raw) -> hyper::error::Result<Forwarded> {
+        let mut elements = Vec::new();
+        for line in raw {
+            let line = str::from_utf8(line)?;
+            for element in split_unquoted(line, ',') {
+                if element.trim().is_empty() {
+                    continue;
+                }
+                elements.push(parse_element(element)?);
+            }
+        }
+
+        if elements.is_empty() {
+            Err(hyper::error::Error::Header)
+        } else {
+            Ok(Forwarded(elements))
+        }
+    }
+
+    fn fmt_header(&self, f: &mut Formatter) -> fmt::Result {
+        f.fmt_line(self)
+    }
+}
+
+impl fmt::Display for Forwarded {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, element) in self.0.iter().enumerate() {
+            if i > 0 {
+                f.write_str(", ")?;
+            }
+            write!(f, "{}", element)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for ForwardedElement {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let pairs = [
+            ("by", &self.by),
+            ("for", &self.for_node),
+            ("host", &self.host),
+            ("proto", &self.proto),
+        ];
+
+        let mut first = true;
+        for &(name, value) in pairs.iter() {
+            if let Some(ref value) = *value {
+                if !first {
+                    f.write_str(";")?;
+                }
+                first = false;
+
+                if value.chars().all(is_tchar) && !value.is_empty() {
+                    write!(f, "{}={}", name, value)?;
+                } else {
+                    write!(
+                        f,
+                        "{}=\"{}\"",
+                        name,
+                        value.replace('\\', "\\\\").replace('"', "\\\"")
+                    )?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn parse_element(element: &str) -> hyper::error::Result<ForwardedElement> {
+    let mut parsed = ForwardedElement::default();
+
+    for pair in split_unquoted(element, ';') {
+        let pair = pair.trim();
+        if pair.is_empty() {
+            continue;
+        }
+
+        let mut parts = pair.splitn(2, '=');
+        let name = parts.next().unwrap_or("").trim();
+        let value = match parts.next() {
+            Some(value) => unquote(value.trim())?,
+            None => return Err(hyper::error::Error::Header),
+        };
+
+        if name.is_empty() || !name.chars().all(is_tchar) {
+            return Err(hyper::error::Error::Header);
+        }
+
+        match name.to_ascii_lowercase().as_str() {
+            "by" => parsed.by = Some(value),
+            "for" => parsed.for_node = Some(value),
+            "host" => parsed.host = Some(value),
+            "proto" => parsed.proto = Some(value),
+            _ => (),
+        }
+    }
+
+    Ok(parsed)
+}
+
+/// Splits `s` on `delim`, ignoring any occurrences inside a quoted-string.
+fn split_unquoted(s: &str, delim: char) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut in_quotes = false;
+    let mut escaped = false;
+    let mut start = 0;
+
+    for (i, c) in s.char_indices() {
+        if escaped {
+            escaped = false;
+        } else if in_quotes && c == '\\' {
+            escaped = true;
+        } else if c == '"' {
+            in_quotes = !in_quotes;
+        } else if c == delim && !in_quotes {
+            parts.push(&s[start..i]);
+            start = i + c.len_utf8();
+        }
+    }
+    parts.push(&s[start..]);
+
+    parts
+}
+
+fn unquote(value: &str) -> hyper::error::Result<String> {
+    if !value.starts_with('"') {
+        if value.is_empty() || !value.chars().all(is_tchar) {
+            return Err(hyper::error::Error::Header);
+        }
+        return Ok(value.to_owned());
+    }
+
+    if value.len() < 2 || !value.ends_with('"') {
+        return Err(hyper::error::Error::Header);
+    }
+
+    let mut unquoted = String::with_capacity(value.len() - 2);
+    let mut escaped = false;
+    for c in value[1..value.len() - 1].chars() {
+        if escaped {
+            unquoted.push(c);
+            escaped = false;
+        } else if c == '\\' {
+            escaped = true;
+        } else if c == '"' {
+            return Err(hyper::error::Error::Header);
+        } else {
+            unquoted.push(c);
+        }
+    }
+
+    if escaped {
+        Err(hyper::error::Error::Header)
+    } else {
+        Ok(unquoted)
+    }
+}
+
+fn is_tchar(c: char) -> bool {
+    c.is_ascii_alphanumeric() || "!#$%&'*+-.^_`|~".contains(c)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_single_element() {
+        let a: Forwarded =
+            Header::parse_header(&"for=192.0.2.60;proto=http;by=203.0.113.43".into()).unwrap();
+        let b = Forwarded(vec![ForwardedElement {
+            by: Some(String::from("203.0.113.43")),
+            for_node: Some(String::from("192.0.2.60")),
+            host: None,
+            proto: Some(String::from("http")),
+        }]);
+        assert_eq!(a, b);
+    }
+
+    #[test]
+    fn parse_quoted_ipv6() {
+        let a: Forwarded =
+            Header::parse_header(&"For=\"[2001:db8:cafe::17]:4711\";proto=https".into()).unwrap();
+        assert_eq!(
+            a.0[0].for_node,
+            Some(String::from("[2001:db8:cafe::17]:4711"))
+        );
+        assert_eq!(a.0[0].proto, Some(String::from("https")));
+    }
+
+    #[test]
+    fn parse_multiple_elements() {
+        let a: Forwarded = Header::parse_header(
+            &"for=192.0.2.43, for=\"[2001:db8::1]\";host=\"a,b\"".into(),
+        ).unwrap();
+        assert_eq!(a.0.len(), 2);
+        assert_eq!(a.0[0].for_node, Some(String::from("192.0.2.43")));
+        assert_eq!(a.0[1].for_node, Some(String::from("[2001:db8::1]")));
+        assert_eq!(a.0[1].host, Some(String::from("a,b")));
+    }
+
+    #[test]
+    fn parse_fails() {
+        let e: hyper::error::Result<Forwarded> = Header::parse_header(&"for".into());
+        assert!(e.is_err());
+
+        let e: hyper::error::Result<Forwarded> = Header::parse_header(&"for=[2001:db8::1]".into());
+        assert!(e.is_err());
+
+        let e: hyper::error::Result<Forwarded> = Header::parse_header(&"for=\"unterminated".into());
+        assert!(e.is_err());
+    }
+
+    #[test]
+    fn valid_display_formatting() {
+        let f = Forwarded(vec![
+            ForwardedElement {
+                for_node: Some(String::from("[2001:db8::1]")),
+                proto: Some(String::from("https")),
+                ..ForwardedElement::default()
+            },
+            ForwardedElement {
+                for_node: Some(String::from("198.51.100.17")),
+                ..ForwardedElement::default()
+            },
+        ]);
+        assert_eq!(
+            format!("{}", f),
+            "for=\"[2001:db8::1]\";proto=https, for=198.51.100.17"
+        );
+    }
+}
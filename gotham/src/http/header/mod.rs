@@ -1,16 +1,33 @@
 //! Defines HTTP headers which are set by Gotham but not provided by Hyper.
+//!
+//! Header names are never normalized, for interoperating with systems which are sensitive to
+//! their casing. The `Headers` of a request keep the casing sent by the client, as seen via
+//! `HeaderView::name` when iterating them, and response headers are written with the casing of
+//! the name given, e.g. via `Headers::set_raw`. Headers are still matched case-insensitively.
 
 mod x_request_id;
 mod x_frame_options;
 mod x_xss_protection;
 mod x_content_type_options;
 mod x_runtime_microseconds;
+mod forwarded;
+mod x_forwarded_for;
+mod x_forwarded_host;
+mod x_forwarded_proto;
+pub(crate) mod server_timing;
+mod content_security_policy;
 
 pub use http::header::x_request_id::XRequestId;
 pub use http::header::x_frame_options::XFrameOptions;
 pub use http::header::x_xss_protection::XXssProtection;
 pub use http::header::x_content_type_options::XContentTypeOptions;
 pub use http::header::x_runtime_microseconds::XRuntimeMicroseconds;
+pub use http::header::forwarded::{Forwarded, ForwardedElement};
+pub use http::header::x_forwarded_for::XForwardedFor;
+pub use http::header::x_forwarded_host::XForwardedHost;
+pub use http::header::x_forwarded_proto::XForwardedProto;
+pub use http::header::server_timing::{ServerTiming, ServerTimingMetric};
+pub use http::header::content_security_policy::ContentSecurityPolicy;
 
 use std::str;
 use hyper;
@@ -48,6 +65,12 @@ pub fn from_rws_delimited_raw_str<T: str::FromStr>(raw: &Raw) -> hyper::error::R
 mod tests {
     use super::*;
 
+    use hyper::{Headers, Response, StatusCode};
+
+    use http::response::create_response;
+    use state::{FromState, State};
+    use test::TestServer;
+
     #[test]
     fn valid_rws_from_one_header() {
         let r: Raw = Raw::from("X Y".as_bytes().to_vec());
@@ -61,4 +84,36 @@ mod tests {
         let values: hyper::error::Result<Vec<String>> = from_one_rws_delimited_raw_str(&r);
         assert!(values.is_err());
     }
+
+    #[test]
+    fn preserves_header_name_casing() {
+        fn handler(state: State) -> (State, Response) {
+            let name = Headers::borrow_from(&state)
+                .iter()
+                .find(|header| header.name().eq_ignore_ascii_case("x-legacy-id"))
+                .map(|header| header.name().to_owned());
+
+            let mut res = create_response(&state, StatusCode::Ok, None);
+            res.headers_mut().set_raw("X-Custom-Header", "custom");
+            res.headers_mut().set_raw("x-lowercase-header", "lowercase");
+            res.headers_mut().set_raw("X-Echoed-Name", name.unwrap_or_default());
+            (state, res)
+        }
+
+        let test_server = TestServer::new(|| Ok(handler)).unwrap();
+        let response = test_server
+            .client()
+            .get("http://localhost/")
+            .with_raw_header("X-Legacy-ID", "1")
+            .perform()
+            .unwrap();
+
+        let names: Vec<_> = response.headers().iter().map(|header| header.name()).collect();
+        assert!(names.contains(&"X-Custom-Header"), "{:?}", names);
+        assert!(names.contains(&"x-lowercase-header"), "{:?}", names);
+        assert_eq!(
+            response.headers().get_raw("x-echoed-name").unwrap().one(),
+            Some(&b"X-Legacy-ID"[..])
+        );
+    }
 }
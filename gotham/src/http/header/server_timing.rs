@@ -0,0 +1,187 @@
+//! Defines the Server-Timing header.
+
+use std::fmt;
+use std::str;
+
+use hyper;
+use hyper::header::{Formatter, Header, Raw};
+
+static NAME: &str = "Server-Timing";
+
+/// The Server-Timing header as defined by the [W3C Server Timing
+/// specification](https://www.w3.org/TR/server-timing/).
+///
+/// Communicates one or more metrics about the handling of a request, which browser developer
+/// tools display alongside the client-side timing of the request.
+///
+/// Only the name and duration of each metric are represented. The `desc` parameter is ignored
+/// when parsing and never emitted, so that no free-form text is exposed to clients.
+///
+/// # Example values
+/// * `total;dur=12.5`
+/// * `db;dur=3.2, cache`
+///
+/// # Example
+/// ```
+/// # extern crate hyper;
+/// # extern crate gotham;
+///
+/// use hyper::header::Headers;
+/// use gotham::http::header::{ServerTiming, ServerTimingMetric};
+///
+/// # fn main () {
+/// let mut headers = Headers::new();
+/// headers.set(ServerTiming(vec![ServerTimingMetric {
+///     name: String::from("db"),
+///     duration: Some(3.2),
+/// }]));
+/// # }
+/// ```
+#[derive(Clone, PartialEq, Debug)]
+pub struct ServerTiming(pub Vec<ServerTimingMetric>);
+
+/// A single metric described by the `Server-Timing` header.
+#[derive(Clone, PartialEq, Debug)]
+pub struct ServerTimingMetric {
+    /// The name of the metric, which must be a valid HTTP token.
+    pub name: String,
+
+    /// The duration of the metric in milliseconds, if one was given.
+    pub duration: Option<f64>,
+}
+
+impl Header for ServerTiming {
+    fn header_name() -> &'static str {
+        NAME
+    }
+
+    fn parse_header(raw: &Raw) -> hyper::error::Result<ServerTiming> {
+        let mut metrics = Vec::new();
+        for line in raw {
+            let line = str::from_utf8(line)?;
+            for metric in line.split(',') {
+                if metric.trim().is_empty() {
+                    continue;
+                }
+                metrics.push(parse_metric(metric)?);
+            }
+        }
+
+        if metrics.is_empty() {
+            Err(hyper::error::Error::Header)
+        } else {
+            Ok(ServerTiming(metrics))
+        }
+    }
+
+    fn fmt_header(&self, f: &mut Formatter) -> fmt::Result {
+        f.fmt_line(self)
+    }
+}
+
+impl fmt::Display for ServerTiming {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, metric) in self.0.iter().enumerate() {
+            if i > 0 {
+                f.write_str(", ")?;
+            }
+            write!(f, "{}", metric)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for ServerTimingMetric {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.name)?;
+        if let Some(duration) = self.duration {
+            write!(f, ";dur={:.3}", duration)?;
+        }
+        Ok(())
+    }
+}
+
+fn parse_metric(metric: &str) -> hyper::error::Result<ServerTimingMetric> {
+    let mut params = metric.split(';');
+    let name = params.next().unwrap_or("").trim();
+    if name.is_empty() || !name.chars().all(is_tchar) {
+        return Err(hyper::error::Error::Header);
+    }
+
+    let mut duration = None;
+    for param in params {
+        let mut parts = param.splitn(2, '=');
+        let key = parts.next().unwrap_or("").trim();
+        let value = parts.next().map(str::trim);
+
+        if key.eq_ignore_ascii_case("dur") && duration.is_none() {
+            match value.and_then(|v| v.trim_matches('"').parse().ok()) {
+                Some(dur) => duration = Some(dur),
+                None => return Err(hyper::error::Error::Header),
+            }
+        }
+    }
+
+    Ok(ServerTimingMetric {
+        name: name.to_owned(),
+        duration,
+    })
+}
+
+pub(crate) fn is_tchar(c: char) -> bool {
+    c.is_ascii_alphanumeric() || "!#$%&'*+-.^_`|~".contains(c)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_metrics() {
+        let a: ServerTiming =
+            Header::parse_header(&"total;dur=12.5, db;desc=\"Database\";dur=3, cache".into())
+                .unwrap();
+        let b = ServerTiming(vec![
+            ServerTimingMetric {
+                name: String::from("total"),
+                duration: Some(12.5),
+            },
+            ServerTimingMetric {
+                name: String::from("db"),
+                duration: Some(3.0),
+            },
+            ServerTimingMetric {
+                name: String::from("cache"),
+                duration: None,
+            },
+        ]);
+        assert_eq!(a, b);
+    }
+
+    #[test]
+    fn parse_invalid() {
+        let r: hyper::error::Result<ServerTiming> = Header::parse_header(&"".into());
+        assert!(r.is_err());
+
+        let r: hyper::error::Result<ServerTiming> = Header::parse_header(&"db;dur=fast".into());
+        assert!(r.is_err());
+
+        let r: hyper::error::Result<ServerTiming> = Header::parse_header(&"a b;dur=1".into());
+        assert!(r.is_err());
+    }
+
+    #[test]
+    fn format_metrics() {
+        let header = ServerTiming(vec![
+            ServerTimingMetric {
+                name: String::from("total"),
+                duration: Some(1.25),
+            },
+            ServerTimingMetric {
+                name: String::from("cache"),
+                duration: None,
+            },
+        ]);
+        assert_eq!(header.to_string(), "total;dur=1.250, cache");
+    }
+}
@@ -0,0 +1,26 @@
+//! Defines the X-Forwarded-For header.
+
+header! {
+    /// Defines the X-Forwarded-For header.
+    ///
+    /// A de-facto standard header set by proxies, holding the originating client address followed
+    /// by the address of each proxy the request passed through. The standardized equivalent is the
+    /// `Forwarded` header.
+    ///
+    /// No formal specification/RFC exists for this header.
+    ///
+    /// # Example
+    /// ```
+    /// # extern crate hyper;
+    /// # extern crate gotham;
+    ///
+    /// use hyper::header::Headers;
+    /// use gotham::http::header::XForwardedFor;
+    ///
+    /// # fn main () {
+    /// let mut headers = Headers::new();
+    /// headers.set(XForwardedFor(vec![String::from("192.0.2.60")]));
+    /// # }
+    /// ```
+    (XForwardedFor, "X-Forwarded-For") => (String)+
+}
@@ -0,0 +1,25 @@
+//! Defines the X-Forwarded-Host header.
+
+header! {
+    /// Defines the X-Forwarded-Host header.
+    ///
+    /// A de-facto standard header set by proxies, holding the `Host` header originally requested by
+    /// the client. The standardized equivalent is the `Forwarded` header.
+    ///
+    /// No formal specification/RFC exists for this header.
+    ///
+    /// # Example
+    /// ```
+    /// # extern crate hyper;
+    /// # extern crate gotham;
+    ///
+    /// use hyper::header::Headers;
+    /// use gotham::http::header::XForwardedHost;
+    ///
+    /// # fn main () {
+    /// let mut headers = Headers::new();
+    /// headers.set(XForwardedHost(String::from("example.com")));
+    /// # }
+    /// ```
+    (XForwardedHost, "X-Forwarded-Host") => [String]
+}
@@ -0,0 +1,25 @@
+//! Defines the X-Forwarded-Proto header.
+
+header! {
+    /// Defines the X-Forwarded-Proto header.
+    ///
+    /// A de-facto standard header set by proxies, holding the protocol originally used by the
+    /// client to connect to a proxy. The standardized equivalent is the `Forwarded` header.
+    ///
+    /// No formal specification/RFC exists for this header.
+    ///
+    /// # Example
+    /// ```
+    /// # extern crate hyper;
+    /// # extern crate gotham;
+    ///
+    /// use hyper::header::Headers;
+    /// use gotham::http::header::XForwardedProto;
+    ///
+    /// # fn main () {
+    /// let mut headers = Headers::new();
+    /// headers.set(XForwardedProto(String::from("https")));
+    /// # }
+    /// ```
+    (XForwardedProto, "X-Forwarded-Proto") => [String]
+}
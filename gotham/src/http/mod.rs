@@ -1,5 +1,6 @@
 //! Helpers for HTTP Request handling and Response generation
 
+pub mod format;
 pub mod request;
 pub mod response;
 pub mod header;
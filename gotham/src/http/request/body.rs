@@ -0,0 +1,251 @@
+//! Defines helpers for buffering the `Request` body into memory.
+
+use std::cmp;
+
+use futures::{future, Future, Stream};
+use hyper::{self, Body};
+use hyper::header::{ContentLength, Headers};
+
+use state::{FromState, State};
+
+/// The default number of bytes reserved whenever the buffer needs to grow.
+const DEFAULT_CHUNK_SIZE: usize = 8 * 1024;
+
+/// The default upper bound on the capacity reserved up front from `Content-Length`.
+const DEFAULT_MAX_PREALLOCATION: usize = 1024 * 1024;
+
+/// Buffers the `Request` body into a `Vec<u8>`, for use by extractors which operate on the whole
+/// body.
+///
+/// When the request carries a `Content-Length` header the buffer is sized from it up front, so a
+/// body of the declared length is read without reallocating. The up front allocation is bounded,
+/// so that a client cannot force a large allocation by declaring a length it never sends. When
+/// the buffer must grow, its capacity at least doubles, and grows by no less than the configured
+/// chunk size, so that a long body is copied a logarithmic number of times.
+///
+/// # Examples
+///
+/// ```rust
+/// # extern crate futures;
+/// # extern crate hyper;
+/// # extern crate gotham;
+/// # extern crate mime;
+/// #
+/// # use futures::{future, Future};
+/// # use hyper::StatusCode;
+/// # use gotham::handler::{HandlerFuture, IntoHandlerError};
+/// # use gotham::http::request::body::BodyReader;
+/// # use gotham::http::response::create_response;
+/// # use gotham::state::State;
+/// # use gotham::test::TestServer;
+/// #
+/// fn handler(mut state: State) -> Box<HandlerFuture> {
+///     let f = BodyReader::default()
+///         .with_chunk_size(64 * 1024)
+///         .read(&mut state)
+///         .then(move |result| match result {
+///             Ok(body) => {
+///                 let message = format!("{} bytes", body.len()).into_bytes();
+///                 let body = Some((message, mime::TEXT_PLAIN));
+///                 let res = create_response(&state, StatusCode::Ok, body);
+///                 future::ok((state, res))
+///             }
+///             Err(e) => future::err((state, e.into_handler_error())),
+///         });
+///
+///     Box::new(f)
+/// }
+/// #
+/// # fn main() {
+/// #   let test_server = TestServer::new(|| Ok(handler)).unwrap();
+/// #   let response = test_server
+/// #       .client()
+/// #       .post("http://localhost/", "abc", mime::TEXT_PLAIN)
+/// #       .perform()
+/// #       .unwrap();
+/// #   assert_eq!(response.read_utf8_body().unwrap(), "3 bytes");
+/// # }
+/// ```
+#[derive(Clone, Copy, Debug)]
+pub struct BodyReader {
+    chunk_size: usize,
+    max_preallocation: usize,
+}
+
+impl Default for BodyReader {
+    fn default() -> BodyReader {
+        BodyReader {
+            chunk_size: DEFAULT_CHUNK_SIZE,
+            max_preallocation: DEFAULT_MAX_PREALLOCATION,
+        }
+    }
+}
+
+impl BodyReader {
+    /// Sets the minimum number of bytes reserved whenever the buffer needs to grow. Defaults to
+    /// 8 KiB.
+    pub fn with_chunk_size(self, chunk_size: usize) -> BodyReader {
+        BodyReader {
+            chunk_size: cmp::max(chunk_size, 1),
+            ..self
+        }
+    }
+
+    /// Sets the upper bound on the capacity reserved up front from `Content-Length`. Defaults to
+    /// 1 MiB.
+    pub fn with_max_preallocation(self, max_preallocation: usize) -> BodyReader {
+        BodyReader {
+            max_preallocation,
+            ..self
+        }
+    }
+
+    /// Takes the `Body` from `State`, and resolves with its contents once fully read.
+    ///
+    /// If the `Body` has already been taken by another extractor, resolves with an empty buffer.
+    pub fn read(
+        &self,
+        state: &mut State,
+    ) -> Box<dyn Future<Item = Vec<u8>, Error = hyper::Error>> {
+        let content_length = Headers::borrow_from(state)
+            .get::<ContentLength>()
+            .map(|len| len.0);
+
+        match Body::try_take_from(state) {
+            Some(body) => self.read_body(body, content_length),
+            None => Box::new(future::ok(Vec::new())),
+        }
+    }
+
+    fn read_body(
+        &self,
+        body: Body,
+        content_length: Option<u64>,
+    ) -> Box<dyn Future<Item = Vec<u8>, Error = hyper::Error>> {
+        let capacity = content_length
+            .map(|len| cmp::min(len, self.max_preallocation as u64) as usize)
+            .unwrap_or(0);
+        let chunk_size = self.chunk_size;
+
+        Box::new(
+            body.fold(Vec::with_capacity(capacity), move |mut buf, chunk| {
+                append(&mut buf, &chunk, chunk_size);
+                Ok::<_, hyper::Error>(buf)
+            }),
+        )
+    }
+}
+
+/// Appends `chunk` to `buf`, growing `buf` by at least `chunk_size` bytes if it is full. `reserve`
+/// is amortized, so the capacity also at least doubles.
+fn append(buf: &mut Vec<u8>, chunk: &[u8], chunk_size: usize) {
+    if buf.len() + chunk.len() > buf.capacity() {
+        buf.reserve(cmp::max(chunk.len(), chunk_size));
+    }
+    buf.extend_from_slice(chunk);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use futures::sync::mpsc;
+    use hyper::Chunk;
+
+    fn chunked_body(chunks: usize, chunk_len: usize) -> Body {
+        let (mut tx, rx) = mpsc::channel(chunks);
+        for _ in 0..chunks {
+            tx.try_send(Ok(Chunk::from(vec![b'x'; chunk_len]))).unwrap();
+        }
+        Body::from(rx)
+    }
+
+    /// Appends `chunks` chunks of `chunk_len` bytes, returning how many times `buf` reallocated.
+    fn count_reallocations(
+        mut buf: Vec<u8>,
+        chunks: usize,
+        chunk_len: usize,
+        chunk_size: usize,
+    ) -> usize {
+        let chunk = vec![b'x'; chunk_len];
+        let mut capacity = buf.capacity();
+        let mut reallocations = 0;
+
+        for _ in 0..chunks {
+            append(&mut buf, &chunk, chunk_size);
+            if buf.capacity() != capacity {
+                reallocations += 1;
+                capacity = buf.capacity();
+            }
+        }
+
+        reallocations
+    }
+
+    /// The most reallocations allowed when growing to `len` bytes, `chunk_size` or more at a time,
+    /// if the capacity at least doubles on each reallocation.
+    fn logarithmic_bound(len: usize, chunk_size: usize) -> usize {
+        let mut bound = 1;
+        let mut capacity = chunk_size;
+        while capacity < len {
+            capacity *= 2;
+            bound += 1;
+        }
+        bound
+    }
+
+    #[test]
+    fn presized_buffer_does_not_reallocate() {
+        let presized = Vec::with_capacity(64 * 4096);
+        assert_eq!(count_reallocations(presized, 64, 4096, DEFAULT_CHUNK_SIZE), 0);
+    }
+
+    #[test]
+    fn unsized_buffer_reallocates_logarithmically() {
+        // A 10 MiB upload, received in 8 KiB chunks, rather than reallocating for each chunk.
+        let reallocations = count_reallocations(Vec::new(), 1280, 8192, DEFAULT_CHUNK_SIZE);
+        assert_eq!(logarithmic_bound(1280 * 8192, DEFAULT_CHUNK_SIZE), 12);
+        assert!(reallocations <= 12, "{} reallocations", reallocations);
+    }
+
+    #[test]
+    fn larger_chunk_size_reallocates_less() {
+        let large = count_reallocations(Vec::new(), 64, 4096, 64 * 1024);
+        let small = count_reallocations(Vec::new(), 64, 4096, 1);
+        assert!(large < small, "{} >= {}", large, small);
+        assert!(large <= logarithmic_bound(64 * 4096, 64 * 1024));
+        assert!(small <= logarithmic_bound(64 * 4096, 4096));
+
+        let mut buf = Vec::new();
+        append(&mut buf, b"x", 64 * 1024);
+        assert!(buf.capacity() >= 64 * 1024);
+    }
+
+    #[test]
+    fn presizes_from_content_length() {
+        let buf = BodyReader::default()
+            .read_body(chunked_body(64, 4096), Some(64 * 4096))
+            .wait()
+            .unwrap();
+        assert_eq!(buf.len(), 64 * 4096);
+        assert_eq!(buf.capacity(), 64 * 4096);
+    }
+
+    #[test]
+    fn bounds_preallocation() {
+        let buf = BodyReader::default()
+            .with_max_preallocation(16)
+            .read_body(chunked_body(3, 10), Some(1 << 40))
+            .wait()
+            .unwrap();
+        assert_eq!(buf, vec![b'x'; 30]);
+    }
+
+    #[test]
+    fn missing_body_is_empty() {
+        let mut state = State::new();
+        state.put(Headers::new());
+        let buf = BodyReader::default().read(&mut state).wait().unwrap();
+        assert!(buf.is_empty());
+    }
+}
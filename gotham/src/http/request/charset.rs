@@ -0,0 +1,124 @@
+//! Defines helpers for transcoding text bodies between their declared charset and UTF-8.
+
+use std::borrow::Cow;
+use std::error::Error;
+use std::fmt;
+
+use encoding_rs::{Encoding, UTF_8};
+use mime::{self, Mime};
+
+/// Decodes `body` from the charset declared by the `charset` parameter of `content_type`, into
+/// UTF-8.
+///
+/// Bodies without a `charset` parameter, or which are already declared as UTF-8, are returned
+/// unchanged. Charset labels are matched as described by the [WHATWG Encoding
+/// Standard](https://encoding.spec.whatwg.org/#names-and-labels), so `latin1` and `iso-8859-1`
+/// are both recognised.
+///
+/// # Examples
+///
+/// ```rust
+/// # extern crate gotham;
+/// # extern crate mime;
+/// #
+/// # use gotham::http::request::charset::decode_to_utf8;
+/// #
+/// # fn main() {
+/// let content_type = "text/plain; charset=iso-8859-1".parse().unwrap();
+/// let body = decode_to_utf8(vec![0x63, 0x61, 0x66, 0xe9], &content_type).unwrap();
+///
+/// assert_eq!(String::from_utf8(body).unwrap(), "café");
+/// # }
+/// ```
+pub fn decode_to_utf8(body: Vec<u8>, content_type: &Mime) -> Result<Vec<u8>, UnsupportedCharset> {
+    let encoding = match content_type.get_param(mime::CHARSET) {
+        Some(charset) => encoding_for(charset.as_str())?,
+        None => return Ok(body),
+    };
+
+    if encoding == UTF_8 {
+        return Ok(body);
+    }
+
+    let decoded = match encoding.decode_without_bom_handling(&body).0 {
+        Cow::Borrowed(_) => None,
+        Cow::Owned(decoded) => Some(decoded.into_bytes()),
+    };
+
+    // A borrowed result means `body` was already valid UTF-8 in the declared charset.
+    Ok(decoded.unwrap_or(body))
+}
+
+/// Encodes `text` into the charset named by `charset`, for use as a response body.
+///
+/// Characters which cannot be represented in the charset are written as HTML numeric character
+/// references, as browsers do when submitting forms.
+pub fn encode_from_utf8(text: &str, charset: &str) -> Result<Vec<u8>, UnsupportedCharset> {
+    let encoding = encoding_for(charset)?;
+    Ok(encoding.encode(text).0.into_owned())
+}
+
+fn encoding_for(charset: &str) -> Result<&'static Encoding, UnsupportedCharset> {
+    Encoding::for_label(charset.as_bytes())
+        // The replacement encoding is the decoding of last resort in the Encoding Standard, and
+        // is not a charset a client can usefully declare.
+        .filter(|encoding| encoding.name() != "replacement")
+        .ok_or_else(|| UnsupportedCharset(charset.to_owned()))
+}
+
+/// The error returned when a body declares a charset which is not supported. Extractors respond
+/// to this with `415 Unsupported Media Type`.
+#[derive(Debug, PartialEq, Eq)]
+pub struct UnsupportedCharset(pub String);
+
+impl fmt::Display for UnsupportedCharset {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "unsupported charset: {}", self.0)
+    }
+}
+
+impl Error for UnsupportedCharset {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decode(body: &[u8], content_type: &str) -> Result<String, UnsupportedCharset> {
+        decode_to_utf8(body.to_vec(), &content_type.parse().unwrap())
+            .map(|body| String::from_utf8(body).unwrap())
+    }
+
+    #[test]
+    fn decodes_declared_charset() {
+        assert_eq!(decode(b"caf\xe9", "text/plain; charset=latin1").unwrap(), "café");
+        assert_eq!(
+            decode(b"\x80", "text/plain; charset=windows-1252").unwrap(),
+            "€"
+        );
+    }
+
+    #[test]
+    fn leaves_utf8_and_undeclared_bodies_alone() {
+        assert_eq!(decode("café".as_bytes(), "text/plain").unwrap(), "café");
+        assert_eq!(
+            decode("café".as_bytes(), "text/plain; charset=utf-8").unwrap(),
+            "café"
+        );
+        assert_eq!(decode(b"ascii", "text/plain; charset=latin1").unwrap(), "ascii");
+    }
+
+    #[test]
+    fn rejects_unknown_charset() {
+        assert_eq!(
+            decode(b"x", "text/plain; charset=klingon"),
+            Err(UnsupportedCharset(String::from("klingon")))
+        );
+        assert!(decode(b"x", "text/plain; charset=iso-2022-kr").is_err());
+    }
+
+    #[test]
+    fn encodes_into_charset() {
+        assert_eq!(encode_from_utf8("café", "latin1").unwrap(), b"caf\xe9".to_vec());
+        assert!(encode_from_utf8("café", "klingon").is_err());
+    }
+}
@@ -0,0 +1,131 @@
+//! Defines a hook for mapping errors which occur while deserializing a `Request` body to the
+//! `HandlerError` sent to the client.
+
+use std::fmt;
+use std::sync::Arc;
+
+use mime::Mime;
+use serde_json;
+use serde_json::error::Category;
+
+use handler::HandlerError;
+use state::StateData;
+
+/// Distinguishes the ways in which deserializing a body can fail.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DeserializeErrorKind {
+    /// The body was not well-formed in its format, e.g. truncated or malformed JSON.
+    Syntax,
+
+    /// The body was well-formed, but its contents did not match the type being deserialized, e.g.
+    /// a missing field or a value of the wrong type.
+    Data,
+}
+
+impl DeserializeErrorKind {
+    pub(crate) fn of_json(e: &serde_json::Error) -> DeserializeErrorKind {
+        match e.classify() {
+            Category::Data => DeserializeErrorKind::Data,
+            Category::Io | Category::Syntax | Category::Eof => DeserializeErrorKind::Syntax,
+        }
+    }
+}
+
+/// Maps the `HandlerError` produced when a body cannot be deserialized, for every body extractor
+/// in the application. Configured via `ServerConfig::with_deserialize_error_mapper`.
+///
+/// The mapping function is given the `HandlerError` which would otherwise be sent, with its
+/// default status of `400 Bad Request` or `422 Unprocessable Entity`, along with the kind of
+/// error and the `Content-Type` of the request. The cause of the deserialization error remains
+/// available via `Error::cause` of the `HandlerError`.
+///
+/// This applies to `JsonBodyExtractor` and `BodyHandler`. Errors in XML bodies are always
+/// `DeserializeErrorKind::Syntax`, as the XML deserializer does not distinguish them.
+///
+/// # Examples
+///
+/// ```rust
+/// # extern crate gotham;
+/// # extern crate hyper;
+/// # extern crate mime;
+/// # #[macro_use]
+/// # extern crate serde_derive;
+/// #
+/// # use hyper::StatusCode;
+/// # use gotham::config::ServerConfig;
+/// # use gotham::handler::BodyHandler;
+/// # use gotham::http::request::deserialize_error::{DeserializeErrorKind, DeserializeErrorMapper};
+/// # use gotham::router::Router;
+/// # use gotham::router::builder::*;
+/// # use gotham::state::State;
+/// # use gotham::test::TestServer;
+/// #
+/// # #[derive(Deserialize, Serialize)]
+/// # struct Article {
+/// #     title: String,
+/// # }
+/// #
+/// # fn echo(state: State, article: Article) -> (State, Article) {
+/// #     (state, article)
+/// # }
+/// #
+/// # fn router() -> Router {
+/// #     build_simple_router(|route| {
+/// #         route.post("/articles").to_new_handler(BodyHandler::new(echo));
+/// #     })
+/// # }
+/// #
+/// # fn main() {
+/// let mapper = DeserializeErrorMapper::new(|error, kind, _content_type| match kind {
+///     DeserializeErrorKind::Data => error.with_status(StatusCode::UnprocessableEntity),
+///     DeserializeErrorKind::Syntax => error,
+/// });
+/// let config = ServerConfig::default().with_deserialize_error_mapper(mapper);
+/// #
+/// # let test_server = TestServer::with_config(router(), config).unwrap();
+/// # let post = |body: &'static str| {
+/// #     test_server
+/// #         .client()
+/// #         .post("http://localhost/articles", body, mime::APPLICATION_JSON)
+/// #         .perform()
+/// #         .unwrap()
+/// #         .status()
+/// # };
+/// #
+/// # assert_eq!(post(r#"{"title": 1}"#), StatusCode::UnprocessableEntity);
+/// # assert_eq!(post(r#"{"title": "#), StatusCode::BadRequest);
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct DeserializeErrorMapper {
+    f: Arc<MapFn>,
+}
+
+type MapFn = dyn Fn(HandlerError, DeserializeErrorKind, &Mime) -> HandlerError + Send + Sync;
+
+impl DeserializeErrorMapper {
+    /// Creates a `DeserializeErrorMapper` which maps errors using `f`.
+    pub fn new<F>(f: F) -> DeserializeErrorMapper
+    where
+        F: Fn(HandlerError, DeserializeErrorKind, &Mime) -> HandlerError + Send + Sync + 'static,
+    {
+        DeserializeErrorMapper { f: Arc::new(f) }
+    }
+
+    pub(crate) fn map(
+        &self,
+        error: HandlerError,
+        kind: DeserializeErrorKind,
+        content_type: &Mime,
+    ) -> HandlerError {
+        (self.f)(error, kind, content_type)
+    }
+}
+
+impl StateData for DeserializeErrorMapper {}
+
+impl fmt::Debug for DeserializeErrorMapper {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("DeserializeErrorMapper")
+    }
+}
@@ -0,0 +1,235 @@
+//! Defines a hook which is told of each request which an extractor fails to extract, for use in
+//! metrics.
+
+use std::fmt;
+use std::sync::Arc;
+
+use hyper;
+
+use state::{FromState, State, StateData};
+
+/// Distinguishes the ways in which an extractor can fail, as reported to an
+/// `ExtractorFailureHook`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExtractorFailureReason {
+    /// Part of the request required by the extractor was absent, e.g. the `Content-Type` header
+    /// of a body.
+    Missing,
+
+    /// Part of the request could not be parsed or deserialized, e.g. a path segment which is not
+    /// a valid number, or a malformed JSON body.
+    Parse,
+
+    /// The request body exceeded a size limit while being read.
+    SizeExceeded,
+
+    /// The request used a media type or charset which the extractor does not accept.
+    Unsupported,
+
+    /// The request body could not be read, e.g. as the client closed the connection.
+    Body,
+}
+
+impl ExtractorFailureReason {
+    pub(crate) fn of_body(e: &hyper::Error) -> ExtractorFailureReason {
+        match *e {
+            hyper::Error::TooLarge => ExtractorFailureReason::SizeExceeded,
+            _ => ExtractorFailureReason::Body,
+        }
+    }
+}
+
+/// Told of each request which an extractor fails to extract, along with the type being extracted
+/// and the reason for the failure. Configured via `ServerConfig::with_extractor_failure_hook`.
+///
+/// This applies to path and query string extractors, `JsonBodyExtractor`, `RawBodyExtractor` and
+/// `BodyHandler`, and lets operators see which classes of bad input are common. The hook is
+/// called before the error response is created, and cannot change it.
+///
+/// # Examples
+///
+/// ```rust
+/// # extern crate gotham;
+/// # #[macro_use]
+/// # extern crate gotham_derive;
+/// # extern crate hyper;
+/// # #[macro_use]
+/// # extern crate log;
+/// #
+/// # use std::sync::Arc;
+/// # use std::sync::atomic::{AtomicUsize, Ordering};
+/// # use hyper::{Response, StatusCode};
+/// # use gotham::config::ServerConfig;
+/// # use gotham::http::request::extractor_failure::{ExtractorFailureHook, ExtractorFailureReason};
+/// # use gotham::router::Router;
+/// # use gotham::router::builder::*;
+/// # use gotham::state::{FromState, State};
+/// # use gotham::test::TestServer;
+/// #
+/// # #[derive(StateData, PathExtractor, StaticResponseExtender)]
+/// # struct ProductPath {
+/// #     id: u64,
+/// # }
+/// #
+/// # fn product(state: State) -> (State, Response) {
+/// #     let body = format!("product {}", ProductPath::borrow_from(&state).id);
+/// #     (state, Response::new().with_status(StatusCode::Ok).with_body(body))
+/// # }
+/// #
+/// # fn router() -> Router {
+/// #     build_simple_router(|route| {
+/// #         route
+/// #             .get("/products/:id")
+/// #             .with_path_extractor::<ProductPath>()
+/// #             .to(product);
+/// #     })
+/// # }
+/// #
+/// # fn main() {
+/// let parse_failures = Arc::new(AtomicUsize::new(0));
+/// let counter = parse_failures.clone();
+///
+/// let hook = ExtractorFailureHook::new(move |_extractor, reason| {
+///     if reason == ExtractorFailureReason::Parse {
+///         counter.fetch_add(1, Ordering::Relaxed);
+///     }
+/// });
+/// let config = ServerConfig::default().with_extractor_failure_hook(hook);
+/// #
+/// # let test_server = TestServer::with_config(router(), config).unwrap();
+/// # let response = test_server
+/// #     .client()
+/// #     .get("http://localhost/products/abc")
+/// #     .perform()
+/// #     .unwrap();
+/// # assert_eq!(response.status(), StatusCode::BadRequest);
+/// # assert_eq!(parse_failures.load(Ordering::Relaxed), 1);
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct ExtractorFailureHook {
+    f: Arc<HookFn>,
+}
+
+type HookFn = dyn Fn(&str, ExtractorFailureReason) + Send + Sync;
+
+impl ExtractorFailureHook {
+    /// Creates an `ExtractorFailureHook` which calls `f` with the name of the type being
+    /// extracted, as given by `std::any::type_name`, and the reason for the failure.
+    pub fn new<F>(f: F) -> ExtractorFailureHook
+    where
+        F: Fn(&str, ExtractorFailureReason) + Send + Sync + 'static,
+    {
+        ExtractorFailureHook { f: Arc::new(f) }
+    }
+
+    pub(crate) fn call(&self, extractor: &str, reason: ExtractorFailureReason) {
+        (self.f)(extractor, reason)
+    }
+}
+
+impl StateData for ExtractorFailureHook {}
+
+impl fmt::Debug for ExtractorFailureHook {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("ExtractorFailureHook")
+    }
+}
+
+/// Tells the `ExtractorFailureHook` in `state`, if any, that extracting `extractor` failed.
+pub(crate) fn report_extractor_failure(
+    state: &State,
+    extractor: &str,
+    reason: ExtractorFailureReason,
+) {
+    if let Some(hook) = ExtractorFailureHook::try_borrow_from(state) {
+        hook.call(extractor, reason);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::Mutex;
+
+    use futures::{future, Future};
+    use hyper::StatusCode;
+    use mime;
+
+    use config::ServerConfig;
+    use handler::HandlerFuture;
+    use http::request::json::JsonBodyExtractor;
+    use http::response::create_response;
+    use test::TestServer;
+
+    #[derive(Deserialize)]
+    struct Order {
+        #[allow(dead_code)]
+        quantity: u32,
+    }
+
+    fn handler(mut state: State) -> Box<HandlerFuture> {
+        let f = JsonBodyExtractor::default()
+            .extract::<Order>(&mut state)
+            .then(move |result| match result {
+                Ok(_) => {
+                    let res = create_response(&state, StatusCode::Ok, None);
+                    future::ok((state, res))
+                }
+                Err(e) => future::err((state, e)),
+            });
+        Box::new(f)
+    }
+
+    fn post(content_type: mime::Mime, body: &'static str) -> Vec<(String, ExtractorFailureReason)> {
+        let failures = Arc::new(Mutex::new(Vec::new()));
+        let recorded = failures.clone();
+        let hook = ExtractorFailureHook::new(move |extractor, reason| {
+            recorded.lock().unwrap().push((extractor.to_owned(), reason));
+        });
+
+        let config = ServerConfig::default().with_extractor_failure_hook(hook);
+        let test_server = TestServer::with_config(|| Ok(handler), config).unwrap();
+        test_server
+            .client()
+            .post("http://localhost/", body, content_type)
+            .perform()
+            .unwrap();
+
+        let failures = failures.lock().unwrap();
+        failures.clone()
+    }
+
+    #[test]
+    fn reports_parse_failure() {
+        let failures = post(mime::APPLICATION_JSON, r#"{"quantity": "#);
+        assert_eq!(failures.len(), 1);
+        assert!(failures[0].0.ends_with("::Order"), "{}", failures[0].0);
+        assert_eq!(failures[0].1, ExtractorFailureReason::Parse);
+    }
+
+    #[test]
+    fn reports_unsupported_media_type() {
+        let failures = post(mime::TEXT_PLAIN, r#"{"quantity": 1}"#);
+        assert_eq!(failures.len(), 1);
+        assert_eq!(failures[0].1, ExtractorFailureReason::Unsupported);
+    }
+
+    #[test]
+    fn does_not_report_success() {
+        assert!(post(mime::APPLICATION_JSON, r#"{"quantity": 1}"#).is_empty());
+    }
+
+    #[test]
+    fn classifies_body_errors() {
+        assert_eq!(
+            ExtractorFailureReason::of_body(&hyper::Error::TooLarge),
+            ExtractorFailureReason::SizeExceeded
+        );
+        assert_eq!(
+            ExtractorFailureReason::of_body(&hyper::Error::Incomplete),
+            ExtractorFailureReason::Body
+        );
+    }
+}
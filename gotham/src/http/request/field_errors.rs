@@ -0,0 +1,194 @@
+//! Defines a collection of per-field errors, used by extractors to report every invalid value in
+//! a `Request` at once rather than only the first.
+
+use std::error::Error;
+use std::fmt;
+
+use hyper::{Response, StatusCode};
+use serde_json::{Map, Value};
+
+use handler::{IntoHandlerError, IntoResponse};
+use state::{State, StateData};
+
+/// An error in a single field of the `Request`, such as a path segment, query string value or
+/// member of a body.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FieldError {
+    field: String,
+    message: String,
+}
+
+impl FieldError {
+    /// Creates a `FieldError` for the named field.
+    pub fn new<F, M>(field: F, message: M) -> FieldError
+    where
+        F: Into<String>,
+        M: Into<String>,
+    {
+        FieldError {
+            field: field.into(),
+            message: message.into(),
+        }
+    }
+
+    /// The name of the field which was invalid.
+    pub fn field(&self) -> &str {
+        &self.field
+    }
+
+    /// Describes why the field was invalid.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+/// Every `FieldError` found while extracting a value from the `Request`.
+///
+/// When extraction by a derived `PathExtractor` or `QueryStringExtractor` fails, the errors are
+/// placed in `State`, and the derived `StaticResponseExtender` includes them in the body of the
+/// `400 Bad Request` response. `JsonBodyExtractor` reports them via the `HandlerError`.
+///
+/// The body is `application/problem+json` with an `errors` member when
+/// `ErrorFormat::ProblemJson` is configured, and otherwise `application/json` with only the
+/// `errors` member:
+///
+/// ```json
+/// {"errors": [{"field": "page", "message": "missing value"}]}
+/// ```
+///
+/// # Examples
+///
+/// ```rust
+/// # extern crate gotham;
+/// # #[macro_use]
+/// # extern crate gotham_derive;
+/// # extern crate hyper;
+/// # #[macro_use]
+/// # extern crate log;
+/// # extern crate mime;
+/// # extern crate serde_json;
+/// #
+/// # use hyper::{Response, StatusCode};
+/// # use gotham::http::response::create_response;
+/// # use gotham::router::Router;
+/// # use gotham::router::builder::*;
+/// # use gotham::state::{FromState, State};
+/// # use gotham::test::TestServer;
+/// #
+/// #[derive(StateData, QueryStringExtractor, StaticResponseExtender)]
+/// struct SearchParams {
+///     page: u32,
+///     per_page: u32,
+/// }
+///
+/// fn search(state: State) -> (State, Response) {
+///     let offset = {
+///         let params = SearchParams::borrow_from(&state);
+///         params.page * params.per_page
+///     };
+///     let body = format!("from {}", offset).into_bytes();
+///     let res = create_response(&state, StatusCode::Ok, Some((body, mime::TEXT_PLAIN)));
+///     (state, res)
+/// }
+///
+/// fn router() -> Router {
+///     build_simple_router(|route| {
+///         route
+///             .get("/search")
+///             .with_query_string_extractor::<SearchParams>()
+///             .to(search);
+///     })
+/// }
+/// #
+/// # fn main() {
+/// #   let test_server = TestServer::new(router()).unwrap();
+/// #   let response = test_server
+/// #       .client()
+/// #       .get("http://localhost/search?page=2&per_page=10")
+/// #       .perform()
+/// #       .unwrap();
+/// #   assert_eq!(response.read_utf8_body().unwrap(), "from 20");
+/// #
+/// #   let response = test_server
+/// #       .client()
+/// #       .get("http://localhost/search?per_page=many")
+/// #       .perform()
+/// #       .unwrap();
+/// #   assert_eq!(response.status(), StatusCode::BadRequest);
+/// #
+/// #   let body: serde_json::Value =
+/// #       serde_json::from_str(&response.read_utf8_body().unwrap()).unwrap();
+/// #   assert_eq!(body["errors"][0]["field"], "page");
+/// #   assert_eq!(body["errors"][0]["message"], "missing value");
+/// #   assert_eq!(body["errors"][1]["field"], "per_page");
+/// # }
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct FieldErrors {
+    errors: Vec<FieldError>,
+}
+
+impl StateData for FieldErrors {}
+
+impl FieldErrors {
+    /// Creates an empty `FieldErrors`.
+    pub fn new() -> FieldErrors {
+        FieldErrors::default()
+    }
+
+    /// Adds an error.
+    pub fn push(&mut self, error: FieldError) {
+        self.errors.push(error);
+    }
+
+    /// Returns `true` if an error has been recorded for the named field.
+    pub fn contains(&self, field: &str) -> bool {
+        self.errors.iter().any(|e| e.field == field)
+    }
+
+    /// Returns `true` if no errors have been recorded.
+    pub fn is_empty(&self) -> bool {
+        self.errors.is_empty()
+    }
+
+    /// The errors, in the order they were found.
+    pub fn errors(&self) -> &[FieldError] {
+        &self.errors
+    }
+
+    /// Replaces `res` with a response of the given status, describing these errors.
+    pub fn extend_response(self, state: &State, res: &mut Response, status: StatusCode) {
+        *res = self.into_handler_error()
+            .with_status(status)
+            .into_response(state);
+    }
+
+    /// The `errors` member of the response body.
+    pub(crate) fn to_json(&self) -> Value {
+        let errors = self.errors
+            .iter()
+            .map(|e| {
+                let mut error = Map::new();
+                error.insert("field".to_owned(), Value::from(e.field.as_str()));
+                error.insert("message".to_owned(), Value::from(e.message.as_str()));
+                Value::Object(error)
+            })
+            .collect();
+
+        Value::Array(errors)
+    }
+}
+
+impl fmt::Display for FieldErrors {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, e) in self.errors.iter().enumerate() {
+            if i > 0 {
+                f.write_str("; ")?;
+            }
+            write!(f, "`{}`: {}", e.field, e.message)?;
+        }
+        Ok(())
+    }
+}
+
+impl Error for FieldErrors {}
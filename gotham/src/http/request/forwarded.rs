@@ -0,0 +1,304 @@
+//! Defines helpers for determining details of the original client when a `Request` has been
+//! received via one or more proxies.
+
+use std::net::{IpAddr, Ipv6Addr};
+
+use hyper::header::Headers;
+
+use http::header::{Forwarded, XForwardedFor, XForwardedHost, XForwardedProto};
+use state::{FromState, State};
+
+/// Determines which proxy headers are consulted first when building `ForwardedInfo`.
+///
+/// Values missing from the preferred source are taken from the other, when present.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum ForwardedPrecedence {
+    /// Prefer the standardized `Forwarded` header defined by RFC 7239. This is the default.
+    #[default]
+    Forwarded,
+
+    /// Prefer the legacy `X-Forwarded-For`, `X-Forwarded-Proto` and `X-Forwarded-Host` headers.
+    XForwarded,
+}
+
+/// Details of the original client, as disclosed by proxies via request headers.
+///
+/// These headers are supplied by the client or intermediaries, and each proxy appends its own
+/// element to those it received. `from_headers` considers the hop closest to the client, which is
+/// whatever the client chose to send, so is only meaningful when the headers are not trusted.
+/// `from_trusted_headers` considers the element appended by the nearest proxy, and must be used
+/// when the application is deployed behind a proxy which sets them.
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct ForwardedInfo {
+    client_ip: Option<IpAddr>,
+    proto: Option<String>,
+    host: Option<String>,
+}
+
+impl ForwardedInfo {
+    /// Builds `ForwardedInfo` from the proxy headers present in `headers`.
+    ///
+    /// This uses the first element of each header, which claims to describe the original client
+    /// but may have been sent by the client itself. Use `from_trusted_headers` to rely on the
+    /// element appended by a trusted proxy instead.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # extern crate hyper;
+    /// # extern crate gotham;
+    /// #
+    /// # use hyper::header::Headers;
+    /// # use gotham::http::header::XForwardedProto;
+    /// # use gotham::http::request::forwarded::{ForwardedInfo, ForwardedPrecedence};
+    /// #
+    /// # fn main() {
+    /// let mut headers = Headers::new();
+    /// headers.set_raw("Forwarded", "for=\"[2001:db8::1]\";proto=https");
+    /// headers.set(XForwardedProto(String::from("http")));
+    ///
+    /// let info = ForwardedInfo::from_headers(&headers, ForwardedPrecedence::Forwarded);
+    /// assert_eq!(info.client_ip(), Some("2001:db8::1".parse().unwrap()));
+    /// assert_eq!(info.proto(), Some("https"));
+    ///
+    /// let info = ForwardedInfo::from_headers(&headers, ForwardedPrecedence::XForwarded);
+    /// assert_eq!(info.proto(), Some("http"));
+    /// # }
+    /// ```
+    pub fn from_headers(headers: &Headers, precedence: ForwardedPrecedence) -> ForwardedInfo {
+        ForwardedInfo::from_hop(headers, precedence, Hop::First)
+    }
+
+    /// Builds `ForwardedInfo` from the last element of each proxy header in `headers`, which was
+    /// appended by the proxy nearest to the application. Elements before it may have been sent by
+    /// the client, so are ignored. This is the element to rely upon when the nearest proxy is
+    /// trusted to set these headers.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # extern crate hyper;
+    /// # extern crate gotham;
+    /// #
+    /// # use hyper::header::Headers;
+    /// # use gotham::http::request::forwarded::{ForwardedInfo, ForwardedPrecedence};
+    /// #
+    /// # fn main() {
+    /// let mut headers = Headers::new();
+    /// // The client sent the first element, and the trusted proxy appended the second.
+    /// headers.set_raw("Forwarded", "proto=https, for=192.0.2.43;proto=http");
+    ///
+    /// let info = ForwardedInfo::from_trusted_headers(&headers, ForwardedPrecedence::Forwarded);
+    /// assert_eq!(info.client_ip(), Some("192.0.2.43".parse().unwrap()));
+    /// assert_eq!(info.proto(), Some("http"));
+    /// # }
+    /// ```
+    pub fn from_trusted_headers(
+        headers: &Headers,
+        precedence: ForwardedPrecedence,
+    ) -> ForwardedInfo {
+        ForwardedInfo::from_hop(headers, precedence, Hop::Last)
+    }
+
+    fn from_hop(headers: &Headers, precedence: ForwardedPrecedence, hop: Hop) -> ForwardedInfo {
+        let forwarded = from_forwarded(headers, hop);
+        let x_forwarded = from_x_forwarded(headers, hop);
+
+        let (preferred, fallback) = match precedence {
+            ForwardedPrecedence::Forwarded => (forwarded, x_forwarded),
+            ForwardedPrecedence::XForwarded => (x_forwarded, forwarded),
+        };
+
+        ForwardedInfo {
+            client_ip: preferred.client_ip.or(fallback.client_ip),
+            proto: preferred.proto.or(fallback.proto),
+            host: preferred.host.or(fallback.host),
+        }
+    }
+
+    /// Builds `ForwardedInfo` from the `Headers` stored in `State`.
+    ///
+    /// As with `from_headers`, this uses the first element of each header, which the client
+    /// controls. Use `from_trusted_headers` to rely on the element appended by a trusted proxy.
+    pub fn from_state(state: &State, precedence: ForwardedPrecedence) -> ForwardedInfo {
+        ForwardedInfo::from_headers(Headers::borrow_from(state), precedence)
+    }
+
+    /// The IP address of the original client, if disclosed and not obfuscated.
+    pub fn client_ip(&self) -> Option<IpAddr> {
+        self.client_ip
+    }
+
+    /// The protocol used by the original client to connect to the first proxy.
+    pub fn proto(&self) -> Option<&str> {
+        self.proto.as_deref()
+    }
+
+    /// The `Host` header originally requested by the client.
+    pub fn host(&self) -> Option<&str> {
+        self.host.as_deref()
+    }
+}
+
+/// Which element of a proxy header is considered.
+#[derive(Clone, Copy)]
+enum Hop {
+    /// The element closest to the client.
+    First,
+    /// The element appended by the nearest proxy.
+    Last,
+}
+
+impl Hop {
+    fn select<T>(self, elements: &[T]) -> Option<&T> {
+        match self {
+            Hop::First => elements.first(),
+            Hop::Last => elements.last(),
+        }
+    }
+
+    /// Selects from a comma separated value, as written by proxies which append to a header.
+    fn select_value(self, value: &str) -> String {
+        let elements: Vec<&str> = value.split(',').collect();
+        self.select(&elements).unwrap_or(&value).trim().to_owned()
+    }
+}
+
+fn from_forwarded(headers: &Headers, hop: Hop) -> ForwardedInfo {
+    match headers.get::<Forwarded>().and_then(|f| hop.select(&f.0)) {
+        Some(element) => ForwardedInfo {
+            client_ip: element.for_node.as_ref().and_then(|n| parse_node(n)),
+            proto: element.proto.clone(),
+            host: element.host.clone(),
+        },
+        None => ForwardedInfo::default(),
+    }
+}
+
+fn from_x_forwarded(headers: &Headers, hop: Hop) -> ForwardedInfo {
+    ForwardedInfo {
+        client_ip: headers
+            .get::<XForwardedFor>()
+            .and_then(|f| hop.select(&f.0))
+            .and_then(|n| parse_node(n.trim())),
+        proto: headers
+            .get::<XForwardedProto>()
+            .map(|p| hop.select_value(&p.0)),
+        host: headers
+            .get::<XForwardedHost>()
+            .map(|h| hop.select_value(&h.0)),
+    }
+}
+
+/// Parses a node identifier, which may be an IPv4 address, a bracketed IPv6 address, or either of
+/// those followed by a port. Obfuscated identifiers and "unknown" yield `None`.
+fn parse_node(node: &str) -> Option<IpAddr> {
+    if let Some(bracketed) = node.strip_prefix('[') {
+        return bracketed
+            .split(']')
+            .next()
+            .and_then(|addr| addr.parse::<Ipv6Addr>().ok())
+            .map(IpAddr::V6);
+    }
+
+    if let Ok(addr) = node.parse::<IpAddr>() {
+        return Some(addr);
+    }
+
+    node.rsplit_once(':')
+        .and_then(|(addr, _port)| addr.parse::<IpAddr>().ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use futures::future;
+    use hyper::{Response, StatusCode};
+
+    use handler::HandlerFuture;
+    use http::header::ForwardedElement;
+    use test::TestServer;
+
+    #[test]
+    fn parses_node_identifiers() {
+        assert_eq!(parse_node("192.0.2.43"), Some("192.0.2.43".parse().unwrap()));
+        assert_eq!(parse_node("192.0.2.43:47011"), Some("192.0.2.43".parse().unwrap()));
+        assert_eq!(parse_node("[2001:db8::1]"), Some("2001:db8::1".parse().unwrap()));
+        assert_eq!(
+            parse_node("[2001:db8:cafe::17]:4711"),
+            Some("2001:db8:cafe::17".parse().unwrap())
+        );
+        assert_eq!(parse_node("2001:db8::1"), Some("2001:db8::1".parse().unwrap()));
+        assert_eq!(parse_node("unknown"), None);
+        assert_eq!(parse_node("_hidden"), None);
+    }
+
+    #[test]
+    fn falls_back_to_other_source() {
+        let mut headers = Headers::new();
+        headers.set_raw("Forwarded", "proto=https");
+        headers.set(XForwardedFor(vec![String::from("198.51.100.17")]));
+        headers.set(XForwardedHost(String::from("example.com")));
+
+        let info = ForwardedInfo::from_headers(&headers, ForwardedPrecedence::Forwarded);
+        assert_eq!(info.client_ip(), Some("198.51.100.17".parse().unwrap()));
+        assert_eq!(info.proto(), Some("https"));
+        assert_eq!(info.host(), Some("example.com"));
+    }
+
+    #[test]
+    fn trusts_only_the_element_added_by_the_nearest_proxy() {
+        let mut headers = Headers::new();
+        headers.set_raw(
+            "Forwarded",
+            "for=203.0.113.9;proto=https;host=evil.example, \
+             for=198.51.100.17;proto=http;host=example.com",
+        );
+        headers.set_raw("X-Forwarded-For", "203.0.113.9, 198.51.100.17");
+        headers.set_raw("X-Forwarded-Proto", "https, http");
+        headers.set_raw("X-Forwarded-Host", "evil.example, example.com");
+
+        for &precedence in &[ForwardedPrecedence::Forwarded, ForwardedPrecedence::XForwarded] {
+            let info = ForwardedInfo::from_trusted_headers(&headers, precedence);
+            assert_eq!(info.client_ip(), Some("198.51.100.17".parse().unwrap()));
+            assert_eq!(info.proto(), Some("http"));
+            assert_eq!(info.host(), Some("example.com"));
+
+            let info = ForwardedInfo::from_headers(&headers, precedence);
+            assert_eq!(info.client_ip(), Some("203.0.113.9".parse().unwrap()));
+            assert_eq!(info.host(), Some("evil.example"));
+        }
+    }
+
+    #[test]
+    fn no_proxy_headers() {
+        let info = ForwardedInfo::from_headers(&Headers::new(), ForwardedPrecedence::default());
+        assert_eq!(info, ForwardedInfo::default());
+    }
+
+    #[test]
+    fn reads_forwarded_header_from_state() {
+        fn handler(state: State) -> Box<HandlerFuture> {
+            let info = ForwardedInfo::from_state(&state, ForwardedPrecedence::Forwarded);
+            let body = format!("{} {}", info.client_ip().unwrap(), info.proto().unwrap());
+            let response = Response::new().with_status(StatusCode::Ok).with_body(body);
+            Box::new(future::ok((state, response)))
+        }
+
+        let test_server = TestServer::new(|| Ok(handler)).unwrap();
+        let response = test_server
+            .client()
+            .get("http://localhost/")
+            .with_header(Forwarded(vec![ForwardedElement {
+                for_node: Some(String::from("[2001:db8::1]")),
+                proto: Some(String::from("https")),
+                ..ForwardedElement::default()
+            }]))
+            .perform()
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::Ok);
+        assert_eq!(response.read_utf8_body().unwrap(), "2001:db8::1 https");
+    }
+}
@@ -0,0 +1,1271 @@
+//! Defines an extractor which deserializes a JSON `Request` body.
+
+use std::any::type_name;
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::fmt;
+
+use futures::{future, Future};
+use hyper::StatusCode;
+use hyper::header::{ContentType, Headers};
+use mime::{self, Mime};
+use serde::de::{self, DeserializeOwned, IntoDeserializer};
+use serde_json::{self, Map, Value};
+
+use handler::{HandlerError, IntoHandlerError};
+use http::request::body::BodyReader;
+use http::request::charset::{decode_to_utf8, UnsupportedCharset};
+use http::request::deserialize_error::{DeserializeErrorKind, DeserializeErrorMapper};
+use http::request::extractor_failure::{report_extractor_failure, ExtractorFailureHook,
+                                       ExtractorFailureReason};
+use http::request::field_errors::{FieldError, FieldErrors};
+use state::{FromState, State, StateData};
+
+/// Deserializes the `Request` body as JSON, after checking that the `Content-Type` of the
+/// request is one of the accepted media types.
+///
+/// By default only `application/json` is accepted. Vendor media types using the `+json`
+/// structured syntax suffix (e.g. `application/vnd.api+json`, see [RFC
+/// 6839](https://tools.ietf.org/html/rfc6839)) can be accepted via `with_structured_suffix`, and
+/// further types via `with_accepted_type`. Media type parameters such as `charset` are ignored
+/// when matching.
+///
+/// Bodies declaring a `charset` other than UTF-8 can be transcoded before deserialization via
+/// `with_charset_transcoding`, for interoperability with legacy clients.
+///
+/// Requests with any other `Content-Type`, or with a `charset` which cannot be transcoded, fail
+/// with `415 Unsupported Media Type`, and bodies which are not valid JSON fail with `400 Bad
+/// Request`. An empty body, such as a chunked body holding only the terminating chunk, also fails
+/// with `400 Bad Request`, unless it is deserialized as `null` via `with_empty_as_null`.
+///
+/// When the body is a JSON object which cannot be deserialized into a struct, every missing or
+/// invalid member is reported as `FieldErrors`, failing with `422 Unprocessable Entity`. Other
+/// types, such as maps, enums and `Option`, are deserialized as by `serde_json::from_slice`.
+///
+/// Members of the object which are not fields of the deserialized struct are ignored, unless
+/// `UnknownFields::Reject` is set via `with_unknown_fields` or for the whole application via
+/// `ServerConfig::with_unknown_fields`.
+///
+/// Numbers are parsed as `u64`, `i64` or `f64`, so a decimal or a very large integer loses
+/// precision unless deserialized into a `String`. Enabling the `arbitrary_precision` feature of
+/// Gotham preserves the digits of every number exactly, for deserialization into
+/// `serde_json::Number` or a decimal type which supports it, as suits financial applications.
+/// The feature applies to all use of `serde_json` in the application.
+///
+/// # Examples
+///
+/// ```rust
+/// # extern crate futures;
+/// # extern crate hyper;
+/// # extern crate gotham;
+/// # extern crate mime;
+/// # #[macro_use]
+/// # extern crate serde_derive;
+/// #
+/// # use futures::{future, Future};
+/// # use hyper::StatusCode;
+/// # use gotham::handler::HandlerFuture;
+/// # use gotham::http::request::json::JsonBodyExtractor;
+/// # use gotham::http::response::create_response;
+/// # use gotham::state::State;
+/// # use gotham::test::TestServer;
+/// #
+/// #[derive(Deserialize)]
+/// struct Article {
+///     title: String,
+/// }
+///
+/// fn handler(mut state: State) -> Box<HandlerFuture> {
+///     let f = JsonBodyExtractor::default()
+///         .with_structured_suffix(true)
+///         .extract::<Article>(&mut state)
+///         .then(move |result| match result {
+///             Ok(article) => {
+///                 let body = Some((article.title.into_bytes(), mime::TEXT_PLAIN));
+///                 let res = create_response(&state, StatusCode::Ok, body);
+///                 future::ok((state, res))
+///             }
+///             Err(e) => future::err((state, e)),
+///         });
+///
+///     Box::new(f)
+/// }
+/// #
+/// # fn main() {
+/// #   let test_server = TestServer::new(|| Ok(handler)).unwrap();
+/// #   let response = test_server
+/// #       .client()
+/// #       .post(
+/// #           "http://localhost/",
+/// #           r#"{"title": "Gotham"}"#,
+/// #           "application/vnd.api+json".parse().unwrap(),
+/// #       )
+/// #       .perform()
+/// #       .unwrap();
+/// #   assert_eq!(response.read_utf8_body().unwrap(), "Gotham");
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct JsonBodyExtractor {
+    accepted_types: Vec<Mime>,
+    structured_suffix: bool,
+    charset_transcoding: bool,
+    empty_as_null: bool,
+    unknown_fields: Option<UnknownFields>,
+    reader: BodyReader,
+}
+
+/// What a body extractor does with members of the body which are not fields of the struct being
+/// deserialized.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum UnknownFields {
+    /// Unknown members are ignored. This is the default.
+    #[default]
+    Ignore,
+
+    /// Unknown members fail the request with `400 Bad Request`, as with serde's
+    /// `deny_unknown_fields`. Useful for catching mistakes in clients, such as misspelled fields.
+    ///
+    /// Only the members of the top level object are checked.
+    Reject,
+}
+
+impl StateData for UnknownFields {}
+
+impl Default for JsonBodyExtractor {
+    fn default() -> JsonBodyExtractor {
+        JsonBodyExtractor {
+            accepted_types: vec![mime::APPLICATION_JSON],
+            structured_suffix: false,
+            charset_transcoding: false,
+            empty_as_null: false,
+            unknown_fields: None,
+            reader: BodyReader::default(),
+        }
+    }
+}
+
+impl JsonBodyExtractor {
+    /// Sets whether media types with a `+json` structured syntax suffix are accepted, in addition
+    /// to the accepted types. Defaults to `false`.
+    pub fn with_structured_suffix(self, structured_suffix: bool) -> JsonBodyExtractor {
+        JsonBodyExtractor {
+            structured_suffix,
+            ..self
+        }
+    }
+
+    /// Adds a media type to the set of accepted types.
+    pub fn with_accepted_type(self, accepted_type: Mime) -> JsonBodyExtractor {
+        let mut accepted_types = self.accepted_types;
+        accepted_types.push(accepted_type);
+
+        JsonBodyExtractor {
+            accepted_types,
+            ..self
+        }
+    }
+
+    /// Sets whether bodies are transcoded to UTF-8 from the `charset` declared in their
+    /// `Content-Type`. Defaults to `false`, in which case the body must be UTF-8.
+    pub fn with_charset_transcoding(self, charset_transcoding: bool) -> JsonBodyExtractor {
+        JsonBodyExtractor {
+            charset_transcoding,
+            ..self
+        }
+    }
+
+    /// Sets whether a body which is empty, or holds only whitespace, is deserialized as JSON
+    /// `null`, so that an optional body can be extracted as an `Option` which is `None`. Defaults
+    /// to `false`, in which case an empty body fails with `400 Bad Request`.
+    pub fn with_empty_as_null(self, empty_as_null: bool) -> JsonBodyExtractor {
+        JsonBodyExtractor {
+            empty_as_null,
+            ..self
+        }
+    }
+
+    /// Sets what is done with members of the body which are not fields of the deserialized struct,
+    /// overriding `ServerConfig::with_unknown_fields`.
+    pub fn with_unknown_fields(self, unknown_fields: UnknownFields) -> JsonBodyExtractor {
+        JsonBodyExtractor {
+            unknown_fields: Some(unknown_fields),
+            ..self
+        }
+    }
+
+    /// Sets the `BodyReader` used to buffer the body before it is deserialized.
+    pub fn with_body_reader(self, reader: BodyReader) -> JsonBodyExtractor {
+        JsonBodyExtractor { reader, ..self }
+    }
+
+    /// Returns `true` if a request with the given `Content-Type` would be accepted.
+    pub fn accepts(&self, content_type: &Mime) -> bool {
+        if self.structured_suffix && content_type.suffix() == Some(mime::JSON) {
+            return true;
+        }
+
+        self.accepted_types.iter().any(|accepted| {
+            accepted.type_() == content_type.type_() && accepted.subtype() == content_type.subtype()
+                && accepted.suffix() == content_type.suffix()
+        })
+    }
+
+    /// Takes the `Body` from `State`, and resolves with the deserialized value once fully read.
+    pub fn extract<T>(&self, state: &mut State) -> Box<dyn Future<Item = T, Error = HandlerError>>
+    where
+        T: DeserializeOwned + 'static,
+    {
+        let content_type = Headers::borrow_from(state)
+            .get::<ContentType>()
+            .map(|ct| ct.0.clone());
+
+        let content_type = match content_type {
+            Some(ref ct) if self.accepts(ct) => ct.clone(),
+            _ => {
+                let e = JsonBodyError::UnsupportedMediaType(content_type);
+                report_extractor_failure(state, type_name::<T>(), e.extractor_failure_reason());
+                return Box::new(future::err(e.into()));
+            }
+        };
+        let charset_transcoding = self.charset_transcoding;
+        let empty_as_null = self.empty_as_null;
+        let unknown_fields = self.unknown_fields
+            .or_else(|| UnknownFields::try_borrow_from(state).cloned())
+            .unwrap_or_default();
+        let mapper = DeserializeErrorMapper::try_borrow_from(state).cloned();
+        let hook = ExtractorFailureHook::try_borrow_from(state).cloned();
+        let mapped_type = content_type.clone();
+
+        Box::new(
+            self.reader
+                .read(state)
+                .map_err(JsonBodyError::Body)
+                .and_then(move |body| {
+                    if charset_transcoding {
+                        decode_to_utf8(body, &content_type).map_err(JsonBodyError::Charset)
+                    } else {
+                        Ok(body)
+                    }
+                })
+                .and_then(move |body| {
+                    if !body.iter().all(u8::is_ascii_whitespace) {
+                        serde_json::from_slice(&body).map_err(JsonBodyError::Parse)
+                    } else if empty_as_null {
+                        Ok(Value::Null)
+                    } else {
+                        Err(JsonBodyError::Empty)
+                    }
+                })
+                .and_then(move |value| match value {
+                    value @ Value::Object(_) => from_json_object(value, unknown_fields),
+                    value => serde_json::from_value(value).map_err(JsonBodyError::Parse),
+                })
+                .map_err(move |e| {
+                    if let Some(hook) = hook {
+                        hook.call(type_name::<T>(), e.extractor_failure_reason());
+                    }
+
+                    let kind = e.deserialize_error_kind();
+                    let error = HandlerError::from(e);
+
+                    match (kind, mapper) {
+                        (Some(kind), Some(mapper)) => mapper.map(error, kind, &mapped_type),
+                        _ => error,
+                    }
+                }),
+        )
+    }
+}
+
+/// The reasons a `JsonBodyExtractor` can fail, which are carried as the cause of the
+/// `HandlerError`.
+#[derive(Debug)]
+pub enum JsonBodyError {
+    /// The `Content-Type` of the request was missing, or not an accepted media type.
+    UnsupportedMediaType(Option<Mime>),
+
+    /// The `charset` declared by the `Content-Type` of the request could not be transcoded.
+    Charset(UnsupportedCharset),
+
+    /// The body could not be read.
+    Body(::hyper::Error),
+
+    /// The body was empty, or held only whitespace.
+    Empty,
+
+    /// The body was not valid JSON, or could not be deserialized.
+    Parse(serde_json::Error),
+
+    /// The body was a JSON object, but one or more of its members were missing or invalid.
+    Fields(FieldErrors),
+
+    /// The body was a JSON object with members which are not fields of the deserialized struct.
+    /// Any other missing or invalid members are also included.
+    UnknownFields(FieldErrors),
+}
+
+impl JsonBodyError {
+    /// The HTTP status code to respond with for this error.
+    pub fn status(&self) -> StatusCode {
+        match *self {
+            JsonBodyError::UnsupportedMediaType(_) => StatusCode::UnsupportedMediaType,
+            JsonBodyError::Charset(_) => StatusCode::UnsupportedMediaType,
+            JsonBodyError::Body(_) => StatusCode::BadRequest,
+            JsonBodyError::Empty => StatusCode::BadRequest,
+            JsonBodyError::Parse(_) => StatusCode::BadRequest,
+            JsonBodyError::Fields(_) => StatusCode::UnprocessableEntity,
+            JsonBodyError::UnknownFields(_) => StatusCode::BadRequest,
+        }
+    }
+}
+
+impl JsonBodyError {
+    /// The kind of deserialization error, or `None` if the body was not deserialized.
+    fn deserialize_error_kind(&self) -> Option<DeserializeErrorKind> {
+        match *self {
+            JsonBodyError::Parse(ref e) => Some(DeserializeErrorKind::of_json(e)),
+            JsonBodyError::Empty => Some(DeserializeErrorKind::Syntax),
+            JsonBodyError::Fields(_) | JsonBodyError::UnknownFields(_) => {
+                Some(DeserializeErrorKind::Data)
+            }
+            JsonBodyError::UnsupportedMediaType(_)
+            | JsonBodyError::Charset(_)
+            | JsonBodyError::Body(_) => None,
+        }
+    }
+
+    /// The reason reported to an `ExtractorFailureHook` for this error.
+    fn extractor_failure_reason(&self) -> ExtractorFailureReason {
+        match *self {
+            JsonBodyError::UnsupportedMediaType(None) => ExtractorFailureReason::Missing,
+            JsonBodyError::UnsupportedMediaType(Some(_)) | JsonBodyError::Charset(_) => {
+                ExtractorFailureReason::Unsupported
+            }
+            JsonBodyError::Body(ref e) => ExtractorFailureReason::of_body(e),
+            JsonBodyError::Empty => ExtractorFailureReason::Missing,
+            JsonBodyError::Parse(_)
+            | JsonBodyError::Fields(_)
+            | JsonBodyError::UnknownFields(_) => ExtractorFailureReason::Parse,
+        }
+    }
+}
+
+impl From<JsonBodyError> for HandlerError {
+    fn from(e: JsonBodyError) -> HandlerError {
+        let status = e.status();
+        e.into_handler_error().with_status(status)
+    }
+}
+
+impl fmt::Display for JsonBodyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            JsonBodyError::UnsupportedMediaType(Some(ref ct)) => {
+                write!(f, "unsupported media type: {}", ct)
+            }
+            JsonBodyError::UnsupportedMediaType(None) => f.write_str("missing content type"),
+            JsonBodyError::Charset(ref e) => e.fmt(f),
+            JsonBodyError::Body(ref e) => write!(f, "unable to read body: {}", e),
+            JsonBodyError::Empty => f.write_str("empty JSON body"),
+            JsonBodyError::Parse(ref e) => write!(f, "invalid JSON body: {}", e),
+            JsonBodyError::Fields(ref e) => write!(f, "invalid JSON body: {}", e),
+            JsonBodyError::UnknownFields(ref e) => write!(f, "unknown fields in JSON body: {}", e),
+        }
+    }
+}
+
+impl Error for JsonBodyError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match *self {
+            JsonBodyError::UnsupportedMediaType(_) | JsonBodyError::Empty => None,
+            JsonBodyError::Charset(ref e) => Some(e),
+            JsonBodyError::Body(ref e) => Some(e),
+            JsonBodyError::Parse(ref e) => Some(e),
+            JsonBodyError::Fields(ref e) => Some(e),
+            JsonBodyError::UnknownFields(ref e) => Some(e),
+        }
+    }
+}
+
+/// Values substituted in turn for a member which is missing or invalid, so that deserialization
+/// can continue and report errors in the remaining members. Most types accept one of these.
+fn placeholders() -> [Value; 6] {
+    [
+        Value::Null,
+        Value::Bool(false),
+        Value::from(0),
+        Value::from(""),
+        Value::Array(Vec::new()),
+        Value::Object(Map::new()),
+    ]
+}
+
+/// The most members reported as missing or invalid for one body. Each is found by deserializing
+/// the body again, so this bounds the work caused by a body with many invalid members.
+const MAX_FIELD_ERRORS: usize = 32;
+
+/// Deserializes a JSON object, collecting an error for every member which is missing or invalid
+/// rather than stopping at the first.
+///
+/// Each failure identifies the member responsible. It is recorded, and the member is replaced by a
+/// placeholder value before deserializing again, until deserialization succeeds, no placeholder
+/// is accepted, or `MAX_FIELD_ERRORS` members have been reported. Unknown members are all found
+/// in the first pass, and removed before deserializing again.
+fn from_json_object<T>(mut value: Value, unknown_fields: UnknownFields) -> Result<T, JsonBodyError>
+where
+    T: DeserializeOwned,
+{
+    let placeholders = placeholders();
+    let mut attempts: HashMap<String, usize> = HashMap::new();
+    let mut reported: HashSet<String> = HashSet::new();
+    let mut errors = FieldErrors::new();
+    let mut unknown = false;
+    let failed = |errors, unknown| {
+        if unknown {
+            JsonBodyError::UnknownFields(errors)
+        } else {
+            JsonBodyError::Fields(errors)
+        }
+    };
+
+    loop {
+        let deserializer = ObjectDeserializer {
+            value: &value,
+            unknown_fields: if unknown {
+                UnknownFields::Ignore
+            } else {
+                unknown_fields
+            },
+        };
+
+        let field = match T::deserialize(deserializer) {
+            Ok(value) if errors.is_empty() => return Ok(value),
+            Ok(_) => return Err(failed(errors, unknown)),
+            Err(MemberError::Invalid(field, message)) => {
+                if reported.insert(field.clone()) {
+                    errors.push(FieldError::new(field.as_str(), message));
+                }
+                field
+            }
+            Err(MemberError::Missing(field)) => {
+                if reported.insert(field.to_owned()) {
+                    errors.push(FieldError::new(field, "missing field"));
+                }
+                field.to_owned()
+            }
+            Err(MemberError::Unknown(fields)) => {
+                for field in fields {
+                    if let Value::Object(ref mut object) = value {
+                        object.remove(&field);
+                    }
+                    reported.insert(field.clone());
+                    errors.push(FieldError::new(field, "unknown field"));
+                }
+                unknown = true;
+
+                if reported.len() >= MAX_FIELD_ERRORS {
+                    return Err(failed(errors, unknown));
+                }
+                continue;
+            }
+            Err(MemberError::Json(e)) => return Err(JsonBodyError::Parse(e)),
+            Err(MemberError::Other(message)) => {
+                if errors.is_empty() {
+                    return Err(JsonBodyError::Parse(de::Error::custom(message)));
+                }
+                return Err(failed(errors, unknown));
+            }
+        };
+
+        if reported.len() >= MAX_FIELD_ERRORS {
+            return Err(failed(errors, unknown));
+        }
+
+        let attempt = attempts.entry(field.clone()).or_insert(0);
+        match placeholders.get(*attempt) {
+            Some(placeholder) => {
+                value[field.as_str()] = placeholder.clone();
+                *attempt += 1;
+            }
+            None => return Err(failed(errors, unknown)),
+        }
+    }
+}
+
+/// An error raised by `ObjectDeserializer`, identifying the member responsible where possible.
+#[derive(Debug)]
+enum MemberError {
+    Invalid(String, String),
+    Missing(&'static str),
+    Unknown(Vec<String>),
+    Json(serde_json::Error),
+    Other(String),
+}
+
+impl fmt::Display for MemberError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            MemberError::Invalid(ref field, ref message) => write!(f, "`{}`: {}", field, message),
+            MemberError::Missing(field) => write!(f, "missing field `{}`", field),
+            MemberError::Unknown(ref fields) => {
+                write!(f, "unknown fields `{}`", fields.join("`, `"))
+            }
+            MemberError::Json(ref e) => e.fmt(f),
+            MemberError::Other(ref message) => f.write_str(message),
+        }
+    }
+}
+
+impl Error for MemberError {}
+
+impl de::Error for MemberError {
+    fn custom<M: fmt::Display>(message: M) -> MemberError {
+        MemberError::Other(message.to_string())
+    }
+
+    fn missing_field(field: &'static str) -> MemberError {
+        MemberError::Missing(field)
+    }
+
+    fn unknown_field(field: &str, _expected: &'static [&'static str]) -> MemberError {
+        MemberError::Unknown(vec![field.to_owned()])
+    }
+}
+
+/// Deserializes a JSON object into a struct, attributing any error in a member's value to that
+/// member. Any other type is deserialized from the object as `serde_json` would.
+struct ObjectDeserializer<'a> {
+    value: &'a Value,
+    unknown_fields: UnknownFields,
+}
+
+impl<'de> de::Deserializer<'de> for ObjectDeserializer<'de> {
+    type Error = MemberError;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, MemberError>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.value
+            .deserialize_any(visitor)
+            .map_err(MemberError::Json)
+    }
+
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value, MemberError>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.value
+            .deserialize_option(visitor)
+            .map_err(MemberError::Json)
+    }
+
+    fn deserialize_newtype_struct<V>(
+        self,
+        name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, MemberError>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.value
+            .deserialize_newtype_struct(name, visitor)
+            .map_err(MemberError::Json)
+    }
+
+    fn deserialize_enum<V>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, MemberError>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.value
+            .deserialize_enum(name, variants, visitor)
+            .map_err(MemberError::Json)
+    }
+
+    fn deserialize_struct<V>(
+        self,
+        name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, MemberError>
+    where
+        V: de::Visitor<'de>,
+    {
+        let object = match *self.value {
+            Value::Object(ref object) => object,
+            ref value => {
+                return value
+                    .deserialize_struct(name, fields, visitor)
+                    .map_err(MemberError::Json)
+            }
+        };
+
+        if self.unknown_fields == UnknownFields::Reject {
+            let unknown: Vec<String> = object
+                .keys()
+                .filter(|key| !fields.contains(&key.as_str()))
+                .take(MAX_FIELD_ERRORS)
+                .cloned()
+                .collect();
+
+            if !unknown.is_empty() {
+                return Err(MemberError::Unknown(unknown));
+            }
+        }
+
+        visitor.visit_map(ObjectAccess {
+            members: object.iter(),
+            current: None,
+        })
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string bytes byte_buf
+        unit unit_struct seq tuple tuple_struct map identifier ignored_any
+    }
+}
+
+struct ObjectAccess<'a> {
+    members: serde_json::map::Iter<'a>,
+    current: Option<(&'a String, &'a Value)>,
+}
+
+impl<'de> de::MapAccess<'de> for ObjectAccess<'de> {
+    type Error = MemberError;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>, MemberError>
+    where
+        K: de::DeserializeSeed<'de>,
+    {
+        match self.members.next() {
+            Some((key, value)) => {
+                let k = seed.deserialize(key.as_str().into_deserializer())?;
+                self.current = Some((key, value));
+                Ok(Some(k))
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value, MemberError>
+    where
+        V: de::DeserializeSeed<'de>,
+    {
+        let (key, value) = self.current
+            .take()
+            .expect("next_value_seed called before next_key_seed");
+
+        seed.deserialize(value)
+            .map_err(|e| MemberError::Invalid(key.clone(), e.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use hyper::{Method, Response, Uri};
+
+    use config::ServerConfig;
+    use handler::HandlerFuture;
+    use http::request::query_string;
+    use http::response::create_response;
+    use router::builder::*;
+    use router::request::query_string::QueryStringExtractor;
+    use router::response::extender::StaticResponseExtender;
+    use state::StateData;
+    use test::TestServer;
+
+    #[derive(Deserialize)]
+    struct Article {
+        title: String,
+    }
+
+    fn post(extractor: JsonBodyExtractor, content_type: &str) -> StatusCode {
+        post_bytes(extractor, content_type, br#"{"title": "Gotham"}"#.to_vec()).0
+    }
+
+    fn post_bytes(
+        extractor: JsonBodyExtractor,
+        content_type: &str,
+        body: Vec<u8>,
+    ) -> (StatusCode, String) {
+        let test_server = TestServer::new(move || {
+            let extractor = extractor.clone();
+            Ok(move |mut state: State| -> Box<HandlerFuture> {
+                let f = extractor
+                    .extract::<Article>(&mut state)
+                    .then(move |result| match result {
+                        Ok(article) => {
+                            let body = Some((article.title.into_bytes(), mime::TEXT_PLAIN));
+                            let res = create_response(&state, StatusCode::Ok, body);
+                            future::ok((state, res))
+                        }
+                        Err(e) => future::err((state, e)),
+                    });
+
+                Box::new(f)
+            })
+        }).unwrap();
+
+        let response = test_server
+            .client()
+            .post("http://localhost/", body, content_type.parse().unwrap())
+            .perform()
+            .unwrap();
+
+        let status = response.status();
+        (status, response.read_utf8_body().unwrap())
+    }
+
+    #[test]
+    fn accepts_json() {
+        let extractor = JsonBodyExtractor::default();
+        assert!(extractor.accepts(&mime::APPLICATION_JSON));
+        assert!(extractor.accepts(&"application/json; charset=utf-8".parse().unwrap()));
+        assert!(!extractor.accepts(&"application/vnd.api+json".parse().unwrap()));
+        assert!(!extractor.accepts(&mime::TEXT_PLAIN));
+    }
+
+    #[test]
+    fn accepts_structured_suffix_when_enabled() {
+        let extractor = JsonBodyExtractor::default().with_structured_suffix(true);
+        assert!(extractor.accepts(&"application/vnd.api+json".parse().unwrap()));
+        assert!(extractor.accepts(&"application/problem+json".parse().unwrap()));
+        assert!(!extractor.accepts(&"application/vnd.api+xml".parse().unwrap()));
+    }
+
+    #[test]
+    fn accepts_configured_types() {
+        let extractor =
+            JsonBodyExtractor::default().with_accepted_type("text/json".parse().unwrap());
+        assert!(extractor.accepts(&"text/json".parse().unwrap()));
+        assert!(extractor.accepts(&mime::APPLICATION_JSON));
+    }
+
+    #[test]
+    fn parses_body_with_media_type_parameters() {
+        let extractor = JsonBodyExtractor::default();
+        assert_eq!(
+            post(extractor.clone(), "application/json; charset=utf-8"),
+            StatusCode::Ok
+        );
+        assert_eq!(
+            post(extractor, "Application/JSON; Charset=\"UTF-8\""),
+            StatusCode::Ok
+        );
+    }
+
+    #[test]
+    fn recognizes_content_type_header_name_in_any_case() {
+        fn title(mut state: State) -> Box<HandlerFuture> {
+            let f = JsonBodyExtractor::default()
+                .extract::<Article>(&mut state)
+                .then(move |result| match result {
+                    Ok(article) => {
+                        let body = Some((article.title.into_bytes(), mime::TEXT_PLAIN));
+                        let res = create_response(&state, StatusCode::Ok, body);
+                        future::ok((state, res))
+                    }
+                    Err(e) => future::err((state, e)),
+                });
+
+            Box::new(f)
+        }
+
+        let test_server = TestServer::new(|| Ok(title)).unwrap();
+        let response = test_server
+            .client()
+            .build_request(Method::Post, "http://localhost/")
+            .with_raw_header("content-TYPE", "application/json")
+            .with_body(r#"{"title": "Gotham"}"#)
+            .perform()
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::Ok);
+        assert_eq!(response.read_utf8_body().unwrap(), "Gotham");
+    }
+
+    struct DraftParams {
+        draft: bool,
+    }
+
+    impl StateData for DraftParams {}
+
+    impl StaticResponseExtender for DraftParams {
+        fn extend(_: &mut State, _: &mut Response) {}
+    }
+
+    impl QueryStringExtractor for DraftParams {
+        fn extract(state: &mut State) -> Result<(), String> {
+            let draft = query_string::split(Uri::borrow_from(state).query())
+                .get("draft")
+                .and_then(|values| values.first())
+                .is_some_and(|value| value.val() == "true");
+
+            state.put(DraftParams { draft });
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn parses_body_alongside_query_string() {
+        fn save(mut state: State) -> Box<HandlerFuture> {
+            let f = JsonBodyExtractor::default()
+                .extract::<Article>(&mut state)
+                .then(move |result| match result {
+                    Ok(article) => {
+                        let draft = DraftParams::borrow_from(&state).draft;
+                        let body = format!("{} (draft: {})", article.title, draft);
+                        let body = Some((body.into_bytes(), mime::TEXT_PLAIN));
+                        let res = create_response(&state, StatusCode::Ok, body);
+                        future::ok((state, res))
+                    }
+                    Err(e) => future::err((state, e)),
+                });
+
+            Box::new(f)
+        }
+
+        let router = build_simple_router(|route| {
+            route
+                .post("/articles")
+                .with_query_string_extractor::<DraftParams>()
+                .to(save);
+        });
+
+        let test_server = TestServer::new(router).unwrap();
+        let response = test_server
+            .client()
+            .post(
+                "http://localhost/articles?draft=true",
+                r#"{"title": "Gotham"}"#,
+                mime::APPLICATION_JSON,
+            )
+            .perform()
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::Ok);
+        assert_eq!(response.read_utf8_body().unwrap(), "Gotham (draft: true)");
+    }
+
+    #[test]
+    fn parses_structured_suffix_body() {
+        let extractor = JsonBodyExtractor::default().with_structured_suffix(true);
+        assert_eq!(post(extractor, "application/vnd.api+json"), StatusCode::Ok);
+    }
+
+    #[test]
+    fn rejects_structured_suffix_by_default() {
+        assert_eq!(
+            post(JsonBodyExtractor::default(), "application/vnd.api+json"),
+            StatusCode::UnsupportedMediaType
+        );
+    }
+
+    #[test]
+    fn transcodes_declared_charset() {
+        let extractor = JsonBodyExtractor::default().with_charset_transcoding(true);
+        let body = b"{\"title\": \"Caf\xe9\"}".to_vec();
+        let (status, title) = post_bytes(extractor, "application/json; charset=iso-8859-1", body);
+        assert_eq!(status, StatusCode::Ok);
+        assert_eq!(title, "Café");
+    }
+
+    #[test]
+    fn rejects_unsupported_charset() {
+        let extractor = JsonBodyExtractor::default().with_charset_transcoding(true);
+        let body = br#"{"title": "Gotham"}"#.to_vec();
+        let (status, _) = post_bytes(extractor, "application/json; charset=klingon", body);
+        assert_eq!(status, StatusCode::UnsupportedMediaType);
+    }
+
+    #[test]
+    fn rejects_non_utf8_body_without_transcoding() {
+        let body = b"{\"title\": \"Caf\xe9\"}".to_vec();
+        let (status, _) = post_bytes(
+            JsonBodyExtractor::default(),
+            "application/json; charset=iso-8859-1",
+            body,
+        );
+        assert_eq!(status, StatusCode::BadRequest);
+    }
+
+    #[test]
+    fn rejects_invalid_body() {
+        let mut state = State::new();
+        let mut headers = Headers::new();
+        headers.set(ContentType::json());
+        state.put(headers);
+        state.put(::hyper::Body::from("{"));
+
+        let result = JsonBodyExtractor::default()
+            .extract::<Article>(&mut state)
+            .wait();
+
+        match result {
+            Err(e) => assert_eq!(e.status(), StatusCode::BadRequest),
+            Ok(_) => panic!("expected invalid body to be rejected"),
+        }
+    }
+
+    #[derive(Deserialize)]
+    #[allow(dead_code)]
+    struct Order {
+        quantity: u32,
+        email: String,
+        note: Option<String>,
+        express: bool,
+    }
+
+    fn post_order(body: &str) -> (StatusCode, String) {
+        post_order_with(JsonBodyExtractor::default(), ServerConfig::default(), body)
+    }
+
+    fn post_order_with(
+        extractor: JsonBodyExtractor,
+        config: ServerConfig,
+        body: &str,
+    ) -> (StatusCode, String) {
+        let test_server = TestServer::with_config(move || {
+            let extractor = extractor.clone();
+            Ok(move |mut state: State| -> Box<HandlerFuture> {
+                let f = extractor
+                    .extract::<Order>(&mut state)
+                    .then(move |result| match result {
+                        Ok(_) => {
+                            let res = create_response(&state, StatusCode::Ok, None);
+                            future::ok((state, res))
+                        }
+                        Err(e) => future::err((state, e)),
+                    });
+
+                Box::new(f)
+            })
+        }, config).unwrap();
+
+        let response = test_server
+            .client()
+            .post("http://localhost/", body.to_owned(), mime::APPLICATION_JSON)
+            .perform()
+            .unwrap();
+
+        let status = response.status();
+        (status, response.read_utf8_body().unwrap())
+    }
+
+    #[test]
+    fn accepts_valid_object() {
+        let (status, _) = post_order(r#"{"quantity": 2, "email": "a@example.com", "express": true}"#);
+        assert_eq!(status, StatusCode::Ok);
+    }
+
+    #[test]
+    fn reports_every_invalid_member() {
+        let (status, body) = post_order(r#"{"quantity": "many", "email": 5, "note": null}"#);
+        assert_eq!(status, StatusCode::UnprocessableEntity);
+
+        let body: Value = serde_json::from_str(&body).unwrap();
+        let fields: Vec<&str> = body["errors"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|e| e["field"].as_str().unwrap())
+            .collect();
+
+        assert_eq!(fields.len(), 3);
+        assert!(fields.contains(&"quantity"));
+        assert!(fields.contains(&"email"));
+        assert!(fields.contains(&"express"));
+        assert_eq!(body["errors"][2]["message"], "missing field");
+    }
+
+    #[test]
+    fn rejects_non_object_body() {
+        let (status, _) = post_order("[1, 2]");
+        assert_eq!(status, StatusCode::BadRequest);
+    }
+
+    #[test]
+    fn ignores_unknown_fields_by_default() {
+        let body = r#"{"quantity": 2, "email": "a@example.com", "express": true, "colour": 1}"#;
+        let (status, _) = post_order(body);
+        assert_eq!(status, StatusCode::Ok);
+    }
+
+    #[test]
+    fn rejects_unknown_fields_when_strict() {
+        let body = r#"{"quantity": 2, "email": "a@example.com", "express": true, "colour": 1}"#;
+        let extractor = JsonBodyExtractor::default().with_unknown_fields(UnknownFields::Reject);
+        let (status, body) = post_order_with(extractor, ServerConfig::default(), body);
+        assert_eq!(status, StatusCode::BadRequest);
+
+        let body: Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(body["errors"][0]["field"], "colour");
+        assert_eq!(body["errors"][0]["message"], "unknown field");
+    }
+
+    #[test]
+    fn reports_every_unknown_field_in_one_pass() {
+        let body = r#"{"quantity": 2, "email": 5, "express": true, "colour": 1, "size": 2}"#;
+        let extractor = JsonBodyExtractor::default().with_unknown_fields(UnknownFields::Reject);
+        let (status, body) = post_order_with(extractor, ServerConfig::default(), body);
+        assert_eq!(status, StatusCode::BadRequest);
+
+        let body: Value = serde_json::from_str(&body).unwrap();
+        let fields: Vec<&str> = body["errors"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|e| e["field"].as_str().unwrap())
+            .collect();
+        assert_eq!(fields, vec!["colour", "size", "email"]);
+    }
+
+    #[test]
+    fn limits_unknown_fields_reported() {
+        let members: Vec<String> = (0..5000).map(|i| format!(r#""extra{}": 1"#, i)).collect();
+        let body = format!("{{{}}}", members.join(", "));
+        let value = serde_json::from_str(&body).unwrap();
+
+        match from_json_object::<Order>(value, UnknownFields::Reject) {
+            Err(JsonBodyError::UnknownFields(errors)) => {
+                assert_eq!(errors.errors().len(), MAX_FIELD_ERRORS);
+            }
+            _ => panic!("expected unknown fields to be rejected"),
+        }
+    }
+
+    #[test]
+    fn unknown_fields_policy_is_configurable_globally_and_per_extractor() {
+        let body = r#"{"quantity": 2, "email": "a@example.com", "express": true, "colour": 1}"#;
+        let config = ServerConfig::default().with_unknown_fields(UnknownFields::Reject);
+
+        let (status, _) = post_order_with(JsonBodyExtractor::default(), config.clone(), body);
+        assert_eq!(status, StatusCode::BadRequest);
+
+        let extractor = JsonBodyExtractor::default().with_unknown_fields(UnknownFields::Ignore);
+        let (status, _) = post_order_with(extractor, config, body);
+        assert_eq!(status, StatusCode::Ok);
+    }
+
+    #[test]
+    fn maps_deserialize_errors_by_kind() {
+        let mapper = DeserializeErrorMapper::new(|error, kind, content_type| {
+            assert_eq!(*content_type, mime::APPLICATION_JSON);
+            match kind {
+                DeserializeErrorKind::Syntax => error.with_status(StatusCode::BadRequest),
+                DeserializeErrorKind::Data => error.with_status(StatusCode::UnprocessableEntity),
+            }
+        });
+        let config = ServerConfig::default().with_deserialize_error_mapper(mapper);
+        let post = |body| post_order_with(JsonBodyExtractor::default(), config.clone(), body).0;
+
+        assert_eq!(post(r#"{"quantity": "#), StatusCode::BadRequest);
+        assert_eq!(post("[1, 2]"), StatusCode::UnprocessableEntity);
+        assert_eq!(post(r#"{"quantity": "many"}"#), StatusCode::UnprocessableEntity);
+        assert_eq!(
+            post_order_with(JsonBodyExtractor::default(), ServerConfig::default(), "[1, 2]").0,
+            StatusCode::BadRequest
+        );
+    }
+
+    /// Posts `body`, extracting a `T` and responding with it as rendered by `render`.
+    fn post_as<T>(body: &str, render: fn(T) -> String) -> (StatusCode, String)
+    where
+        T: DeserializeOwned + 'static,
+    {
+        let test_server = TestServer::new(move || {
+            Ok(move |mut state: State| -> Box<HandlerFuture> {
+                let f = JsonBodyExtractor::default()
+                    .extract::<T>(&mut state)
+                    .then(move |result| match result {
+                        Ok(value) => {
+                            let body = Some((render(value).into_bytes(), mime::TEXT_PLAIN));
+                            let res = create_response(&state, StatusCode::Ok, body);
+                            future::ok((state, res))
+                        }
+                        Err(e) => future::err((state, e)),
+                    });
+
+                Box::new(f)
+            })
+        }).unwrap();
+
+        let response = test_server
+            .client()
+            .post("http://localhost/", body.to_owned(), mime::APPLICATION_JSON)
+            .perform()
+            .unwrap();
+
+        let status = response.status();
+        (status, response.read_utf8_body().unwrap())
+    }
+
+    #[test]
+    fn deserializes_optional_struct() {
+        let render = |article: Option<Article>| format!("{:?}", article.map(|a| a.title));
+        assert_eq!(
+            post_as(r#"{"title": "Gotham"}"#, render),
+            (StatusCode::Ok, r#"Some("Gotham")"#.to_owned())
+        );
+    }
+
+    #[test]
+    fn deserializes_externally_tagged_enum() {
+        #[derive(Deserialize)]
+        enum Command {
+            Start { n: u32 },
+            Stop,
+        }
+
+        let render = |command| match command {
+            Command::Start { n } => format!("start {}", n),
+            Command::Stop => "stop".to_owned(),
+        };
+        assert_eq!(
+            post_as(r#"{"Start": {"n": 1}}"#, render),
+            (StatusCode::Ok, "start 1".to_owned())
+        );
+    }
+
+    #[test]
+    fn deserializes_newtype_struct() {
+        #[derive(Deserialize)]
+        struct Wrap(Article);
+
+        let render = |wrap: Wrap| wrap.0.title;
+        assert_eq!(
+            post_as(r#"{"title": "Gotham"}"#, render),
+            (StatusCode::Ok, "Gotham".to_owned())
+        );
+    }
+
+    #[test]
+    fn deserializes_map() {
+        fn render(map: HashMap<String, u32>) -> String {
+            let mut entries: Vec<_> = map.into_iter().collect();
+            entries.sort();
+            format!("{:?}", entries)
+        }
+
+        assert_eq!(
+            post_as(r#"{"b": 2, "a": 1}"#, render),
+            (StatusCode::Ok, r#"[("a", 1), ("b", 2)]"#.to_owned())
+        );
+        assert_eq!(
+            post_as(r#"{"a": "one"}"#, render).0,
+            StatusCode::BadRequest
+        );
+    }
+
+    #[test]
+    fn stops_at_first_invalid_member_of_map() {
+        let members: Vec<String> = (0..5000).map(|i| format!(r#""{}": "x""#, i)).collect();
+        let body = format!("{{{}}}", members.join(", "));
+        let value = serde_json::from_str(&body).unwrap();
+
+        match from_json_object::<HashMap<String, u32>>(value, UnknownFields::Ignore) {
+            Err(JsonBodyError::Parse(_)) => (),
+            _ => panic!("expected the first invalid member to fail deserialization"),
+        }
+    }
+
+    /// Posts a chunked body holding only the terminating chunk, extracting an `Option<Article>`.
+    fn post_empty_chunked(extractor: JsonBodyExtractor) -> (StatusCode, String) {
+        let test_server = TestServer::new(move || {
+            let extractor = extractor.clone();
+            Ok(move |mut state: State| -> Box<HandlerFuture> {
+                let f = extractor
+                    .extract::<Option<Article>>(&mut state)
+                    .then(move |result| match result {
+                        Ok(article) => {
+                            let title = format!("{:?}", article.map(|article| article.title));
+                            let body = Some((title.into_bytes(), mime::TEXT_PLAIN));
+                            let res = create_response(&state, StatusCode::Ok, body);
+                            future::ok((state, res))
+                        }
+                        Err(e) => future::err((state, e)),
+                    });
+
+                Box::new(f)
+            })
+        }).unwrap();
+
+        let (tx, body) = ::hyper::Body::pair();
+        drop(tx);
+
+        let response = test_server
+            .client()
+            .post("http://localhost/", body, mime::APPLICATION_JSON)
+            .perform()
+            .unwrap();
+
+        let status = response.status();
+        (status, response.read_utf8_body().unwrap())
+    }
+
+    #[test]
+    fn rejects_empty_chunked_body() {
+        assert_eq!(
+            post_empty_chunked(JsonBodyExtractor::default()).0,
+            StatusCode::BadRequest
+        );
+        assert_eq!(JsonBodyError::Empty.to_string(), "empty JSON body");
+    }
+
+    #[test]
+    fn deserializes_empty_body_as_null_when_enabled() {
+        let extractor = JsonBodyExtractor::default().with_empty_as_null(true);
+        assert_eq!(
+            post_empty_chunked(extractor),
+            (StatusCode::Ok, "None".to_owned())
+        );
+    }
+
+    #[cfg(feature = "arbitrary_precision")]
+    #[test]
+    fn preserves_arbitrary_precision_numbers() {
+        #[derive(Deserialize)]
+        struct Payment {
+            amount: serde_json::Number,
+        }
+
+        let test_server = TestServer::new(|| {
+            Ok(|mut state: State| -> Box<HandlerFuture> {
+                let f = JsonBodyExtractor::default()
+                    .extract::<Payment>(&mut state)
+                    .then(move |result| match result {
+                        Ok(payment) => {
+                            let amount = payment.amount.to_string().into_bytes();
+                            let body = Some((amount, mime::TEXT_PLAIN));
+                            let res = create_response(&state, StatusCode::Ok, body);
+                            future::ok((state, res))
+                        }
+                        Err(e) => future::err((state, e)),
+                    });
+                Box::new(f)
+            })
+        }).unwrap();
+
+        let amount = "123456789012345678901234567890.000000000000000001";
+        let response = test_server
+            .client()
+            .post(
+                "http://localhost/",
+                format!(r#"{{"amount": {}}}"#, amount),
+                mime::APPLICATION_JSON,
+            )
+            .perform()
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::Ok);
+        assert_eq!(response.read_utf8_body().unwrap(), amount);
+    }
+}
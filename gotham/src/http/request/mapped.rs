@@ -0,0 +1,305 @@
+//! Defines helpers for spooling the `Request` body to a temporary file, and accessing it via a
+//! memory map.
+
+use std::env;
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::ops::Deref;
+use std::os::unix::io::AsRawFd;
+use std::path::{Path, PathBuf};
+use std::ptr;
+use std::slice;
+
+use futures::{future, Future, Stream};
+use hyper::Body;
+use libc;
+use uuid::Uuid;
+
+use state::{FromState, State};
+
+/// Spools the `Request` body to a temporary file, and resolves with a `MappedBody` giving access
+/// to the whole body via a memory map.
+///
+/// This suits very large bodies which must be processed as a whole, and which would otherwise
+/// need to be buffered into memory by `BodyReader`. Pages of the body are loaded by the operating
+/// system as they are accessed, and may be evicted again, so the resident size of the process is
+/// bounded regardless of the size of the body.
+///
+/// The temporary file is removed from the file system as soon as it is created, and its storage is
+/// released when the `MappedBody` is dropped, so no cleanup is required by the application, even
+/// if the request fails part way.
+///
+/// Only available on Unix platforms.
+///
+/// # Examples
+///
+/// ```rust
+/// # extern crate futures;
+/// # extern crate hyper;
+/// # extern crate gotham;
+/// # extern crate mime;
+/// #
+/// # use futures::{future, Future};
+/// # use hyper::StatusCode;
+/// # use gotham::handler::{HandlerFuture, IntoHandlerError};
+/// # use gotham::http::request::mapped::MappedBodyReader;
+/// # use gotham::http::response::create_response;
+/// # use gotham::state::State;
+/// # use gotham::test::TestServer;
+/// #
+/// fn handler(mut state: State) -> Box<HandlerFuture> {
+///     let f = MappedBodyReader::default()
+///         .read(&mut state)
+///         .then(move |result| match result {
+///             Ok(body) => {
+///                 let trailer = body[body.len() - 3..].to_vec();
+///                 let body = Some((trailer, mime::TEXT_PLAIN));
+///                 let res = create_response(&state, StatusCode::Ok, body);
+///                 future::ok((state, res))
+///             }
+///             Err(e) => future::err((state, e.into_handler_error())),
+///         });
+///
+///     Box::new(f)
+/// }
+/// #
+/// # fn main() {
+/// #   let test_server = TestServer::new(|| Ok(handler)).unwrap();
+/// #   let response = test_server
+/// #       .client()
+/// #       .post("http://localhost/", "header...trailer:end", mime::TEXT_PLAIN)
+/// #       .perform()
+/// #       .unwrap();
+/// #   assert_eq!(response.read_utf8_body().unwrap(), "end");
+/// # }
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct MappedBodyReader {
+    temp_dir: Option<PathBuf>,
+}
+
+impl MappedBodyReader {
+    /// Sets the directory in which the temporary file is created. Defaults to the directory
+    /// returned by `std::env::temp_dir`.
+    pub fn with_temp_dir<P>(self, temp_dir: P) -> MappedBodyReader
+    where
+        P: AsRef<Path>,
+    {
+        MappedBodyReader {
+            temp_dir: Some(temp_dir.as_ref().to_path_buf()),
+        }
+    }
+
+    /// Takes the `Body` from `State`, writes it to a temporary file, and resolves with a
+    /// `MappedBody` once fully written.
+    ///
+    /// If the `Body` has already been taken by another extractor, resolves with an empty
+    /// `MappedBody`.
+    pub fn read(&self, state: &mut State) -> Box<dyn Future<Item = MappedBody, Error = io::Error>> {
+        let body = match Body::try_take_from(state) {
+            Some(body) => body,
+            None => return Box::new(future::ok(MappedBody::empty())),
+        };
+
+        let file = match self.spool_file() {
+            Ok(file) => file,
+            Err(e) => return Box::new(future::err(e)),
+        };
+
+        Box::new(
+            body.map_err(io::Error::other)
+                .fold(file, |mut file, chunk| {
+                    file.write_all(&chunk).map(|()| file)
+                })
+                .and_then(MappedBody::map),
+        )
+    }
+
+    /// Creates the temporary file, and removes it from the file system so that its storage is
+    /// released once closed.
+    fn spool_file(&self) -> io::Result<File> {
+        let dir = match self.temp_dir {
+            Some(ref dir) => dir.clone(),
+            None => env::temp_dir(),
+        };
+        let path = dir.join(format!("gotham-body-{}", Uuid::new_v4()));
+
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create_new(true)
+            .open(&path)?;
+        fs::remove_file(&path)?;
+
+        Ok(file)
+    }
+}
+
+/// The `Request` body, spooled to a temporary file by `MappedBodyReader` and mapped into memory.
+///
+/// Dereferences to the bytes of the body. The mapping is removed, and the storage of the
+/// temporary file released, when the `MappedBody` is dropped.
+pub struct MappedBody {
+    ptr: *mut u8,
+    len: usize,
+    _file: Option<File>,
+}
+
+impl MappedBody {
+    fn empty() -> MappedBody {
+        MappedBody {
+            ptr: ptr::null_mut(),
+            len: 0,
+            _file: None,
+        }
+    }
+
+    /// Maps the whole of `file` into memory. An empty file cannot be mapped, and is represented
+    /// by an empty `MappedBody`.
+    fn map(file: File) -> io::Result<MappedBody> {
+        let len = file.metadata()?.len() as usize;
+        if len == 0 {
+            return Ok(MappedBody::empty());
+        }
+
+        let ptr = unsafe {
+            libc::mmap(
+                ptr::null_mut(),
+                len,
+                libc::PROT_READ,
+                libc::MAP_PRIVATE,
+                file.as_raw_fd(),
+                0,
+            )
+        };
+
+        if ptr == libc::MAP_FAILED {
+            return Err(io::Error::last_os_error());
+        }
+
+        Ok(MappedBody {
+            ptr: ptr as *mut u8,
+            len,
+            _file: Some(file),
+        })
+    }
+}
+
+impl Deref for MappedBody {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        if self.len == 0 {
+            &[]
+        } else {
+            unsafe { slice::from_raw_parts(self.ptr, self.len) }
+        }
+    }
+}
+
+impl Drop for MappedBody {
+    fn drop(&mut self) {
+        if self.len > 0 {
+            unsafe {
+                libc::munmap(self.ptr as *mut libc::c_void, self.len);
+            }
+        }
+    }
+}
+
+impl fmt::Debug for MappedBody {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("MappedBody").field("len", &self.len).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use hyper::StatusCode;
+    use hyper::header::Headers;
+    use mime;
+
+    use handler::{HandlerFuture, IntoHandlerError};
+    use http::response::create_response;
+    use test::TestServer;
+
+    fn temp_dir() -> PathBuf {
+        let dir = env::temp_dir().join(format!("gotham-mapped-{}", Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn maps_large_body() {
+        let dir = temp_dir();
+        let reader = MappedBodyReader::default().with_temp_dir(&dir);
+
+        let handler = move |mut state: State| -> Box<HandlerFuture> {
+            let f = reader.read(&mut state).then(move |result| match result {
+                Ok(body) => {
+                    let slice = format!("{} {:?}", body.len(), &body[4_000_000..4_000_004]);
+                    let res = create_response(
+                        &state,
+                        StatusCode::Ok,
+                        Some((slice.into_bytes(), mime::TEXT_PLAIN)),
+                    );
+                    future::ok((state, res))
+                }
+                Err(e) => future::err((state, e.into_handler_error())),
+            });
+            Box::new(f)
+        };
+
+        let body: Vec<u8> = (0..8_000_000u32).map(|i| (i % 251) as u8).collect();
+        let test_server = TestServer::new(move || Ok(handler.clone())).unwrap();
+        let response = test_server
+            .client()
+            .post("http://localhost/", body, mime::APPLICATION_OCTET_STREAM)
+            .perform()
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::Ok);
+        assert_eq!(
+            response.read_utf8_body().unwrap(),
+            format!(
+                "8000000 [{}, {}, {}, {}]",
+                4_000_000 % 251,
+                4_000_001 % 251,
+                4_000_002 % 251,
+                4_000_003 % 251
+            )
+        );
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 0);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn maps_empty_body() {
+        let dir = temp_dir();
+        let mut state = State::new();
+        state.put(Headers::new());
+        state.put(Body::empty());
+
+        let body = MappedBodyReader::default()
+            .with_temp_dir(&dir)
+            .read(&mut state)
+            .wait()
+            .unwrap();
+        assert!(body.is_empty());
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 0);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn missing_body_is_empty() {
+        let mut state = State::new();
+        state.put(Headers::new());
+        let body = MappedBodyReader::default().read(&mut state).wait().unwrap();
+        assert!(body.is_empty());
+    }
+}
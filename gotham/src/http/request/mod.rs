@@ -1,4 +1,16 @@
 //! Helpers for HTTP Request handling
 
+pub mod body;
+pub mod charset;
+pub mod deserialize_error;
+pub mod extractor_failure;
+pub mod field_errors;
+pub mod forwarded;
+pub mod json;
+#[cfg(unix)]
+pub mod mapped;
+pub mod parts;
 pub mod path;
 pub mod query_string;
+pub mod raw;
+pub mod te;
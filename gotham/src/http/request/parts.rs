@@ -0,0 +1,153 @@
+//! Defines `RequestParts`, the details of the `Request` as it was received from Hyper.
+
+use hyper::{Headers, HttpVersion, Method, Uri};
+
+use state::StateData;
+
+/// The method, URI, version and headers of the `Request`, exactly as they were received from
+/// Hyper, for integrations which need the low-level details of the `Request`.
+///
+/// These are the same values Gotham puts into `State` individually, except that they are never
+/// changed. A `PreRoutingHook` or `Middleware` may replace the `Uri` or `Headers` in `State`, such
+/// as when stripping a path prefix, but `RequestParts` always holds the originals. The body is not
+/// included, and remains in `State` for extractors to take.
+///
+/// # Examples
+///
+/// ```rust
+/// # extern crate gotham;
+/// # extern crate hyper;
+/// # extern crate mime;
+/// #
+/// # use hyper::{Response, StatusCode};
+/// # use hyper::header::UserAgent;
+/// # use gotham::http::request::parts::RequestParts;
+/// # use gotham::http::response::create_response;
+/// # use gotham::state::{FromState, State};
+/// # use gotham::test::TestServer;
+/// #
+/// fn my_handler(state: State) -> (State, Response) {
+///     let body = {
+///         let parts = RequestParts::borrow_from(&state);
+///         let agent = parts.headers().get::<UserAgent>().map(|a| a.to_string());
+///         format!("{} from {}", parts.uri(), agent.unwrap_or_default())
+///     };
+///
+///     let body = Some((body.into_bytes(), mime::TEXT_PLAIN));
+///     let res = create_response(&state, StatusCode::Ok, body);
+///     (state, res)
+/// }
+/// #
+/// # fn main() {
+/// #   let test_server = TestServer::new(|| Ok(my_handler)).unwrap();
+/// #   let response = test_server
+/// #       .client()
+/// #       .get("http://localhost/status?verbose")
+/// #       .with_header(UserAgent::new("probe"))
+/// #       .perform()
+/// #       .unwrap();
+/// #   assert_eq!(response.read_utf8_body().unwrap(), "/status?verbose from probe");
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct RequestParts {
+    method: Method,
+    uri: Uri,
+    version: HttpVersion,
+    headers: Headers,
+}
+
+impl StateData for RequestParts {}
+
+impl RequestParts {
+    pub(crate) fn new(
+        method: Method,
+        uri: Uri,
+        version: HttpVersion,
+        headers: Headers,
+    ) -> RequestParts {
+        RequestParts {
+            method,
+            uri,
+            version,
+            headers,
+        }
+    }
+
+    /// The `Method` of the `Request`.
+    pub fn method(&self) -> &Method {
+        &self.method
+    }
+
+    /// The `Uri` of the `Request`, as given in the request line.
+    pub fn uri(&self) -> &Uri {
+        &self.uri
+    }
+
+    /// The `HttpVersion` of the `Request`.
+    pub fn version(&self) -> HttpVersion {
+        self.version
+    }
+
+    /// The `Headers` of the `Request`.
+    pub fn headers(&self) -> &Headers {
+        &self.headers
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use hyper::{Response, StatusCode};
+    use hyper::header::Accept;
+    use mime;
+
+    use config::{PreRoutingHook, ServerConfig};
+    use http::response::create_response;
+    use state::{FromState, State};
+    use test::TestServer;
+
+    fn describe(state: State) -> (State, Response) {
+        let body = {
+            let parts = RequestParts::borrow_from(&state);
+            format!(
+                "{} {} {} {} (routed as {})",
+                parts.method(),
+                parts.uri(),
+                parts.version(),
+                parts.headers().get::<Accept>().unwrap(),
+                Uri::borrow_from(&state)
+            )
+        };
+
+        let body = Some((body.into_bytes(), mime::TEXT_PLAIN));
+        let res = create_response(&state, StatusCode::Ok, body);
+        (state, res)
+    }
+
+    #[test]
+    fn holds_request_as_received() {
+        let hook = PreRoutingHook::new(|state| {
+            let path = Uri::borrow_from(state).path().to_owned();
+            if let Some(rest) = path.strip_prefix("/v1") {
+                state.put::<Uri>(rest.parse().unwrap());
+            }
+            None
+        });
+
+        let config = ServerConfig::default().with_pre_routing_hook(hook);
+        let test_server = TestServer::with_config(|| Ok(describe), config).unwrap();
+        let response = test_server
+            .client()
+            .get("http://localhost/v1/items?page=2")
+            .with_header(Accept::json())
+            .perform()
+            .unwrap();
+
+        assert_eq!(
+            response.read_utf8_body().unwrap(),
+            "GET /v1/items?page=2 HTTP/1.1 application/json (routed as /items)"
+        );
+    }
+}
@@ -105,4 +105,34 @@ impl RequestPathSegments {
     pub fn set_offset(&mut self, offset: usize) {
         self.offset = offset;
     }
+
+    /// Returns `true` if any segment is `.` or `..` once percent-decoded.
+    pub(crate) fn has_dot_segments(&self) -> bool {
+        self.segments
+            .iter()
+            .any(|segment| segment.val() == "." || segment.val() == "..")
+    }
+
+    /// Removes `.` segments, and each `..` segment along with the segment before it, as
+    /// described by RFC 3986. A `..` segment at the root is removed alone.
+    pub(crate) fn remove_dot_segments(&mut self) {
+        if !self.has_dot_segments() {
+            return;
+        }
+
+        let mut segments = Vec::with_capacity(self.segments.len());
+        for segment in self.segments.iter() {
+            match segment.val() {
+                "." => (),
+                ".." => {
+                    if segments.len() > 1 {
+                        segments.pop();
+                    }
+                }
+                _ => segments.push(segment.clone()),
+            }
+        }
+
+        self.segments = Arc::new(segments);
+    }
 }
@@ -21,6 +21,11 @@ impl QueryStringMapping {
         self.data.contains_key(key)
     }
 
+    /// Determines if no keys were provided as part of the `Request` query string.
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
     /// Adds an empty value for a key, useful for keys that are considered
     /// optional and haven't been explicitly provided as part of a `Request` query string.
     pub fn add_unmapped_segment(&mut self, key: &str) {
@@ -46,6 +51,9 @@ impl QueryStringMapping {
 ///
 /// For keys that are provided but don't have a value associated an empty string will be stored.
 ///
+/// An empty query string (e.g. a `Request` path ending in a bare `?`) is treated the same as no
+/// query string at all, and pairs without a key are ignored.
+///
 /// #Examples
 ///
 /// ```rust
@@ -65,12 +73,17 @@ impl QueryStringMapping {
 ///       let res = split(Some("key=val&key2="));
 ///       assert_eq!("val", res.get("key").unwrap().first().unwrap().val());
 ///       assert_eq!("", res.get("key2").unwrap().first().unwrap().val());
+///
+///       let res = split(Some(""));
+///       assert!(res.is_empty());
 /// # }
 /// ```
 pub fn split<'r>(query: Option<&'r str>) -> QueryStringMapping {
     match query {
-        Some(query) => {
-            let pairs = query.split("&").filter(|pair| pair.contains("="));
+        Some(query) if !query.is_empty() => {
+            let pairs = query
+                .split("&")
+                .filter(|pair| pair.contains("=") && !pair.starts_with("="));
             let data = pairs.fold(HashMap::new(), |mut acc, p| {
                 let mut sp = p.split("=");
                 let (k, v) = (sp.next().unwrap(), sp.next().unwrap());
@@ -89,8 +102,29 @@ pub fn split<'r>(query: Option<&'r str>) -> QueryStringMapping {
 
             QueryStringMapping { data }
         }
-        None => QueryStringMapping {
+        _ => QueryStringMapping {
             data: HashMap::new(),
         },
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use hyper::Uri;
+
+    #[test]
+    fn empty_query_string_has_no_parameters() {
+        let uri: Uri = "/path?".parse().unwrap();
+        let res = split(uri.query());
+        assert!(res.is_empty());
+    }
+
+    #[test]
+    fn ignores_pairs_without_a_key() {
+        let res = split(Some("=val&&key=val"));
+        assert!(!res.contains_key(""));
+        assert_eq!("val", res.get("key").unwrap().first().unwrap().val());
+    }
+}
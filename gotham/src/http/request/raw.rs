@@ -0,0 +1,303 @@
+//! Defines an extractor which reads the `Request` body as `Bytes`, without copying it.
+
+use std::error::Error;
+use std::fmt;
+
+use bytes::{Bytes, BytesMut};
+use futures::{future, Future, Stream};
+use hyper::{self, Body, Chunk, StatusCode};
+use hyper::header::{ContentLength, Headers};
+
+use handler::{HandlerError, IntoHandlerError};
+use http::request::extractor_failure::{report_extractor_failure, ExtractorFailureHook,
+                                       ExtractorFailureReason};
+use state::{FromState, State};
+
+/// The type reported to an `ExtractorFailureHook` when a `RawBodyExtractor` fails.
+const EXTRACTED_TYPE: &str = "bytes::Bytes";
+
+/// Reads the `Request` body as `Bytes`, for handlers which need the raw body, such as those
+/// forwarding it to another service.
+///
+/// `Bytes` is reference counted, so the body can be cloned and passed on without a deep copy.
+/// When the body arrives as a single chunk, as is typical of small bodies, the `Bytes` shares the
+/// buffer Hyper read it into. Otherwise the chunks are joined into a single buffer, sized once
+/// the whole body has been read.
+///
+/// No limit is placed on the size of the body by default. A limit can be set via
+/// `with_max_size`, beyond which the request fails with `413 Payload Too Large`.
+///
+/// # Examples
+///
+/// ```rust
+/// # extern crate futures;
+/// # extern crate gotham;
+/// # extern crate hyper;
+/// # extern crate mime;
+/// #
+/// # use futures::{future, Future};
+/// # use hyper::StatusCode;
+/// # use gotham::handler::HandlerFuture;
+/// # use gotham::http::request::raw::RawBodyExtractor;
+/// # use gotham::http::response::create_response;
+/// # use gotham::state::State;
+/// # use gotham::test::TestServer;
+/// #
+/// fn echo(mut state: State) -> Box<HandlerFuture> {
+///     let f = RawBodyExtractor::default()
+///         .with_max_size(1024)
+///         .extract(&mut state)
+///         .then(move |result| match result {
+///             Ok(body) => {
+///                 let body = Some((body.to_vec(), mime::APPLICATION_OCTET_STREAM));
+///                 let res = create_response(&state, StatusCode::Ok, body);
+///                 future::ok((state, res))
+///             }
+///             Err(e) => future::err((state, e)),
+///         });
+///
+///     Box::new(f)
+/// }
+/// #
+/// # fn main() {
+/// #   let test_server = TestServer::new(|| Ok(echo)).unwrap();
+/// #   let response = test_server
+/// #       .client()
+/// #       .post("http://localhost/", "raw", mime::APPLICATION_OCTET_STREAM)
+/// #       .perform()
+/// #       .unwrap();
+/// #   assert_eq!(response.read_body().unwrap(), b"raw");
+/// #
+/// #   let response = test_server
+/// #       .client()
+/// #       .post("http://localhost/", vec![0; 2048], mime::APPLICATION_OCTET_STREAM)
+/// #       .perform()
+/// #       .unwrap();
+/// #   assert_eq!(response.status(), StatusCode::PayloadTooLarge);
+/// # }
+/// ```
+#[derive(Clone, Copy, Debug, Default)]
+pub struct RawBodyExtractor {
+    max_size: Option<u64>,
+}
+
+impl RawBodyExtractor {
+    /// Sets the maximum size of the body, in bytes. Requests declaring a larger `Content-Length`
+    /// are rejected before the body is read, and others once the limit is passed.
+    pub fn with_max_size(self, max_size: u64) -> RawBodyExtractor {
+        RawBodyExtractor {
+            max_size: Some(max_size),
+        }
+    }
+
+    /// Takes the `Body` from `State`, and resolves with its contents once fully read.
+    ///
+    /// If the `Body` has already been taken by another extractor, resolves with an empty `Bytes`.
+    pub fn extract(
+        &self,
+        state: &mut State,
+    ) -> Box<dyn Future<Item = Bytes, Error = HandlerError>> {
+        let max_size = self.max_size.unwrap_or(u64::MAX);
+
+        let content_length = Headers::borrow_from(state)
+            .get::<ContentLength>()
+            .map(|len| len.0);
+
+        if let Some(content_length) = content_length {
+            if content_length > max_size {
+                let e = RawBodyError::TooLarge(max_size);
+                report_extractor_failure(state, EXTRACTED_TYPE, e.extractor_failure_reason());
+                return Box::new(future::err(e.into()));
+            }
+        }
+
+        let body = match Body::try_take_from(state) {
+            Some(body) => body,
+            None => return Box::new(future::ok(Bytes::new())),
+        };
+        let hook = ExtractorFailureHook::try_borrow_from(state).cloned();
+
+        Box::new(
+            body.map_err(RawBodyError::Body)
+                .fold((Vec::new(), 0u64), move |(mut chunks, size), chunk| {
+                    let size = size + chunk.len() as u64;
+                    if size > max_size {
+                        return Err(RawBodyError::TooLarge(max_size));
+                    }
+
+                    chunks.push(chunk);
+                    Ok((chunks, size))
+                })
+                .map(|(chunks, size)| join(chunks, size as usize))
+                .map_err(move |e| {
+                    if let Some(hook) = hook {
+                        hook.call(EXTRACTED_TYPE, e.extractor_failure_reason());
+                    }
+
+                    HandlerError::from(e)
+                }),
+        )
+    }
+}
+
+/// Joins `chunks`, totalling `size` bytes, into a single `Bytes`. A single chunk is returned as
+/// is, without copying.
+fn join(mut chunks: Vec<Chunk>, size: usize) -> Bytes {
+    if chunks.len() == 1 {
+        return Bytes::from(chunks.remove(0));
+    }
+
+    let mut buf = BytesMut::with_capacity(size);
+    for chunk in chunks {
+        buf.extend_from_slice(&chunk);
+    }
+    buf.freeze()
+}
+
+/// The reasons a `RawBodyExtractor` can fail, which are carried as the cause of the
+/// `HandlerError`.
+#[derive(Debug)]
+pub enum RawBodyError {
+    /// The body was larger than the configured maximum size, in bytes.
+    TooLarge(u64),
+
+    /// The body could not be read.
+    Body(hyper::Error),
+}
+
+impl RawBodyError {
+    /// The HTTP status code to respond with for this error.
+    pub fn status(&self) -> StatusCode {
+        match *self {
+            RawBodyError::TooLarge(_) => StatusCode::PayloadTooLarge,
+            RawBodyError::Body(_) => StatusCode::BadRequest,
+        }
+    }
+
+    /// The reason reported to an `ExtractorFailureHook` for this error.
+    fn extractor_failure_reason(&self) -> ExtractorFailureReason {
+        match *self {
+            RawBodyError::TooLarge(_) => ExtractorFailureReason::SizeExceeded,
+            RawBodyError::Body(ref e) => ExtractorFailureReason::of_body(e),
+        }
+    }
+}
+
+impl From<RawBodyError> for HandlerError {
+    fn from(e: RawBodyError) -> HandlerError {
+        let status = e.status();
+        e.into_handler_error().with_status(status)
+    }
+}
+
+impl fmt::Display for RawBodyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            RawBodyError::TooLarge(max_size) => {
+                write!(f, "body larger than the limit of {} bytes", max_size)
+            }
+            RawBodyError::Body(ref e) => write!(f, "unable to read body: {}", e),
+        }
+    }
+}
+
+impl Error for RawBodyError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match *self {
+            RawBodyError::TooLarge(_) => None,
+            RawBodyError::Body(ref e) => Some(e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use futures::Sink;
+    use hyper::Response;
+    use mime;
+    use tokio_core::reactor::Handle;
+
+    use handler::HandlerFuture;
+    use test::TestServer;
+
+    fn echo(extractor: RawBodyExtractor, mut state: State) -> Box<HandlerFuture> {
+        let f = extractor.extract(&mut state).then(move |result| match result {
+            Ok(body) => {
+                let res = Response::new()
+                    .with_status(StatusCode::Ok)
+                    .with_body(Chunk::from(body));
+                future::ok((state, res))
+            }
+            Err(e) => future::err((state, e)),
+        });
+
+        Box::new(f)
+    }
+
+    /// Replaces the request body with one sent in the given chunks, without a `Content-Length`.
+    fn rechunk(state: &mut State, chunks: Vec<&'static str>) {
+        let (tx, body) = Body::pair();
+        let chunks = ::futures::stream::iter_ok(chunks.into_iter().map(|c| Ok(Chunk::from(c))));
+        Handle::borrow_from(state).spawn(tx.sink_map_err(|_| ()).send_all(chunks).map(|_| ()));
+        state.put(body);
+    }
+
+    #[test]
+    fn joins_single_chunk_without_copying() {
+        // Large enough that `Bytes` is not stored inline, and so is shared when cloned.
+        let chunk = Bytes::from(vec![b'x'; 1024]);
+        let joined = join(vec![Chunk::from(chunk.clone())], chunk.len());
+
+        assert_eq!(joined, chunk);
+        assert_eq!(joined.as_ptr(), chunk.as_ptr());
+    }
+
+    #[test]
+    fn joins_multiple_chunks() {
+        let chunks = vec![Chunk::from("raw "), Chunk::from("body")];
+        assert_eq!(join(chunks, 8), Bytes::from(&b"raw body"[..]));
+        assert!(join(Vec::new(), 0).is_empty());
+    }
+
+    #[test]
+    fn extracts_raw_body() {
+        let test_server =
+            TestServer::new(|| Ok(|state| echo(RawBodyExtractor::default(), state))).unwrap();
+        let response = test_server
+            .client()
+            .post("http://localhost/", "raw body", mime::APPLICATION_OCTET_STREAM)
+            .perform()
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::Ok);
+        assert_eq!(response.read_body().unwrap(), b"raw body");
+    }
+
+    #[test]
+    fn rejects_body_over_max_size() {
+        let extractor = RawBodyExtractor::default().with_max_size(8);
+        let test_server = TestServer::new(move || {
+            Ok(move |mut state: State| {
+                rechunk(&mut state, vec!["raw ", "body", "!"]);
+                echo(extractor, state)
+            })
+        }).unwrap();
+        let response = test_server
+            .client()
+            .post("http://localhost/", "", mime::APPLICATION_OCTET_STREAM)
+            .perform()
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::PayloadTooLarge);
+
+        let test_server = TestServer::new(move || Ok(move |state| echo(extractor, state))).unwrap();
+        let response = test_server
+            .client()
+            .post("http://localhost/", "raw body", mime::APPLICATION_OCTET_STREAM)
+            .perform()
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::Ok);
+        assert_eq!(response.read_body().unwrap(), b"raw body");
+    }
+}
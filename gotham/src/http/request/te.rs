@@ -0,0 +1,88 @@
+//! Defines helpers for the transfer codings a client is willing to accept in a response, as
+//! advertised by the `TE` request header.
+
+use hyper::header::{q, Encoding, Headers, Te};
+
+use state::{FromState, State};
+
+/// Returns `true` when the client has advertised `TE: trailers`, indicating that it is willing to
+/// accept trailer fields after a chunked response body.
+///
+/// Trailer fields must not be sent to a client which has not advertised `trailers`, as they may be
+/// discarded without notice. Values which would otherwise be sent as trailers should instead be
+/// sent as headers, when they are known before the body, or omitted.
+///
+/// # Examples
+///
+/// ```rust
+/// # extern crate gotham;
+/// # extern crate hyper;
+/// #
+/// # use hyper::{Response, StatusCode};
+/// # use gotham::http::request::te::accepts_trailers;
+/// # use gotham::state::State;
+/// # use gotham::test::TestServer;
+/// #
+/// fn my_handler(state: State) -> (State, Response) {
+///     let body = if accepts_trailers(&state) { "trailers" } else { "no trailers" };
+///     let response = Response::new().with_status(StatusCode::Ok).with_body(body);
+///     (state, response)
+/// }
+/// #
+/// # fn main() {
+/// #   let test_server = TestServer::new(|| Ok(my_handler)).unwrap();
+/// #   let response = test_server
+/// #       .client()
+/// #       .get("http://localhost/")
+/// #       .with_raw_header("TE", "trailers, deflate;q=0.5")
+/// #       .perform()
+/// #       .unwrap();
+/// #
+/// #   assert_eq!(response.read_body().unwrap().as_slice(), b"trailers");
+/// # }
+/// ```
+pub fn accepts_trailers(state: &State) -> bool {
+    headers_accept_trailers(Headers::borrow_from(state))
+}
+
+fn headers_accept_trailers(headers: &Headers) -> bool {
+    match headers.get::<Te>() {
+        Some(te) => te.iter()
+            .filter(|item| item.quality > q(0))
+            .any(|item| match item.item {
+                Encoding::Trailers => true,
+                Encoding::EncodingExt(ref ext) => ext.eq_ignore_ascii_case("trailers"),
+                _ => false,
+            }),
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn trailers_advertised() {
+        let mut headers = Headers::new();
+        headers.set_raw("TE", "trailers");
+        assert!(headers_accept_trailers(&headers));
+
+        let mut headers = Headers::new();
+        headers.set_raw("TE", "gzip;q=0.5, Trailers");
+        assert!(headers_accept_trailers(&headers));
+    }
+
+    #[test]
+    fn trailers_not_advertised() {
+        assert!(!headers_accept_trailers(&Headers::new()));
+
+        let mut headers = Headers::new();
+        headers.set_raw("TE", "gzip, deflate");
+        assert!(!headers_accept_trailers(&headers));
+
+        let mut headers = Headers::new();
+        headers.set_raw("TE", "trailers;q=0");
+        assert!(!headers_accept_trailers(&headers));
+    }
+}
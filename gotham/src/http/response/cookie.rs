@@ -0,0 +1,97 @@
+//! Defines helpers for setting cookies on a response.
+
+use hyper::Response;
+use hyper::header::SetCookie;
+
+/// Adds `cookie` to the `Set-Cookie` headers of `res`, keeping any cookies which are already set.
+///
+/// `cookie` is the full value of the header, e.g. `id=a3fWa; Path=/; HttpOnly`. Each cookie is
+/// sent as a distinct `Set-Cookie` header line. Unlike other headers, `Set-Cookie` values cannot
+/// be folded into a single comma-separated line, as the `Expires` attribute itself contains a
+/// comma, and so a client would misread folded cookies. Setting a new `SetCookie` header
+/// directly replaces the cookies set before it, including any set by `NewSessionMiddleware`,
+/// which also uses this function.
+///
+/// # Examples
+///
+/// ```rust
+/// # extern crate gotham;
+/// # extern crate hyper;
+/// #
+/// # use hyper::{Response, StatusCode};
+/// # use hyper::header::SetCookie;
+/// # use gotham::http::response::create_response;
+/// # use gotham::http::response::cookie::add_cookie;
+/// # use gotham::state::State;
+/// # use gotham::test::TestServer;
+/// #
+/// fn my_handler(state: State) -> (State, Response) {
+///     let mut res = create_response(&state, StatusCode::Ok, None);
+///     add_cookie(&mut res, "theme=dark; Path=/");
+///     add_cookie(&mut res, "lang=en; Path=/; Expires=Wed, 21 Oct 2037 07:28:00 GMT");
+///     (state, res)
+/// }
+/// #
+/// # fn main() {
+/// #   let test_server = TestServer::new(|| Ok(my_handler)).unwrap();
+/// #   let response = test_server.client().get("http://localhost/").perform().unwrap();
+/// #   assert_eq!(response.headers().get::<SetCookie>().unwrap().len(), 2);
+/// # }
+/// ```
+pub fn add_cookie<C>(res: &mut Response, cookie: C)
+where
+    C: Into<String>,
+{
+    let headers = res.headers_mut();
+    if let Some(cookies) = headers.get_mut::<SetCookie>() {
+        return cookies.push(cookie.into());
+    }
+
+    headers.set(SetCookie(vec![cookie.into()]));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use hyper::StatusCode;
+
+    use http::response::create_response;
+    use state::State;
+    use test::TestServer;
+
+    #[test]
+    fn sends_each_cookie_as_a_separate_header_line() {
+        fn handler(state: State) -> (State, Response) {
+            let mut res = create_response(&state, StatusCode::Ok, None);
+            res.headers_mut().set_raw("Set-Cookie", "a=1");
+            add_cookie(&mut res, "b=2; Expires=Wed, 21 Oct 2037 07:28:00 GMT");
+            add_cookie(&mut res, "c=3; Path=/".to_owned());
+            (state, res)
+        }
+
+        let test_server = TestServer::new(|| Ok(handler)).unwrap();
+        let response = test_server
+            .client()
+            .get("http://localhost/")
+            .perform()
+            .unwrap();
+
+        let lines: Vec<_> = response
+            .headers()
+            .get_raw("Set-Cookie")
+            .unwrap()
+            .iter()
+            .map(|line| String::from_utf8(line.to_vec()).unwrap())
+            .collect();
+
+        assert_eq!(
+            lines,
+            vec![
+                "a=1",
+                "b=2; Expires=Wed, 21 Oct 2037 07:28:00 GMT",
+                "c=3; Path=/",
+            ]
+        );
+    }
+}
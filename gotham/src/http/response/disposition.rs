@@ -0,0 +1,132 @@
+//! Defines helpers for setting the `Content-Disposition` header.
+
+use std::fmt::Write;
+
+use hyper::Response;
+
+/// Sets the `Content-Disposition` header of `res`, asking the client to save the body as a file
+/// named `filename` rather than display it.
+///
+/// The `filename` parameter is limited to ASCII, so a name containing other characters is also
+/// given in the `filename*` parameter, encoded as UTF-8 as described by RFC 5987. Clients which
+/// understand `filename*` prefer it, and others use `filename`, in which each non-ASCII
+/// character is replaced with `_`. Quotes and backslashes are escaped, and control characters
+/// are replaced, so that `filename` cannot end the header parameter early.
+///
+/// # Examples
+///
+/// ```rust
+/// # extern crate gotham;
+/// # extern crate hyper;
+/// # extern crate mime;
+/// #
+/// # use hyper::{Response, StatusCode};
+/// # use gotham::http::response::create_response;
+/// # use gotham::http::response::disposition::set_attachment;
+/// # use gotham::state::State;
+/// # use gotham::test::TestServer;
+/// #
+/// fn download(state: State) -> (State, Response) {
+///     let body = b"name,total\nwidgets,12\n".to_vec();
+///     let mut res = create_response(&state, StatusCode::Ok, Some((body, mime::TEXT_CSV)));
+///     set_attachment(&mut res, "Résumé 2018.csv");
+///     (state, res)
+/// }
+/// #
+/// # fn main() {
+/// #   let test_server = TestServer::new(|| Ok(download)).unwrap();
+/// #   let response = test_server.client().get("http://localhost/").perform().unwrap();
+/// #   assert_eq!(
+/// #       response.headers().get_raw("Content-Disposition").unwrap().one(),
+/// #       Some(&b"attachment; filename=\"R_sum_ 2018.csv\"; \
+/// #               filename*=UTF-8''R%C3%A9sum%C3%A9%202018.csv"[..])
+/// #   );
+/// # }
+/// ```
+pub fn set_attachment(res: &mut Response, filename: &str) {
+    let value = attachment(filename);
+    res.headers_mut().set_raw("Content-Disposition", value);
+}
+
+/// Formats the value of a `Content-Disposition` header for an attachment named `filename`.
+fn attachment(filename: &str) -> String {
+    let mut value = String::from("attachment; filename=\"");
+    for c in filename.chars() {
+        match c {
+            '"' | '\\' => {
+                value.push('\\');
+                value.push(c);
+            }
+            c if c.is_ascii() && !c.is_ascii_control() => value.push(c),
+            _ => value.push('_'),
+        }
+    }
+    value.push('"');
+
+    if !filename.is_ascii() {
+        value.push_str("; filename*=UTF-8''");
+        for &b in filename.as_bytes() {
+            if is_attr_char(b) {
+                value.push(b as char);
+            } else {
+                write!(value, "%{:02X}", b).unwrap();
+            }
+        }
+    }
+
+    value
+}
+
+/// Returns `true` if `b` may appear in an RFC 5987 extended value without percent-encoding.
+fn is_attr_char(b: u8) -> bool {
+    b.is_ascii_alphanumeric()
+        || matches!(
+            b,
+            b'!' | b'#' | b'$' | b'&' | b'+' | b'-' | b'.' | b'^' | b'_' | b'`' | b'|' | b'~'
+        )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn disposition(filename: &str) -> String {
+        let mut res = Response::new();
+        set_attachment(&mut res, filename);
+        let raw = res.headers().get_raw("Content-Disposition").unwrap();
+        String::from_utf8(raw.one().unwrap().to_vec()).unwrap()
+    }
+
+    #[test]
+    fn sets_ascii_filename() {
+        assert_eq!(
+            disposition("report-2018.pdf"),
+            "attachment; filename=\"report-2018.pdf\""
+        );
+    }
+
+    #[test]
+    fn encodes_non_ascii_filename() {
+        assert_eq!(
+            disposition("naïve café €.txt"),
+            "attachment; filename=\"na_ve caf_ _.txt\"; \
+             filename*=UTF-8''na%C3%AFve%20caf%C3%A9%20%E2%82%AC.txt"
+        );
+        assert_eq!(
+            disposition("日本.pdf"),
+            "attachment; filename=\"__.pdf\"; filename*=UTF-8''%E6%97%A5%E6%9C%AC.pdf"
+        );
+    }
+
+    #[test]
+    fn escapes_filename_parameter() {
+        assert_eq!(
+            disposition("say \"hi\"\\\r\n.txt"),
+            "attachment; filename=\"say \\\"hi\\\"\\\\__.txt\""
+        );
+        assert_eq!(
+            disposition("50% ünder;.txt"),
+            "attachment; filename=\"50% _nder;.txt\"; filename*=UTF-8''50%25%20%C3%BCnder%3B.txt"
+        );
+    }
+}
@@ -0,0 +1,143 @@
+//! Defines a helper for answering long-polling requests.
+
+use std::time::Duration;
+
+use futures::{future, Future};
+use hyper::StatusCode;
+use tokio_core::reactor::{Handle, Timeout};
+
+use handler::{HandlerError, HandlerFuture, IntoHandlerError, IntoResponse};
+use http::response::create_response;
+use state::{request_id, FromState, State};
+
+/// Holds the request open until `event` resolves, responding with its result, or until `timeout`
+/// elapses, responding with `204 No Content` so that the client polls again.
+///
+/// This suits clients which cannot use WebSockets or server-sent events, and instead poll for
+/// updates with requests that wait until there is something to report. The result of `event` is
+/// rendered via `IntoResponse` with the `State` of the request. If `event` fails, its error is
+/// rendered as for any other `HandlerError`. When the timeout elapses, `event` is dropped and no
+/// longer polled, and should release anything it holds, such as its place in a list of waiters.
+///
+/// # Examples
+///
+/// ```rust
+/// # extern crate futures;
+/// # extern crate gotham;
+/// # extern crate hyper;
+/// #
+/// # use std::thread;
+/// # use std::time::Duration;
+/// # use futures::Future;
+/// # use futures::sync::oneshot;
+/// # use hyper::StatusCode;
+/// # use gotham::handler::{HandlerFuture, IntoHandlerError};
+/// # use gotham::http::response::long_poll::long_poll;
+/// # use gotham::state::State;
+/// # use gotham::test::TestServer;
+/// #
+/// fn next_message(state: State) -> Box<HandlerFuture> {
+///     // Waits on a message from elsewhere in the application.
+///     let (tx, rx) = oneshot::channel();
+///     thread::spawn(move || tx.send(String::from("hello")));
+///
+///     let message = rx.map_err(|e| e.into_handler_error());
+///     long_poll(state, Duration::from_secs(30), message)
+/// }
+/// #
+/// # fn main() {
+/// #   let test_server = TestServer::new(|| Ok(next_message)).unwrap();
+/// #   let response = test_server.client().get("http://localhost/").perform().unwrap();
+/// #   assert_eq!(response.status(), StatusCode::Ok);
+/// #   assert_eq!(response.read_utf8_body().unwrap(), "hello");
+/// # }
+/// ```
+pub fn long_poll<F>(state: State, timeout: Duration, event: F) -> Box<HandlerFuture>
+where
+    F: Future<Error = HandlerError> + 'static,
+    F::Item: IntoResponse,
+{
+    let timeout = match Timeout::new(timeout, Handle::borrow_from(&state)) {
+        Ok(timeout) => timeout,
+        Err(e) => return Box::new(future::err((state, e.into_handler_error()))),
+    };
+
+    let f = event.select2(timeout).then(move |result| match result {
+        Ok(future::Either::A((item, _))) => {
+            let res = item.into_response(&state);
+            Ok((state, res))
+        }
+        Err(future::Either::A((e, _))) => Err((state, e)),
+        Ok(future::Either::B(_)) => {
+            trace!("[{}] long poll timed out", request_id(&state));
+            let res = create_response(&state, StatusCode::NoContent, None);
+            Ok((state, res))
+        }
+        Err(future::Either::B((e, _))) => Err((state, e.into_handler_error())),
+    });
+
+    Box::new(f)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::panic::RefUnwindSafe;
+    use std::sync::{Arc, Mutex};
+
+    use futures::sync::oneshot;
+
+    use test::TestServer;
+
+    fn poll_with<F>(waiter: F) -> (StatusCode, String)
+    where
+        F: Fn(oneshot::Sender<String>) + RefUnwindSafe + Send + Sync + 'static,
+    {
+        let waiter = Arc::new(waiter);
+        let new_handler = move || {
+            let waiter = waiter.clone();
+            Ok(move |state: State| {
+                let (tx, rx) = oneshot::channel();
+                waiter(tx);
+                let event = rx.map_err(|e| e.into_handler_error());
+                long_poll(state, Duration::from_millis(100), event)
+            })
+        };
+
+        let test_server = TestServer::new(new_handler).unwrap();
+        let response = test_server
+            .client()
+            .get("http://localhost/")
+            .perform()
+            .unwrap();
+        let status = response.status();
+        (status, response.read_utf8_body().unwrap())
+    }
+
+    #[test]
+    fn responds_with_event_before_timeout() {
+        let (status, body) = poll_with(|tx| {
+            tx.send(String::from("update")).unwrap();
+        });
+
+        assert_eq!(status, StatusCode::Ok);
+        assert_eq!(body, "update");
+    }
+
+    #[test]
+    fn responds_no_content_on_timeout() {
+        let waiting = Arc::new(Mutex::new(Vec::new()));
+        let (status, body) = {
+            let waiting = waiting.clone();
+            poll_with(move |tx| waiting.lock().unwrap().push(tx))
+        };
+
+        assert_eq!(status, StatusCode::NoContent);
+        assert_eq!(body, "");
+
+        // The event was dropped once the timeout elapsed.
+        let tx = waiting.lock().unwrap().pop().unwrap();
+        assert!(tx.is_canceled());
+    }
+}
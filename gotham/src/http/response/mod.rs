@@ -1,12 +1,22 @@
 //! Helpers for HTTP Response generation
 
+use futures::Future;
 use hyper::{Method, Response, StatusCode};
 use hyper::header::{ContentLength, ContentType};
 use mime::Mime;
 
+use handler::HandlerFuture;
 use state::{request_id, FromState, State};
 use http::header::{XContentTypeOptions, XFrameOptions, XRequestId, XXssProtection};
 
+pub mod cookie;
+pub mod disposition;
+pub mod long_poll;
+pub mod redirect;
+pub mod retry_after;
+pub mod sniff;
+pub mod stream;
+
 type Body = (Vec<u8>, Mime);
 
 /// Creates a `Response` object and populates it with a set of default headers that ensure
@@ -165,3 +175,151 @@ pub fn set_headers(state: &State, res: &mut Response, mime: Option<Mime>, length
     headers.set(XXssProtection::EnableBlock);
     headers.set(XContentTypeOptions::NoSniff);
 }
+
+/// Modifies the `Response` resolved by `future` in place, once it is available. This is intended
+/// for `Middleware` which post-process the response produced by the rest of the chain, such as
+/// changing its status or adding headers, without rebuilding it.
+///
+/// `f` is not called if `future` resolves with an error.
+///
+/// # Examples
+///
+/// ```rust
+/// # extern crate gotham;
+/// # extern crate hyper;
+/// #
+/// # extern crate mime;
+/// #
+/// # use std::io;
+/// # use hyper::{Response, StatusCode};
+/// # use hyper::header::{ContentRange, ContentRangeSpec};
+/// # use gotham::handler::HandlerFuture;
+/// # use gotham::http::response::{create_response, map_response};
+/// # use gotham::middleware::{Middleware, NewMiddleware};
+/// # use gotham::pipeline::new_pipeline;
+/// # use gotham::pipeline::single::single_pipeline;
+/// # use gotham::router::Router;
+/// # use gotham::router::builder::*;
+/// # use gotham::state::State;
+/// # use gotham::test::TestServer;
+/// #
+/// struct PartialContent;
+///
+/// impl Middleware for PartialContent {
+///     fn call<Chain>(self, state: State, chain: Chain) -> Box<HandlerFuture>
+///     where
+///         Chain: FnOnce(State) -> Box<HandlerFuture> + 'static,
+///     {
+///         map_response(chain(state), |_state, res| {
+///             if res.status() == StatusCode::Ok {
+///                 res.set_status(StatusCode::PartialContent);
+///                 res.headers_mut().set(ContentRange(ContentRangeSpec::Bytes {
+///                     range: Some((0, 4)),
+///                     instance_length: Some(10),
+///                 }));
+///             }
+///         })
+///     }
+/// }
+/// #
+/// # impl NewMiddleware for PartialContent {
+/// #     type Instance = PartialContent;
+/// #
+/// #     fn new_middleware(&self) -> io::Result<PartialContent> {
+/// #         Ok(PartialContent)
+/// #     }
+/// # }
+/// #
+/// # fn handler(state: State) -> (State, Response) {
+/// #     let body = Some((b"hello".to_vec(), mime::TEXT_PLAIN));
+/// #     let res = create_response(&state, StatusCode::Ok, body);
+/// #     (state, res)
+/// # }
+/// #
+/// # fn router() -> Router {
+/// #     let (chain, pipelines) = single_pipeline(new_pipeline().add(PartialContent).build());
+/// #     build_router(chain, pipelines, |route| {
+/// #         route.get("/").to(handler);
+/// #     })
+/// # }
+/// #
+/// # fn main() {
+/// #     let test_server = TestServer::new(router()).unwrap();
+/// #     let response = test_server.client().get("http://localhost/").perform().unwrap();
+/// #     assert_eq!(response.status(), StatusCode::PartialContent);
+/// # }
+/// ```
+pub fn map_response<F>(future: Box<HandlerFuture>, f: F) -> Box<HandlerFuture>
+where
+    F: FnOnce(&State, &mut Response) + 'static,
+{
+    Box::new(future.map(move |(state, mut res)| {
+        f(&state, &mut res);
+        (state, res)
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::io;
+
+    use futures::future;
+    use mime;
+
+    use handler::{Handler, IntoHandlerError};
+    use middleware::{Middleware, NewMiddleware};
+    use pipeline::new_pipeline;
+    use test::TestServer;
+
+    struct PartialContent;
+
+    impl NewMiddleware for PartialContent {
+        type Instance = PartialContent;
+
+        fn new_middleware(&self) -> io::Result<PartialContent> {
+            Ok(PartialContent)
+        }
+    }
+
+    impl Middleware for PartialContent {
+        fn call<Chain>(self, state: State, chain: Chain) -> Box<HandlerFuture>
+        where
+            Chain: FnOnce(State) -> Box<HandlerFuture> + 'static,
+        {
+            map_response(chain(state), |_state, res| {
+                if res.status() == StatusCode::Ok {
+                    res.set_status(StatusCode::PartialContent);
+                }
+            })
+        }
+    }
+
+    fn handler(state: State) -> (State, Response) {
+        let body = Some((b"hello".to_vec(), mime::TEXT_PLAIN));
+        let res = create_response(&state, StatusCode::Ok, body);
+        (state, res)
+    }
+
+    #[test]
+    fn middleware_changes_status_of_built_response() {
+        let test_server = TestServer::new(|| {
+            let pipeline = new_pipeline().add(PartialContent).build();
+
+            Ok(move |state| match pipeline.construct() {
+                Ok(p) => p.call(state, |state| handler.handle(state)),
+                Err(e) => Box::new(future::err((state, e.into_handler_error()))),
+            })
+        }).unwrap();
+
+        let response = test_server
+            .client()
+            .get("http://localhost/")
+            .perform()
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::PartialContent);
+        assert_eq!(response.read_body().unwrap(), b"hello");
+    }
+}
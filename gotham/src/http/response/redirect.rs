@@ -0,0 +1,316 @@
+//! Defines helpers for creating redirect responses.
+
+use hyper::{Response, StatusCode};
+use hyper::header::Location;
+
+use http::response::create_response;
+use state::{is_secure, request_host, FromState, State, StateData};
+
+/// Determines how the `Location` header of a redirect is written by `create_redirect`.
+///
+/// Configured for the whole application via `ServerConfig::with_location_style`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum LocationStyle {
+    /// The path is written as given, and resolved by the client against the request URL. This is
+    /// the default.
+    #[default]
+    Relative,
+
+    /// The path is written as an absolute URL, using the scheme and host requested by the client.
+    /// Some clients and security policies accept only absolute URLs.
+    ///
+    /// The scheme is `https` when `gotham::state::is_secure` reports a secure connection, and the
+    /// host is resolved by `gotham::state::request_host`, so both honour trusted proxy headers.
+    Absolute,
+}
+
+impl StateData for LocationStyle {}
+
+/// Creates a redirect `Response` with the given status, sending the client to `path`.
+///
+/// `path` is written to the `Location` header as configured by `LocationStyle`. A `path` which is
+/// already an absolute URL is written unchanged, as is any `path` when the requested host is not
+/// known.
+///
+/// # Examples
+///
+/// ```rust
+/// # extern crate gotham;
+/// # extern crate hyper;
+/// #
+/// # use hyper::{Response, StatusCode};
+/// # use hyper::header::Location;
+/// # use gotham::config::ServerConfig;
+/// # use gotham::http::request::forwarded::ForwardedPrecedence;
+/// # use gotham::http::response::redirect::{create_redirect, LocationStyle};
+/// # use gotham::state::State;
+/// # use gotham::test::TestServer;
+/// #
+/// fn my_handler(state: State) -> (State, Response) {
+///     let res = create_redirect(&state, StatusCode::SeeOther, "/orders/1");
+///     (state, res)
+/// }
+/// #
+/// # fn main() {
+/// #   let config = ServerConfig::default()
+/// #       .with_location_style(LocationStyle::Absolute)
+/// #       .with_trusted_proxy_headers(ForwardedPrecedence::XForwarded);
+/// #   let test_server = TestServer::with_config(|| Ok(my_handler), config).unwrap();
+/// #   let response = test_server
+/// #       .client()
+/// #       .post("http://localhost/orders", "", "text/plain".parse().unwrap())
+/// #       .with_raw_header("X-Forwarded-Proto", "https")
+/// #       .with_raw_header("X-Forwarded-Host", "shop.example.com")
+/// #       .perform()
+/// #       .unwrap();
+/// #
+/// #   assert_eq!(response.status(), StatusCode::SeeOther);
+/// #   assert_eq!(
+/// #       &**response.headers().get::<Location>().unwrap(),
+/// #       "https://shop.example.com/orders/1"
+/// #   );
+/// # }
+/// ```
+pub fn create_redirect(state: &State, status: StatusCode, path: &str) -> Response {
+    let mut res = create_response(state, status, None);
+    res.headers_mut().set(Location::new(location(state, path)));
+    res
+}
+
+/// Creates a `308 Permanent Redirect` `Response`, sending the client to `path`, as for
+/// `create_redirect`.
+///
+/// Unlike `301 Moved Permanently`, which clients have historically followed with a `GET`, a
+/// client following a `308` repeats the request with the same method and body. This suits APIs
+/// which accept `POST` or `PUT` requests at a location which has moved.
+///
+/// # Examples
+///
+/// ```rust
+/// # extern crate gotham;
+/// # extern crate hyper;
+/// # extern crate mime;
+/// #
+/// # use hyper::{Response, StatusCode};
+/// # use hyper::header::Location;
+/// # use gotham::http::response::redirect::create_permanent_redirect;
+/// # use gotham::router::Router;
+/// # use gotham::router::builder::*;
+/// # use gotham::state::State;
+/// # use gotham::test::TestServer;
+/// #
+/// fn moved(state: State) -> (State, Response) {
+///     let res = create_permanent_redirect(&state, "/v2/orders");
+///     (state, res)
+/// }
+///
+/// fn router() -> Router {
+///     build_simple_router(|route| {
+///         route.post("/v1/orders").to(moved);
+///     })
+/// }
+/// #
+/// # fn main() {
+/// #   let test_server = TestServer::new(router()).unwrap();
+/// #   let response = test_server
+/// #       .client()
+/// #       .post("http://localhost/v1/orders", "{}", mime::APPLICATION_JSON)
+/// #       .perform()
+/// #       .unwrap();
+/// #
+/// #   assert_eq!(response.status(), StatusCode::PermanentRedirect);
+/// #   assert_eq!(&**response.headers().get::<Location>().unwrap(), "/v2/orders");
+/// # }
+/// ```
+pub fn create_permanent_redirect(state: &State, path: &str) -> Response {
+    create_redirect(state, StatusCode::PermanentRedirect, path)
+}
+
+/// Builds the value of the `Location` header for a redirect to `path`.
+fn location(state: &State, path: &str) -> String {
+    let style = LocationStyle::try_borrow_from(state)
+        .cloned()
+        .unwrap_or_default();
+
+    if style == LocationStyle::Relative || has_scheme(path) {
+        return path.to_owned();
+    }
+
+    match request_host(state) {
+        Some(host) => {
+            let scheme = if is_secure(state) { "https" } else { "http" };
+            let separator = if path.starts_with('/') { "" } else { "/" };
+            format!("{}://{}{}{}", scheme, host, separator, path)
+        }
+        None => path.to_owned(),
+    }
+}
+
+/// Determines whether `path` is already an absolute URI, which starts with a scheme followed by
+/// `:` before any `/`, `?` or `#`.
+fn has_scheme(path: &str) -> bool {
+    let end = match path.find([':', '/', '?', '#']) {
+        Some(end) if path[end..].starts_with(':') => end,
+        _ => return false,
+    };
+
+    let scheme = &path[..end];
+    scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+        && scheme
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '+' || c == '-' || c == '.')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use futures::{Future, Stream};
+    use hyper::{Body, Method};
+    use hyper::header::{Headers, Host};
+    use mime;
+
+    use config::ServerConfig;
+    use handler::HandlerFuture;
+    use http::request::forwarded::ForwardedPrecedence;
+    use router::builder::*;
+    use test::TestServer;
+
+    const FORWARDED: &str = "proto=https;host=example.com";
+
+    fn handler(state: State) -> (State, Response) {
+        let res = create_redirect(&state, StatusCode::Found, "/login?next=%2F");
+        (state, res)
+    }
+
+    fn redirect_to(config: ServerConfig, forwarded: Option<&str>) -> String {
+        let test_server = TestServer::with_config(|| Ok(handler), config).unwrap();
+        let mut req = test_server.client().get("http://localhost:8080/account");
+
+        if let Some(forwarded) = forwarded {
+            req = req.with_raw_header("Forwarded", forwarded.to_owned());
+        }
+
+        let response = req.perform().unwrap();
+        assert_eq!(response.status(), StatusCode::Found);
+        response.headers().get::<Location>().unwrap().to_string()
+    }
+
+    #[test]
+    fn relative_by_default() {
+        assert_eq!(
+            redirect_to(ServerConfig::default(), Some(FORWARDED)),
+            "/login?next=%2F"
+        );
+    }
+
+    #[test]
+    fn absolute_using_forwarded_host() {
+        let config = ServerConfig::default()
+            .with_location_style(LocationStyle::Absolute)
+            .with_trusted_proxy_headers(ForwardedPrecedence::Forwarded);
+
+        assert_eq!(
+            redirect_to(config, Some(FORWARDED)),
+            "https://example.com/login?next=%2F"
+        );
+    }
+
+    #[test]
+    fn absolute_ignores_host_sent_by_client_before_proxy() {
+        let config = ServerConfig::default()
+            .with_location_style(LocationStyle::Absolute)
+            .with_trusted_proxy_headers(ForwardedPrecedence::Forwarded);
+
+        let forwarded = "proto=https;host=evil.example, proto=https;host=example.com";
+        assert_eq!(
+            redirect_to(config, Some(forwarded)),
+            "https://example.com/login?next=%2F"
+        );
+    }
+
+    #[test]
+    fn absolute_rewrites_path_with_uri_in_query() {
+        let mut state = State::new();
+        state.put(LocationStyle::Absolute);
+        let mut headers = Headers::new();
+        headers.set(Host::new("example.com", None));
+        state.put(headers);
+
+        assert_eq!(
+            location(&state, "/login?next=https://example.com/account"),
+            "http://example.com/login?next=https://example.com/account"
+        );
+        assert_eq!(
+            location(&state, "https://example.org/"),
+            "https://example.org/"
+        );
+    }
+
+    #[test]
+    fn detects_leading_scheme() {
+        assert!(has_scheme("https://example.com/"));
+        assert!(has_scheme("mailto:someone@example.com"));
+        assert!(has_scheme("web+app:open"));
+        assert!(!has_scheme("/login?next=https://example.com/"));
+        assert!(!has_scheme("login#section:2"));
+        assert!(!has_scheme("//example.com/"));
+        assert!(!has_scheme("1ab:c"));
+        assert!(!has_scheme(":path"));
+    }
+
+    #[test]
+    fn absolute_using_host_header_when_proxy_untrusted() {
+        let config = ServerConfig::default().with_location_style(LocationStyle::Absolute);
+
+        assert_eq!(
+            redirect_to(config, Some(FORWARDED)),
+            "http://localhost:8080/login?next=%2F"
+        );
+    }
+
+    #[test]
+    fn permanent_redirect_is_followed_with_same_method_and_body() {
+        fn moved(state: State) -> (State, Response) {
+            let res = create_permanent_redirect(&state, "/v2/orders");
+            (state, res)
+        }
+
+        fn create_order(mut state: State) -> Box<HandlerFuture> {
+            let f = Body::take_from(&mut state).concat2().then(move |body| {
+                let mut echoed = format!("{} ", Method::borrow_from(&state)).into_bytes();
+                echoed.extend_from_slice(&body.unwrap());
+
+                let body = Some((echoed, mime::TEXT_PLAIN));
+                let res = create_response(&state, StatusCode::Created, body);
+                Ok((state, res))
+            });
+
+            Box::new(f)
+        }
+
+        let router = build_simple_router(|route| {
+            route.post("/v1/orders").to(moved);
+            route.post("/v2/orders").to(create_order);
+        });
+        let test_server = TestServer::new(router).unwrap();
+
+        let post = |uri: &str| {
+            test_server
+                .client()
+                .post(uri, r#"{"item": 1}"#, mime::APPLICATION_JSON)
+                .perform()
+                .unwrap()
+        };
+
+        let response = post("http://localhost/v1/orders");
+        assert_eq!(response.status(), StatusCode::PermanentRedirect);
+        let location = response.headers().get::<Location>().unwrap().to_string();
+        assert_eq!(location, "/v2/orders");
+
+        // A client follows a `308` by repeating the request, with the same method and body.
+        let response = post(&format!("http://localhost{}", location));
+        assert_eq!(response.status(), StatusCode::Created);
+        assert_eq!(response.read_utf8_body().unwrap(), r#"POST {"item": 1}"#);
+    }
+}
@@ -0,0 +1,147 @@
+//! Defines helpers for setting the `Retry-After` header.
+
+use std::time::Duration;
+
+use hyper::Response;
+use hyper::header::RetryAfter;
+
+use clock::clock;
+use state::{FromState, State, StateData};
+
+/// Determines how the `Retry-After` header is written by `set_retry_after`.
+///
+/// Configured for the whole application via `ServerConfig::with_retry_after_format`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum RetryAfterFormat {
+    /// The delay is written as a number of seconds, e.g. `Retry-After: 120`. This is the default.
+    #[default]
+    DeltaSeconds,
+
+    /// The delay is added to the current time and written as an HTTP-date, e.g.
+    /// `Retry-After: Wed, 21 Oct 2015 07:28:00 GMT`. Some clients understand only this form.
+    HttpDate,
+}
+
+impl StateData for RetryAfterFormat {}
+
+/// Sets the `Retry-After` header of `res`, asking the client to wait for `delay` before retrying
+/// the request.
+///
+/// The header is written as configured by `RetryAfterFormat`, with a date read from the
+/// configured `Clock`. This is used by the `503 Service
+/// Unavailable` responses generated by Gotham, and should be used by applications which send
+/// `429 Too Many Requests` or similar responses so that all agree.
+///
+/// # Examples
+///
+/// ```rust
+/// # extern crate gotham;
+/// # extern crate hyper;
+/// #
+/// # use std::time::Duration;
+/// # use hyper::{Response, StatusCode};
+/// # use hyper::header::RetryAfter;
+/// # use gotham::config::ServerConfig;
+/// # use gotham::http::response::create_response;
+/// # use gotham::http::response::retry_after::{set_retry_after, RetryAfterFormat};
+/// # use gotham::state::State;
+/// # use gotham::test::TestServer;
+/// #
+/// fn my_handler(state: State) -> (State, Response) {
+///     let mut res = create_response(&state, StatusCode::TooManyRequests, None);
+///     set_retry_after(&state, &mut res, Duration::from_secs(60));
+///     (state, res)
+/// }
+/// #
+/// # fn main() {
+/// #   let config = ServerConfig::default().with_retry_after_format(RetryAfterFormat::HttpDate);
+/// #   let test_server = TestServer::with_config(|| Ok(my_handler), config).unwrap();
+/// #   let response = test_server
+/// #       .client()
+/// #       .get("http://localhost/")
+/// #       .perform()
+/// #       .unwrap();
+/// #
+/// #   match response.headers().get::<RetryAfter>() {
+/// #       Some(&RetryAfter::DateTime(_)) => (),
+/// #       other => panic!("unexpected Retry-After: {:?}", other),
+/// #   }
+/// # }
+/// ```
+pub fn set_retry_after(state: &State, res: &mut Response, delay: Duration) {
+    let format = RetryAfterFormat::try_borrow_from(state)
+        .cloned()
+        .unwrap_or_default();
+
+    let header = match format {
+        RetryAfterFormat::DeltaSeconds => RetryAfter::Delay(delay),
+        RetryAfterFormat::HttpDate => {
+            RetryAfter::DateTime((clock(state).system_time() + delay).into())
+        }
+    };
+
+    res.headers_mut().set(header);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    fn retry_after(format: Option<RetryAfterFormat>) -> String {
+        let mut state = State::new();
+        if let Some(format) = format {
+            state.put(format);
+        }
+
+        let mut res = Response::new();
+        set_retry_after(&state, &mut res, Duration::from_secs(120));
+        res.headers().get::<RetryAfter>().unwrap().to_string()
+    }
+
+    #[test]
+    fn writes_delta_seconds_by_default() {
+        assert_eq!(retry_after(None), "120");
+        assert_eq!(retry_after(Some(RetryAfterFormat::DeltaSeconds)), "120");
+    }
+
+    #[test]
+    fn writes_http_date() {
+        let before = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+        let value = retry_after(Some(RetryAfterFormat::HttpDate));
+        let after = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+
+        assert!(value.ends_with(" GMT"), "{}", value);
+        let date: SystemTime = value.parse::<::hyper::header::HttpDate>().unwrap().into();
+        let date = date.duration_since(UNIX_EPOCH).unwrap().as_secs();
+        assert!(date >= before + 120 && date <= after + 120, "{}", value);
+    }
+
+    #[test]
+    fn reads_http_date_from_configured_clock() {
+        use std::sync::Arc;
+
+        use clock::{put_clock, Clock, MockClock};
+
+        let clock = MockClock::new();
+        clock.advance(Duration::from_secs(86_400 * 365));
+
+        let mut state = State::new();
+        state.put(RetryAfterFormat::HttpDate);
+        put_clock(&mut state, Arc::new(clock.clone()));
+
+        let mut res = Response::new();
+        set_retry_after(&state, &mut res, Duration::from_secs(120));
+
+        let expected = clock.system_time() + Duration::from_secs(120);
+        let expected = expected.duration_since(UNIX_EPOCH).unwrap().as_secs();
+        match res.headers().get::<RetryAfter>() {
+            Some(&RetryAfter::DateTime(date)) => {
+                let date: SystemTime = date.into();
+                assert_eq!(date.duration_since(UNIX_EPOCH).unwrap().as_secs(), expected);
+            }
+            other => panic!("unexpected Retry-After: {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,71 @@
+//! Defines a conservative fallback for determining the `Content-Type` of a response body from its
+//! leading bytes.
+
+use mime::{self, Mime};
+
+/// Determines the content type of `body` from the signature at its start, for use when the
+/// content type cannot be determined otherwise, such as a file without an extension.
+///
+/// Only a small set of unambiguous binary formats is recognized: PNG, JPEG, GIF, WebP and PDF.
+/// Formats which a browser could interpret as active content, such as HTML, SVG and scripts, are
+/// never sniffed, as labelling an untrusted body with one of those types can lead to cross-site
+/// scripting. Gotham sends `X-Content-Type-Options: nosniff` with every response, so the browser
+/// trusts the sniffed type rather than sniffing again itself.
+///
+/// Returns `None` when the body does not start with a recognized signature.
+///
+/// # Examples
+///
+/// ```rust
+/// # extern crate gotham;
+/// # extern crate mime;
+/// #
+/// # use gotham::http::response::sniff::sniff_content_type;
+/// #
+/// # fn main() {
+/// let png = b"\x89PNG\r\n\x1a\n\x00\x00\x00\rIHDR";
+/// assert_eq!(sniff_content_type(png), Some(mime::IMAGE_PNG));
+///
+/// assert_eq!(sniff_content_type(b"<html><script>alert(1)</script>"), None);
+/// # }
+/// ```
+pub fn sniff_content_type(body: &[u8]) -> Option<Mime> {
+    if body.starts_with(b"\x89PNG\r\n\x1a\n") {
+        Some(mime::IMAGE_PNG)
+    } else if body.starts_with(b"\xff\xd8\xff") {
+        Some(mime::IMAGE_JPEG)
+    } else if body.starts_with(b"GIF87a") || body.starts_with(b"GIF89a") {
+        Some(mime::IMAGE_GIF)
+    } else if body.len() >= 12 && body.starts_with(b"RIFF") && &body[8..12] == b"WEBP" {
+        Some("image/webp".parse().unwrap())
+    } else if body.starts_with(b"%PDF-") {
+        Some(mime::APPLICATION_PDF)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sniffs_known_signatures() {
+        assert_eq!(sniff_content_type(b"\xff\xd8\xff\xe0"), Some(mime::IMAGE_JPEG));
+        assert_eq!(sniff_content_type(b"GIF89a\x01\x00"), Some(mime::IMAGE_GIF));
+        assert_eq!(
+            sniff_content_type(b"RIFF\x24\x00\x00\x00WEBPVP8 "),
+            Some("image/webp".parse().unwrap())
+        );
+        assert_eq!(sniff_content_type(b"%PDF-1.7\n"), Some(mime::APPLICATION_PDF));
+    }
+
+    #[test]
+    fn does_not_sniff_unknown_or_active_content() {
+        assert_eq!(sniff_content_type(b""), None);
+        assert_eq!(sniff_content_type(b"\x89PN"), None);
+        assert_eq!(sniff_content_type(b"RIFF\x24\x00\x00\x00WAVE"), None);
+        assert_eq!(sniff_content_type(b"<!DOCTYPE html>"), None);
+        assert_eq!(sniff_content_type(b"<svg xmlns=\"http://www.w3.org/2000/svg\">"), None);
+    }
+}
@@ -0,0 +1,408 @@
+//! Defines helpers for streaming a response body from a channel or a stream of values.
+
+use std::io;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use futures::{Future, Sink, Stream};
+use futures::sync::mpsc::Receiver;
+use hyper::{self, Body, Chunk, Method, Response, StatusCode};
+use hyper::header::ContentLength;
+use mime::Mime;
+use serde::Serialize;
+use serde_json;
+use tokio_core::reactor::Handle;
+
+use http::response::{create_response, set_headers};
+use state::{request_id, FromState, State, StateData};
+
+/// The number of responses being streamed at once, across all worker threads, and the maximum
+/// configured via `ServerConfig::with_max_streaming_responses`.
+#[derive(Clone, Debug)]
+pub(crate) struct StreamingLimit {
+    max: usize,
+    active: Arc<AtomicUsize>,
+}
+
+impl StateData for StreamingLimit {}
+
+impl StreamingLimit {
+    pub(crate) fn new(max: usize) -> StreamingLimit {
+        StreamingLimit {
+            max,
+            active: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    pub(crate) fn max(&self) -> usize {
+        self.max
+    }
+
+    /// Counts a new stream, unless the maximum has been reached.
+    fn acquire(&self) -> Option<StreamingPermit> {
+        let mut active = self.active.load(Ordering::SeqCst);
+        loop {
+            if active >= self.max {
+                return None;
+            }
+
+            let next = active + 1;
+            match self.active
+                .compare_exchange(active, next, Ordering::SeqCst, Ordering::SeqCst)
+            {
+                Ok(_) => {
+                    return Some(StreamingPermit {
+                        active: self.active.clone(),
+                    })
+                }
+                Err(current) => active = current,
+            }
+        }
+    }
+}
+
+/// Counts a stream towards the `StreamingLimit` until dropped.
+struct StreamingPermit {
+    active: Arc<AtomicUsize>,
+}
+
+impl Drop for StreamingPermit {
+    fn drop(&mut self) {
+        self.active.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Creates a `Response` whose body is streamed from the receiving end of a `futures` channel,
+/// populated with the same default headers as `create_response`.
+///
+/// Each item sent on the channel is written to the client as it arrives, using chunked transfer
+/// encoding, so one part of the application can produce the body while it is being sent, as
+/// suits progress reports and log tailing. The body ends cleanly once every `Sender` has been
+/// dropped. If the client disconnects first, the `Receiver` is dropped, and sending on the channel
+/// fails.
+///
+/// When `ServerConfig::with_max_streaming_responses` is configured and that many responses are
+/// already being streamed, `503 Service Unavailable` is returned instead, and the `Receiver` is
+/// dropped.
+///
+/// # Examples
+///
+/// ```rust
+/// # extern crate futures;
+/// # extern crate gotham;
+/// # extern crate hyper;
+/// # extern crate mime;
+/// #
+/// # use std::thread;
+/// # use futures::{Future, Sink};
+/// # use futures::sync::mpsc;
+/// # use hyper::{Response, StatusCode};
+/// # use gotham::http::response::stream::create_channel_response;
+/// # use gotham::state::State;
+/// # use gotham::test::TestServer;
+/// #
+/// fn progress(state: State) -> (State, Response) {
+///     let (tx, rx) = mpsc::channel(4);
+///
+///     thread::spawn(move || {
+///         let mut tx = tx;
+///         for percent in &[25, 50, 75, 100] {
+///             tx = tx.send(format!("{}%\n", percent)).wait().unwrap();
+///         }
+///     });
+///
+///     let res = create_channel_response(&state, StatusCode::Ok, mime::TEXT_PLAIN, rx);
+///     (state, res)
+/// }
+/// #
+/// # fn main() {
+/// #   let test_server = TestServer::new(|| Ok(progress)).unwrap();
+/// #   let response = test_server.client().get("http://localhost/").perform().unwrap();
+/// #   assert_eq!(response.read_utf8_body().unwrap(), "25%\n50%\n75%\n100%\n");
+/// # }
+/// ```
+pub fn create_channel_response<T>(
+    state: &State,
+    status: StatusCode,
+    mime: Mime,
+    rx: Receiver<T>,
+) -> Response
+where
+    T: Into<Chunk> + 'static,
+{
+    stream_response(state, status, mime, rx.map(|item| Ok(item.into())))
+}
+
+/// Creates a `Response` whose body is streamed from `items` as newline-delimited JSON (NDJSON),
+/// with `Content-Type: application/x-ndjson` and the same default headers as `create_response`.
+///
+/// Each item is serialized to a single line of JSON and written to the client as it is produced,
+/// so a large collection is exported without buffering all of it in memory. A collection which is
+/// already in memory, or produced by an iterator, can be streamed using `futures::stream::iter_ok`.
+///
+/// If an item cannot be serialized, or `items` fails, the error is logged and the response is
+/// aborted, so the client sees the body end without a complete final line rather than a body
+/// which appears complete. The same limit on concurrent streams applies as for
+/// `create_channel_response`.
+///
+/// # Examples
+///
+/// ```rust
+/// # extern crate futures;
+/// # extern crate gotham;
+/// # extern crate hyper;
+/// # #[macro_use]
+/// # extern crate serde_derive;
+/// #
+/// # use std::io;
+/// # use futures::stream;
+/// # use hyper::{Response, StatusCode};
+/// # use gotham::http::response::stream::create_ndjson_response;
+/// # use gotham::state::State;
+/// # use gotham::test::TestServer;
+/// #
+/// #[derive(Serialize)]
+/// struct Order {
+///     id: u64,
+///     total: u64,
+/// }
+///
+/// fn export(state: State) -> (State, Response) {
+///     let orders = (1..4).map(|id| Order { id, total: id * 100 });
+///     let items = stream::iter_ok::<_, io::Error>(orders);
+///     let res = create_ndjson_response(&state, StatusCode::Ok, items);
+///     (state, res)
+/// }
+/// #
+/// # fn main() {
+/// #   let test_server = TestServer::new(|| Ok(export)).unwrap();
+/// #   let response = test_server.client().get("http://localhost/").perform().unwrap();
+/// #   let body = response.read_utf8_body().unwrap();
+/// #   assert_eq!(body.lines().next(), Some("{\"id\":1,\"total\":100}"));
+/// #   assert_eq!(body.lines().count(), 3);
+/// # }
+/// ```
+pub fn create_ndjson_response<S>(state: &State, status: StatusCode, items: S) -> Response
+where
+    S: Stream<Error = io::Error> + 'static,
+    S::Item: Serialize,
+{
+    let id = request_id(state).to_owned();
+    let lines = items
+        .and_then(|item| {
+            let mut line = serde_json::to_vec(&item)?;
+            line.push(b'\n');
+            Ok(Chunk::from(line))
+        })
+        .then(move |result| {
+            if let Err(ref e) = result {
+                error!("[{}] aborting NDJSON response: {}", id, e);
+            }
+            Ok(result.map_err(hyper::Error::Io))
+        });
+
+    let mime = "application/x-ndjson".parse().unwrap();
+    stream_response(state, status, mime, lines)
+}
+
+/// Creates a `Response` whose body is streamed from `chunks`, subject to the `StreamingLimit`.
+fn stream_response<S>(state: &State, status: StatusCode, mime: Mime, chunks: S) -> Response
+where
+    S: Stream<Item = Result<Chunk, hyper::Error>, Error = ()> + 'static,
+{
+    let permit = match StreamingLimit::try_borrow_from(state) {
+        Some(limit) => match limit.acquire() {
+            Some(permit) => Some(permit),
+            None => {
+                trace!(
+                    "[{}] rejecting streamed response, {} already streaming",
+                    request_id(state),
+                    limit.max()
+                );
+                return create_response(state, StatusCode::ServiceUnavailable, None);
+            }
+        },
+        None => None,
+    };
+
+    let mut res = Response::new().with_status(status);
+    set_headers(state, &mut res, Some(mime), None);
+    res.headers_mut().remove::<ContentLength>();
+
+    if *Method::borrow_from(state) == Method::Head {
+        return res;
+    }
+
+    let (tx, body) = Body::pair();
+    let id = request_id(state).to_owned();
+    let forward = tx
+        .sink_map_err(move |_| trace!("[{}] client disconnected from streamed response", id))
+        .send_all(chunks)
+        .then(move |_| {
+            drop(permit);
+            Ok(())
+        });
+    Handle::borrow_from(state).spawn(forward);
+
+    res.with_body(body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::Mutex;
+    use std::thread;
+
+    use futures::stream;
+    use futures::sync::mpsc;
+    use hyper::header::ContentType;
+
+    use config::ServerConfig;
+
+    use test::TestServer;
+
+    #[test]
+    fn streams_items_until_channel_closes() {
+        fn handler(state: State) -> (State, Response) {
+            let (tx, rx) = mpsc::channel(1);
+
+            thread::spawn(move || {
+                let mut tx = tx;
+                for i in 0..100 {
+                    tx = tx.send(format!("{},", i).into_bytes()).wait().unwrap();
+                }
+            });
+
+            let res = create_channel_response(&state, StatusCode::Ok, ::mime::TEXT_PLAIN, rx);
+            (state, res)
+        }
+
+        let test_server = TestServer::new(|| Ok(handler)).unwrap();
+        let response = test_server
+            .client()
+            .get("http://localhost/")
+            .perform()
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::Ok);
+        assert!(response.headers().get::<ContentLength>().is_none());
+
+        let expected: String = (0..100).map(|i| format!("{},", i)).collect();
+        assert_eq!(response.read_utf8_body().unwrap(), expected);
+    }
+
+    #[test]
+    fn rejects_streams_beyond_configured_maximum() {
+        let senders = Arc::new(Mutex::new(Vec::new()));
+        let new_handler = {
+            let senders = senders.clone();
+            move || {
+                let senders = senders.clone();
+                Ok(move |state: State| {
+                    let (tx, rx) = mpsc::channel::<Vec<u8>>(1);
+                    senders.lock().unwrap().push(tx);
+                    let res =
+                        create_channel_response(&state, StatusCode::Ok, ::mime::TEXT_PLAIN, rx);
+                    (state, res)
+                })
+            }
+        };
+
+        let config = ServerConfig::default().with_max_streaming_responses(2);
+        let test_server = TestServer::with_config(new_handler, config).unwrap();
+        let open = || {
+            test_server
+                .client()
+                .get("http://localhost/")
+                .perform()
+                .unwrap()
+        };
+
+        let first = open();
+        let second = open();
+        assert_eq!(first.status(), StatusCode::Ok);
+        assert_eq!(second.status(), StatusCode::Ok);
+        assert_eq!(open().status(), StatusCode::ServiceUnavailable);
+
+        senders.lock().unwrap().clear();
+        assert_eq!(first.read_body().unwrap(), b"");
+        assert_eq!(second.read_body().unwrap(), b"");
+
+        let response = open();
+        assert_eq!(response.status(), StatusCode::Ok);
+        senders.lock().unwrap().clear();
+        assert_eq!(response.read_body().unwrap(), b"");
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Row {
+        id: u32,
+        name: String,
+    }
+
+    #[test]
+    fn streams_items_as_ndjson() {
+        fn handler(state: State) -> (State, Response) {
+            let rows = (0..5).map(|id| Row {
+                id,
+                name: format!("row\n{}", id),
+            });
+            let res = create_ndjson_response(&state, StatusCode::Ok, stream::iter_ok(rows));
+            (state, res)
+        }
+
+        let test_server = TestServer::new(|| Ok(handler)).unwrap();
+        let response = test_server
+            .client()
+            .get("http://localhost/")
+            .perform()
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::Ok);
+        assert_eq!(
+            response.headers().get::<ContentType>().unwrap().to_string(),
+            "application/x-ndjson"
+        );
+        assert!(response.headers().get::<ContentLength>().is_none());
+
+        let body = response.read_utf8_body().unwrap();
+        assert!(body.ends_with('\n'));
+        let rows: Vec<Row> = body
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        let expected: Vec<Row> = (0..5)
+            .map(|id| Row {
+                id,
+                name: format!("row\n{}", id),
+            })
+            .collect();
+        assert_eq!(rows, expected);
+    }
+
+    #[test]
+    fn aborts_ndjson_response_on_error() {
+        fn handler(state: State) -> (State, Response) {
+            let rows = vec![
+                Ok(Row {
+                    id: 1,
+                    name: "first".to_owned(),
+                }),
+                Err(io::Error::other("database went away")),
+            ];
+            let res = create_ndjson_response(&state, StatusCode::Ok, stream::iter_result(rows));
+            (state, res)
+        }
+
+        let test_server = TestServer::new(|| Ok(handler)).unwrap();
+        let response = test_server
+            .client()
+            .get("http://localhost/")
+            .perform()
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::Ok);
+        assert!(response.read_body().is_err());
+    }
+}
@@ -15,22 +15,32 @@
 extern crate base64;
 extern crate bincode;
 extern crate borrow_bag;
+extern crate bytes;
 extern crate chrono;
 #[cfg(windows)]
 extern crate crossbeam;
+extern crate encoding_rs;
 extern crate futures;
 #[macro_use]
 extern crate hyper;
+#[cfg(unix)]
+extern crate libc;
 extern crate linked_hash_map;
 #[macro_use]
 extern crate log;
 extern crate mime;
 extern crate mio;
+#[cfg(unix)]
+extern crate net2;
 extern crate num_cpus;
 extern crate rand;
 extern crate regex;
 extern crate serde;
+extern crate serde_json;
+#[cfg(feature = "xml")]
+extern crate serde_xml_rs;
 extern crate tokio_core;
+extern crate tokio_io;
 extern crate url;
 extern crate uuid;
 
@@ -38,17 +48,20 @@ extern crate uuid;
 #[macro_use]
 extern crate serde_derive;
 
+pub mod clock;
+pub mod config;
 pub mod handler;
 pub mod middleware;
 pub mod pipeline;
 pub mod http;
 pub mod router;
 mod service;
+pub mod shutdown;
 pub mod state;
 pub mod test;
 mod os;
 
-pub use os::current::start_with_num_threads;
+pub use os::current::{start_with_config, start_with_num_threads};
 
 use std::net::{SocketAddr, TcpListener, ToSocketAddrs};
 use handler::NewHandler;
@@ -71,13 +84,19 @@ fn tcp_listener<A>(addr: A) -> (TcpListener, SocketAddr)
 where
     A: ToSocketAddrs,
 {
-    let addr = match addr.to_socket_addrs().map(|ref mut i| i.next()) {
-        Ok(Some(a)) => a,
-        Ok(_) => panic!("unable to resolve listener address"),
-        Err(_) => panic!("unable to parse listener address"),
-    };
-
+    let addr = resolve_addr(addr);
     let listener = TcpListener::bind(addr).expect("unable to open TCP listener");
 
     (listener, addr)
 }
+
+fn resolve_addr<A>(addr: A) -> SocketAddr
+where
+    A: ToSocketAddrs,
+{
+    match addr.to_socket_addrs().map(|ref mut i| i.next()) {
+        Ok(Some(a)) => a,
+        Ok(_) => panic!("unable to resolve listener address"),
+        Err(_) => panic!("unable to parse listener address"),
+    }
+}
@@ -0,0 +1,252 @@
+//! Defines a middleware which generates a nonce for each request, and permits scripts carrying
+//! that nonce via the `Content-Security-Policy` header.
+
+use std::io;
+use std::sync::{Arc, Mutex, PoisonError};
+
+use base64;
+use rand::Rng;
+
+use handler::HandlerFuture;
+use http::header::ContentSecurityPolicy;
+use http::response::map_response;
+use middleware::{Middleware, NewMiddleware};
+use middleware::session::rng::{self, SessionIdentifierRng};
+use state::{State, StateData};
+
+/// The nonce generated for the current request by `CspMiddleware`.
+///
+/// Inline scripts must carry the nonce in their `nonce` attribute to be executed by the browser,
+/// e.g. `<script nonce="...">`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CspNonce {
+    value: String,
+}
+
+impl CspNonce {
+    /// The nonce, as embedded in the `nonce` attribute of an inline script.
+    pub fn value(&self) -> &str {
+        &self.value
+    }
+}
+
+impl StateData for CspNonce {}
+
+/// Configures and creates `CspMiddleware` instances.
+///
+/// Each request is given a fresh nonce of 128 random bits, which is stored in `State` as a
+/// `CspNonce` and added to the `script-src` directive of the configured policy. When the policy
+/// has no `script-src` directive, one is added which permits only scripts carrying the nonce.
+///
+/// The `Content-Security-Policy` header is set on every response passed back through the
+/// middleware, replacing any set by the handler.
+///
+/// # Examples
+///
+/// ```rust
+/// # extern crate gotham;
+/// # extern crate hyper;
+/// # extern crate mime;
+/// #
+/// # use hyper::{Response, StatusCode};
+/// # use gotham::http::header::ContentSecurityPolicy;
+/// # use gotham::http::response::create_response;
+/// # use gotham::middleware::csp::{CspNonce, NewCspMiddleware};
+/// # use gotham::pipeline::new_pipeline;
+/// # use gotham::pipeline::single::single_pipeline;
+/// # use gotham::router::Router;
+/// # use gotham::router::builder::*;
+/// # use gotham::state::{FromState, State};
+/// # use gotham::test::TestServer;
+/// #
+/// fn handler(state: State) -> (State, Response) {
+///     let body = format!(
+///         "<script nonce=\"{}\">alert('hello')</script>",
+///         CspNonce::borrow_from(&state).value()
+///     );
+///     let res = create_response(&state, StatusCode::Ok, Some((body.into_bytes(), mime::TEXT_HTML)));
+///     (state, res)
+/// }
+///
+/// fn router() -> Router {
+///     let csp = NewCspMiddleware::new("default-src 'self'; script-src 'self'");
+///     let (chain, pipelines) = single_pipeline(new_pipeline().add(csp).build());
+///
+///     build_router(chain, pipelines, |route| {
+///         route.get("/").to(handler);
+///     })
+/// }
+/// #
+/// # fn main() {
+/// #   let test_server = TestServer::new(router()).unwrap();
+/// #   let response = test_server.client().get("http://localhost/").perform().unwrap();
+/// #   let policy = response.headers().get::<ContentSecurityPolicy>().unwrap().0.clone();
+/// #   let body = response.read_utf8_body().unwrap();
+/// #
+/// #   let nonce = body.split('"').nth(1).unwrap();
+/// #   assert_eq!(policy, format!("default-src 'self'; script-src 'self' 'nonce-{}'", nonce));
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct NewCspMiddleware {
+    policy: Arc<String>,
+    nonce_rng: Arc<Mutex<SessionIdentifierRng>>,
+}
+
+/// The per-request value which generates a `CspNonce` and sets the `Content-Security-Policy`
+/// header.
+///
+/// See `NewCspMiddleware` for usage details.
+pub struct CspMiddleware {
+    policy: Arc<String>,
+    nonce_rng: Arc<Mutex<SessionIdentifierRng>>,
+}
+
+impl NewCspMiddleware {
+    /// Creates a `NewCspMiddleware` which adds the nonce to `policy`.
+    pub fn new<S: Into<String>>(policy: S) -> NewCspMiddleware {
+        NewCspMiddleware {
+            policy: Arc::new(policy.into()),
+            nonce_rng: Arc::new(Mutex::new(rng::session_identifier_rng())),
+        }
+    }
+}
+
+impl Default for NewCspMiddleware {
+    /// Creates a `NewCspMiddleware` with the policy `default-src 'self'`, which permits inline
+    /// scripts only when they carry the nonce.
+    fn default() -> NewCspMiddleware {
+        NewCspMiddleware::new("default-src 'self'")
+    }
+}
+
+impl NewMiddleware for NewCspMiddleware {
+    type Instance = CspMiddleware;
+
+    fn new_middleware(&self) -> io::Result<CspMiddleware> {
+        Ok(CspMiddleware {
+            policy: self.policy.clone(),
+            nonce_rng: self.nonce_rng.clone(),
+        })
+    }
+}
+
+impl Middleware for CspMiddleware {
+    fn call<Chain>(self, mut state: State, chain: Chain) -> Box<HandlerFuture>
+    where
+        Chain: FnOnce(State) -> Box<HandlerFuture> + 'static,
+        Self: Sized,
+    {
+        let nonce = self.random_nonce();
+        let policy = policy_with_nonce(&self.policy, &nonce.value);
+        state.put(nonce);
+
+        map_response(chain(state), move |_state, res| {
+            res.headers_mut().set(ContentSecurityPolicy(policy));
+        })
+    }
+}
+
+impl CspMiddleware {
+    fn random_nonce(&self) -> CspNonce {
+        let mut bytes = [0u8; 16];
+
+        match self.nonce_rng.lock() {
+            Ok(mut rng) => rng.fill_bytes(&mut bytes),
+            Err(PoisonError { .. }) => unreachable!("nonce_rng lock poisoned. Rng panicked?"),
+        };
+
+        CspNonce {
+            value: base64::encode(&bytes[..]),
+        }
+    }
+}
+
+/// Adds `'nonce-<nonce>'` to the `script-src` directive of `policy`, adding the directive if it
+/// is not present.
+fn policy_with_nonce(policy: &str, nonce: &str) -> String {
+    let source = format!("'nonce-{}'", nonce);
+    let mut found = false;
+
+    let mut directives: Vec<String> = policy
+        .split(';')
+        .map(str::trim)
+        .filter(|directive| !directive.is_empty())
+        .map(|directive| {
+            let name = directive.split_whitespace().next().unwrap_or("");
+            if !found && name.eq_ignore_ascii_case("script-src") {
+                found = true;
+                format!("{} {}", directive, source)
+            } else {
+                directive.to_owned()
+            }
+        })
+        .collect();
+
+    if !found {
+        directives.push(format!("script-src {}", source));
+    }
+
+    directives.join("; ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use futures::{future, Future};
+    use hyper::{Headers, Response, StatusCode};
+
+    use http::response::create_response;
+    use state::{set_request_id, FromState};
+
+    fn call(m: CspMiddleware) -> (String, Response) {
+        let mut state = State::new();
+        state.put(Headers::new());
+        set_request_id(&mut state);
+
+        let f = m.call(state, |state| {
+            let res = create_response(&state, StatusCode::Ok, None);
+            Box::new(future::ok((state, res))) as Box<HandlerFuture>
+        });
+
+        match f.wait() {
+            Ok((state, response)) => (CspNonce::borrow_from(&state).value().to_owned(), response),
+            Err(_) => panic!("middleware failed"),
+        }
+    }
+
+    #[test]
+    fn header_contains_nonce_from_state() {
+        let nm = NewCspMiddleware::new("default-src 'self'; script-src 'self'");
+        let (nonce, response) = call(nm.new_middleware().unwrap());
+
+        assert_eq!(base64::decode(&nonce).unwrap().len(), 16);
+        assert_eq!(
+            response.headers().get::<ContentSecurityPolicy>().unwrap().0,
+            format!("default-src 'self'; script-src 'self' 'nonce-{}'", nonce)
+        );
+    }
+
+    #[test]
+    fn nonce_is_unique_per_request() {
+        let nm = NewCspMiddleware::default();
+        let (first, response) = call(nm.new_middleware().unwrap());
+        let (second, _) = call(nm.new_middleware().unwrap());
+
+        assert_ne!(first, second);
+        assert_eq!(
+            response.headers().get::<ContentSecurityPolicy>().unwrap().0,
+            format!("default-src 'self'; script-src 'nonce-{}'", first)
+        );
+    }
+
+    #[test]
+    fn adds_nonce_to_script_src() {
+        assert_eq!(
+            policy_with_nonce("Script-Src https:;img-src *;", "abc"),
+            "Script-Src https: 'nonce-abc'; img-src *"
+        );
+        assert_eq!(policy_with_nonce("", "abc"), "script-src 'nonce-abc'");
+    }
+}
@@ -6,7 +6,10 @@ use std::panic::RefUnwindSafe;
 use handler::HandlerFuture;
 use state::State;
 
+pub mod csp;
+pub mod response;
 pub mod session;
+pub mod toggle;
 
 /// `Middleware` has the opportunity to provide additional behaviour to the `Request` / `Response`
 /// interaction. Middleware-specific state data can be recorded in the `State` struct for
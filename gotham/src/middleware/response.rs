@@ -0,0 +1,181 @@
+//! Defines a middleware which acts only on the response, as it passes back through the pipeline.
+
+use std::fmt;
+use std::io;
+use std::panic::RefUnwindSafe;
+use std::sync::Arc;
+
+use hyper::Response;
+
+use handler::HandlerFuture;
+use http::response::map_response;
+use middleware::{Middleware, NewMiddleware};
+use state::State;
+
+/// A `Middleware` which is given each response produced by the rest of the pipeline and the
+/// handler, along with the `State` of the request, without taking part in the request.
+///
+/// This suits concerns which act only on the way out, such as adding security headers, without
+/// implementing `Middleware` and `NewMiddleware` for a new type. It is equivalent to a
+/// `Middleware` which calls `map_response` on the result of `chain`, and so is ordered in the
+/// pipeline as any other `Middleware`: responses pass back through the pipeline in the reverse of
+/// the order in which the middleware was added.
+///
+/// Errors from the handler are passed back untouched, as they are turned into responses by the
+/// `Router` once they have left the pipeline.
+///
+/// # Examples
+///
+/// ```rust
+/// # extern crate gotham;
+/// # extern crate hyper;
+/// #
+/// # use hyper::{Response, StatusCode};
+/// # use gotham::http::header::XFrameOptions;
+/// # use gotham::middleware::response::ResponseMiddleware;
+/// # use gotham::pipeline::new_pipeline;
+/// # use gotham::pipeline::single::single_pipeline;
+/// # use gotham::router::Router;
+/// # use gotham::router::builder::*;
+/// # use gotham::state::State;
+/// # use gotham::test::TestServer;
+/// #
+/// fn handler(state: State) -> (State, Response) {
+///     (state, Response::new().with_status(StatusCode::Ok))
+/// }
+///
+/// fn router() -> Router {
+///     let deny_framing = ResponseMiddleware::new(|_state, res| {
+///         res.headers_mut().set(XFrameOptions::Deny);
+///     });
+///     let (chain, pipelines) = single_pipeline(new_pipeline().add(deny_framing).build());
+///
+///     build_router(chain, pipelines, |route| {
+///         route.get("/").to(handler);
+///     })
+/// }
+/// #
+/// # fn main() {
+/// #   let test_server = TestServer::new(router()).unwrap();
+/// #   let response = test_server.client().get("http://localhost/").perform().unwrap();
+/// #   assert_eq!(response.headers().get::<XFrameOptions>(), Some(&XFrameOptions::Deny));
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct ResponseMiddleware {
+    f: Arc<ResponseFn>,
+}
+
+type ResponseFn = dyn Fn(&State, &mut Response) + Send + Sync + RefUnwindSafe;
+
+impl ResponseMiddleware {
+    /// Creates a `ResponseMiddleware` which calls `f` with the `State` and response of each
+    /// request passing back through it.
+    pub fn new<F>(f: F) -> ResponseMiddleware
+    where
+        F: Fn(&State, &mut Response) + Send + Sync + RefUnwindSafe + 'static,
+    {
+        ResponseMiddleware { f: Arc::new(f) }
+    }
+}
+
+impl fmt::Debug for ResponseMiddleware {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("ResponseMiddleware")
+    }
+}
+
+impl NewMiddleware for ResponseMiddleware {
+    type Instance = ResponseMiddleware;
+
+    fn new_middleware(&self) -> io::Result<ResponseMiddleware> {
+        Ok(self.clone())
+    }
+}
+
+impl Middleware for ResponseMiddleware {
+    fn call<Chain>(self, state: State, chain: Chain) -> Box<HandlerFuture>
+    where
+        Chain: FnOnce(State) -> Box<HandlerFuture> + 'static,
+        Self: Sized,
+    {
+        let f = self.f;
+        map_response(chain(state), move |state, res| f(state, res))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use futures::future;
+    use hyper::StatusCode;
+
+    use handler::IntoHandlerError;
+    use http::response::create_response;
+    use pipeline::new_pipeline;
+    use pipeline::single::single_pipeline;
+    use router::Router;
+    use router::builder::*;
+    use state::{request_id, FromState, StateData};
+    use test::TestServer;
+
+    struct Greeting(&'static str);
+
+    impl StateData for Greeting {}
+
+    fn greet(mut state: State) -> (State, Response) {
+        state.put(Greeting("hello"));
+        let res = create_response(&state, StatusCode::Ok, None);
+        (state, res)
+    }
+
+    fn fail(state: State) -> Box<HandlerFuture> {
+        let e = io::Error::other("failed");
+        Box::new(future::err((state, e.into_handler_error())))
+    }
+
+    fn router() -> Router {
+        let middleware = ResponseMiddleware::new(|state, res| {
+            let greeting = Greeting::try_borrow_from(state).map(|g| g.0).unwrap_or("none");
+            let value = format!("{} {}", greeting, request_id(state));
+            res.headers_mut().set_raw("X-Outbound", value);
+        });
+        let (chain, pipelines) = single_pipeline(new_pipeline().add(middleware).build());
+
+        build_router(chain, pipelines, |route| {
+            route.get("/greet").to(greet);
+            route.get("/fail").to(fail);
+        })
+    }
+
+    #[test]
+    fn runs_on_response_with_handler_state() {
+        let test_server = TestServer::new(router()).unwrap();
+        let response = test_server
+            .client()
+            .get("http://localhost/greet")
+            .with_header(::http::header::XRequestId("outbound".to_owned()))
+            .perform()
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::Ok);
+        assert_eq!(
+            response.headers().get_raw("X-Outbound").unwrap().one(),
+            Some(&b"hello outbound"[..])
+        );
+    }
+
+    #[test]
+    fn passes_errors_back_untouched() {
+        let test_server = TestServer::new(router()).unwrap();
+        let response = test_server
+            .client()
+            .get("http://localhost/fail")
+            .perform()
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::InternalServerError);
+        assert!(response.headers().get_raw("X-Outbound").is_none());
+    }
+}
@@ -5,6 +5,7 @@ use std::{io, thread};
 use linked_hash_map::LinkedHashMap;
 use futures::future;
 
+use clock::{Clock, SystemClock};
 use middleware::session::{SessionError, SessionIdentifier};
 use middleware::session::backend::{Backend, NewBackend, SessionFuture};
 
@@ -28,6 +29,8 @@ pub struct MemoryBackend {
     // might show a need to replace this with a smarter implementation, but today there's very
     // little overhead here.
     storage: Arc<Mutex<LinkedHashMap<String, (Instant, Vec<u8>)>>>,
+    ttl: Duration,
+    clock: Arc<dyn Clock>,
 }
 
 impl MemoryBackend {
@@ -47,14 +50,43 @@ impl MemoryBackend {
     /// # ;}
     /// ```
     pub fn new(ttl: Duration) -> MemoryBackend {
+        MemoryBackend::with_clock(ttl, SystemClock)
+    }
+
+    /// Creates a new `MemoryBackend` where sessions expire after the `ttl` has elapsed, as
+    /// measured by `clock`. A session which has expired is never read, even before it has been
+    /// removed.
+    ///
+    /// ## Examples
+    ///
+    /// ```rust
+    /// # extern crate gotham;
+    /// # use std::time::Duration;
+    /// # use gotham::clock::MockClock;
+    /// # use gotham::middleware::session::{MemoryBackend, NewSessionMiddleware};
+    /// # fn main() {
+    /// let clock = MockClock::new();
+    /// NewSessionMiddleware::new(MemoryBackend::with_clock(Duration::from_secs(3600), clock))
+    /// # ;}
+    /// ```
+    pub fn with_clock<C>(ttl: Duration, clock: C) -> MemoryBackend
+    where
+        C: Clock + 'static,
+    {
         let storage = Arc::new(Mutex::new(LinkedHashMap::new()));
+        let clock: Arc<dyn Clock> = Arc::new(clock);
 
         {
             let storage = Arc::downgrade(&storage);
-            thread::spawn(move || cleanup_loop(storage, ttl));
+            let clock = clock.clone();
+            thread::spawn(move || cleanup_loop(storage, ttl, clock));
         }
 
-        MemoryBackend { storage }
+        MemoryBackend {
+            storage,
+            ttl,
+            clock,
+        }
     }
 }
 
@@ -80,7 +112,7 @@ impl Backend for MemoryBackend {
     ) -> Result<(), SessionError> {
         match self.storage.lock() {
             Ok(mut storage) => {
-                storage.insert(identifier.value, (Instant::now(), Vec::from(content)));
+                storage.insert(identifier.value, (self.clock.instant(), Vec::from(content)));
                 Ok(())
             }
             Err(PoisonError { .. }) => {
@@ -90,10 +122,17 @@ impl Backend for MemoryBackend {
     }
 
     fn read_session(&self, identifier: SessionIdentifier) -> Box<SessionFuture> {
+        let now = self.clock.instant();
+
         match self.storage.lock() {
             Ok(mut storage) => match storage.get_refresh(&identifier.value) {
+                Some(&mut (ref instant, _)) if now.duration_since(*instant) >= self.ttl => {
+                    storage.remove(&identifier.value);
+                    trace!(" expired session {} on read from MemoryBackend", identifier.value);
+                    Box::new(future::ok(None))
+                }
                 Some(&mut (ref mut instant, ref value)) => {
-                    *instant = Instant::now();
+                    *instant = now;
                     Box::new(future::ok(Some(value.clone())))
                 }
                 None => Box::new(future::ok(None)),
@@ -117,7 +156,11 @@ impl Backend for MemoryBackend {
     }
 }
 
-fn cleanup_loop(storage: Weak<Mutex<LinkedHashMap<String, (Instant, Vec<u8>)>>>, ttl: Duration) {
+fn cleanup_loop(
+    storage: Weak<Mutex<LinkedHashMap<String, (Instant, Vec<u8>)>>>,
+    ttl: Duration,
+    clock: Arc<dyn Clock>,
+) {
     loop {
         // If the original `Arc<_>` goes away, we don't need to keep sweeping the cache, because
         // it's gone too. We can bail out of this thread when the weak ref fails to upgrade.
@@ -128,7 +171,7 @@ fn cleanup_loop(storage: Weak<Mutex<LinkedHashMap<String, (Instant, Vec<u8>)>>>,
 
         let duration = match storage.lock() {
             Err(PoisonError { .. }) => break,
-            Ok(mut storage) => cleanup_once(&mut storage, ttl),
+            Ok(mut storage) => cleanup_once(&mut storage, ttl, clock.instant()),
         };
 
         if let Some(duration) = duration {
@@ -140,10 +183,11 @@ fn cleanup_loop(storage: Weak<Mutex<LinkedHashMap<String, (Instant, Vec<u8>)>>>,
 fn cleanup_once(
     storage: &mut LinkedHashMap<String, (Instant, Vec<u8>)>,
     ttl: Duration,
+    now: Instant,
 ) -> Option<Duration> {
     match storage.front() {
         Some((_, &(instant, _))) => {
-            let age = instant.elapsed();
+            let age = now.duration_since(instant);
 
             if age >= ttl {
                 if let Some((key, _)) = storage.pop_front() {
@@ -191,6 +235,8 @@ mod tests {
     use futures::Future;
     use rand;
 
+    use clock::MockClock;
+
     #[test]
     fn cleanup_test() {
         let mut storage = LinkedHashMap::new();
@@ -200,7 +246,7 @@ mod tests {
             (Instant::now() - Duration::from_secs(2), vec![]),
         );
 
-        cleanup_once(&mut storage, Duration::from_secs(1));
+        cleanup_once(&mut storage, Duration::from_secs(1), Instant::now());
         assert!(storage.is_empty());
     }
 
@@ -209,7 +255,9 @@ mod tests {
         let storage = Arc::new(Mutex::new(LinkedHashMap::new()));
         let weak = Arc::downgrade(&storage);
 
-        let handle = thread::spawn(move || cleanup_loop(weak, Duration::from_millis(1)));
+        let handle = thread::spawn(move || {
+            cleanup_loop(weak, Duration::from_millis(1), Arc::new(SystemClock))
+        });
 
         drop(storage);
         handle.join().unwrap();
@@ -296,4 +344,34 @@ mod tests {
             );
         }
     }
+
+    #[test]
+    fn memory_backend_expires_session_as_clock_advances() {
+        let clock = MockClock::new();
+        let backend = MemoryBackend::with_clock(Duration::from_secs(60), clock.clone());
+        let identifier = SessionIdentifier {
+            value: "totally_random_identifier".to_owned(),
+        };
+        let read = || {
+            backend
+                .read_session(identifier.clone())
+                .wait()
+                .expect("no response from backend")
+        };
+
+        backend
+            .persist_session(identifier.clone(), b"session")
+            .expect("failed to persist");
+
+        clock.advance(Duration::from_secs(59));
+        assert_eq!(read(), Some(b"session".to_vec()));
+
+        // Reading refreshed the session, so it expires 60 seconds from the read.
+        clock.advance(Duration::from_secs(59));
+        assert_eq!(read(), Some(b"session".to_vec()));
+
+        clock.advance(Duration::from_secs(60));
+        assert_eq!(read(), None);
+        assert!(backend.storage.lock().unwrap().is_empty());
+    }
 }
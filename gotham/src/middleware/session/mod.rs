@@ -10,7 +10,7 @@ use base64;
 use rand::Rng;
 use hyper::StatusCode;
 use hyper::server::Response;
-use hyper::header::{Cookie, Headers, SetCookie};
+use hyper::header::{Cookie, Headers};
 use futures::{future, Future};
 use serde::{Deserialize, Serialize};
 use bincode;
@@ -19,9 +19,10 @@ use super::{Middleware, NewMiddleware};
 use handler::{HandlerError, HandlerFuture, IntoHandlerError};
 use state::{self, FromState, State, StateData};
 use http::response::create_response;
+use http::response::cookie::add_cookie;
 
 mod backend;
-mod rng;
+pub(crate) mod rng;
 
 pub use self::backend::{Backend, NewBackend};
 pub use self::backend::memory::MemoryBackend;
@@ -832,7 +833,7 @@ where
     let cookie_string = session_data
         .cookie_config
         .to_cookie_string(&session_data.identifier.value);
-    write_cookie(cookie_string, response);
+    add_cookie(response, cookie_string);
 }
 
 fn reset_cookie(response: &mut Response, session_drop_data: &SessionDropData) {
@@ -843,17 +844,7 @@ fn reset_cookie(response: &mut Response, session_drop_data: &SessionDropData) {
         "{}; expires=Thu, 01 Jan 1970 00:00:00 GMT; max-age=0",
         cookie_string
     );
-    write_cookie(cookie_string, response);
-}
-
-fn write_cookie(cookie: String, response: &mut Response) {
-    let headers = response.headers_mut();
-    if let Some(existing_cookies) = headers.get_mut::<SetCookie>() {
-        return existing_cookies.push(cookie);
-    }
-
-    let set_cookie = SetCookie(vec![cookie]);
-    headers.set(set_cookie);
+    add_cookie(response, cookie_string);
 }
 
 fn write_session<T>(
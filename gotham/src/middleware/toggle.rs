@@ -0,0 +1,207 @@
+//! Defines a middleware which can be switched on and off while the server is running.
+
+use std::io;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use handler::HandlerFuture;
+use middleware::{Middleware, NewMiddleware};
+use state::State;
+
+/// A shared flag which switches a `NewToggleMiddleware` on and off. Clones share the same flag,
+/// so one can be kept by the application to operate the switch, such as from an admin endpoint
+/// or a signal handler.
+#[derive(Clone, Debug)]
+pub struct MiddlewareSwitch {
+    enabled: Arc<AtomicBool>,
+}
+
+impl MiddlewareSwitch {
+    /// Creates a `MiddlewareSwitch`, initially switched on if `enabled` is `true`.
+    pub fn new(enabled: bool) -> MiddlewareSwitch {
+        MiddlewareSwitch {
+            enabled: Arc::new(AtomicBool::new(enabled)),
+        }
+    }
+
+    /// Switches the middleware on or off, taking effect from the next request.
+    pub fn set(&self, enabled: bool) {
+        self.enabled.store(enabled, Ordering::SeqCst);
+    }
+
+    /// Whether the middleware is switched on.
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::SeqCst)
+    }
+}
+
+/// Wraps another `NewMiddleware`, which is only invoked for requests received while its
+/// `MiddlewareSwitch` is on. While the switch is off, requests are passed straight on to the
+/// rest of the pipeline, and the wrapped middleware is not created.
+///
+/// This allows middleware which is costly or noisy, such as debug or body logging, to be enabled
+/// by operators without restarting the server. The switch is checked once, as each request
+/// enters the middleware, so a request is never handled by half of the middleware.
+///
+/// # Examples
+///
+/// ```rust
+/// # extern crate gotham;
+/// # extern crate hyper;
+/// #
+/// # use hyper::{Response, StatusCode};
+/// # use gotham::middleware::response::ResponseMiddleware;
+/// # use gotham::middleware::toggle::{MiddlewareSwitch, NewToggleMiddleware};
+/// # use gotham::pipeline::new_pipeline;
+/// # use gotham::pipeline::single::single_pipeline;
+/// # use gotham::router::Router;
+/// # use gotham::router::builder::*;
+/// # use gotham::state::{request_id, State};
+/// # use gotham::test::TestServer;
+/// #
+/// fn handler(state: State) -> (State, Response) {
+///     (state, Response::new().with_status(StatusCode::Ok))
+/// }
+///
+/// fn router(debug: MiddlewareSwitch) -> Router {
+///     let debug_headers = ResponseMiddleware::new(|state, res| {
+///         let id = request_id(state).to_owned();
+///         res.headers_mut().set_raw("X-Debug-Request-Id", id);
+///     });
+///
+///     let pipeline = new_pipeline()
+///         .add(NewToggleMiddleware::new(debug_headers, debug))
+///         .build();
+///     let (chain, pipelines) = single_pipeline(pipeline);
+///
+///     build_router(chain, pipelines, |route| {
+///         route.get("/").to(handler);
+///     })
+/// }
+/// #
+/// # fn main() {
+/// let debug = MiddlewareSwitch::new(false);
+/// let test_server = TestServer::new(router(debug.clone())).unwrap();
+///
+/// // Later, perhaps in response to an operator's request:
+/// debug.set(true);
+/// #
+/// # let response = test_server.client().get("http://localhost/").perform().unwrap();
+/// # assert!(response.headers().get_raw("X-Debug-Request-Id").is_some());
+/// # }
+/// ```
+pub struct NewToggleMiddleware<NM> {
+    new_middleware: NM,
+    switch: MiddlewareSwitch,
+}
+
+/// Handles a single request on behalf of a wrapped `Middleware`, if it was switched on when the
+/// request was received.
+///
+/// See `NewToggleMiddleware` for usage details.
+pub struct ToggleMiddleware<M> {
+    middleware: Option<M>,
+}
+
+impl<NM> NewToggleMiddleware<NM>
+where
+    NM: NewMiddleware,
+{
+    /// Creates a `NewToggleMiddleware` which invokes the middleware created by `new_middleware`
+    /// while `switch` is on.
+    pub fn new(new_middleware: NM, switch: MiddlewareSwitch) -> NewToggleMiddleware<NM> {
+        NewToggleMiddleware {
+            new_middleware,
+            switch,
+        }
+    }
+}
+
+impl<NM> NewMiddleware for NewToggleMiddleware<NM>
+where
+    NM: NewMiddleware,
+{
+    type Instance = ToggleMiddleware<NM::Instance>;
+
+    fn new_middleware(&self) -> io::Result<Self::Instance> {
+        let middleware = if self.switch.is_enabled() {
+            Some(self.new_middleware.new_middleware()?)
+        } else {
+            None
+        };
+
+        Ok(ToggleMiddleware { middleware })
+    }
+}
+
+impl<M> Middleware for ToggleMiddleware<M>
+where
+    M: Middleware,
+{
+    fn call<Chain>(self, state: State, chain: Chain) -> Box<HandlerFuture>
+    where
+        Chain: FnOnce(State) -> Box<HandlerFuture> + 'static,
+        Self: Sized,
+    {
+        match self.middleware {
+            Some(middleware) => middleware.call(state, chain),
+            None => chain(state),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use hyper::{Response, StatusCode};
+
+    use middleware::response::ResponseMiddleware;
+    use pipeline::new_pipeline;
+    use pipeline::single::single_pipeline;
+    use router::Router;
+    use router::builder::*;
+    use test::TestServer;
+
+    fn ok(state: State) -> (State, Response) {
+        (state, Response::new().with_status(StatusCode::Ok))
+    }
+
+    fn router(switch: MiddlewareSwitch) -> Router {
+        let marker = ResponseMiddleware::new(|_state, res| {
+            res.headers_mut().set_raw("X-Toggled", "on");
+        });
+        let pipeline = new_pipeline()
+            .add(NewToggleMiddleware::new(marker, switch))
+            .build();
+        let (chain, pipelines) = single_pipeline(pipeline);
+
+        build_router(chain, pipelines, |route| {
+            route.get("/").to(ok);
+        })
+    }
+
+    #[test]
+    fn invokes_middleware_only_while_switched_on() {
+        let switch = MiddlewareSwitch::new(true);
+        let test_server = TestServer::new(router(switch.clone())).unwrap();
+        let toggled = || {
+            let response = test_server
+                .client()
+                .get("http://localhost/")
+                .perform()
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::Ok);
+            response.headers().get_raw("X-Toggled").is_some()
+        };
+
+        assert!(toggled());
+
+        switch.set(false);
+        assert!(!switch.is_enabled());
+        assert!(!toggled());
+
+        switch.set(true);
+        assert!(toggled());
+    }
+}
@@ -0,0 +1,119 @@
+//! Defines the backoff applied when a connection cannot be accepted as the process or system has
+//! run out of file descriptors.
+
+use std::io;
+use std::time::Duration;
+
+use futures::{Async, Future, Poll, Stream};
+use tokio_core::reactor::{Handle, Timeout};
+
+/// Wraps a stream of incoming connections, pausing for `backoff` when it fails as no file
+/// descriptor is available for the connection, rather than ending the stream.
+///
+/// The pending connection remains queued by the operating system, so accepting it immediately
+/// would fail again and spin the worker until another connection is closed. Other errors are
+/// passed on unchanged.
+pub(crate) struct AcceptBackoff<S> {
+    incoming: S,
+    backoff: Duration,
+    handle: Handle,
+    delay: Option<Timeout>,
+}
+
+impl<S> AcceptBackoff<S> {
+    pub(crate) fn new(incoming: S, backoff: Duration, handle: &Handle) -> AcceptBackoff<S> {
+        AcceptBackoff {
+            incoming,
+            backoff,
+            handle: handle.clone(),
+            delay: None,
+        }
+    }
+}
+
+impl<S> Stream for AcceptBackoff<S>
+where
+    S: Stream<Error = io::Error>,
+{
+    type Item = S::Item;
+    type Error = io::Error;
+
+    fn poll(&mut self) -> Poll<Option<S::Item>, io::Error> {
+        loop {
+            if let Some(ref mut delay) = self.delay {
+                if let Async::NotReady = delay.poll()? {
+                    return Ok(Async::NotReady);
+                }
+            }
+            self.delay = None;
+
+            match self.incoming.poll() {
+                Err(ref e) if out_of_descriptors(e) => {
+                    warn!(
+                        "unable to accept connection, retrying in {:?}: {}",
+                        self.backoff,
+                        e
+                    );
+                    self.delay = Some(Timeout::new(self.backoff, &self.handle)?);
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+/// Returns `true` if `e` reports that the process or system has no file descriptor available.
+#[cfg(unix)]
+fn out_of_descriptors(e: &io::Error) -> bool {
+    match e.raw_os_error() {
+        Some(code) => code == ::libc::EMFILE || code == ::libc::ENFILE,
+        None => false,
+    }
+}
+
+/// Returns `true` if `e` reports that the process has no socket handle available.
+#[cfg(windows)]
+fn out_of_descriptors(e: &io::Error) -> bool {
+    const WSAEMFILE: i32 = 10024;
+    e.raw_os_error() == Some(WSAEMFILE)
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    use std::time::Instant;
+
+    use futures::stream;
+    use tokio_core::reactor::Core;
+
+    fn emfile() -> io::Error {
+        io::Error::from_raw_os_error(::libc::EMFILE)
+    }
+
+    #[test]
+    fn backs_off_while_out_of_descriptors() {
+        let mut core = Core::new().unwrap();
+        let incoming = stream::iter_result(vec![Err(emfile()), Err(emfile()), Ok(1), Ok(2)]);
+        let backoff = Duration::from_millis(50);
+        let accepted = AcceptBackoff::new(incoming, backoff, &core.handle()).collect();
+
+        let start = Instant::now();
+        assert_eq!(core.run(accepted).unwrap(), vec![1, 2]);
+        assert!(start.elapsed() >= backoff * 2);
+    }
+
+    #[test]
+    fn passes_on_other_errors() {
+        let mut core = Core::new().unwrap();
+        let e = io::Error::from_raw_os_error(::libc::EINVAL);
+        let incoming = stream::iter_result(vec![Ok(1), Err(e), Ok(2)]);
+        let accepted = AcceptBackoff::new(incoming, Duration::from_secs(60), &core.handle());
+
+        let (first, accepted) = core.run(accepted.into_future()).ok().unwrap();
+        assert_eq!(first, Some(1));
+
+        let (e, _) = core.run(accepted.into_future()).err().unwrap();
+        assert_eq!(e.raw_os_error(), Some(::libc::EINVAL));
+    }
+}
@@ -0,0 +1,121 @@
+//! Defines the limit on the rate at which connections are accepted from each client.
+
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use clock::Clock;
+
+/// Above this many tracked addresses, those which have recovered their full allowance are
+/// forgotten, bounding the memory used when connections arrive from many addresses.
+const PRUNE_THRESHOLD: usize = 1024;
+
+/// Limits the connections accepted from each IP address to `rate` per second, shared by every
+/// worker of a server.
+///
+/// Each address has an allowance of up to `rate` connections, which is used by each accepted
+/// connection and recovers continuously at `rate` per second. This permits short bursts while
+/// bounding the sustained rate.
+pub(crate) struct AcceptRateLimiter {
+    rate: f64,
+    allowances: Mutex<HashMap<IpAddr, Allowance>>,
+    clock: Arc<dyn Clock>,
+}
+
+struct Allowance {
+    remaining: f64,
+    updated: Instant,
+}
+
+impl AcceptRateLimiter {
+    pub(crate) fn new(rate: u32, clock: Arc<dyn Clock>) -> AcceptRateLimiter {
+        AcceptRateLimiter {
+            rate: f64::from(rate),
+            allowances: Mutex::new(HashMap::new()),
+            clock,
+        }
+    }
+
+    /// Returns `true` if a connection from `ip` is within the rate, using up part of its
+    /// allowance.
+    pub(crate) fn accept(&self, ip: IpAddr) -> bool {
+        self.accept_at(ip, self.clock.instant())
+    }
+
+    fn accept_at(&self, ip: IpAddr, now: Instant) -> bool {
+        let mut allowances = self.allowances
+            .lock()
+            .expect("accept rate mutex poisoned");
+
+        if allowances.len() >= PRUNE_THRESHOLD && !allowances.contains_key(&ip) {
+            let rate = self.rate;
+            allowances.retain(|_, allowance| allowance.recovered(rate, now) < rate);
+        }
+
+        let rate = self.rate;
+        let allowance = allowances.entry(ip).or_insert(Allowance {
+            remaining: rate,
+            updated: now,
+        });
+
+        allowance.remaining = allowance.recovered(rate, now);
+        allowance.updated = now;
+
+        if allowance.remaining >= 1.0 {
+            allowance.remaining -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+impl Allowance {
+    /// The allowance available at `now`, having recovered since it was last used.
+    fn recovered(&self, rate: f64, now: Instant) -> f64 {
+        let elapsed = now.duration_since(self.updated);
+        let elapsed = elapsed.as_secs() as f64 + f64::from(elapsed.subsec_nanos()) / 1e9;
+        (self.remaining + elapsed * rate).min(rate)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::time::Duration;
+
+    use clock::{MockClock, SystemClock};
+
+    #[test]
+    fn limits_each_address_and_recovers() {
+        let limiter = AcceptRateLimiter::new(2, Arc::new(SystemClock));
+        let start = Instant::now();
+        let first: IpAddr = "192.0.2.1".parse().unwrap();
+        let second: IpAddr = "192.0.2.2".parse().unwrap();
+
+        assert!(limiter.accept_at(first, start));
+        assert!(limiter.accept_at(first, start));
+        assert!(!limiter.accept_at(first, start));
+        assert!(limiter.accept_at(second, start));
+
+        let later = start + Duration::from_millis(500);
+        assert!(limiter.accept_at(first, later));
+        assert!(!limiter.accept_at(first, later));
+    }
+
+    #[test]
+    fn recovers_as_clock_advances() {
+        let clock = MockClock::new();
+        let limiter = AcceptRateLimiter::new(1, Arc::new(clock.clone()));
+        let ip: IpAddr = "192.0.2.1".parse().unwrap();
+
+        assert!(limiter.accept(ip));
+        assert!(!limiter.accept(ip));
+
+        clock.advance(Duration::from_secs(1));
+        assert!(limiter.accept(ip));
+        assert!(!limiter.accept(ip));
+    }
+}
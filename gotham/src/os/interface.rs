@@ -0,0 +1,82 @@
+//! Defines the resolution of a network interface name to its address, for
+//! `ServerConfig::with_bind_interface`.
+
+use std::ffi::CStr;
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::ptr;
+
+use libc;
+
+/// Returns the first address of the network interface `name`, which is an IPv6 address if `ipv6`
+/// is set, and an IPv4 address otherwise.
+pub(crate) fn interface_addr(name: &str, ipv6: bool) -> io::Result<IpAddr> {
+    let mut addrs: *mut libc::ifaddrs = ptr::null_mut();
+    if unsafe { libc::getifaddrs(&mut addrs) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    let mut found = None;
+    let mut cursor = addrs;
+    while !cursor.is_null() && found.is_none() {
+        let ifaddr = unsafe { &*cursor };
+        cursor = ifaddr.ifa_next;
+
+        if ifaddr.ifa_addr.is_null()
+            || unsafe { CStr::from_ptr(ifaddr.ifa_name) }.to_bytes() != name.as_bytes()
+        {
+            continue;
+        }
+
+        found = unsafe { ip_addr(ifaddr.ifa_addr, ipv6) };
+    }
+
+    unsafe { libc::freeifaddrs(addrs) };
+
+    found.ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::NotFound,
+            format!(
+                "network interface {} has no {} address",
+                name,
+                if ipv6 { "IPv6" } else { "IPv4" }
+            ),
+        )
+    })
+}
+
+/// Reads the address at `sockaddr` if it belongs to the requested address family.
+unsafe fn ip_addr(sockaddr: *const libc::sockaddr, ipv6: bool) -> Option<IpAddr> {
+    match i32::from((*sockaddr).sa_family) {
+        libc::AF_INET if !ipv6 => {
+            let sockaddr = &*(sockaddr as *const libc::sockaddr_in);
+            let octets = u32::from_be(sockaddr.sin_addr.s_addr);
+            Some(IpAddr::V4(Ipv4Addr::from(octets)))
+        }
+        libc::AF_INET6 if ipv6 => {
+            let sockaddr = &*(sockaddr as *const libc::sockaddr_in6);
+            Some(IpAddr::V6(Ipv6Addr::from(sockaddr.sin6_addr.s6_addr)))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn resolves_loopback() {
+        assert_eq!(
+            interface_addr("lo", false).unwrap(),
+            IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1))
+        );
+    }
+
+    #[test]
+    fn missing_interface_is_not_found() {
+        let e = interface_addr("gotham-missing0", false).unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::NotFound);
+    }
+}
@@ -1,3 +1,20 @@
+use std::io;
+use std::net::SocketAddr;
+
+use futures::{future, Future};
+use tokio_io::AsyncRead;
+
+use config::ServerConfig;
+
+use self::accept_rate::AcceptRateLimiter;
+
+mod accept_backoff;
+mod accept_rate;
+#[cfg(not(windows))]
+mod interface;
+mod proxy_protocol;
+mod read_timeout;
+
 #[cfg(not(windows))]
 pub mod unix;
 #[cfg(not(windows))]
@@ -7,3 +24,68 @@ pub use self::unix as current;
 pub mod windows;
 #[cfg(windows)]
 pub use self::windows as current;
+
+/// Returns `true` when a worker holding `active` connections has no room for another, recording
+/// the refusal in the configured `ConnectionMetrics`.
+fn at_capacity(config: &ServerConfig, active: usize) -> bool {
+    match config.max_connections_per_worker() {
+        Some(max) if active >= max => {
+            config.connection_metrics().record_refused();
+            warn!(
+                "refusing connection, worker already holds {} of {} connections",
+                active,
+                max
+            );
+            true
+        }
+        _ => false,
+    }
+}
+
+/// Creates the `AcceptRateLimiter` shared by the workers of a server, if a rate is configured.
+fn accept_rate_limiter(config: &ServerConfig) -> Option<AcceptRateLimiter> {
+    config
+        .max_accept_rate()
+        .map(|rate| AcceptRateLimiter::new(rate, config.clock().clone()))
+}
+
+/// Returns `true` when a connection from `addr` exceeds the configured accept rate, recording the
+/// refusal in the configured `ConnectionMetrics`.
+fn rate_limited(
+    config: &ServerConfig,
+    limiter: Option<&AcceptRateLimiter>,
+    addr: &SocketAddr,
+) -> bool {
+    match limiter {
+        Some(limiter) if !limiter.accept(addr.ip()) => {
+            config.connection_metrics().record_rate_limited();
+            warn!("refusing connection from {}, accept rate exceeded", addr.ip());
+            true
+        }
+        _ => false,
+    }
+}
+
+/// Resolves with `socket` and the address of the client, which is read from the PROXY protocol
+/// header when configured and is otherwise the peer address of the socket.
+fn client_connection<S>(
+    socket: S,
+    addr: SocketAddr,
+    config: &ServerConfig,
+) -> Box<dyn Future<Item = (S, SocketAddr), Error = io::Error>>
+where
+    S: AsyncRead + 'static,
+{
+    if !config.proxy_protocol() {
+        return Box::new(future::ok((socket, addr)));
+    }
+
+    let f = proxy_protocol::read_header(socket)
+        .map(move |(socket, client_addr)| (socket, client_addr.unwrap_or(addr)))
+        .map_err(move |e| {
+            warn!("closing connection from {}: {}", addr, e);
+            e
+        });
+
+    Box::new(f)
+}
@@ -0,0 +1,206 @@
+//! Defines the reading of the PROXY protocol header, which load balancers prepend to a
+//! connection to disclose the address of the client.
+//!
+//! Both the text (v1) and binary (v2) forms are supported, as described by
+//! <https://www.haproxy.org/download/1.8/doc/proxy-protocol.txt>.
+
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::str;
+
+use futures::{future, Future};
+use tokio_io::AsyncRead;
+use tokio_io::io::read_exact;
+
+/// The signature which begins a v2 header.
+const V2_SIGNATURE: [u8; 12] = [
+    0x0D, 0x0A, 0x0D, 0x0A, 0x00, 0x0D, 0x0A, 0x51, 0x55, 0x49, 0x54, 0x0A,
+];
+
+/// The length of the shortest v1 header, `PROXY UNKNOWN\r\n`. Both forms are at least this long,
+/// so it is read before the form is known.
+const MIN_LENGTH: usize = 15;
+
+/// The length of the longest v1 header, including the trailing CRLF.
+const V1_MAX_LENGTH: usize = 107;
+
+/// The length of the fixed part of a v2 header, which ends with the length of the addresses.
+const V2_HEADER_LENGTH: usize = 16;
+
+type HeaderFuture<R> = Box<dyn Future<Item = (R, Option<SocketAddr>), Error = io::Error>>;
+
+/// Reads the PROXY protocol header from the start of `io`, resolving with `io` positioned at the
+/// first byte after it and the source address which the header carries.
+///
+/// The address is `None` for headers which do not describe a TCP or UDP connection, such as
+/// health checks sent by the load balancer itself. Connections which do not begin with a valid
+/// header fail with `io::ErrorKind::InvalidData`.
+pub(crate) fn read_header<R>(io: R) -> HeaderFuture<R>
+where
+    R: AsyncRead + 'static,
+{
+    let f = read_exact(io, vec![0; MIN_LENGTH]).and_then(|(io, buf)| {
+        if buf.starts_with(&V2_SIGNATURE) {
+            read_v2(io, buf)
+        } else if buf.starts_with(b"PROXY ") {
+            read_v1(io, buf)
+        } else {
+            Box::new(future::err(invalid("missing PROXY protocol header")))
+        }
+    });
+
+    Box::new(f)
+}
+
+/// Reads the remainder of a v1 header one byte at a time, so that nothing beyond its CRLF is
+/// consumed.
+fn read_v1<R>(io: R, buf: Vec<u8>) -> HeaderFuture<R>
+where
+    R: AsyncRead + 'static,
+{
+    let f = future::loop_fn((io, buf), |(io, mut buf)| {
+        if buf.ends_with(b"\r\n") {
+            let addr = parse_v1(&buf[..buf.len() - 2]);
+            return future::Either::A(future::result(addr.map(|addr| {
+                future::Loop::Break((io, addr))
+            })));
+        }
+
+        if buf.len() >= V1_MAX_LENGTH {
+            return future::Either::A(future::err(invalid("PROXY protocol header too long")));
+        }
+
+        future::Either::B(read_exact(io, [0; 1]).map(move |(io, byte)| {
+            buf.push(byte[0]);
+            future::Loop::Continue((io, buf))
+        }))
+    });
+
+    Box::new(f)
+}
+
+/// Reads the remainder of a v2 header, whose length is given by its fixed part.
+fn read_v2<R>(io: R, mut buf: Vec<u8>) -> HeaderFuture<R>
+where
+    R: AsyncRead + 'static,
+{
+    let f = read_exact(io, vec![0; V2_HEADER_LENGTH - MIN_LENGTH])
+        .and_then(|(io, rest)| {
+            buf.extend_from_slice(&rest);
+            let len = (usize::from(buf[14]) << 8) | usize::from(buf[15]);
+            read_exact(io, vec![0; len]).map(move |(io, addresses)| (io, buf, addresses))
+        })
+        .and_then(|(io, buf, addresses)| parse_v2(&buf, &addresses).map(|addr| (io, addr)));
+
+    Box::new(f)
+}
+
+/// Parses a v1 header without its trailing CRLF, e.g.
+/// `PROXY TCP4 192.0.2.1 192.0.2.2 56324 443`.
+fn parse_v1(line: &[u8]) -> io::Result<Option<SocketAddr>> {
+    let line = str::from_utf8(line).map_err(|_| invalid("PROXY protocol header is not ASCII"))?;
+    let mut parts = line.split(' ');
+
+    match (parts.next(), parts.next()) {
+        (Some("PROXY"), Some("UNKNOWN")) => return Ok(None),
+        (Some("PROXY"), Some("TCP4")) | (Some("PROXY"), Some("TCP6")) => (),
+        _ => return Err(invalid("unsupported PROXY protocol family")),
+    }
+
+    let source: Option<IpAddr> = parts.next().and_then(|ip| ip.parse().ok());
+    let _destination = parts.next();
+    let port: Option<u16> = parts.next().and_then(|port| port.parse().ok());
+
+    match (source, port) {
+        (Some(ip), Some(port)) => Ok(Some(SocketAddr::new(ip, port))),
+        _ => Err(invalid("malformed PROXY protocol header")),
+    }
+}
+
+/// Parses a v2 header, given its fixed part and the address block which follows it.
+fn parse_v2(header: &[u8], addresses: &[u8]) -> io::Result<Option<SocketAddr>> {
+    let version_command = header[12];
+    if version_command >> 4 != 2 {
+        return Err(invalid("unsupported PROXY protocol version"));
+    }
+
+    match version_command & 0x0F {
+        // LOCAL: the connection was made by the load balancer itself.
+        0x0 => return Ok(None),
+        // PROXY
+        0x1 => (),
+        _ => return Err(invalid("unsupported PROXY protocol command")),
+    }
+
+    let port = |at: usize| (u16::from(addresses[at]) << 8) | u16::from(addresses[at + 1]);
+
+    match header[13] >> 4 {
+        // AF_INET: source, destination, source port, destination port.
+        0x1 if addresses.len() >= 12 => {
+            let mut ip = [0; 4];
+            ip.copy_from_slice(&addresses[..4]);
+            Ok(Some(SocketAddr::new(IpAddr::V4(Ipv4Addr::from(ip)), port(8))))
+        }
+        // AF_INET6
+        0x2 if addresses.len() >= 36 => {
+            let mut ip = [0; 16];
+            ip.copy_from_slice(&addresses[..16]);
+            Ok(Some(SocketAddr::new(IpAddr::V6(Ipv6Addr::from(ip)), port(32))))
+        }
+        0x1 | 0x2 => Err(invalid("truncated PROXY protocol addresses")),
+        // AF_UNSPEC or AF_UNIX, which carry no client IP address.
+        _ => Ok(None),
+    }
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::io::Cursor;
+
+    fn read(bytes: &[u8]) -> io::Result<(Option<SocketAddr>, Vec<u8>)> {
+        let (cursor, addr) = read_header(Cursor::new(bytes.to_vec())).wait()?;
+        let position = cursor.position() as usize;
+        Ok((addr, cursor.into_inner()[position..].to_vec()))
+    }
+
+    #[test]
+    fn reads_v1_header() {
+        let (addr, rest) = read(b"PROXY TCP4 192.0.2.1 192.0.2.2 56324 443\r\nGET /").unwrap();
+        assert_eq!(addr, Some("192.0.2.1:56324".parse().unwrap()));
+        assert_eq!(rest, b"GET /");
+
+        let (addr, _) = read(b"PROXY TCP6 2001:db8::1 2001:db8::2 4711 443\r\n").unwrap();
+        assert_eq!(addr, Some("[2001:db8::1]:4711".parse().unwrap()));
+
+        let (addr, rest) = read(b"PROXY UNKNOWN\r\nGET /").unwrap();
+        assert_eq!(addr, None);
+        assert_eq!(rest, b"GET /");
+    }
+
+    #[test]
+    fn reads_v2_header() {
+        let mut bytes = V2_SIGNATURE.to_vec();
+        bytes.extend_from_slice(&[0x21, 0x11, 0x00, 0x0C]);
+        bytes.extend_from_slice(&[192, 0, 2, 1, 192, 0, 2, 2, 0xDC, 0x04, 0x01, 0xBB]);
+        bytes.extend_from_slice(b"GET /");
+
+        let (addr, rest) = read(&bytes).unwrap();
+        assert_eq!(addr, Some("192.0.2.1:56324".parse().unwrap()));
+        assert_eq!(rest, b"GET /");
+    }
+
+    #[test]
+    fn rejects_missing_header() {
+        let e = read(b"GET / HTTP/1.1\r\nHost: localhost\r\n\r\n").unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+
+        let e = read(b"PROXY TCP4 not-an-address 192.0.2.2 56324 443\r\n").unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+    }
+}
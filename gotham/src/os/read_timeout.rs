@@ -0,0 +1,156 @@
+//! Defines a wrapper for connections which fails reads that stall for too long.
+
+use std::io::{self, Read, Write};
+use std::time::Duration;
+
+use futures::{Async, Future, Poll};
+use tokio_core::reactor::{Handle, Timeout};
+use tokio_io::{AsyncRead, AsyncWrite};
+
+/// Wraps a connection, failing a read with `io::ErrorKind::TimedOut` if no data arrives within
+/// the timeout. Hyper then closes the connection.
+///
+/// The timer starts when a read would block, and is reset whenever data arrives, so a connection
+/// which keeps sending is never timed out regardless of the total duration of the request.
+pub(crate) struct ReadTimeout<S> {
+    stream: S,
+    timeout: Option<Duration>,
+    handle: Handle,
+    timer: Option<Timeout>,
+}
+
+impl<S> ReadTimeout<S> {
+    /// Wraps `stream`. Reads never time out when `timeout` is `None`.
+    pub(crate) fn new(stream: S, timeout: Option<Duration>, handle: &Handle) -> ReadTimeout<S> {
+        ReadTimeout {
+            stream,
+            timeout,
+            handle: handle.clone(),
+            timer: None,
+        }
+    }
+
+    /// Called when a read would block, returning the error to fail the read with: `TimedOut`
+    /// once the timeout has elapsed, and `WouldBlock` otherwise.
+    fn poll_timer(&mut self) -> io::Error {
+        let timeout = match self.timeout {
+            Some(timeout) => timeout,
+            None => return io::ErrorKind::WouldBlock.into(),
+        };
+
+        if self.timer.is_none() {
+            match Timeout::new(timeout, &self.handle) {
+                Ok(timer) => self.timer = Some(timer),
+                Err(e) => return e,
+            }
+        }
+
+        match self.timer.as_mut().map(Future::poll) {
+            Some(Ok(Async::Ready(()))) => {
+                self.timer = None;
+                io::Error::new(io::ErrorKind::TimedOut, "connection read timed out")
+            }
+            Some(Err(e)) => e,
+            _ => io::ErrorKind::WouldBlock.into(),
+        }
+    }
+}
+
+impl<S> Read for ReadTimeout<S>
+where
+    S: Read,
+{
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self.stream.read(buf) {
+            Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => Err(self.poll_timer()),
+            result => {
+                self.timer = None;
+                result
+            }
+        }
+    }
+}
+
+impl<S> Write for ReadTimeout<S>
+where
+    S: Write,
+{
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.stream.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.stream.flush()
+    }
+}
+
+impl<S> AsyncRead for ReadTimeout<S>
+where
+    S: AsyncRead,
+{
+}
+
+impl<S> AsyncWrite for ReadTimeout<S>
+where
+    S: AsyncWrite,
+{
+    fn shutdown(&mut self) -> Poll<(), io::Error> {
+        self.stream.shutdown()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::time::Instant;
+
+    use futures::future;
+    use tokio_core::reactor::Core;
+
+    /// A stream which never has any data available.
+    struct Stalled;
+
+    impl Read for Stalled {
+        fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
+            Err(io::ErrorKind::WouldBlock.into())
+        }
+    }
+
+    fn read_until_done(
+        core: &mut Core,
+        stream: &mut ReadTimeout<Stalled>,
+    ) -> io::Result<usize> {
+        core.run(future::poll_fn(|| match stream.read(&mut [0; 8]) {
+            Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => Ok(Async::NotReady),
+            Err(e) => Err(e),
+            Ok(n) => Ok(Async::Ready(n)),
+        }))
+    }
+
+    #[test]
+    fn times_out_stalled_read() {
+        let mut core = Core::new().unwrap();
+        let timeout = Duration::from_millis(100);
+        let mut stream = ReadTimeout::new(Stalled, Some(timeout), &core.handle());
+
+        let start = Instant::now();
+        let err = read_until_done(&mut core, &mut stream).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+        assert!(start.elapsed() >= timeout);
+    }
+
+    #[test]
+    fn passes_through_without_timeout() {
+        let core = Core::new().unwrap();
+        let mut stream = ReadTimeout::new(&b"abc"[..], None, &core.handle());
+        let mut buf = [0; 8];
+        assert_eq!(stream.read(&mut buf).unwrap(), 3);
+
+        let mut stalled = ReadTimeout::new(Stalled, None, &core.handle());
+        assert_eq!(
+            stalled.read(&mut buf).unwrap_err().kind(),
+            io::ErrorKind::WouldBlock
+        );
+    }
+}
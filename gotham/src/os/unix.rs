@@ -1,14 +1,25 @@
+use std::cell::Cell;
 use std::net::{SocketAddr, TcpListener, ToSocketAddrs};
+use std::rc::Rc;
 use std::thread;
 use std::sync::Arc;
 
 use hyper::server::Http;
+use net2::TcpBuilder;
+use net2::unix::UnixTcpBuilderExt;
 use tokio_core;
 use tokio_core::reactor::Core;
 use futures::{Future, Stream};
 
+use config::ServerConfig;
 use handler::NewHandler;
+use os::{accept_rate_limiter, at_capacity, client_connection, rate_limited};
+use os::accept_backoff::AcceptBackoff;
+use os::accept_rate::AcceptRateLimiter;
+use os::interface::interface_addr;
+use os::read_timeout::ReadTimeout;
 use service::GothamService;
+use shutdown::{self, close_when_draining, DrainBehavior};
 
 /// Starts a Gotham application, with the given number of threads.
 pub fn start_with_num_threads<NH, A>(addr: A, threads: usize, new_handler: NH)
@@ -16,9 +27,38 @@ where
     NH: NewHandler + 'static,
     A: ToSocketAddrs,
 {
-    let (listener, addr) = ::tcp_listener(addr);
+    start_with_config(
+        addr,
+        ServerConfig::default().with_num_threads(threads),
+        new_handler,
+    )
+}
+
+/// Starts a Gotham application, using the given `ServerConfig`.
+///
+/// Returns once the server has drained, after being told to do so via the `Shutdown` handle in
+/// `config`.
+pub fn start_with_config<NH, A>(addr: A, config: ServerConfig, new_handler: NH)
+where
+    NH: NewHandler + 'static,
+    A: ToSocketAddrs,
+{
+    let mut addr = ::resolve_addr(addr);
+    if let Some(name) = config.bind_interface() {
+        let ip = interface_addr(name, addr.is_ipv6()).expect("unable to resolve network interface");
+        addr.set_ip(ip);
+    }
+
+    let (listener, addr) = if config.reuse_port() {
+        reuse_port_listener(addr)
+    } else {
+        ::tcp_listener(addr)
+    };
 
-    let protocol = Arc::new(Http::new());
+    let threads = config.num_threads();
+    let protocol = Arc::new(config.protocol());
+    let limiter = accept_rate_limiter(&config).map(Arc::new);
+    let config = Arc::new(config);
     let new_handler = Arc::new(new_handler);
 
     info!(
@@ -28,33 +68,673 @@ where
         threads,
     );
 
-    for _ in 0..threads - 1 {
-        let listener = listener.try_clone().expect("unable to clone TCP listener");
-        let protocol = protocol.clone();
-        let new_handler = new_handler.clone();
-        thread::spawn(move || serve(listener, &addr, &protocol, new_handler));
+    let workers: Vec<_> = (0..threads - 1)
+        .map(|_| {
+            let listener = listener.try_clone().expect("unable to clone TCP listener");
+            let protocol = protocol.clone();
+            let config = config.clone();
+            let limiter = limiter.clone();
+            let new_handler = new_handler.clone();
+            thread::spawn(move || serve(listener, &addr, &protocol, config, limiter, new_handler))
+        })
+        .collect();
+
+    serve(listener, &addr, &protocol, config, limiter, new_handler);
+
+    for worker in workers {
+        worker.join().expect("worker thread panicked");
     }
 
-    serve(listener, &addr, &protocol, new_handler);
+    info!(target: "gotham::start", " Gotham on http://{} has shut down", addr);
 }
 
-fn serve<NH>(listener: TcpListener, addr: &SocketAddr, protocol: &Http, new_handler: Arc<NH>)
+fn reuse_port_listener<A>(addr: A) -> (TcpListener, SocketAddr)
 where
+    A: ToSocketAddrs,
+{
+    let addr = ::resolve_addr(addr);
+
+    let builder = match addr {
+        SocketAddr::V4(_) => TcpBuilder::new_v4(),
+        SocketAddr::V6(_) => TcpBuilder::new_v6(),
+    }.expect("unable to create TCP socket");
+
+    let listener = builder
+        .reuse_address(true)
+        .and_then(|b| b.reuse_port(true))
+        .and_then(|b| b.bind(addr))
+        .and_then(|b| b.listen(1024))
+        .expect("unable to open TCP listener");
+
+    (listener, addr)
+}
+
+fn serve<NH>(
+    listener: TcpListener,
+    addr: &SocketAddr,
+    protocol: &Http,
+    config: Arc<ServerConfig>,
+    limiter: Option<Arc<AcceptRateLimiter>>,
+    new_handler: Arc<NH>,
+) where
     NH: NewHandler + 'static,
 {
     let mut core = Core::new().expect("unable to spawn tokio reactor");
+    if let Some(init) = config.worker_init() {
+        init.run(&mut core).expect("worker initialization failed");
+    }
     let handle = core.handle();
 
-    let gotham_service = GothamService::new(new_handler, handle.clone());
+    let gotham_service = Rc::new(GothamService::new(new_handler, handle.clone(), config.clone()));
 
     let listener = tokio_core::net::TcpListener::from_listener(listener, addr, &handle)
         .expect("unable to convert TCP listener to tokio listener");
 
-    core.run(listener.incoming().for_each(|(socket, addr)| {
-        let service = gotham_service.connect(addr);
-        let f = protocol.serve_connection(socket, service).then(|_| Ok(()));
+    let active = Rc::new(Cell::new(0));
+    let drain_signal = shutdown::drain_signal(config.shutdown().clone(), &handle);
+
+    let accept = {
+        let active = active.clone();
+        let handle = handle.clone();
+        let read_timeout = config.read_timeout();
+        let config = config.clone();
+
+        let incoming = AcceptBackoff::new(listener.incoming(), config.accept_backoff(), &handle);
+        incoming.for_each(move |(socket, addr)| {
+            if at_capacity(&config, active.get())
+                || rate_limited(&config, limiter.as_deref(), &addr)
+            {
+                return Ok(());
+            }
+
+            active.set(active.get() + 1);
+            let active = active.clone();
+            let gotham_service = gotham_service.clone();
+            let protocol = protocol.clone();
+            let signal = if config.shutdown().is_draining() {
+                None
+            } else {
+                Some(drain_signal.clone())
+            };
+            let socket = ReadTimeout::new(socket, read_timeout, &handle);
+            let f = client_connection(socket, addr, &config)
+                .map_err(|_| ())
+                .and_then(move |(socket, client_addr)| {
+                    let service = gotham_service.connect(client_addr);
+                    let connection = protocol.serve_connection(socket, service);
+                    close_when_draining(connection, signal).map_err(|_| ())
+                })
+                .then(move |_| {
+                    active.set(active.get() - 1);
+                    Ok(())
+                });
+
+            handle.spawn(f);
+            Ok(())
+        })
+    };
+
+    let draining = shutdown::draining(config.shutdown().clone(), &handle);
+
+    match config.drain_behavior() {
+        DrainBehavior::CloseListener => {
+            // The listener is closed as soon as draining begins, as `accept` is dropped.
+            core.run(accept.select(draining).map(|_| ()).map_err(|(e, _)| e))
+                .expect("unable to run reactor over listener");
+
+            core.run(shutdown::drained(active, config.shutdown_timeout(), &handle))
+                .expect("unable to run reactor while draining");
+        }
+        DrainBehavior::RespondUnavailable => {
+            // The listener stays open until draining completes, and new requests are answered
+            // with `503 Service Unavailable` by the service.
+            let timeout = config.shutdown_timeout();
+            let drained = {
+                let handle = handle.clone();
+                draining.and_then(move |_| shutdown::drained(active, timeout, &handle))
+            };
+
+            core.run(accept.select(drained).map(|_| ()).map_err(|(e, _)| e))
+                .expect("unable to run reactor while draining");
+        }
+    }
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+
+    use std::cell::RefCell;
+    use std::env;
+    use std::io::{self, Read, Write};
+    use std::net::TcpStream;
+    use std::process::{Child, Command, Stdio};
+    use std::time::{Duration, Instant};
+
+    use hyper::{Response, StatusCode};
+    use mime;
+
+    use futures::future;
+
+    use handler::{HandlerFuture, IntoHandlerError};
+    use http::request::body::BodyReader;
+    use http::response::create_response;
+    use config::{ConnectionMetrics, WorkerInit};
+    use shutdown::Shutdown;
+    use state::{client_addr, State};
+
+    thread_local!(static WORKER_NAME: RefCell<Option<String>> = const { RefCell::new(None) });
+
+    fn worker_name_handler(state: State) -> (State, Response) {
+        let name = WORKER_NAME.with(|name| name.borrow().clone());
+        let body = name.unwrap_or_else(|| String::from("uninitialized")).into_bytes();
+        let res = create_response(&state, StatusCode::Ok, Some((body, mime::TEXT_PLAIN)));
+        (state, res)
+    }
+
+    fn old_handler(state: State) -> (State, Response) {
+        let res = create_response(
+            &state,
+            StatusCode::Ok,
+            Some((b"old".to_vec(), mime::TEXT_PLAIN)),
+        );
+        (state, res)
+    }
+
+    fn new_handler(state: State) -> (State, Response) {
+        let res = create_response(
+            &state,
+            StatusCode::Ok,
+            Some((b"new".to_vec(), mime::TEXT_PLAIN)),
+        );
+        (state, res)
+    }
+
+    fn body_handler(mut state: State) -> Box<HandlerFuture> {
+        let f = BodyReader::default().read(&mut state).then(move |result| match result {
+            Ok(body) => {
+                let body = format!("{} bytes", body.len()).into_bytes();
+                let res = create_response(&state, StatusCode::Ok, Some((body, mime::TEXT_PLAIN)));
+                future::ok((state, res))
+            }
+            Err(e) => future::err((state, e.into_handler_error())),
+        });
+
+        Box::new(f)
+    }
+
+    fn get(addr: SocketAddr) -> io::Result<String> {
+        let mut stream = TcpStream::connect(addr)?;
+        stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+        stream.write_all(b"GET / HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")?;
+
+        let mut response = String::new();
+        stream.read_to_string(&mut response)?;
+
+        match response.split("\r\n\r\n").nth(1) {
+            Some(body) if response.starts_with("HTTP/1.1 200") => Ok(body.to_owned()),
+            _ => Err(io::Error::other(response)),
+        }
+    }
+
+    fn wait_for(addr: SocketAddr, body: &str) {
+        let start = Instant::now();
+        while get(addr).ok().as_deref() != Some(body) {
+            assert!(start.elapsed() < Duration::from_secs(5), "server did not start");
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    fn free_addr() -> SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        listener.local_addr().unwrap()
+    }
+
+    fn status_line(addr: SocketAddr) -> io::Result<String> {
+        match get(addr) {
+            Ok(_) => Ok(String::from("HTTP/1.1 200 OK")),
+            Err(ref e) if e.kind() == io::ErrorKind::Other => {
+                Ok(e.to_string().lines().next().unwrap_or("").to_owned())
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Starts a server with `behavior`, and begins draining it while a connection is held open.
+    /// Returns the address, the open connection, and the server thread.
+    fn start_draining(
+        behavior: DrainBehavior,
+    ) -> (SocketAddr, TcpStream, thread::JoinHandle<()>) {
+        let addr = free_addr();
+        let shutdown = Shutdown::new();
+        let config = config(&shutdown).with_drain_behavior(behavior);
+        let server = thread::spawn(move || start_with_config(addr, config, || Ok(old_handler)));
+        wait_for(addr, "old");
+
+        let idle = TcpStream::connect(addr).unwrap();
+        thread::sleep(Duration::from_millis(100));
+        shutdown.drain();
+        thread::sleep(Duration::from_millis(200));
+
+        (addr, idle, server)
+    }
+
+    fn config(shutdown: &Shutdown) -> ServerConfig {
+        ServerConfig::default()
+            .with_num_threads(1)
+            .with_reuse_port(true)
+            .with_shutdown(shutdown.clone())
+            .with_shutdown_timeout(Duration::from_secs(5))
+    }
+
+    #[test]
+    fn hands_off_between_reuse_port_servers() {
+        let addr = free_addr();
+
+        let old_shutdown = Shutdown::new();
+        let old_config = config(&old_shutdown);
+        let old = thread::spawn(move || start_with_config(addr, old_config, || Ok(old_handler)));
+        wait_for(addr, "old");
+
+        let new_shutdown = Shutdown::new();
+        let new_config = config(&new_shutdown);
+        let new = thread::spawn(move || start_with_config(addr, new_config, || Ok(new_handler)));
+        wait_for(addr, "new");
+
+        for _ in 0..10 {
+            get(addr).unwrap();
+        }
+
+        old_shutdown.drain();
+        old.join().unwrap();
+
+        for _ in 0..20 {
+            assert_eq!(get(addr).unwrap(), "new");
+        }
+
+        new_shutdown.drain();
+        new.join().unwrap();
+        assert!(get(addr).is_err());
+    }
+
+    /// Set in the environment of the process spawned by `hands_off_between_reuse_port_processes`
+    /// to the address it should serve.
+    const HANDOFF_ADDR: &str = "GOTHAM_TEST_HANDOFF_ADDR";
+
+    /// Kills the spawned process if the test fails before it has exited.
+    struct KillOnDrop(Child);
+
+    impl Drop for KillOnDrop {
+        fn drop(&mut self) {
+            let _ = self.0.kill();
+            let _ = self.0.wait();
+        }
+    }
 
-        handle.spawn(f);
-        Ok(())
-    })).expect("unable to run reactor over listener");
+    #[test]
+    fn hands_off_between_reuse_port_processes() {
+        if let Ok(addr) = env::var(HANDOFF_ADDR) {
+            // Running as the old process, until told to drain by the test process below.
+            let addr: SocketAddr = addr.parse().unwrap();
+            let shutdown = Shutdown::new();
+            shutdown.drain_on_signal().unwrap();
+            start_with_config(addr, config(&shutdown), || Ok(old_handler));
+            return;
+        }
+
+        let addr = free_addr();
+        let test_name = module_path!().split_once("::").unwrap().1.to_owned()
+            + "::hands_off_between_reuse_port_processes";
+        let old = Command::new(env::current_exe().unwrap())
+            .args(["--exact", &test_name, "--test-threads=1", "--quiet"])
+            .env(HANDOFF_ADDR, addr.to_string())
+            .stdout(Stdio::null())
+            .spawn()
+            .unwrap();
+        let mut old = KillOnDrop(old);
+        wait_for(addr, "old");
+
+        let new_shutdown = Shutdown::new();
+        let new_config = config(&new_shutdown);
+        let new = thread::spawn(move || start_with_config(addr, new_config, || Ok(new_handler)));
+        wait_for(addr, "new");
+
+        for _ in 0..10 {
+            get(addr).unwrap();
+        }
+
+        shutdown::signal_drain(old.0.id()).unwrap();
+        assert!(old.0.wait().unwrap().success());
+
+        for _ in 0..20 {
+            assert_eq!(get(addr).unwrap(), "new");
+        }
+
+        new_shutdown.drain();
+        new.join().unwrap();
+        assert!(get(addr).is_err());
+    }
+
+    #[test]
+    fn runs_worker_init_before_serving() {
+        let addr = free_addr();
+        let shutdown = Shutdown::new();
+        let init = WorkerInit::new(|_handle| -> io::Result<()> {
+            WORKER_NAME.with(|name| *name.borrow_mut() = Some(String::from("worker")));
+            Ok(())
+        });
+        let config = config(&shutdown).with_worker_init(init);
+        let server =
+            thread::spawn(move || start_with_config(addr, config, || Ok(worker_name_handler)));
+        wait_for(addr, "worker");
+
+        for _ in 0..5 {
+            assert_eq!(get(addr).unwrap(), "worker");
+        }
+
+        shutdown.drain();
+        server.join().unwrap();
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn binds_to_interface_by_name() {
+        let addr = free_addr();
+        let unspecified = SocketAddr::new("0.0.0.0".parse().unwrap(), addr.port());
+        let shutdown = Shutdown::new();
+        let config = config(&shutdown).with_bind_interface("lo");
+        let server =
+            thread::spawn(move || start_with_config(unspecified, config, || Ok(old_handler)));
+        wait_for(addr, "old");
+
+        shutdown.drain();
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn refuses_connections_while_draining_with_close_listener() {
+        let (addr, idle, server) = start_draining(DrainBehavior::CloseListener);
+
+        assert_eq!(
+            TcpStream::connect(addr).unwrap_err().kind(),
+            io::ErrorKind::ConnectionRefused
+        );
+
+        drop(idle);
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn responds_unavailable_while_draining_with_respond_unavailable() {
+        let (addr, idle, server) = start_draining(DrainBehavior::RespondUnavailable);
+
+        assert_eq!(status_line(addr).unwrap(), "HTTP/1.1 503 Service Unavailable");
+
+        drop(idle);
+        server.join().unwrap();
+        assert!(TcpStream::connect(addr).is_err());
+    }
+
+    #[test]
+    fn closes_idle_kept_alive_connection_when_draining() {
+        let addr = free_addr();
+        let shutdown = Shutdown::new();
+        let config = config(&shutdown).with_read_timeout(Duration::from_secs(60));
+        let server = thread::spawn(move || start_with_config(addr, config, || Ok(old_handler)));
+        wait_for(addr, "old");
+
+        let mut kept_alive = TcpStream::connect(addr).unwrap();
+        kept_alive
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        kept_alive
+            .write_all(b"GET / HTTP/1.1\r\nHost: localhost\r\n\r\n")
+            .unwrap();
+        let mut buf = [0; 12];
+        kept_alive.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"HTTP/1.1 200");
+
+        // The idle connection, and its pending read timeout, would otherwise hold the server open
+        // until the shutdown timeout.
+        let start = Instant::now();
+        shutdown.drain();
+        server.join().unwrap();
+        assert!(start.elapsed() < Duration::from_secs(2));
+
+        // The connection was closed, rather than timing out.
+        let mut rest = String::new();
+        kept_alive.read_to_string(&mut rest).unwrap();
+        assert!(rest.ends_with("old"));
+    }
+
+    #[test]
+    fn closes_connection_stalled_mid_body() {
+        let addr = free_addr();
+        let shutdown = Shutdown::new();
+        let config = config(&shutdown).with_read_timeout(Duration::from_millis(200));
+        let server = thread::spawn(move || start_with_config(addr, config, || Ok(body_handler)));
+        wait_for(addr, "0 bytes");
+
+        let mut stream = TcpStream::connect(addr).unwrap();
+        stream.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+        stream
+            .write_all(b"POST / HTTP/1.1\r\nHost: localhost\r\nContent-Length: 10\r\n\r\nabc")
+            .unwrap();
+
+        let start = Instant::now();
+        let mut response = Vec::new();
+        stream.read_to_end(&mut response).unwrap();
+        let elapsed = start.elapsed();
+
+        assert!(elapsed >= Duration::from_millis(200));
+        assert!(elapsed < Duration::from_secs(5));
+
+        shutdown.drain();
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn rejects_obsolete_line_folding() {
+        let addr = free_addr();
+        let shutdown = Shutdown::new();
+        let config = config(&shutdown);
+        let server = thread::spawn(move || start_with_config(addr, config, || Ok(old_handler)));
+        wait_for(addr, "old");
+
+        // A folded header may be read differently by a proxy in front of the server, so the
+        // request is rejected rather than unfolded, as RFC 7230 allows.
+        let requests: [&[u8]; 2] = [
+            b"GET / HTTP/1.1\r\nHost: localhost\r\nX-Folded: a\r\n b\r\n\r\n",
+            b"POST / HTTP/1.1\r\nHost: localhost\r\nTransfer-Encoding:\r\n\tchunked\r\n\r\n",
+        ];
+        for request in &requests {
+            let mut stream = TcpStream::connect(addr).unwrap();
+            stream.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+            stream.write_all(request).unwrap();
+
+            let mut response = String::new();
+            stream.read_to_string(&mut response).unwrap();
+            assert!(response.starts_with("HTTP/1.1 400"), "{}", response);
+            assert!(!response.ends_with("old"));
+        }
+
+        shutdown.drain();
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn rejects_conflicting_content_lengths() {
+        let addr = free_addr();
+        let shutdown = Shutdown::new();
+        let config = config(&shutdown);
+        let server = thread::spawn(move || start_with_config(addr, config, || Ok(body_handler)));
+        wait_for(addr, "0 bytes");
+
+        let post = |headers: &str| {
+            let mut stream = TcpStream::connect(addr).unwrap();
+            stream.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+            let request = format!(
+                "POST / HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n{}\r\nabcdef",
+                headers
+            );
+            stream.write_all(request.as_bytes()).unwrap();
+
+            let mut response = String::new();
+            stream.read_to_string(&mut response).unwrap();
+            response
+        };
+
+        // A proxy in front of the server may frame the body by either value, so the request is
+        // rejected rather than guessing.
+        for headers in &[
+            "Content-Length: 3\r\nContent-Length: 6\r\n",
+            "Content-Length: 3, 6\r\n",
+        ] {
+            let response = post(headers);
+            assert!(response.starts_with("HTTP/1.1 400"), "{}", response);
+        }
+
+        // Repeating the same value is unambiguous, and is read as a single header.
+        let response = post("Content-Length: 6\r\nContent-Length: 6\r\n");
+        assert!(response.starts_with("HTTP/1.1 200"), "{}", response);
+        assert!(response.ends_with("6 bytes"), "{}", response);
+
+        shutdown.drain();
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn serves_requests_asking_to_upgrade_to_unsupported_protocols() {
+        let addr = free_addr();
+        let shutdown = Shutdown::new();
+        let config = config(&shutdown);
+        let server = thread::spawn(move || start_with_config(addr, config, || Ok(body_handler)));
+        wait_for(addr, "0 bytes");
+
+        let mut stream = TcpStream::connect(addr).unwrap();
+        stream.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+        stream
+            .write_all(
+                b"POST / HTTP/1.1\r\nHost: localhost\r\nConnection: Upgrade\r\n\
+                  Upgrade: unknown-proto\r\nContent-Length: 3\r\n\r\nabc\
+                  POST / HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\
+                  Content-Length: 6\r\n\r\nabcdef",
+            )
+            .unwrap();
+
+        // The upgrade is ignored, so both requests are served normally over HTTP/1.1 on the same
+        // connection, rather than the connection being switched or left hanging.
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        assert_eq!(response.matches("HTTP/1.1 200").count(), 2, "{}", response);
+        assert!(!response.contains("101 Switching Protocols"), "{}", response);
+        assert!(response.contains("3 bytes"), "{}", response);
+        assert!(response.ends_with("6 bytes"), "{}", response);
+
+        shutdown.drain();
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn refuses_connections_beyond_max_connections_per_worker() {
+        let addr = free_addr();
+        let shutdown = Shutdown::new();
+        let metrics = ConnectionMetrics::new();
+        let config = config(&shutdown)
+            .with_max_connections_per_worker(2)
+            .with_connection_metrics(metrics.clone());
+        let server = thread::spawn(move || start_with_config(addr, config, || Ok(old_handler)));
+        wait_for(addr, "old");
+
+        let held: Vec<_> = (0..2).map(|_| TcpStream::connect(addr).unwrap()).collect();
+        thread::sleep(Duration::from_millis(100));
+        assert_eq!(metrics.refused(), 0);
+
+        for _ in 0..3 {
+            let mut stream = TcpStream::connect(addr).unwrap();
+            stream.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+            let _ = stream.write_all(b"GET / HTTP/1.1\r\nHost: localhost\r\n\r\n");
+
+            let mut response = Vec::new();
+            let _ = stream.read_to_end(&mut response);
+            assert!(response.is_empty());
+        }
+        assert_eq!(metrics.refused(), 3);
+
+        drop(held);
+        thread::sleep(Duration::from_millis(100));
+        assert_eq!(get(addr).unwrap(), "old");
+
+        shutdown.drain();
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn refuses_connections_beyond_max_accept_rate() {
+        let addr = free_addr();
+        let shutdown = Shutdown::new();
+        let metrics = ConnectionMetrics::new();
+        let config = config(&shutdown)
+            .with_max_accept_rate(3)
+            .with_connection_metrics(metrics.clone());
+        let server = thread::spawn(move || start_with_config(addr, config, || Ok(old_handler)));
+        wait_for(addr, "old");
+
+        // Allow the allowance used while waiting for the server to recover.
+        thread::sleep(Duration::from_millis(1100));
+        let refused_before = metrics.rate_limited();
+
+        let responses: Vec<_> = (0..5).map(|_| get(addr).is_ok()).collect();
+        assert_eq!(responses, vec![true, true, true, false, false]);
+        assert_eq!(metrics.rate_limited(), refused_before + 2);
+
+        thread::sleep(Duration::from_millis(1100));
+        assert_eq!(get(addr).unwrap(), "old");
+
+        shutdown.drain();
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn reads_client_address_from_proxy_protocol() {
+        fn addr_handler(state: State) -> (State, Response) {
+            let body = client_addr(&state).unwrap().to_string().into_bytes();
+            let res = create_response(&state, StatusCode::Ok, Some((body, mime::TEXT_PLAIN)));
+            (state, res)
+        }
+
+        let addr = free_addr();
+        let shutdown = Shutdown::new();
+        let config = config(&shutdown).with_proxy_protocol(true);
+        let server = thread::spawn(move || start_with_config(addr, config, || Ok(addr_handler)));
+
+        let request = |prefix: &[u8]| {
+            let start = Instant::now();
+            loop {
+                if let Ok(mut stream) = TcpStream::connect(addr) {
+                    stream.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+                    stream.write_all(prefix).unwrap();
+                    stream
+                        .write_all(b"GET / HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+                        .unwrap();
+
+                    let mut response = String::new();
+                    let _ = stream.read_to_string(&mut response);
+                    return response;
+                }
+                assert!(start.elapsed() < Duration::from_secs(5), "server did not start");
+                thread::sleep(Duration::from_millis(10));
+            }
+        };
+
+        let response = request(b"PROXY TCP4 192.0.2.1 192.0.2.2 56324 80\r\n");
+        assert!(response.starts_with("HTTP/1.1 200"));
+        assert!(response.ends_with("\r\n\r\n192.0.2.1:56324"));
+
+        // Connections without the header are closed without a response.
+        assert_eq!(request(b""), "");
+
+        shutdown.drain();
+        server.join().unwrap();
+    }
 }
@@ -1,5 +1,9 @@
+use std::cell::Cell;
+use std::io;
 use std::net::{SocketAddr, TcpListener, ToSocketAddrs};
+use std::rc::Rc;
 use std::thread;
+use std::time::Duration;
 use std::sync::{Arc, Mutex};
 
 use hyper::server::Http;
@@ -8,8 +12,14 @@ use tokio_core::net::TcpStream;
 use tokio_core::reactor::Core;
 use futures::{future, task, Async, Future, Poll, Stream};
 
+use config::ServerConfig;
 use handler::NewHandler;
+use os::{accept_rate_limiter, at_capacity, client_connection, rate_limited};
+use os::accept_backoff::AcceptBackoff;
+use os::accept_rate::AcceptRateLimiter;
+use os::read_timeout::ReadTimeout;
 use service::GothamService;
+use shutdown::{self, close_when_draining, DrainBehavior, Shutdown};
 
 use crossbeam::sync::SegQueue;
 
@@ -45,21 +55,53 @@ impl Stream for SocketQueue {
 ///
 /// An additional thread is used on Windows to accept connections.
 pub fn start_with_num_threads<NH, A>(addr: A, threads: usize, new_handler: NH)
+where
+    NH: NewHandler + 'static,
+    A: ToSocketAddrs,
+{
+    start_with_config(
+        addr,
+        ServerConfig::default().with_num_threads(threads),
+        new_handler,
+    )
+}
+
+/// Starts a Gotham application, using the given `ServerConfig`.
+///
+/// Returns once the server has drained, after being told to do so via the `Shutdown` handle in
+/// `config`.
+///
+/// ## Windows
+///
+/// An additional thread is used on Windows to accept connections.
+pub fn start_with_config<NH, A>(addr: A, config: ServerConfig, new_handler: NH)
 where
     NH: NewHandler + 'static,
     A: ToSocketAddrs,
 {
     let (listener, addr) = ::tcp_listener(addr);
 
-    let protocol = Arc::new(Http::new());
+    let threads = config.num_threads();
+    let protocol = Arc::new(config.protocol());
+    let limiter = accept_rate_limiter(&config).map(Arc::new);
+    let config = Arc::new(config);
     let new_handler = Arc::new(new_handler);
 
     let queue = SocketQueue::new();
 
-    {
+    // When new requests are answered with `503 Service Unavailable` while draining, the listener
+    // is only closed once the workers have drained.
+    let stop_listening = match config.drain_behavior() {
+        DrainBehavior::CloseListener => config.shutdown().clone(),
+        DrainBehavior::RespondUnavailable => Shutdown::new(),
+    };
+
+    let listener = {
         let queue = queue.clone();
-        thread::spawn(move || listen(listener, addr, queue));
-    }
+        let stop_listening = stop_listening.clone();
+        let backoff = config.accept_backoff();
+        thread::spawn(move || listen(listener, addr, backoff, queue, stop_listening))
+    };
 
     info!(
         target: "gotham::start",
@@ -68,17 +110,35 @@ where
         threads,
     );
 
-    for _ in 0..threads - 1 {
-        let protocol = protocol.clone();
-        let queue = queue.clone();
-        let new_handler = new_handler.clone();
-        thread::spawn(move || serve(queue, &protocol, new_handler));
+    let workers: Vec<_> = (0..threads - 1)
+        .map(|_| {
+            let protocol = protocol.clone();
+            let queue = queue.clone();
+            let config = config.clone();
+            let limiter = limiter.clone();
+            let new_handler = new_handler.clone();
+            thread::spawn(move || serve(queue, &protocol, config, limiter, new_handler))
+        })
+        .collect();
+
+    serve(queue, &protocol, config, limiter, new_handler);
+
+    for worker in workers {
+        worker.join().expect("worker thread panicked");
     }
+    stop_listening.drain();
+    listener.join().expect("listener thread panicked");
 
-    serve(queue, &protocol, new_handler);
+    info!(target: "gotham::start", " Gotham on http://{} has shut down", addr);
 }
 
-fn listen(listener: TcpListener, addr: SocketAddr, queue: SocketQueue) {
+fn listen(
+    listener: TcpListener,
+    addr: SocketAddr,
+    backoff: Duration,
+    queue: SocketQueue,
+    shutdown: Shutdown,
+) {
     let mut core = Core::new().expect("unable to spawn tokio reactor");
     let handle = core.handle();
 
@@ -87,7 +147,8 @@ fn listen(listener: TcpListener, addr: SocketAddr, queue: SocketQueue) {
 
     let mut n: usize = 0;
 
-    core.run(listener.incoming().for_each(|conn| {
+    let incoming = AcceptBackoff::new(listener.incoming(), backoff, &handle);
+    let accept = incoming.for_each(|conn| {
         queue.queue.push(conn);
         let tasks = queue
             .notify
@@ -97,33 +158,107 @@ fn listen(listener: TcpListener, addr: SocketAddr, queue: SocketQueue) {
         n = (n + 1) % tasks.len();
         tasks[n].notify();
         Ok(())
-    })).expect("unable to run reactor over listener");
+    });
+
+    // The listener is closed as soon as draining begins, as `accept` is dropped.
+    core.run(
+        accept
+            .select(shutdown::draining(shutdown, &handle))
+            .map(|_| ())
+            .map_err(|(e, _)| e),
+    ).expect("unable to run reactor over listener");
 }
 
-fn serve<NH>(queue: SocketQueue, protocol: &Http, new_handler: Arc<NH>)
-where
+fn serve<NH>(
+    queue: SocketQueue,
+    protocol: &Http,
+    config: Arc<ServerConfig>,
+    limiter: Option<Arc<AcceptRateLimiter>>,
+    new_handler: Arc<NH>,
+) where
     NH: NewHandler + 'static,
 {
     let mut core = Core::new().expect("unable to spawn tokio reactor");
+    if let Some(init) = config.worker_init() {
+        init.run(&mut core).expect("worker initialization failed");
+    }
     let handle = core.handle();
-    let gotham_service = GothamService::new(new_handler, handle.clone());
+    let gotham_service = Rc::new(GothamService::new(new_handler, handle.clone(), config.clone()));
     let tasks_m = queue.notify.clone();
+    let active = Rc::new(Cell::new(0));
+    let drain_signal = shutdown::drain_signal(config.shutdown().clone(), &handle);
+
+    let work = {
+        let active = active.clone();
+        let handle = handle.clone();
+        let read_timeout = config.read_timeout();
+        let config = config.clone();
 
-    core.run(
         future::lazy(move || {
             let mut tasks = tasks_m
                 .lock()
                 .expect("mutex poisoned, futures::task::Task::notify panicked?");
             tasks.push(task::current());
             future::ok(())
-        }).and_then(|_| {
-            queue.for_each(|(socket, addr)| {
-                let service = gotham_service.connect(addr);
-                let f = protocol.serve_connection(socket, service).then(|_| Ok(()));
-
-                handle.spawn(f);
-                Ok(())
-            })
-        }),
-    ).expect("unable to run reactor for work stealing");
+        }).and_then(move |_| {
+            queue
+                .for_each(move |(socket, addr)| {
+                    if at_capacity(&config, active.get())
+                        || rate_limited(&config, limiter.as_deref(), &addr)
+                    {
+                        return Ok(());
+                    }
+
+                    active.set(active.get() + 1);
+                    let active = active.clone();
+                    let gotham_service = gotham_service.clone();
+                    let protocol = protocol.clone();
+                    let signal = if config.shutdown().is_draining() {
+                        None
+                    } else {
+                        Some(drain_signal.clone())
+                    };
+                    let socket = ReadTimeout::new(socket, read_timeout, &handle);
+                    let f = client_connection(socket, addr, &config)
+                        .map_err(|_| ())
+                        .and_then(move |(socket, client_addr)| {
+                            let service = gotham_service.connect(client_addr);
+                            let connection = protocol.serve_connection(socket, service);
+                            close_when_draining(connection, signal).map_err(|_| ())
+                        })
+                        .then(move |_| {
+                            active.set(active.get() - 1);
+                            Ok(())
+                        });
+
+                    handle.spawn(f);
+                    Ok(())
+                })
+                .map_err(|_| io::Error::new(io::ErrorKind::Other, "socket queue failed"))
+        })
+    };
+
+    let draining = shutdown::draining(config.shutdown().clone(), &handle);
+
+    match config.drain_behavior() {
+        DrainBehavior::CloseListener => {
+            core.run(work.select(draining).map(|_| ()).map_err(|(e, _)| e))
+                .expect("unable to run reactor for work stealing");
+
+            core.run(shutdown::drained(active, config.shutdown_timeout(), &handle))
+                .expect("unable to run reactor while draining");
+        }
+        DrainBehavior::RespondUnavailable => {
+            // Connections continue to be taken from the queue until draining completes, and new
+            // requests are answered with `503 Service Unavailable` by the service.
+            let timeout = config.shutdown_timeout();
+            let drained = {
+                let handle = handle.clone();
+                draining.and_then(move |_| shutdown::drained(active, timeout, &handle))
+            };
+
+            core.run(work.select(drained).map(|_| ()).map_err(|(e, _)| e))
+                .expect("unable to run reactor while draining");
+        }
+    }
 }
@@ -3,11 +3,13 @@ use std::panic::RefUnwindSafe;
 
 use hyper::Method;
 
+use handler::Handler;
 use router::route::dispatch::{PipelineHandleChain, PipelineSet};
 use router::route::matcher::MethodOnlyRouteMatcher;
 use router::request::path::NoopPathExtractor;
 use router::request::query_string::NoopQueryStringExtractor;
-use router::builder::{DelegateRouteBuilder, RouterBuilder, ScopeBuilder, SingleRouteBuilder};
+use router::builder::{DefineSingleRoute, DelegateRouteBuilder, RouterBuilder, ScopeBuilder,
+                      SingleRouteBuilder};
 use router::tree::node::{NodeBuilder, SegmentType};
 use router::tree::regex::ConstrainedSegmentRegex;
 
@@ -244,6 +246,9 @@ where
     ///
     /// * `"/hello/world"` - a static path, matching only a request for exactly `"/hello/world"`
     /// * `"/hello/:name"` - a dynamic path, matching requests for `"/hello/any_value_here"`
+    /// * `"/hello/:name?"` - a dynamic path ending with an optional segment, matching requests for
+    ///   both `"/hello"` and `"/hello/any_value_here"`. Optional segments must end the path, and
+    ///   are extracted as `Option` fields, which are `None` when the segment is omitted.
     ///
     /// # Examples
     ///
@@ -285,6 +290,56 @@ where
         }
     }
 
+    /// Creates a route for each `(method, path, handler)` in `routes`, as `request` followed by
+    /// `to` does, for applications which define many similar routes from a table.
+    ///
+    /// The handlers must all be of the same type, and so functions are given as `fn` pointers.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # extern crate gotham;
+    /// # extern crate hyper;
+    /// # use hyper::{Method, Response};
+    /// # use gotham::state::State;
+    /// # use gotham::router::Router;
+    /// # use gotham::router::builder::*;
+    /// # mod products {
+    /// #   use super::*;
+    /// #   pub fn list(_: State) -> (State, Response) {
+    /// #       unreachable!()
+    /// #   }
+    /// #   pub fn create(_: State) -> (State, Response) {
+    /// #       unreachable!()
+    /// #   }
+    /// #   pub fn show(_: State) -> (State, Response) {
+    /// #       unreachable!()
+    /// #   }
+    /// # }
+    /// #
+    /// # fn router() -> Router {
+    /// let routes: Vec<(Method, &str, fn(State) -> (State, Response))> = vec![
+    ///     (Method::Get, "/products", products::list),
+    ///     (Method::Post, "/products", products::create),
+    ///     (Method::Get, "/products/:id", products::show),
+    /// ];
+    ///
+    /// build_simple_router(|route| {
+    ///     route.routes(routes);
+    /// })
+    /// # }
+    /// # fn main() { router(); }
+    /// ```
+    fn routes<'p, I, H>(&mut self, routes: I)
+    where
+        I: IntoIterator<Item = (Method, &'p str, H)>,
+        H: Handler + RefUnwindSafe + Copy + Send + Sync + 'static,
+    {
+        for (method, path, handler) in routes {
+            self.request(vec![method], path).to(handler);
+        }
+    }
+
     /// Begins defining a new scope, based on a given `path` prefix.
     ///
     /// # Examples
@@ -379,10 +434,21 @@ fn descend<'n>(node_builder: &'n mut NodeBuilder, path: &str) -> &'n mut NodeBui
     if path.is_empty() {
         node_builder
     } else {
+        let mut segments = path.split("/").skip_while(|segment| !is_optional(segment));
+        if segments.any(|segment| !is_optional(segment)) {
+            panic!("optional segments must end the path, but found `{}`", path);
+        }
+
         build_subtree(node_builder, path.split("/"))
     }
 }
 
+/// Determines whether `segment` is a dynamic segment which the request path may omit, written as
+/// `:name?`. A constrained segment is never optional, as `?` is part of its regex.
+fn is_optional(segment: &str) -> bool {
+    segment.starts_with(':') && segment.ends_with('?') && !segment[1..].contains(':')
+}
+
 fn build_subtree<'n, 's, I>(node: &'n mut NodeBuilder, mut i: I) -> &'n mut NodeBuilder
 where
     I: Iterator<Item = &'s str>,
@@ -391,7 +457,9 @@ where
         Some(segment) => {
             trace!("[descending into {}]", segment);
 
+            let optional = is_optional(segment);
             let (segment, segment_type) = match segment.chars().next() {
+                Some(':') if optional => (&segment[1..segment.len() - 1], SegmentType::Dynamic),
                 Some(':') => {
                     let segment = &segment[1..];
                     match segment.find(":") {
@@ -414,6 +482,9 @@ where
             }
 
             let child = node.borrow_mut_child(segment, segment_type).unwrap();
+            if optional {
+                child.make_optional();
+            }
             build_subtree(child, i)
         }
         None => {
@@ -24,7 +24,7 @@ use router::tree::node::NodeBuilder;
 
 pub use self::single::DefineSingleRoute;
 pub use self::draw::{DefaultSingleRouteBuilder, DrawRoutes};
-pub use self::replace::{ReplacePathExtractor, ReplaceQueryStringExtractor};
+pub use self::replace::{ExtendRouteMatcher, ReplacePathExtractor, ReplaceQueryStringExtractor};
 
 /// Builds a `Router` using the provided closure. Routes are defined using the `RouterBuilder`
 /// value passed to the closure, and the `Router` is constructed before returning.
@@ -72,10 +72,13 @@ where
             pipeline_chain,
             pipelines,
             response_finalizer_builder: ResponseFinalizerBuilder::new(),
+            route_conflicts: RouteConflicts::default(),
         };
 
         f(&mut builder);
 
+        let route_conflicts = builder.route_conflicts;
+        builder.node_builder.resolve_conflicts("", route_conflicts);
         builder.response_finalizer_builder.finalize()
     };
 
@@ -125,6 +128,26 @@ where
     pipeline_chain: C,
     pipelines: PipelineSet<P>,
     response_finalizer_builder: ResponseFinalizerBuilder,
+    route_conflicts: RouteConflicts,
+}
+
+/// Determines what happens when a `Router` is built with conflicting routes, configured via
+/// `RouterBuilder::on_route_conflict`.
+///
+/// Two routes conflict when they share a path, both match requests by method alone, and accept a
+/// method in common, e.g. when the same route is defined twice. Requests are given to the first
+/// matching route, and so the later route would never be reached for that method. Routes which
+/// add further conditions, such as `add_route_matcher`, are not considered to conflict.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RouteConflicts {
+    /// Building the `Router` panics, reporting the conflicting methods and path. This is the
+    /// default.
+    #[default]
+    Panic,
+
+    /// The later route is moved ahead of the route it conflicts with, so that it handles the
+    /// requests they have in common, and a warning is logged.
+    LastWins,
 }
 
 impl<'a, C, P> RouterBuilder<'a, C, P>
@@ -177,6 +200,37 @@ where
         self.response_finalizer_builder
             .add(status_code, Box::new(extender))
     }
+
+    /// Sets what happens when conflicting routes are defined anywhere in the `Router`. By
+    /// default, building the `Router` panics.
+    ///
+    /// ```rust
+    /// # extern crate gotham;
+    /// # extern crate hyper;
+    /// # use hyper::Response;
+    /// # use gotham::state::State;
+    /// # use gotham::router::Router;
+    /// # use gotham::router::builder::*;
+    /// # fn old_handler(_: State) -> (State, Response) {
+    /// #   unreachable!()
+    /// # }
+    /// # fn new_handler(_: State) -> (State, Response) {
+    /// #   unreachable!()
+    /// # }
+    /// #
+    /// fn router() -> Router {
+    ///     build_simple_router(|route| {
+    ///         route.on_route_conflict(RouteConflicts::LastWins);
+    ///
+    ///         route.get("/request/path").to(old_handler);
+    ///         route.get("/request/path").to(new_handler);
+    ///     })
+    /// }
+    /// # fn main() { router(); }
+    /// ```
+    pub fn on_route_conflict(&mut self, route_conflicts: RouteConflicts) {
+        self.route_conflicts = route_conflicts;
+    }
 }
 
 /// A scoped builder, which is created by `DrawRoutes::scope` and passed to the provided closure.
@@ -276,16 +330,23 @@ mod tests {
 
     use hyper::{Method, Request, Response, StatusCode, Uri};
     use hyper::server::Service;
+    use hyper::header::{Accept, Host};
     use futures::{Future, Stream};
+    use mime;
     use tokio_core::reactor::Core;
 
     use pipeline::new_pipeline;
     use middleware::session::NewSessionMiddleware;
     use state::{FromState, State, StateData};
+    use config::ServerConfig;
     use service::GothamService;
     use router::route::dispatch::{finalize_pipeline_set, new_pipeline_set};
     use router::response::extender::StaticResponseExtender;
+    use router::route::matcher::accept::AcceptHeaderRouteMatcher;
+    use router::request::path::FromRequestPath;
     use router::tree::SegmentMapping;
+    use test::TestServer;
+    use http::response::create_response;
     use http::FormUrlDecoded;
     use http::request::query_string;
 
@@ -457,9 +518,14 @@ mod tests {
         });
 
         let mut core = Core::new().unwrap();
-        let new_service = GothamService::new(Arc::new(router), core.handle());
+        let new_service = GothamService::new(
+            Arc::new(router),
+            core.handle(),
+            Arc::new(ServerConfig::default()),
+        );
 
-        let mut call = move |req| {
+        let mut call = move |mut req: Request| {
+            req.headers_mut().set(Host::new("localhost", None));
             let service = new_service.connect("127.0.0.1:10000".parse().unwrap());
             core.run(service.call(req)).unwrap()
         };
@@ -515,4 +581,183 @@ mod tests {
         let response_bytes = response.body().concat2().wait().unwrap().to_vec();
         assert_eq!(&String::from_utf8(response_bytes).unwrap(), "16 + 71 = 87");
     }
+
+    fn accepted(state: State) -> (State, Response) {
+        (state, Response::new().with_status(StatusCode::Accepted))
+    }
+
+    fn created(state: State) -> (State, Response) {
+        (state, Response::new().with_status(StatusCode::Created))
+    }
+
+    #[test]
+    #[should_panic(expected = "conflicting routes for GET `/products/:id`")]
+    fn panics_on_conflicting_routes() {
+        build_simple_router(|route| {
+            route.scope("/products", |route| {
+                route.get("/:id").to(accepted);
+                route.post("/:id").to(accepted);
+            });
+            route.get_or_head("/products/:id").to(created);
+        });
+    }
+
+    #[test]
+    fn later_route_wins_when_configured() {
+        let router = build_simple_router(|route| {
+            route.on_route_conflict(RouteConflicts::LastWins);
+            route.get_or_head("/products").to(accepted);
+            route.get("/products").to(created);
+        });
+
+        let test_server = TestServer::new(router).unwrap();
+        let response = test_server
+            .client()
+            .get("http://localhost/products")
+            .perform()
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::Created);
+
+        let response = test_server
+            .client()
+            .build_request(Method::Head, "http://localhost/products")
+            .perform()
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::Accepted);
+    }
+
+    #[test]
+    fn routes_with_further_conditions_do_not_conflict() {
+        let router = build_simple_router(|route| {
+            route
+                .get("/products")
+                .add_route_matcher(AcceptHeaderRouteMatcher::new(vec![mime::APPLICATION_JSON]))
+                .to(accepted);
+            route.get("/products").to(created);
+        });
+
+        let test_server = TestServer::new(router).unwrap();
+        let response = test_server
+            .client()
+            .get("http://localhost/products")
+            .with_header(Accept::text())
+            .perform()
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::Created);
+    }
+
+    type RouteData = (Method, &'static str, fn(State) -> (State, Response));
+
+    #[test]
+    fn registers_routes_from_data() {
+        let routes: Vec<RouteData> = vec![
+            (Method::Get, "/products", accepted),
+            (Method::Post, "/products", created),
+            (Method::Delete, "/products/:id", accepted),
+        ];
+        let router = build_simple_router(|route| route.routes(routes));
+
+        let test_server = TestServer::new(router).unwrap();
+        let response = test_server
+            .client()
+            .get("http://localhost/products")
+            .perform()
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::Accepted);
+
+        let response = test_server
+            .client()
+            .post("http://localhost/products", "", mime::TEXT_PLAIN)
+            .perform()
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::Created);
+
+        let response = test_server
+            .client()
+            .build_request(Method::Delete, "http://localhost/products/1")
+            .perform()
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::Accepted);
+
+        let response = test_server
+            .client()
+            .build_request(Method::Put, "http://localhost/products")
+            .perform()
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::MethodNotAllowed);
+    }
+
+    struct PostPath {
+        id: u64,
+        slug: Option<String>,
+    }
+
+    impl StateData for PostPath {}
+
+    impl StaticResponseExtender for PostPath {
+        fn extend(_: &mut State, _: &mut Response) {}
+    }
+
+    impl PathExtractor for PostPath {
+        fn extract(state: &mut State, segment_mapping: SegmentMapping) -> Result<(), String> {
+            let segments = |key| {
+                let segments = segment_mapping.get(key).cloned();
+                segments.unwrap_or_default()
+            };
+            let id = u64::from_request_path(&segments("id")).map_err(|e| e.to_string())?;
+            let slug = Option::from_request_path(&segments("slug")).map_err(|e| e.to_string())?;
+            state.put(PostPath { id, slug });
+            Ok(())
+        }
+    }
+
+    fn post(state: State) -> (State, Response) {
+        let body = {
+            let path = PostPath::borrow_from(&state);
+            match path.slug {
+                Some(ref slug) => format!("post {} titled {}", path.id, slug),
+                None => format!("post {}", path.id),
+            }
+        };
+        let body = Some((body.into_bytes(), mime::TEXT_PLAIN));
+        let res = create_response(&state, StatusCode::Ok, body);
+        (state, res)
+    }
+
+    #[test]
+    fn matches_paths_omitting_optional_segments() {
+        let router = build_simple_router(|route| {
+            route
+                .get("/posts/:id/:slug?")
+                .with_path_extractor::<PostPath>()
+                .to(post);
+        });
+
+        let test_server = TestServer::new(router).unwrap();
+        let get = |path: &str| {
+            let response = test_server
+                .client()
+                .get(&format!("http://localhost{}", path))
+                .perform()
+                .unwrap();
+            let status = response.status();
+            (status, response.read_utf8_body().unwrap())
+        };
+
+        assert_eq!(get("/posts/42"), (StatusCode::Ok, "post 42".to_owned()));
+        assert_eq!(
+            get("/posts/42/my-title"),
+            (StatusCode::Ok, "post 42 titled my-title".to_owned())
+        );
+        assert_eq!(get("/posts/42/my-title/more").0, StatusCode::NotFound);
+        assert_eq!(get("/posts").0, StatusCode::NotFound);
+    }
+
+    #[test]
+    #[should_panic(expected = "optional segments must end the path, but found `posts/:id?/edit`")]
+    fn panics_on_optional_segment_before_end_of_path() {
+        build_simple_router(|route| {
+            route.get("/posts/:id?/edit").to(accepted);
+        });
+    }
 }
@@ -3,6 +3,7 @@ use std::panic::RefUnwindSafe;
 use router::request::path::PathExtractor;
 use router::request::query_string::QueryStringExtractor;
 use router::route::matcher::RouteMatcher;
+use router::route::matcher::and::AndRouteMatcher;
 use router::route::dispatch::PipelineHandleChain;
 use router::builder::SingleRouteBuilder;
 use router::builder::single::DefineSingleRoute;
@@ -70,3 +71,41 @@ where
         self.coerce()
     }
 }
+
+/// Describes the operation of adding a `RouteMatcher` to a route, which must match in addition to
+/// the existing `RouteMatcher`. This trait exists to remove type clutter from the documentation
+/// of `SingleRouteBuilder::add_route_matcher`.
+pub trait ExtendRouteMatcher<NRM>
+where
+    NRM: RouteMatcher + Send + Sync + 'static,
+{
+    /// The type returned when adding the `RouteMatcher`.
+    type Output: DefineSingleRoute;
+
+    #[doc(hidden)]
+    /// Combines the `RouteMatcher` in `self` with `matcher`.
+    fn extend_route_matcher(self, matcher: NRM) -> Self::Output;
+}
+
+impl<'a, M, C, P, PE, QSE, NRM> ExtendRouteMatcher<NRM>
+    for SingleRouteBuilder<'a, M, C, P, PE, QSE>
+where
+    M: RouteMatcher + Send + Sync + 'static,
+    NRM: RouteMatcher + Send + Sync + 'static,
+    C: PipelineHandleChain<P> + Send + Sync + 'static,
+    P: RefUnwindSafe + Send + Sync + 'static,
+    PE: PathExtractor + Send + Sync + 'static,
+    QSE: QueryStringExtractor + Send + Sync + 'static,
+{
+    type Output = SingleRouteBuilder<'a, AndRouteMatcher<M, NRM>, C, P, PE, QSE>;
+
+    fn extend_route_matcher(self, matcher: NRM) -> Self::Output {
+        SingleRouteBuilder {
+            matcher: AndRouteMatcher::new(self.matcher, matcher),
+            node_builder: self.node_builder,
+            pipeline_chain: self.pipeline_chain,
+            pipelines: self.pipelines,
+            phantom: self.phantom,
+        }
+    }
+}
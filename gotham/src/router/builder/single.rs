@@ -3,7 +3,8 @@ use std::panic::RefUnwindSafe;
 use router::request::path::PathExtractor;
 use router::request::query_string::QueryStringExtractor;
 use router::builder::SingleRouteBuilder;
-use router::builder::replace::{ReplacePathExtractor, ReplaceQueryStringExtractor};
+use router::builder::replace::{ExtendRouteMatcher, ReplacePathExtractor,
+                               ReplaceQueryStringExtractor};
 use router::route::{Delegation, Extractors, RouteImpl};
 use router::route::matcher::RouteMatcher;
 use router::route::dispatch::{DispatcherImpl, PipelineHandleChain};
@@ -253,6 +254,39 @@ pub trait DefineSingleRoute {
         NQSE: QueryStringExtractor + Send + Sync + 'static,
         Self: ReplaceQueryStringExtractor<NQSE>,
         Self::Output: DefineSingleRoute;
+
+    /// Adds a `RouteMatcher` to the current route, which must match in addition to the request
+    /// method. When it does not match, the error status it returns is used for the response.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # extern crate gotham;
+    /// # extern crate hyper;
+    /// # use hyper::Response;
+    /// # use gotham::state::State;
+    /// # use gotham::router::Router;
+    /// # use gotham::router::builder::*;
+    /// # use gotham::router::route::matcher::upgrade::UpgradeRequiredRouteMatcher;
+    /// fn my_handler(_: State) -> (State, Response) {
+    ///     // Handler implementation elided.
+    /// #   unimplemented!()
+    /// }
+    /// #
+    /// # fn router() -> Router {
+    /// build_simple_router(|route| {
+    ///     route.get("/account")
+    ///          .add_route_matcher(UpgradeRequiredRouteMatcher::tls())
+    ///          .to(my_handler);
+    /// })
+    /// # }
+    /// # fn main() { router(); }
+    /// ```
+    fn add_route_matcher<NRM>(self, matcher: NRM) -> <Self as ExtendRouteMatcher<NRM>>::Output
+    where
+        NRM: RouteMatcher + Send + Sync + 'static,
+        Self: ExtendRouteMatcher<NRM>,
+        Self::Output: DefineSingleRoute;
 }
 
 impl<'a, M, C, P, PE, QSE> DefineSingleRoute for SingleRouteBuilder<'a, M, C, P, PE, QSE>
@@ -299,4 +333,11 @@ where
     {
         self.replace_query_string_extractor()
     }
+
+    fn add_route_matcher<NRM>(self, matcher: NRM) -> <Self as ExtendRouteMatcher<NRM>>::Output
+    where
+        NRM: RouteMatcher + Send + Sync + 'static,
+    {
+        self.extend_route_matcher(matcher)
+    }
 }
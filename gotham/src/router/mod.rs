@@ -5,30 +5,36 @@ pub mod tree;
 pub mod route;
 pub mod request;
 pub mod response;
+pub mod strategy;
 
 use std::io;
 use std::sync::Arc;
 
 use futures::{future, Future};
-use hyper::{Response, StatusCode};
+use hyper::{Method, Response, StatusCode, Uri};
+use hyper::header::Allow;
 
 use handler::{Handler, HandlerFuture, IntoResponse, NewHandler};
 use http::request::path::RequestPathSegments;
 use http::response::create_response;
 use router::response::finalizer::ResponseFinalizer;
 use router::route::{Delegation, Route};
+use router::strategy::{OptionsAsterisk, RouteMatch, RouteProblem, RouterStrategy};
 use router::tree::{SegmentMapping, Tree};
-use state::{request_id, State};
+use state::{request_id, FromState, State};
 
 struct RouterData {
-    tree: Tree,
+    strategy: Box<dyn RouterStrategy>,
     response_finalizer: ResponseFinalizer,
 }
 
 impl RouterData {
-    pub fn new(tree: Tree, response_finalizer: ResponseFinalizer) -> RouterData {
+    pub fn new(
+        strategy: Box<dyn RouterStrategy>,
+        response_finalizer: ResponseFinalizer,
+    ) -> RouterData {
         RouterData {
-            tree,
+            strategy,
             response_finalizer,
         }
     }
@@ -87,35 +93,61 @@ impl Handler for Router {
     fn handle(self, mut state: State) -> Box<HandlerFuture> {
         trace!("[{}] starting", request_id(&state));
 
+        if is_options_asterisk(&state) {
+            trace!("[{}] responding with capabilities", request_id(&state));
+            let mut allow = self.data.strategy.methods();
+            if !allow.contains(&Method::Options) {
+                allow.push(Method::Options);
+            }
+
+            let mut res = create_response(&state, StatusCode::Ok, None);
+            res.headers_mut().set(Allow(allow));
+            return self.finalize_response(Box::new(future::ok((state, res))));
+        }
+
         let future = match state.try_take::<RequestPathSegments>() {
             Some(rps) => {
-                if let Some((_, leaf, sp, sm)) = self.data.tree.traverse(&rps.segments()) {
-                    match leaf.select_route(&state) {
-                        Ok(route) => match route.delegation() {
-                            Delegation::External => {
-                                trace!("[{}] delegating to secondary router", request_id(&state));
-
-                                let mut rps = rps.clone();
-                                rps.increase_offset(sp);
-                                state.put(rps);
-
-                                route.dispatch(state)
-                            }
-                            Delegation::Internal => {
-                                trace!("[{}] dispatching to route", request_id(&state));
-                                self.dispatch(state, sm, route)
-                            }
-                        },
-                        Err(status) => {
-                            trace!("[{}] responding with error status", request_id(&state));
-                            let res = create_response(&state, status, None);
-                            Box::new(future::ok((state, res)))
+                let segments = rps.segments();
+                match self.data.strategy.find_route(&state, &segments) {
+                    RouteMatch::Found {
+                        route,
+                        segment_mapping,
+                        segments_processed,
+                    } => match route.delegation() {
+                        Delegation::External => {
+                            trace!("[{}] delegating to secondary router", request_id(&state));
+
+                            let mut rps = rps.clone();
+                            rps.increase_offset(segments_processed);
+                            state.put(rps);
+
+                            route.dispatch(state)
+                        }
+                        Delegation::Internal => {
+                            trace!("[{}] dispatching to route", request_id(&state));
+                            self.dispatch(state, segment_mapping, route)
                         }
+                    },
+                    RouteMatch::Refused {
+                        status,
+                        route,
+                        allow,
+                    } => {
+                        trace!("[{}] responding with error status", request_id(&state));
+                        let mut res = create_response(&state, status, None);
+                        if let Some(route) = route {
+                            route.extend_error_response(&state, &mut res);
+                        }
+                        if !allow.is_empty() && !res.headers().has::<Allow>() {
+                            res.headers_mut().set(Allow(allow));
+                        }
+                        Box::new(future::ok((state, res)))
+                    }
+                    RouteMatch::NotFound => {
+                        trace!("[{}] did not find routable node", request_id(&state));
+                        let res = create_response(&state, StatusCode::NotFound, None);
+                        Box::new(future::ok((state, res)))
                     }
-                } else {
-                    trace!("[{}] did not find routable node", request_id(&state));
-                    let res = create_response(&state, StatusCode::NotFound, None);
-                    Box::new(future::ok((state, res)))
                 }
             }
             None => {
@@ -130,19 +162,73 @@ impl Handler for Router {
 }
 
 impl Router {
-    /// Creates a `Router` instance.
+    /// Creates a `Router` instance, which matches requests using `tree`.
     pub fn new(tree: Tree, response_finalizer: ResponseFinalizer) -> Router {
-        let router_data = RouterData::new(tree, response_finalizer);
+        Router::from_strategy(tree, response_finalizer)
+    }
+
+    /// Creates a `Router` instance, which matches requests using a custom `RouterStrategy`.
+    pub fn from_strategy<S>(strategy: S, response_finalizer: ResponseFinalizer) -> Router
+    where
+        S: RouterStrategy + 'static,
+    {
+        let router_data = RouterData::new(Box::new(strategy), response_finalizer);
         Router {
             data: Arc::new(router_data),
         }
     }
 
+    /// Checks the routes of this `Router` for problems which make them unreachable, so that they
+    /// can be reported before the server starts, such as a route defined after another which
+    /// matches all of the same requests.
+    ///
+    /// Other configuration errors are reported while the `Router` is being built, by panicking,
+    /// e.g. an invalid regex constraint on a path segment, or conflicting routes (see
+    /// `RouteConflicts`). Secondary `Router` instances which requests are delegated to are
+    /// validated separately, and a `Router` with a custom `RouterStrategy` reports the problems
+    /// found by `RouterStrategy::validate`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # extern crate gotham;
+    /// # extern crate hyper;
+    /// #
+    /// # use hyper::Response;
+    /// # use gotham::router::builder::*;
+    /// # use gotham::router::strategy::RouteProblem;
+    /// # use gotham::state::State;
+    /// #
+    /// # fn handler(state: State) -> (State, Response) {
+    /// #   (state, Response::new())
+    /// # }
+    /// #
+    /// # fn main() {
+    /// let router = build_simple_router(|route| {
+    ///     route.get("/products/:id").to(handler);
+    ///     route.get("/products/:name").to(handler);
+    /// });
+    ///
+    /// assert_eq!(
+    ///     router.validate(),
+    ///     vec![
+    ///         RouteProblem::UnreachablePath {
+    ///             path: "/products/:name".to_owned(),
+    ///             matched_by: "/products/:id".to_owned(),
+    ///         },
+    ///     ]
+    /// );
+    /// # }
+    /// ```
+    pub fn validate(&self) -> Vec<RouteProblem> {
+        self.data.strategy.validate()
+    }
+
     fn dispatch(
         &self,
         mut state: State,
         sm: SegmentMapping,
-        route: &Box<Route + Send + Sync>,
+        route: &(dyn Route + Send + Sync),
     ) -> Box<HandlerFuture> {
         match route.extract_request_path(&mut state, sm) {
             Ok(()) => {
@@ -199,6 +285,18 @@ impl Router {
     }
 }
 
+/// True if the request is `OPTIONS *`, and is to be answered by the `Router` as configured by
+/// `OptionsAsterisk`.
+fn is_options_asterisk(state: &State) -> bool {
+    let behavior = OptionsAsterisk::try_borrow_from(state)
+        .cloned()
+        .unwrap_or_default();
+
+    behavior == OptionsAsterisk::Capabilities
+        && Method::try_borrow_from(state) == Some(&Method::Options)
+        && Uri::try_borrow_from(state).is_some_and(|uri| uri.path() == "*")
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -20,7 +20,8 @@ use router::response::extender::StaticResponseExtender;
 ///
 /// This functionality can be simply derived for application structs via `PathExtractor`,
 /// which will attempt to populate the associated struct. Combine with the derive
-/// `StaticResponseExtender` to have invalid query string data result in "400 Bad Request".
+/// `StaticResponseExtender` to have invalid request path data result in "400 Bad Request". Every
+/// invalid field is listed in the body of the response, as described by `FieldErrors`.
 ///
 /// Custom responses can be created by using the `PathExtractor` derive and then
 /// implementing `StaticResponseExtender` independently.
@@ -19,10 +19,77 @@ use router::response::extender::StaticResponseExtender;
 ///
 /// This functionality can be simply derived for application structs via `QueryStringExtractor`,
 /// which will attempt to populate the associated struct. Combine with the derive
-/// `StaticResponseExtender` to have invalid query string data result in "400 Bad Request".
+/// `StaticResponseExtender` to have invalid query string data result in "400 Bad Request". Every
+/// invalid field is listed in the body of the response, as described by `FieldErrors`.
 ///
 /// Custom responses can be created by using the `QueryStringExtractor` derive and then
 /// implementing `StaticResponseExtender` independently.
+///
+/// Fields which accept repeated values, such as `Vec<T>`, can bound the number of values with
+/// `#[query_string(min = N, max = N)]`. A query string outside the bounds fails extraction
+/// before any of the values are converted.
+///
+/// The query string is extracted from the `Uri` while the request is routed, and the request body
+/// is left in `State` untouched. A handler can therefore use the extracted struct alongside a body
+/// extractor such as `JsonBodyExtractor`, on requests which carry both, in either order.
+///
+/// # Examples
+///
+/// ```rust
+/// # extern crate gotham;
+/// # #[macro_use]
+/// # extern crate gotham_derive;
+/// # extern crate hyper;
+/// # #[macro_use]
+/// # extern crate log;
+/// # extern crate mime;
+/// #
+/// # use hyper::{Response, StatusCode};
+/// # use gotham::http::response::create_response;
+/// # use gotham::router::Router;
+/// # use gotham::router::builder::*;
+/// # use gotham::state::{FromState, State};
+/// # use gotham::test::TestServer;
+/// #
+/// #[derive(StateData, QueryStringExtractor, StaticResponseExtender)]
+/// struct LookupParams {
+///     #[query_string(min = 1, max = 3)]
+///     id: Vec<u64>,
+/// }
+///
+/// fn lookup(state: State) -> (State, Response) {
+///     let count = LookupParams::borrow_from(&state).id.len();
+///     let body = format!("{} ids", count).into_bytes();
+///     let res = create_response(&state, StatusCode::Ok, Some((body, mime::TEXT_PLAIN)));
+///     (state, res)
+/// }
+///
+/// fn router() -> Router {
+///     build_simple_router(|route| {
+///         route
+///             .get("/lookup")
+///             .with_query_string_extractor::<LookupParams>()
+///             .to(lookup);
+///     })
+/// }
+/// #
+/// # fn main() {
+/// #   let test_server = TestServer::new(router()).unwrap();
+/// #   let response = test_server
+/// #       .client()
+/// #       .get("http://localhost/lookup?id=1&id=2&id=3")
+/// #       .perform()
+/// #       .unwrap();
+/// #   assert_eq!(response.read_utf8_body().unwrap(), "3 ids");
+/// #
+/// #   let response = test_server
+/// #       .client()
+/// #       .get("http://localhost/lookup?id=1&id=2&id=3&id=4")
+/// #       .perform()
+/// #       .unwrap();
+/// #   assert_eq!(response.status(), StatusCode::BadRequest);
+/// # }
+/// ```
 pub trait QueryStringExtractor: StaticResponseExtender {
     /// Populates the struct with data from the `Request` query string and adds it to `State`
     fn extract(state: &mut State) -> Result<(), String>;
@@ -1,6 +1,6 @@
 //! Defines the type `AndRouteMatcher`
 
-use hyper::StatusCode;
+use hyper::{Method, Response, StatusCode};
 
 use router::route::RouteMatcher;
 use state::State;
@@ -79,4 +79,24 @@ where
 
         Ok(())
     }
+
+    fn extend_error_response(&self, state: &State, res: &mut Response) {
+        if self.t.is_match(state).is_err() {
+            self.t.extend_error_response(state, res)
+        } else {
+            self.u.extend_error_response(state, res)
+        }
+    }
+
+    fn allowed_methods(&self) -> Option<Vec<Method>> {
+        match (self.t.allowed_methods(), self.u.allowed_methods()) {
+            (Some(t), Some(u)) => Some(t.into_iter().filter(|m| u.contains(m)).collect()),
+            (Some(methods), None) | (None, Some(methods)) => Some(methods),
+            (None, None) => None,
+        }
+    }
+
+    fn matches_method_only(&self) -> bool {
+        self.t.matches_method_only() && self.u.matches_method_only()
+    }
 }
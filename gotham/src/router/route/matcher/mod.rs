@@ -3,10 +3,11 @@
 pub mod any;
 pub mod and;
 pub mod accept;
+pub mod upgrade;
 
 use std::panic::RefUnwindSafe;
 
-use hyper::{Method, StatusCode};
+use hyper::{Method, Response, StatusCode};
 
 use state::{request_id, FromState, State};
 
@@ -15,6 +16,24 @@ use state::{request_id, FromState, State};
 pub trait RouteMatcher: RefUnwindSafe {
     /// Determines if the `Request` meets pre-defined conditions.
     fn is_match(&self, state: &State) -> Result<(), StatusCode>;
+
+    /// Adds any headers which must accompany the error status returned by `is_match`, such as
+    /// the `Upgrade` header of a `426 Upgrade Required` response. Called only when the `Request`
+    /// did not match.
+    fn extend_error_response(&self, _state: &State, _res: &mut Response) {}
+
+    /// The request methods accepted by this `RouteMatcher`, used to build the `Allow` header of a
+    /// `405 Method Not Allowed` response. `None` when the matcher does not restrict the method.
+    fn allowed_methods(&self) -> Option<Vec<Method>> {
+        None
+    }
+
+    /// Whether the request method is the only condition checked by this `RouteMatcher`, so that
+    /// two such matchers accepting the same method match exactly the same requests. Used to
+    /// detect conflicting routes when building a `Router`. Defaults to `false`.
+    fn matches_method_only(&self) -> bool {
+        false
+    }
 }
 
 /// A `RouteMatcher` that succeeds when the `Request` has been made with one
@@ -71,4 +90,12 @@ impl RouteMatcher for MethodOnlyRouteMatcher {
             Err(StatusCode::MethodNotAllowed)
         }
     }
+
+    fn allowed_methods(&self) -> Option<Vec<Method>> {
+        Some(self.methods.clone())
+    }
+
+    fn matches_method_only(&self) -> bool {
+        true
+    }
 }
@@ -0,0 +1,159 @@
+//! Defines the type `UpgradeRequiredRouteMatcher`
+
+use hyper::{HttpVersion, Response, StatusCode};
+use hyper::header::{Connection, ConnectionOption, Protocol, ProtocolName, Upgrade};
+
+use router::route::matcher::RouteMatcher;
+use state::{is_secure, request_id, FromState, State};
+
+/// A `RouteMatcher` that fails with `426 Upgrade Required` when the `Request` was not made using
+/// the protocol required by the route. The error response carries an `Upgrade` header listing
+/// the protocols the client must switch to.
+///
+/// # Examples
+///
+/// ```rust
+/// # extern crate gotham;
+/// # extern crate hyper;
+/// # fn main() {
+/// # use hyper::{HttpVersion, StatusCode};
+/// # use gotham::state::State;
+/// # use gotham::router::route::matcher::RouteMatcher;
+/// # use gotham::router::route::matcher::upgrade::UpgradeRequiredRouteMatcher;
+/// #
+///   let matcher = UpgradeRequiredRouteMatcher::h2c();
+///   let mut state = State::new();
+///
+///   state.put(HttpVersion::H2c);
+///   assert!(matcher.is_match(&state).is_ok());
+///
+///   state.put(HttpVersion::Http11);
+///   assert_eq!(matcher.is_match(&state), Err(StatusCode::UpgradeRequired));
+/// # }
+/// ```
+pub struct UpgradeRequiredRouteMatcher {
+    protocols: Vec<Protocol>,
+    conforms: fn(&State) -> bool,
+}
+
+impl UpgradeRequiredRouteMatcher {
+    /// Creates a new `UpgradeRequiredRouteMatcher`, which matches requests for which `conforms`
+    /// returns `true`, and otherwise asks the client to upgrade to one of `protocols`.
+    pub fn new(protocols: Vec<Protocol>, conforms: fn(&State) -> bool) -> Self {
+        UpgradeRequiredRouteMatcher {
+            protocols,
+            conforms,
+        }
+    }
+
+    /// Creates a new `UpgradeRequiredRouteMatcher` which requires the `Request` to have been made
+    /// over TLS, as described in [RFC 2817](https://tools.ietf.org/html/rfc2817). See
+    /// `gotham::state::is_secure` for how this is determined.
+    pub fn tls() -> Self {
+        UpgradeRequiredRouteMatcher::new(
+            vec![
+                Protocol::new(ProtocolName::Tls, Some(String::from("1.2"))),
+                Protocol::new(ProtocolName::Http, Some(String::from("1.1"))),
+            ],
+            is_secure,
+        )
+    }
+
+    /// Creates a new `UpgradeRequiredRouteMatcher` which requires the `Request` to have been made
+    /// using HTTP/2.
+    pub fn h2c() -> Self {
+        fn is_http2(state: &State) -> bool {
+            matches!(
+                *HttpVersion::borrow_from(state),
+                HttpVersion::H2 | HttpVersion::H2c
+            )
+        }
+
+        UpgradeRequiredRouteMatcher::new(vec![Protocol::new(ProtocolName::H2c, None)], is_http2)
+    }
+}
+
+impl RouteMatcher for UpgradeRequiredRouteMatcher {
+    fn is_match(&self, state: &State) -> Result<(), StatusCode> {
+        if (self.conforms)(state) {
+            Ok(())
+        } else {
+            trace!(
+                "[{}] request requires upgrade to {}",
+                request_id(state),
+                Upgrade(self.protocols.clone())
+            );
+            Err(StatusCode::UpgradeRequired)
+        }
+    }
+
+    fn extend_error_response(&self, _state: &State, res: &mut Response) {
+        let headers = res.headers_mut();
+        headers.set(Upgrade(self.protocols.clone()));
+        headers.set(Connection(vec![ConnectionOption::ConnectionHeader(
+            "upgrade".parse().unwrap(),
+        )]));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use mime;
+
+    use http::response::create_response;
+    use router::builder::*;
+    use state::secure::put_secure;
+    use test::TestServer;
+
+    fn handler(state: State) -> (State, Response) {
+        let res = create_response(
+            &state,
+            StatusCode::Ok,
+            Some((b"secure".to_vec(), mime::TEXT_PLAIN)),
+        );
+        (state, res)
+    }
+
+    #[test]
+    fn responds_upgrade_required_over_plaintext() {
+        let router = build_simple_router(|route| {
+            route
+                .get("/secure")
+                .add_route_matcher(UpgradeRequiredRouteMatcher::tls())
+                .to(handler);
+            route.get("/open").to(handler);
+        });
+
+        let test_server = TestServer::new(router).unwrap();
+        let response = test_server
+            .client()
+            .get("http://localhost/secure")
+            .perform()
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::UpgradeRequired);
+        assert_eq!(
+            response.headers().get::<Upgrade>(),
+            Some(&Upgrade(vec![
+                Protocol::new(ProtocolName::Tls, Some(String::from("1.2"))),
+                Protocol::new(ProtocolName::Http, Some(String::from("1.1"))),
+            ]))
+        );
+
+        let response = test_server
+            .client()
+            .get("http://localhost/open")
+            .perform()
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::Ok);
+    }
+
+    #[test]
+    fn matches_tls_requests() {
+        let mut state = State::new();
+        put_secure(&mut state, true);
+        assert!(UpgradeRequiredRouteMatcher::tls().is_match(&state).is_ok());
+    }
+}
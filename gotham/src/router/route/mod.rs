@@ -7,14 +7,16 @@
 pub mod matcher;
 pub mod dispatch;
 
+use std::any::type_name;
 use std::marker::PhantomData;
 use std::panic::RefUnwindSafe;
 
-use hyper::Response;
+use hyper::{Method, Response};
 use hyper::StatusCode;
 
 use router::route::dispatch::Dispatcher;
 use handler::HandlerFuture;
+use http::request::extractor_failure::{report_extractor_failure, ExtractorFailureReason};
 use router::request::query_string::QueryStringExtractor;
 use router::route::matcher::RouteMatcher;
 use router::tree::SegmentMapping;
@@ -45,6 +47,22 @@ pub trait Route: RefUnwindSafe {
     /// Determines if this `Route` can be invoked, based on the `Request`.
     fn is_match(&self, state: &State) -> Result<(), StatusCode>;
 
+    /// Extends the error `Response` when `is_match` has failed, as described by
+    /// `RouteMatcher::extend_error_response`.
+    fn extend_error_response(&self, _state: &State, _res: &mut Response) {}
+
+    /// The request methods accepted by this `Route`, as described by
+    /// `RouteMatcher::allowed_methods`.
+    fn allowed_methods(&self) -> Option<Vec<Method>> {
+        None
+    }
+
+    /// Whether the request method is the only condition checked by this `Route`, as described by
+    /// `RouteMatcher::matches_method_only`.
+    fn matches_method_only(&self) -> bool {
+        false
+    }
+
     /// Determines if this `Route` intends to delegate requests to a secondary `Router` instance.
     fn delegation(&self) -> Delegation;
 
@@ -225,6 +243,18 @@ where
         self.matcher.is_match(state)
     }
 
+    fn extend_error_response(&self, state: &State, res: &mut Response) {
+        self.matcher.extend_error_response(state, res)
+    }
+
+    fn allowed_methods(&self) -> Option<Vec<Method>> {
+        self.matcher.allowed_methods()
+    }
+
+    fn matches_method_only(&self) -> bool {
+        self.matcher.matches_method_only()
+    }
+
     fn delegation(&self) -> Delegation {
         self.delegation
     }
@@ -238,7 +268,9 @@ where
         state: &mut State,
         segment_mapping: SegmentMapping,
     ) -> Result<(), String> {
-        RE::extract(state, segment_mapping)
+        RE::extract(state, segment_mapping).inspect_err(|_| {
+            report_extractor_failure(state, type_name::<RE>(), ExtractorFailureReason::Parse);
+        })
     }
 
     fn extend_response_on_path_error(&self, state: &mut State, res: &mut Response) {
@@ -246,7 +278,9 @@ where
     }
 
     fn extract_query_string(&self, state: &mut State) -> Result<(), String> {
-        QSE::extract(state)
+        QSE::extract(state).inspect_err(|_| {
+            report_extractor_failure(state, type_name::<QSE>(), ExtractorFailureReason::Parse);
+        })
     }
 
     fn extend_response_on_query_string_error(&self, state: &mut State, res: &mut Response) {
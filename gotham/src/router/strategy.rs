@@ -0,0 +1,456 @@
+//! Defines the `RouterStrategy` trait, which determines the `Route` that handles each request.
+
+use std::fmt;
+use std::panic::RefUnwindSafe;
+
+use hyper::{Method, StatusCode};
+
+use http::PercentDecoded;
+use router::route::Route;
+use router::tree::{SegmentMapping, Tree};
+use state::{FromState, State, StateData};
+
+/// The outcome of matching a request with a `RouterStrategy`.
+pub enum RouteMatch<'a, 'r> {
+    /// The `Route` which will handle the request.
+    Found {
+        /// The matching `Route`.
+        route: &'a (dyn Route + Send + Sync),
+
+        /// The request path segments captured by the `Route`, for use by its `PathExtractor`.
+        segment_mapping: SegmentMapping<'a, 'r>,
+
+        /// The number of request path segments consumed by the match. When the `Route` delegates
+        /// to a secondary `Router`, that `Router` matches only the remaining segments.
+        segments_processed: usize,
+    },
+
+    /// `Route` instances exist for the request path, but none will handle the request, e.g. as
+    /// none accept the request method.
+    Refused {
+        /// The status of the error response.
+        status: StatusCode,
+
+        /// The `Route` which extends the error response, if any.
+        route: Option<&'a (dyn Route + Send + Sync)>,
+
+        /// The methods listed by the `Allow` header of a `405 Method Not Allowed` response. The
+        /// header is omitted when empty, or when set by the `Route`.
+        allow: Vec<Method>,
+    },
+
+    /// No `Route` exists for the request path.
+    NotFound,
+}
+
+/// A problem with the routes of a `Router`, reported by `Router::validate`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RouteProblem {
+    /// A route is never reached, as an earlier route for the same path matches every request it
+    /// would, e.g. a route for `GET` defined after a route for `GET` and `HEAD`.
+    UnreachableRoute {
+        /// The path of the route, as defined.
+        path: String,
+
+        /// The position of the route among those defined for `path`, counting from zero.
+        index: usize,
+    },
+
+    /// The routes for a path are never reached, as a path with a dynamic segment in the same
+    /// place matches every request they would, e.g. `/products/:name` after `/products/:id`.
+    UnreachablePath {
+        /// The path which is never reached.
+        path: String,
+
+        /// The path which matches requests for `path` instead.
+        matched_by: String,
+    },
+}
+
+impl fmt::Display for RouteProblem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            RouteProblem::UnreachableRoute { ref path, index } => write!(
+                f,
+                "route {} for `{}` is unreachable, as an earlier route matches all its requests",
+                index, path
+            ),
+            RouteProblem::UnreachablePath {
+                ref path,
+                ref matched_by,
+            } => write!(
+                f,
+                "routes for `{}` are unreachable, as `{}` matches all their requests",
+                path, matched_by
+            ),
+        }
+    }
+}
+
+/// Determines which methods are listed by the `Allow` header of the `405 Method Not Allowed`
+/// responses sent by the `Router`, configured via `ServerConfig::with_allow_scope`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum AllowScope {
+    /// Only the methods accepted at the requested path are listed. This is the default.
+    #[default]
+    Path,
+
+    /// The methods accepted at each parent of the requested path are also listed, as a broader
+    /// hint of what the application serves. For example, a `DELETE` of `/products/1` lists the
+    /// methods of `/` and `/products` along with those of `/products/1`.
+    Ancestors,
+}
+
+impl StateData for AllowScope {}
+
+/// Determines how the `Router` answers `OPTIONS *`, which asks about the capabilities of the
+/// server as a whole rather than those of a resource, configured via
+/// `ServerConfig::with_options_asterisk`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OptionsAsterisk {
+    /// `OPTIONS *` is answered with `200 OK` by the `Router`, and an `Allow` header listing every
+    /// method accepted by any of its routes, as given by `RouterStrategy::methods`. This is the
+    /// default.
+    #[default]
+    Capabilities,
+
+    /// `OPTIONS *` is routed as any other request, with a path of `*`, and is answered with
+    /// `404 Not Found` unless the application routes it.
+    Application,
+}
+
+impl StateData for OptionsAsterisk {}
+
+/// Determines the `Route` which handles a request, on behalf of a `Router`.
+///
+/// The `Router` is responsible for extracting the request path and query string, dispatching to
+/// the `Route` through its pipelines, delegating to secondary `Router` instances and finalizing
+/// the response. Only the matching of the request to a `Route` is delegated to the strategy, so
+/// that alternative matchers can be used without reimplementing the rest.
+///
+/// `Tree` is the strategy used by `Router::new`, and by the router builder. A custom strategy is
+/// given to `Router::from_strategy`.
+pub trait RouterStrategy: Send + Sync + RefUnwindSafe {
+    /// Finds the `Route` which handles the request, given the decoded segments of the request
+    /// path.
+    fn find_route<'a, 'r>(
+        &'a self,
+        state: &State,
+        segments: &'r [&'r PercentDecoded],
+    ) -> RouteMatch<'a, 'r>;
+
+    /// Checks the routes for problems which make them unreachable, for `Router::validate`.
+    /// Returns no problems by default.
+    fn validate(&self) -> Vec<RouteProblem> {
+        Vec::new()
+    }
+
+    /// The request methods accepted by any of the routes, for the `Allow` header of the response
+    /// to `OPTIONS *`. Returns no methods by default.
+    fn methods(&self) -> Vec<Method> {
+        Vec::new()
+    }
+}
+
+impl RouterStrategy for Tree {
+    fn find_route<'a, 'r>(
+        &'a self,
+        state: &State,
+        segments: &'r [&'r PercentDecoded],
+    ) -> RouteMatch<'a, 'r> {
+        match self.traverse(segments) {
+            Some((_, leaf, segments_processed, segment_mapping)) => {
+                match leaf.select_route(state) {
+                    Ok(route) => RouteMatch::Found {
+                        route: &**route,
+                        segment_mapping,
+                        segments_processed,
+                    },
+                    Err(status) => {
+                        let allow = if status == StatusCode::MethodNotAllowed {
+                            self.allowed_methods(state, segments, leaf.allowed_methods())
+                        } else {
+                            Vec::new()
+                        };
+
+                        RouteMatch::Refused {
+                            status,
+                            route: leaf.first_route(),
+                            allow,
+                        }
+                    }
+                }
+            }
+            None => RouteMatch::NotFound,
+        }
+    }
+
+    fn validate(&self) -> Vec<RouteProblem> {
+        let mut problems = Vec::new();
+        self.borrow_root().validate("", &mut problems);
+        problems
+    }
+
+    fn methods(&self) -> Vec<Method> {
+        let mut methods = Vec::new();
+        self.borrow_root().collect_allowed_methods(&mut methods);
+        methods
+    }
+}
+
+impl Tree {
+    /// Adds the methods accepted at each parent of the request path to `allowed`, if configured
+    /// by `AllowScope`.
+    fn allowed_methods(
+        &self,
+        state: &State,
+        segments: &[&PercentDecoded],
+        mut allowed: Vec<Method>,
+    ) -> Vec<Method> {
+        let scope = AllowScope::try_borrow_from(state)
+            .cloned()
+            .unwrap_or_default();
+
+        if scope == AllowScope::Ancestors {
+            for len in 1..segments.len() {
+                if let Some((_, node, _, _)) = self.traverse(&segments[..len]) {
+                    for method in node.allowed_methods() {
+                        if !allowed.contains(&method) {
+                            allowed.push(method);
+                        }
+                    }
+                }
+            }
+        }
+
+        allowed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use hyper::{Method, Response};
+
+    use hyper::header::Allow;
+
+    use config::ServerConfig;
+    use http::response::create_response;
+    use router::Router;
+    use router::builder::*;
+    use router::request::path::NoopPathExtractor;
+    use router::request::query_string::NoopQueryStringExtractor;
+    use router::response::finalizer::ResponseFinalizerBuilder;
+    use router::route::{Delegation, Extractors, RouteImpl};
+    use router::route::dispatch::{finalize_pipeline_set, new_pipeline_set, DispatcherImpl};
+    use router::route::matcher::{MethodOnlyRouteMatcher, RouteMatcher};
+    use router::route::matcher::accept::AcceptHeaderRouteMatcher;
+    use router::route::matcher::any::AnyRouteMatcher;
+    use router::tree::TreeBuilder;
+    use router::tree::node::{NodeBuilder, SegmentType};
+    use test::TestServer;
+
+    fn handler(state: State) -> (State, Response) {
+        let res = create_response(&state, StatusCode::Accepted, None);
+        (state, res)
+    }
+
+    /// Routes requests to a single `Route` when the path has exactly `depth` segments.
+    struct DepthStrategy {
+        depth: usize,
+        route: Box<dyn Route + Send + Sync>,
+    }
+
+    impl RouterStrategy for DepthStrategy {
+        fn find_route<'a, 'r>(
+            &'a self,
+            state: &State,
+            segments: &'r [&'r PercentDecoded],
+        ) -> RouteMatch<'a, 'r> {
+            // The root segment is always present.
+            if segments.len() - 1 != self.depth {
+                return RouteMatch::NotFound;
+            }
+
+            match self.route.is_match(state) {
+                Ok(()) => RouteMatch::Found {
+                    route: &*self.route,
+                    segment_mapping: SegmentMapping::default(),
+                    segments_processed: self.depth,
+                },
+                Err(status) => RouteMatch::Refused {
+                    status,
+                    route: Some(&*self.route),
+                    allow: Vec::new(),
+                },
+            }
+        }
+    }
+
+    #[test]
+    fn serves_through_custom_strategy() {
+        let pipeline_set = finalize_pipeline_set(new_pipeline_set());
+        let matcher = MethodOnlyRouteMatcher::new(vec![Method::Get]);
+        let dispatcher = Box::new(DispatcherImpl::new(|| Ok(handler), (), pipeline_set));
+        let extractors: Extractors<NoopPathExtractor, NoopQueryStringExtractor> =
+            Extractors::new();
+        let route = RouteImpl::new(matcher, dispatcher, extractors, Delegation::Internal);
+
+        let strategy = DepthStrategy {
+            depth: 2,
+            route: Box::new(route),
+        };
+        let router = Router::from_strategy(strategy, ResponseFinalizerBuilder::new().finalize());
+        let test_server = TestServer::new(router).unwrap();
+
+        let response = test_server
+            .client()
+            .get("http://localhost/any/thing")
+            .perform()
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::Accepted);
+
+        let response = test_server
+            .client()
+            .get("http://localhost/thing")
+            .perform()
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NotFound);
+
+        let response = test_server
+            .client()
+            .post("http://localhost/any/thing", "", ::mime::TEXT_PLAIN)
+            .perform()
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::MethodNotAllowed);
+    }
+
+    fn allow(config: ServerConfig) -> Option<String> {
+        let router = build_simple_router(|route| {
+            route.put("/").to(handler);
+            route.get("/products").to(handler);
+            route.post("/products").to(handler);
+            route.get("/products/:id").to(handler);
+        });
+
+        let test_server = TestServer::with_config(router, config).unwrap();
+        let response = test_server
+            .client()
+            .build_request(Method::Delete, "http://localhost/products/1")
+            .perform()
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::MethodNotAllowed);
+        response.headers().get::<Allow>().map(|allow| allow.to_string())
+    }
+
+    #[test]
+    fn allow_lists_methods_of_exact_path_by_default() {
+        assert_eq!(allow(ServerConfig::default()), Some("GET".to_owned()));
+    }
+
+    #[test]
+    fn allow_lists_methods_of_ancestors_when_configured() {
+        let config = ServerConfig::default().with_allow_scope(AllowScope::Ancestors);
+        assert_eq!(allow(config), Some("GET, PUT, POST".to_owned()));
+    }
+
+    fn route<M>(matcher: M) -> Box<dyn Route + Send + Sync>
+    where
+        M: RouteMatcher + Send + Sync + 'static,
+    {
+        let pipeline_set = finalize_pipeline_set(new_pipeline_set());
+        let dispatcher = Box::new(DispatcherImpl::new(|| Ok(handler), (), pipeline_set));
+        let extractors: Extractors<NoopPathExtractor, NoopQueryStringExtractor> =
+            Extractors::new();
+        Box::new(RouteImpl::new(
+            matcher,
+            dispatcher,
+            extractors,
+            Delegation::Internal,
+        ))
+    }
+
+    #[test]
+    fn validate_reports_route_after_catch_all() {
+        let mut tree_builder = TreeBuilder::new();
+        let mut products = NodeBuilder::new("products", SegmentType::Static);
+        products.add_route(route(AnyRouteMatcher::new()));
+        products.add_route(route(MethodOnlyRouteMatcher::new(vec![Method::Get])));
+        tree_builder.add_child(products);
+
+        let router = Router::new(
+            tree_builder.finalize(),
+            ResponseFinalizerBuilder::new().finalize(),
+        );
+        assert_eq!(
+            router.validate(),
+            vec![
+                RouteProblem::UnreachableRoute {
+                    path: "/products".to_owned(),
+                    index: 1,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn validate_reports_unreachable_routes_and_paths() {
+        let router = build_simple_router(|route| {
+            route.on_route_conflict(RouteConflicts::LastWins);
+            route.get("/").to(handler);
+            route.get_or_head("/").to(handler);
+            route.scope("/products", |route| {
+                route.get("/:id").to(handler);
+                route.get("/:id/reviews").to(handler);
+                route.get("/:name/reviews").to(handler);
+                route.get("/:name").to(handler);
+                route
+                    .get("/:id")
+                    .add_route_matcher(AcceptHeaderRouteMatcher::new(vec![mime::TEXT_HTML]))
+                    .to(handler);
+            });
+        });
+
+        let problems = router.validate();
+        assert_eq!(
+            problems,
+            vec![
+                RouteProblem::UnreachableRoute {
+                    path: "/".to_owned(),
+                    index: 1,
+                },
+                RouteProblem::UnreachablePath {
+                    path: "/products/:name".to_owned(),
+                    matched_by: "/products/:id".to_owned(),
+                },
+                RouteProblem::UnreachableRoute {
+                    path: "/products/:id".to_owned(),
+                    index: 1,
+                },
+            ]
+        );
+        assert_eq!(
+            problems[1].to_string(),
+            "routes for `/products/:name` are unreachable, as `/products/:id` matches all their \
+             requests"
+        );
+    }
+
+    #[test]
+    fn validate_reports_nothing_for_reachable_routes() {
+        let router = build_simple_router(|route| {
+            route.get("/products").to(handler);
+            route.post("/products").to(handler);
+            route
+                .get("/products/:id")
+                .add_route_matcher(AcceptHeaderRouteMatcher::new(vec![mime::TEXT_HTML]))
+                .to(handler);
+            route.get("/products/:id").to(handler);
+            route.get("/products/:id:[0-9]+/reviews").to(handler);
+        });
+
+        assert!(router.validate().is_empty());
+    }
+}
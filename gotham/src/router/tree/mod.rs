@@ -17,7 +17,7 @@ pub type Path<'a> = Vec<&'a Node>;
 /// which have been matched against the `Request` path.
 ///
 /// Data is Percent and UTF8 decoded.
-#[derive(Debug)]
+#[derive(Debug, Default)]
 pub struct SegmentMapping<'a, 'b> {
     data: HashMap<&'a str, Vec<&'b PercentDecoded>>,
 }
@@ -37,6 +37,12 @@ impl<'a, 'b> SegmentMapping<'a, 'b> {
         self.data.contains_key(key)
     }
 
+    /// Maps `Request` path segments to the segment key, for use by a `RouterStrategy` which
+    /// captures segments of the path.
+    pub fn insert(&mut self, key: &'a str, segments: Vec<&'b PercentDecoded>) {
+        self.data.insert(key, segments);
+    }
+
     /// Adds an empty value for a segment key, useful for segments that are considered
     /// optional and haven't been explicitly provided as part of a `Request` path
     pub fn add_unmapped_segment(&mut self, key: &'a str) {
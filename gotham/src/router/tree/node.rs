@@ -3,10 +3,12 @@
 use std::cmp::Ordering;
 use std::collections::HashMap;
 use std::borrow::Borrow;
-use hyper::StatusCode;
+use hyper::{Method, Response, StatusCode};
 
 use http::PercentDecoded;
+use router::builder::RouteConflicts;
 use router::route::{Delegation, Route};
+use router::strategy::RouteProblem;
 use router::tree::{Path, SegmentMapping, SegmentsProcessed};
 use router::tree::regex::ConstrainedSegmentRegex;
 use state::{request_id, State};
@@ -104,10 +106,49 @@ pub struct Node {
     routes: Vec<Box<Route + Send + Sync>>,
 
     delegating: bool,
+    optional: bool,
     children: Vec<Node>,
 }
 
 impl Node {
+    /// Adds the problems found with the routes of this node and all of its children to
+    /// `problems`, given the path of the parent node.
+    pub(crate) fn validate(&self, parent: &str, problems: &mut Vec<RouteProblem>) {
+        let path = node_path(parent, &self.segment, &self.segment_type);
+
+        for (index, later) in self.routes.iter().enumerate() {
+            if self.routes[..index]
+                .iter()
+                .any(|earlier| shadows(&**earlier, &**later))
+            {
+                problems.push(RouteProblem::UnreachableRoute {
+                    path: path.clone(),
+                    index,
+                });
+            }
+        }
+
+        // Only the first routable `Dynamic` child is ever a leaf, as each matches any segment.
+        let mut dynamic_leaf: Option<&Node> = None;
+        for child in &self.children {
+            if child.segment_type != SegmentType::Dynamic || !child.is_routable() {
+                continue;
+            }
+
+            match dynamic_leaf {
+                Some(earlier) => problems.push(RouteProblem::UnreachablePath {
+                    path: node_path(&path, &child.segment, &child.segment_type),
+                    matched_by: node_path(&path, &earlier.segment, &earlier.segment_type),
+                }),
+                None => dynamic_leaf = Some(child),
+            }
+        }
+
+        for child in &self.children {
+            child.validate(&path, problems);
+        }
+    }
+
     /// Provides the segment this `Node` represents.
     pub fn segment(&self) -> &str {
         &self.segment
@@ -151,6 +192,47 @@ impl Node {
         }
     }
 
+    /// Extends the error `Response` produced when `select_route` fails, using the same `Route`
+    /// which provided the error status.
+    pub fn extend_error_response(&self, state: &State, res: &mut Response) {
+        if let Some(route) = self.first_route() {
+            route.extend_error_response(state, res);
+        }
+    }
+
+    /// The `Route` which provides the error status when `select_route` fails.
+    pub(crate) fn first_route(&self) -> Option<&(dyn Route + Send + Sync)> {
+        self.routes.first().map(|route| &**route)
+    }
+
+    /// The request methods accepted by the `Route` instances of this `Node`, in the order they are
+    /// first accepted. `Route` instances which do not restrict the method are not considered.
+    pub(crate) fn allowed_methods(&self) -> Vec<Method> {
+        let mut allowed = Vec::new();
+        for methods in self.routes.iter().filter_map(|route| route.allowed_methods()) {
+            for method in methods {
+                if !allowed.contains(&method) {
+                    allowed.push(method);
+                }
+            }
+        }
+        allowed
+    }
+
+    /// Adds the request methods accepted by the `Route` instances of this `Node` and its
+    /// descendants to `methods`, skipping those already present.
+    pub(crate) fn collect_allowed_methods(&self, methods: &mut Vec<Method>) {
+        for method in self.allowed_methods() {
+            if !methods.contains(&method) {
+                methods.push(method);
+            }
+        }
+
+        for child in &self.children {
+            child.collect_allowed_methods(methods);
+        }
+    }
+
     /// True if there is at least one child `Node` present
     pub fn is_parent(&self) -> bool {
         !self.children.is_empty()
@@ -232,7 +314,18 @@ impl Node {
                 let child = self.children
                     .iter()
                     .filter_map(|c| c.inner_traverse(xs, vec![]))
-                    .next();
+                    .next()
+                    .or_else(|| {
+                        // The `Request` path ends here, but may omit the optional segments which
+                        // end the path of a routable child.
+                        if xs.is_empty() {
+                            let leaf = self.optional_leaf()?;
+                            trace!(" found leaf node `{}` by omitting it", leaf.segment);
+                            Some((vec![], leaf, 0, HashMap::new()))
+                        } else {
+                            None
+                        }
+                    });
 
                 match child {
                     Some((mut path, leaf, sp, mut sm)) => {
@@ -264,6 +357,20 @@ impl Node {
         }
     }
 
+    /// The routable `Node` reached by omitting one or more optional segments below this one.
+    fn optional_leaf(&self) -> Option<&Node> {
+        self.children
+            .iter()
+            .filter(|c| c.optional)
+            .find_map(|c| {
+                if c.is_routable() {
+                    Some(c)
+                } else {
+                    c.optional_leaf()
+                }
+            })
+    }
+
     fn is_delegating(&self, req_path_segment: &PercentDecoded) -> bool {
         self.is_match(req_path_segment) && self.delegating
     }
@@ -290,9 +397,48 @@ pub struct NodeBuilder {
     routes: Vec<Box<Route + Send + Sync>>,
 
     delegating: bool,
+    optional: bool,
     children: Vec<NodeBuilder>,
 }
 
+/// The path of a node, as written when defining routes, given the path of its parent.
+fn node_path(parent: &str, segment: &str, segment_type: &SegmentType) -> String {
+    let separator = if parent.ends_with('/') { "" } else { "/" };
+    match *segment_type {
+        _ if parent.is_empty() => segment.to_owned(),
+        SegmentType::Static | SegmentType::Glob => format!("{}{}{}", parent, separator, segment),
+        SegmentType::Dynamic | SegmentType::Constrained { .. } => {
+            format!("{}{}:{}", parent, separator, segment)
+        }
+    }
+}
+
+/// Returns `true` if `earlier` matches every request which `later` would, and so `later` is never
+/// reached when both are routes of the same `Node`.
+fn shadows(earlier: &(dyn Route + Send + Sync), later: &(dyn Route + Send + Sync)) -> bool {
+    if !earlier.matches_method_only() {
+        return false;
+    }
+
+    match (earlier.allowed_methods(), later.allowed_methods()) {
+        (None, _) => true,
+        (Some(earlier), Some(later)) => later.iter().all(|method| earlier.contains(method)),
+        (Some(_), None) => false,
+    }
+}
+
+/// The methods accepted by both `a` and `b`, when both match requests by method alone.
+fn shared_methods(a: &(dyn Route + Send + Sync), b: &(dyn Route + Send + Sync)) -> Vec<Method> {
+    if !a.matches_method_only() || !b.matches_method_only() {
+        return Vec::new();
+    }
+
+    match (a.allowed_methods(), b.allowed_methods()) {
+        (Some(a), Some(b)) => a.into_iter().filter(|method| b.contains(method)).collect(),
+        _ => Vec::new(),
+    }
+}
+
 impl NodeBuilder {
     /// Creates new `NodeBuilder` for the given segment.
     pub fn new<S>(segment: S, segment_type: SegmentType) -> Self
@@ -306,6 +452,7 @@ impl NodeBuilder {
             routes: vec![],
             children: vec![],
             delegating: false,
+            optional: false,
         }
     }
 
@@ -333,6 +480,12 @@ impl NodeBuilder {
         self.routes.push(route);
     }
 
+    /// Marks the segment as optional, so that the `Route` instances of the built `Node` are also
+    /// evaluated for `Request` paths which end at its parent.
+    pub fn make_optional(&mut self) {
+        self.optional = true;
+    }
+
     /// Adds a new child to this sub-tree structure
     pub fn add_child(&mut self, child: NodeBuilder) {
         if self.delegating {
@@ -373,6 +526,53 @@ impl NodeBuilder {
             .find(|n| n.segment_type == segment_type && n.segment == segment)
     }
 
+    /// Checks the routes of this node and all of its children for conflicts, as configured by
+    /// `conflicts`, given the path of the parent node.
+    ///
+    /// Two routes conflict when both match requests by method alone, and accept a method in
+    /// common, as the later route is then never reached for that method.
+    pub(crate) fn resolve_conflicts(&mut self, parent: &str, conflicts: RouteConflicts) {
+        let path = node_path(parent, &self.segment, &self.segment_type);
+
+        for later in 1..self.routes.len() {
+            let conflict = (0..later).find_map(|earlier| {
+                let methods = shared_methods(&*self.routes[earlier], &*self.routes[later]);
+                if methods.is_empty() {
+                    None
+                } else {
+                    Some((earlier, methods))
+                }
+            });
+
+            if let Some((earlier, methods)) = conflict {
+                let methods = methods
+                    .iter()
+                    .map(|method| method.to_string())
+                    .collect::<Vec<String>>()
+                    .join(", ");
+
+                match conflicts {
+                    RouteConflicts::Panic => panic!(
+                        "conflicting routes for {} `{}`, the later route would never be reached",
+                        methods, path
+                    ),
+                    RouteConflicts::LastWins => {
+                        warn!(
+                            " route for {} `{}` replaces an earlier route for the same requests",
+                            methods, path
+                        );
+                        let route = self.routes.remove(later);
+                        self.routes.insert(earlier, route);
+                    }
+                }
+            }
+        }
+
+        for child in &mut self.children {
+            child.resolve_conflicts(&path, conflicts);
+        }
+    }
+
     /// Finalizes and sorts all internal data, including all children.
     pub fn finalize(mut self) -> Node {
         self.sort();
@@ -390,6 +590,7 @@ impl NodeBuilder {
             segment_type: self.segment_type,
             routes: self.routes,
             delegating: self.delegating,
+            optional: self.optional,
             children,
         }
     }
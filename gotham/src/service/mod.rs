@@ -4,20 +4,37 @@ use std::thread;
 use std::net::SocketAddr;
 use std::sync::Arc;
 use std::panic::AssertUnwindSafe;
+use std::time::Instant;
 
 use hyper;
 use hyper::server::Service;
-use hyper::{Request, Response};
-use futures::Future;
+use hyper::{HttpVersion, Method, Request, Response, StatusCode, Uri};
+use hyper::header::{Connection, Headers, Host};
+use futures::{future, Future};
 use tokio_core::reactor::Handle;
+use url::percent_encoding::percent_decode;
 
+use clock::put_clock;
+use config::{DotSegments, EncodedSlashes, ServerConfig, TraceBehavior};
 use handler::NewHandler;
-use state::{request_id, set_request_id, State};
+use http::response::create_response;
+use http::response::retry_after::set_retry_after;
+use shutdown::DrainBehavior;
+use state::{request_id, set_request_id, FromState, State};
 use state::client_addr::put_client_addr;
+use state::host::put_forwarded_host;
+use state::request_start::put_request_start;
+use state::sampling::put_sampling_value;
+use state::secure::put_secure;
+use state::server_timing::enable_server_timing;
+use state::transfer::{count_request_body, count_response_body, TransferSizes};
+use http::request::forwarded::ForwardedInfo;
+use http::request::parts::RequestParts;
 use http::request::path::RequestPathSegments;
 
 mod timing;
 mod trap;
+mod validate;
 
 /// Wraps a `NewHandler` to provide a `hyper::server::NewService` implementation for Gotham
 /// handlers.
@@ -27,20 +44,22 @@ where
 {
     t: Arc<T>,
     handle: Handle,
+    config: Arc<ServerConfig>,
 }
 
 impl<T> GothamService<T>
 where
     T: NewHandler + 'static,
 {
-    pub(super) fn new(t: Arc<T>, handle: Handle) -> GothamService<T> {
-        GothamService { t, handle }
+    pub(super) fn new(t: Arc<T>, handle: Handle, config: Arc<ServerConfig>) -> GothamService<T> {
+        GothamService { t, handle, config }
     }
 
     pub(super) fn connect(&self, client_addr: SocketAddr) -> ConnectedGothamService<T> {
         ConnectedGothamService {
             t: self.t.clone(),
             handle: self.handle.clone(),
+            config: self.config.clone(),
             client_addr,
         }
     }
@@ -52,6 +71,7 @@ where
 {
     t: Arc<T>,
     handle: Handle,
+    config: Arc<ServerConfig>,
     client_addr: SocketAddr,
 }
 
@@ -65,28 +85,309 @@ where
     type Future = Box<Future<Item = Self::Response, Error = Self::Error>>;
 
     fn call(&self, req: Self::Request) -> Self::Future {
+        let start = Instant::now();
         let mut state = State::new();
+        put_request_start(&mut state, start);
+        put_sampling_value(&mut state);
+        put_clock(&mut state, self.config.clock().clone());
 
         put_client_addr(&mut state, self.client_addr);
 
         let (method, uri, version, headers, body) = req.deconstruct();
 
         state.put(self.handle.clone());
-        state.put(RequestPathSegments::new(uri.path()));
+        state.put(self.path_segments(uri.path()));
+        state.put(RequestParts::new(
+            method.clone(),
+            uri.clone(),
+            version,
+            headers.clone(),
+        ));
         state.put(method);
         state.put(uri);
         state.put(version);
         state.put(headers);
         state.put(body);
+        state.put(self.config.error_format());
+        state.put(self.config.unknown_fields());
+        state.put(self.config.location_style());
+        state.put(self.config.allow_scope());
+        state.put(self.config.options_asterisk());
+        state.put(self.config.retry_after_format());
+        state.put(self.config.response_validation());
+        if let Some(mapper) = self.config.deserialize_error_mapper() {
+            state.put(mapper.clone());
+        }
+        if let Some(limit) = self.config.streaming_limit() {
+            state.put(limit.clone());
+        }
+        if let Some(hook) = self.config.extractor_failure_hook() {
+            state.put(hook.clone());
+        }
+        for data in self.config.app_data() {
+            data.put(&mut state);
+        }
         set_request_id(&mut state);
 
+        self.put_forwarded(&mut state);
+
+        if self.config.server_timing() {
+            enable_server_timing(&mut state);
+        }
+
         debug!(
             "[DEBUG][{}][Thread][{:?}]",
             request_id(&state),
             thread::current().id(),
         );
 
-        trap::call_handler(self.t.as_ref(), AssertUnwindSafe(state))
+        if let Some(res) = self.reject(&state) {
+            return Box::new(future::ok(res));
+        }
+
+        if let Some(hook) = self.config.pre_routing_hook() {
+            if let Some(res) = hook.call(&mut state) {
+                trace!("[{}] responding from pre-routing hook", request_id(&state));
+                return Box::new(future::ok(res));
+            }
+
+            let segments = self.path_segments(Uri::borrow_from(&state).path());
+            state.put(segments);
+
+            if let Some(res) = self.reject_path(&state) {
+                return Box::new(future::ok(res));
+            }
+        }
+
+        let transfer = if self.config.transfer_accounting() {
+            let sizes = TransferSizes::new();
+            count_request_body(&mut state, &sizes);
+            state.put(sizes.clone());
+            Some((request_id(&state).to_owned(), sizes))
+        } else {
+            None
+        };
+
+        let f = trap::call_handler(self.t.as_ref(), AssertUnwindSafe(state));
+
+        let config = self.config.clone();
+        let handle = self.handle.clone();
+        Box::new(f.map(move |mut res| {
+            if config.shutdown().is_draining() {
+                res.headers_mut().set(Connection::close());
+            }
+
+            match transfer {
+                Some((request_id, sizes)) => {
+                    let hook = config.transfer_hook().cloned();
+                    count_response_body(&handle, res, sizes, move |sizes| {
+                        if let Some(hook) = hook {
+                            hook.call(&request_id, sizes);
+                        }
+                    })
+                }
+                None => res,
+            }
+        }))
+    }
+}
+
+impl<T> ConnectedGothamService<T>
+where
+    T: NewHandler,
+{
+    /// Determines whether the request arrived over a secure connection, and which host the client
+    /// requested. Gotham serves plain HTTP, so a secure connection relies on a trusted proxy having
+    /// reported the protocol used by the client.
+    fn put_forwarded(&self, state: &mut State) {
+        let info = match self.config.trusted_proxy_headers() {
+            Some(precedence) => {
+                ForwardedInfo::from_trusted_headers(Headers::borrow_from(state), precedence)
+            }
+            None => {
+                put_secure(state, false);
+                return;
+            }
+        };
+
+        let secure = info
+            .proto()
+            .is_some_and(|proto| proto.eq_ignore_ascii_case("https"));
+        put_secure(state, secure);
+
+        if let Some(host) = info.host() {
+            put_forwarded_host(state, host.to_owned());
+        }
+    }
+
+    /// Splits `path` into the segments used for routing, removing dot segments if configured.
+    fn path_segments(&self, path: &str) -> RequestPathSegments {
+        let mut segments = RequestPathSegments::new(path);
+        if self.config.dot_segments() == DotSegments::Normalize {
+            segments.remove_dot_segments();
+        }
+        segments
+    }
+
+    /// Checks the request against the limits in `ServerConfig`, returning the response to send
+    /// if the request should not be passed to the application.
+    fn reject(&self, state: &State) -> Option<Response> {
+        if self.config.drain_behavior() == DrainBehavior::RespondUnavailable
+            && self.config.shutdown().is_draining()
+        {
+            trace!("[{}] rejecting request while draining", request_id(state));
+            let mut res = create_response(state, StatusCode::ServiceUnavailable, None);
+            res.headers_mut().set(Connection::close());
+            if let Some(retry_after) = self.config.retry_after() {
+                set_retry_after(state, &mut res, retry_after);
+            }
+            return Some(res);
+        }
+
+        if let Some(max_headers) = self.config.max_headers() {
+            let count: usize = Headers::borrow_from(state)
+                .iter()
+                .map(|header| header.raw().len())
+                .sum();
+
+            if count > max_headers {
+                trace!(
+                    "[{}] rejecting request with {} headers, maximum is {}",
+                    request_id(state),
+                    count,
+                    max_headers
+                );
+                return Some(create_response(
+                    state,
+                    StatusCode::RequestHeaderFieldsTooLarge,
+                    None,
+                ));
+            }
+        }
+
+        if let Some(res) = self.reject_path(state) {
+            return Some(res);
+        }
+
+        let version = *HttpVersion::borrow_from(state);
+        if self.config.host_requirement().requires(version)
+            && !Headers::borrow_from(state).has::<Host>()
+        {
+            trace!("[{}] rejecting request without Host header", request_id(state));
+            return Some(create_response(state, StatusCode::BadRequest, None));
+        }
+
+        if self.config.reject_unknown_expectations() && has_unknown_expectation(state) {
+            trace!("[{}] rejecting request with unknown expectation", request_id(state));
+            return Some(create_response(state, StatusCode::ExpectationFailed, None));
+        }
+
+        if *Method::borrow_from(state) == Method::Trace {
+            match self.config.trace_behavior() {
+                TraceBehavior::Reject => {
+                    trace!("[{}] rejecting TRACE request", request_id(state));
+                    return Some(create_response(state, StatusCode::MethodNotAllowed, None));
+                }
+                TraceBehavior::Echo => {
+                    trace!("[{}] echoing TRACE request", request_id(state));
+                    return Some(trace_echo(state));
+                }
+                TraceBehavior::Application => (),
+            }
+        }
+
+        None
+    }
+
+    /// Checks the request path against the limits in `ServerConfig`, returning the response to
+    /// send if the request should not be passed to the application.
+    fn reject_path(&self, state: &State) -> Option<Response> {
+        if let Some(max_length) = self.config.max_decoded_path_length() {
+            let length = decoded_path_length(RequestPathSegments::borrow_from(state));
+            if length > max_length {
+                trace!(
+                    "[{}] rejecting request with decoded path of {} bytes, maximum is {}",
+                    request_id(state),
+                    length,
+                    max_length
+                );
+                return Some(create_response(state, StatusCode::UriTooLong, None));
+            }
+        }
+
+        if self.config.encoded_slashes() == EncodedSlashes::Reject
+            && has_encoded_slash(RequestPathSegments::borrow_from(state))
+        {
+            trace!("[{}] rejecting request with encoded slash in path", request_id(state));
+            return Some(create_response(state, StatusCode::BadRequest, None));
+        }
+
+        if self.config.dot_segments() == DotSegments::Reject
+            && RequestPathSegments::borrow_from(state).has_dot_segments()
+        {
+            trace!("[{}] rejecting request with dot segment in path", request_id(state));
+            return Some(create_response(state, StatusCode::BadRequest, None));
+        }
+
+        None
+    }
+}
+
+/// The length of the percent-decoded request path, counting a `/` before each segment.
+fn decoded_path_length(segments: &RequestPathSegments) -> usize {
+    segments
+        .segments()
+        .iter()
+        .skip(1)
+        .map(|segment| segment.val().len() + 1)
+        .sum()
+}
+
+/// Returns `true` if any segment of the request path contains `/` once decoded, or would contain
+/// `/` if decoded a second time.
+fn has_encoded_slash(segments: &RequestPathSegments) -> bool {
+    segments.segments().iter().skip(1).any(|segment| {
+        segment.val().contains('/')
+            || percent_decode(segment.val().as_bytes())
+                .decode_utf8_lossy()
+                .contains('/')
+    })
+}
+
+/// Headers which are never echoed in response to a `TRACE` request.
+const CREDENTIAL_HEADERS: [&str; 3] = ["Cookie", "Authorization", "Proxy-Authorization"];
+
+/// Creates the response to a `TRACE` request, echoing the request line and any headers which do
+/// not carry credentials.
+fn trace_echo(state: &State) -> Response {
+    let mut body = format!(
+        "TRACE {} {}\r\n",
+        Uri::borrow_from(state),
+        HttpVersion::borrow_from(state)
+    );
+
+    for header in Headers::borrow_from(state).iter() {
+        let name = header.name();
+        if !CREDENTIAL_HEADERS.iter().any(|h| name.eq_ignore_ascii_case(h)) {
+            body.push_str(&header.to_string());
+        }
+    }
+    body.push_str("\r\n");
+
+    let mime = "message/http".parse().unwrap();
+    create_response(state, StatusCode::Ok, Some((body.into_bytes(), mime)))
+}
+
+/// Returns `true` if the `Expect` header holds any expectation other than `100-continue`, which is
+/// the only expectation defined by RFC 7231.
+fn has_unknown_expectation(state: &State) -> bool {
+    match Headers::borrow_from(state).get_raw("Expect") {
+        Some(raw) => raw.iter().any(|line| {
+            String::from_utf8_lossy(line)
+                .split(',')
+                .any(|value| !value.trim().eq_ignore_ascii_case("100-continue"))
+        }),
+        None => false,
     }
 }
 
@@ -94,24 +395,46 @@ where
 mod tests {
     use super::*;
 
-    use hyper::{Method, StatusCode};
+    use futures::Stream;
+    use mime::TEXT_PLAIN;
     use tokio_core::reactor::Core;
 
-    use http::response::create_response;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::{Duration, SystemTime};
+
+    use hyper::header::{Allow, ContentType, RetryAfter};
+
+    use http::header::ServerTiming;
+    use http::response::retry_after::RetryAfterFormat;
+    use http::request::forwarded::ForwardedPrecedence;
     use router::builder::*;
-    use state::State;
+    use config::{HostRequirement, PreRoutingHook, TraceBehavior};
+    use router::strategy::OptionsAsterisk;
+    use shutdown::Shutdown;
+    use state::{is_secure, record_server_timing, request_host, StateData};
+    use test::TestServer;
 
     fn handler(state: State) -> (State, Response) {
         let res = create_response(&state, StatusCode::Accepted, None);
         (state, res)
     }
 
+    fn get(uri: &str) -> Request {
+        let mut req = Request::new(Method::Get, uri.parse().unwrap());
+        req.headers_mut().set(Host::new("localhost", None));
+        req
+    }
+
     #[test]
     fn new_handler_closure() {
         let mut core = Core::new().unwrap();
-        let service = GothamService::new(Arc::new(|| Ok(handler)), core.handle());
+        let service = GothamService::new(
+            Arc::new(|| Ok(handler)),
+            core.handle(),
+            Arc::new(ServerConfig::default()),
+        );
 
-        let req = Request::new(Method::Get, "http://localhost/".parse().unwrap());
+        let req = get("http://localhost/");
         let f = service
             .connect("127.0.0.1:10000".parse().unwrap())
             .call(req);
@@ -126,13 +449,613 @@ mod tests {
         });
 
         let mut core = Core::new().unwrap();
-        let service = GothamService::new(Arc::new(router), core.handle());
+        let service = GothamService::new(
+            Arc::new(router),
+            core.handle(),
+            Arc::new(ServerConfig::default()),
+        );
 
-        let req = Request::new(Method::Get, "http://localhost/".parse().unwrap());
+        let req = get("http://localhost/");
         let f = service
             .connect("127.0.0.1:10000".parse().unwrap())
             .call(req);
         let response = core.run(f).unwrap();
         assert_eq!(response.status(), StatusCode::Accepted);
     }
+
+    fn call_with_hook(hook: PreRoutingHook, path: &str) -> StatusCode {
+        call_with_hook_and_config(ServerConfig::default(), hook, path)
+    }
+
+    fn call_with_hook_and_config(
+        config: ServerConfig,
+        hook: PreRoutingHook,
+        path: &str,
+    ) -> StatusCode {
+        let router = build_simple_router(|route| {
+            route.get("/foo").to(handler);
+        });
+
+        let mut core = Core::new().unwrap();
+        let config = config.with_pre_routing_hook(hook);
+        let service = GothamService::new(Arc::new(router), core.handle(), Arc::new(config));
+
+        let f = service
+            .connect("127.0.0.1:10000".parse().unwrap())
+            .call(get(&format!("http://localhost{}", path)));
+        core.run(f).unwrap().status()
+    }
+
+    #[test]
+    fn pre_routing_hook_rewrites_path() {
+        let hook = PreRoutingHook::new(|state| {
+            let path = Uri::borrow_from(state).path().to_owned();
+            if let Some(rest) = path.strip_prefix("/v1") {
+                state.put::<Uri>(rest.parse().unwrap());
+            }
+            None
+        });
+
+        assert_eq!(call_with_hook(hook.clone(), "/v1/foo"), StatusCode::Accepted);
+        assert_eq!(call_with_hook(hook.clone(), "/foo"), StatusCode::Accepted);
+        assert_eq!(call_with_hook(hook, "/v2/foo"), StatusCode::NotFound);
+    }
+
+    #[test]
+    fn pre_routing_hook_rewrite_is_checked_against_path_limits() {
+        let hook = PreRoutingHook::new(|state| {
+            let rewritten = match Uri::borrow_from(state).path() {
+                "/slash" => "/foo/%2F",
+                "/dot" => "/foo/../foo",
+                "/long" => "/foo/aaaaaaaaaaaaaaaa",
+                _ => return None,
+            };
+            state.put::<Uri>(rewritten.parse().unwrap());
+            None
+        });
+        let config = ServerConfig::default()
+            .with_max_decoded_path_length(16)
+            .with_encoded_slashes(EncodedSlashes::Reject)
+            .with_dot_segments(DotSegments::Reject);
+        let call = |path| call_with_hook_and_config(config.clone(), hook.clone(), path);
+
+        assert_eq!(call("/foo"), StatusCode::Accepted);
+        assert_eq!(call("/slash"), StatusCode::BadRequest);
+        assert_eq!(call("/dot"), StatusCode::BadRequest);
+        assert_eq!(call("/long"), StatusCode::UriTooLong);
+    }
+
+    #[test]
+    fn pre_routing_hook_short_circuits() {
+        let hook = PreRoutingHook::new(|state| {
+            Some(create_response(state, StatusCode::MovedPermanently, None))
+        });
+
+        assert_eq!(call_with_hook(hook, "/foo"), StatusCode::MovedPermanently);
+    }
+
+    fn secure_handler(state: State) -> (State, Response) {
+        let status = if is_secure(&state) {
+            StatusCode::Ok
+        } else {
+            StatusCode::Accepted
+        };
+        let res = create_response(&state, status, None);
+        (state, res)
+    }
+
+    fn call_with_proto(config: ServerConfig, proto: Option<&str>) -> StatusCode {
+        let mut core = Core::new().unwrap();
+        let service = GothamService::new(
+            Arc::new(|| Ok(secure_handler)),
+            core.handle(),
+            Arc::new(config),
+        );
+
+        let mut req = get("http://localhost/");
+        if let Some(proto) = proto {
+            req.headers_mut().set_raw("X-Forwarded-Proto", proto);
+        }
+        let f = service
+            .connect("127.0.0.1:10000".parse().unwrap())
+            .call(req);
+        core.run(f).unwrap().status()
+    }
+
+    #[test]
+    fn plaintext_requests_are_not_secure() {
+        assert_eq!(
+            call_with_proto(ServerConfig::default(), None),
+            StatusCode::Accepted
+        );
+
+        let config =
+            ServerConfig::default().with_trusted_proxy_headers(ForwardedPrecedence::XForwarded);
+        assert_eq!(call_with_proto(config.clone(), None), StatusCode::Accepted);
+        assert_eq!(call_with_proto(config, Some("http")), StatusCode::Accepted);
+    }
+
+    #[test]
+    fn trusted_proxy_reports_secure_requests() {
+        let config =
+            ServerConfig::default().with_trusted_proxy_headers(ForwardedPrecedence::XForwarded);
+        assert_eq!(call_with_proto(config, Some("HTTPS")), StatusCode::Ok);
+    }
+
+    #[test]
+    fn ignores_proxy_header_elements_sent_by_client() {
+        fn handler(state: State) -> (State, Response) {
+            let body = format!("{} {:?}", is_secure(&state), request_host(&state));
+            let body = Some((body.into_bytes(), TEXT_PLAIN));
+            let res = create_response(&state, StatusCode::Ok, body);
+            (state, res)
+        }
+
+        let mut core = Core::new().unwrap();
+        let config =
+            ServerConfig::default().with_trusted_proxy_headers(ForwardedPrecedence::Forwarded);
+        let service = GothamService::new(Arc::new(|| Ok(handler)), core.handle(), Arc::new(config));
+
+        // The client sent the first element, and the trusted proxy appended the second.
+        let mut req = get("http://localhost/");
+        req.headers_mut().set_raw(
+            "Forwarded",
+            "proto=https;host=evil.example, for=192.0.2.43;proto=http;host=example.com",
+        );
+        let f = service
+            .connect("127.0.0.1:10000".parse().unwrap())
+            .call(req);
+        let body = core.run(f.and_then(|res| res.body().concat2())).unwrap();
+        assert_eq!(&body[..], &b"false Some(\"example.com\")"[..]);
+    }
+
+    #[test]
+    fn ignores_untrusted_proxy_headers() {
+        assert_eq!(
+            call_with_proto(ServerConfig::default(), Some("https")),
+            StatusCode::Accepted
+        );
+    }
+
+    #[test]
+    fn rejects_too_many_headers() {
+        let mut core = Core::new().unwrap();
+        let service = GothamService::new(
+            Arc::new(|| Ok(handler)),
+            core.handle(),
+            Arc::new(ServerConfig::default().with_max_headers(3)),
+        );
+
+        let mut req = get("http://localhost/");
+        req.headers_mut().set_raw("X-A", vec![b"1".to_vec(), b"2".to_vec()]);
+        let f = service
+            .connect("127.0.0.1:10000".parse().unwrap())
+            .call(req);
+        let response = core.run(f).unwrap();
+        assert_eq!(response.status(), StatusCode::Accepted);
+
+        let mut req = get("http://localhost/");
+        req.headers_mut().set_raw("X-A", vec![b"1".to_vec(), b"2".to_vec()]);
+        req.headers_mut().set_raw("X-B", "3");
+        let f = service
+            .connect("127.0.0.1:10000".parse().unwrap())
+            .call(req);
+        let response = core.run(f).unwrap();
+        assert_eq!(response.status(), StatusCode::RequestHeaderFieldsTooLarge);
+    }
+
+    #[test]
+    fn exposes_http_version() {
+        fn version(state: State) -> (State, Response) {
+            let body = format!("{}", HttpVersion::borrow_from(&state));
+            let body = Some((body.into_bytes(), TEXT_PLAIN));
+            let res = create_response(&state, StatusCode::Ok, body);
+            (state, res)
+        }
+
+        let test_server = TestServer::new(|| Ok(version)).unwrap();
+        let response = test_server
+            .client()
+            .get("http://localhost/")
+            .perform()
+            .unwrap();
+        assert_eq!(response.read_utf8_body().unwrap(), "HTTP/1.1");
+    }
+
+    fn call_without_host(config: ServerConfig, version: HttpVersion) -> StatusCode {
+        let mut core = Core::new().unwrap();
+        let service = GothamService::new(Arc::new(|| Ok(handler)), core.handle(), Arc::new(config));
+
+        let mut req = Request::new(Method::Get, "/".parse().unwrap());
+        req.set_version(version);
+        let f = service
+            .connect("127.0.0.1:10000".parse().unwrap())
+            .call(req);
+        core.run(f).unwrap().status()
+    }
+
+    #[test]
+    fn rejects_missing_host() {
+        assert_eq!(
+            call_without_host(ServerConfig::default(), HttpVersion::Http11),
+            StatusCode::BadRequest
+        );
+        assert_eq!(
+            call_without_host(ServerConfig::default(), HttpVersion::Http10),
+            StatusCode::Accepted
+        );
+
+        let config = ServerConfig::default().with_host_requirement(HostRequirement::Always);
+        assert_eq!(
+            call_without_host(config, HttpVersion::Http10),
+            StatusCode::BadRequest
+        );
+
+        let config = ServerConfig::default().with_host_requirement(HostRequirement::Never);
+        assert_eq!(
+            call_without_host(config, HttpVersion::Http11),
+            StatusCode::Accepted
+        );
+    }
+
+    #[test]
+    fn rejects_long_decoded_path() {
+        let mut core = Core::new().unwrap();
+        let config = ServerConfig::default().with_max_decoded_path_length(16);
+        let service = GothamService::new(Arc::new(|| Ok(handler)), core.handle(), Arc::new(config));
+
+        let mut call = |path: &str| {
+            let f = service
+                .connect("127.0.0.1:10000".parse().unwrap())
+                .call(get(&format!("http://localhost{}", path)));
+            core.run(f).unwrap().status()
+        };
+
+        // Each `%41` decodes to a single byte, so these are 15 and 17 bytes once decoded.
+        assert_eq!(call(&format!("/{}", "%41".repeat(14))), StatusCode::Accepted);
+        assert_eq!(call(&format!("/{}/", "%41".repeat(16))), StatusCode::UriTooLong);
+        assert_eq!(call(&format!("/a/{}", "%41".repeat(13))), StatusCode::Accepted);
+        assert_eq!(call(&format!("/a/{}", "%41".repeat(15))), StatusCode::UriTooLong);
+    }
+
+    fn segments_handler(state: State) -> (State, Response) {
+        let body = RequestPathSegments::borrow_from(&state)
+            .segments()
+            .iter()
+            .skip(1)
+            .map(|segment| segment.val().to_owned())
+            .collect::<Vec<_>>()
+            .join("|");
+
+        let body = (body.into_bytes(), TEXT_PLAIN);
+        let res = create_response(&state, StatusCode::Ok, Some(body));
+        (state, res)
+    }
+
+    fn call_with_path(config: ServerConfig, path: &str) -> (StatusCode, String) {
+        let mut core = Core::new().unwrap();
+        let service = GothamService::new(
+            Arc::new(|| Ok(segments_handler)),
+            core.handle(),
+            Arc::new(config),
+        );
+
+        let f = service
+            .connect("127.0.0.1:10000".parse().unwrap())
+            .call(get(&format!("http://localhost{}", path)));
+        let res = core.run(f).unwrap();
+        let status = res.status();
+        let body = core.run(res.body().concat2()).unwrap();
+        (status, String::from_utf8(body.to_vec()).unwrap())
+    }
+
+    #[test]
+    fn decodes_path_segments_once() {
+        let config = ServerConfig::default();
+        assert_eq!(
+            call_with_path(config.clone(), "/files/a%252Fb"),
+            (StatusCode::Ok, "files|a%2Fb".to_owned())
+        );
+        assert_eq!(
+            call_with_path(config, "/files/a%2Fb"),
+            (StatusCode::Ok, "files|a/b".to_owned())
+        );
+    }
+
+    #[test]
+    fn rejects_encoded_slashes_when_configured() {
+        let config = ServerConfig::default().with_encoded_slashes(EncodedSlashes::Reject);
+        assert_eq!(
+            call_with_path(config.clone(), "/files/a%252Fb").0,
+            StatusCode::BadRequest
+        );
+        assert_eq!(
+            call_with_path(config.clone(), "/files/a%2fb").0,
+            StatusCode::BadRequest
+        );
+        assert_eq!(
+            call_with_path(config, "/files/a%2520b"),
+            (StatusCode::Ok, "files|a%20b".to_owned())
+        );
+    }
+
+    #[test]
+    fn normalizes_dot_segments() {
+        let config = ServerConfig::default();
+        assert_eq!(
+            call_with_path(config.clone(), "/a/%2e%2e/b"),
+            (StatusCode::Ok, "b".to_owned())
+        );
+        assert_eq!(
+            call_with_path(config.clone(), "/a/./b/%2E/c/../d"),
+            (StatusCode::Ok, "a|b|d".to_owned())
+        );
+        assert_eq!(
+            call_with_path(config, "/../%2e%2e/files/..."),
+            (StatusCode::Ok, "files|...".to_owned())
+        );
+    }
+
+    #[test]
+    fn rejects_dot_segments_when_configured() {
+        let config = ServerConfig::default().with_dot_segments(DotSegments::Reject);
+        assert_eq!(
+            call_with_path(config.clone(), "/a/%2e%2e/b").0,
+            StatusCode::BadRequest
+        );
+        assert_eq!(call_with_path(config.clone(), "/a/./b").0, StatusCode::BadRequest);
+        assert_eq!(
+            call_with_path(config, "/a/..b/.c"),
+            (StatusCode::Ok, "a|..b|.c".to_owned())
+        );
+    }
+
+    fn call_trace(config: ServerConfig) -> Response {
+        let mut core = Core::new().unwrap();
+        let service = GothamService::new(Arc::new(|| Ok(handler)), core.handle(), Arc::new(config));
+
+        let mut req = get("http://localhost/trace?a=1");
+        req.set_method(Method::Trace);
+        req.headers_mut().set_raw("X-Debug", "yes");
+        req.headers_mut().set_raw("Cookie", "session=secret");
+        req.headers_mut().set_raw("Authorization", "Bearer secret");
+        let f = service
+            .connect("127.0.0.1:10000".parse().unwrap())
+            .call(req);
+        core.run(f).unwrap()
+    }
+
+    #[test]
+    fn rejects_trace_by_default() {
+        let response = call_trace(ServerConfig::default());
+        assert_eq!(response.status(), StatusCode::MethodNotAllowed);
+
+        let config = ServerConfig::default().with_trace_behavior(TraceBehavior::Application);
+        let response = call_trace(config);
+        assert_eq!(response.status(), StatusCode::Accepted);
+    }
+
+    #[test]
+    fn echoes_trace_without_credentials() {
+        let config = ServerConfig::default().with_trace_behavior(TraceBehavior::Echo);
+        let response = call_trace(config);
+        assert_eq!(response.status(), StatusCode::Ok);
+        assert_eq!(
+            response.headers().get::<ContentType>().unwrap().0.as_ref(),
+            "message/http"
+        );
+
+        let body = response.body().concat2().wait().unwrap();
+        let body = String::from_utf8(body.to_vec()).unwrap();
+        assert!(body.starts_with("TRACE http://localhost/trace?a=1 HTTP/1.1\r\n"), "{}", body);
+        assert!(body.contains("X-Debug: yes\r\n"), "{}", body);
+        assert!(body.contains("Host: localhost\r\n"), "{}", body);
+        assert!(!body.contains("secret"), "{}", body);
+        assert!(body.ends_with("\r\n\r\n"), "{}", body);
+    }
+
+    fn call_with_expect(config: ServerConfig, expect: &str) -> StatusCode {
+        let mut core = Core::new().unwrap();
+        let service = GothamService::new(Arc::new(|| Ok(handler)), core.handle(), Arc::new(config));
+
+        let mut req = get("http://localhost/");
+        req.headers_mut().set_raw("Expect", expect);
+        let f = service
+            .connect("127.0.0.1:10000".parse().unwrap())
+            .call(req);
+        core.run(f).unwrap().status()
+    }
+
+    #[test]
+    fn rejects_unknown_expectations() {
+        assert_eq!(
+            call_with_expect(ServerConfig::default(), "something-weird"),
+            StatusCode::ExpectationFailed
+        );
+        assert_eq!(
+            call_with_expect(ServerConfig::default(), "100-Continue"),
+            StatusCode::Accepted
+        );
+
+        let config = ServerConfig::default().with_reject_unknown_expectations(false);
+        assert_eq!(
+            call_with_expect(config, "something-weird"),
+            StatusCode::Accepted
+        );
+    }
+
+    #[test]
+    fn responds_unavailable_while_draining() {
+        let shutdown = Shutdown::new();
+        let config = ServerConfig::default()
+            .with_shutdown(shutdown.clone())
+            .with_drain_behavior(DrainBehavior::RespondUnavailable);
+
+        let mut core = Core::new().unwrap();
+        let service = GothamService::new(Arc::new(|| Ok(handler)), core.handle(), Arc::new(config));
+        let service = service.connect("127.0.0.1:10000".parse().unwrap());
+
+        let req = get("http://localhost/");
+        let response = core.run(service.call(req)).unwrap();
+        assert_eq!(response.status(), StatusCode::Accepted);
+
+        shutdown.drain();
+
+        let req = get("http://localhost/");
+        let response = core.run(service.call(req)).unwrap();
+        assert_eq!(response.status(), StatusCode::ServiceUnavailable);
+        assert_eq!(response.headers().get::<Connection>(), Some(&Connection::close()));
+    }
+
+    fn drained_retry_after(format: RetryAfterFormat) -> Option<RetryAfter> {
+        let shutdown = Shutdown::new();
+        let config = ServerConfig::default()
+            .with_shutdown(shutdown.clone())
+            .with_drain_behavior(DrainBehavior::RespondUnavailable)
+            .with_retry_after(Duration::from_secs(30))
+            .with_retry_after_format(format);
+
+        let mut core = Core::new().unwrap();
+        let service = GothamService::new(Arc::new(|| Ok(handler)), core.handle(), Arc::new(config));
+        shutdown.drain();
+
+        let f = service
+            .connect("127.0.0.1:10000".parse().unwrap())
+            .call(get("http://localhost/"));
+        let response = core.run(f).unwrap();
+        assert_eq!(response.status(), StatusCode::ServiceUnavailable);
+        response.headers().get::<RetryAfter>().cloned()
+    }
+
+    #[test]
+    fn sends_retry_after_while_draining() {
+        assert_eq!(
+            drained_retry_after(RetryAfterFormat::DeltaSeconds),
+            Some(RetryAfter::Delay(Duration::from_secs(30)))
+        );
+
+        match drained_retry_after(RetryAfterFormat::HttpDate) {
+            Some(RetryAfter::DateTime(date)) => {
+                let date: SystemTime = date.into();
+                let delay = date.duration_since(SystemTime::now()).unwrap();
+                assert!(delay > Duration::from_secs(28) && delay <= Duration::from_secs(30));
+            }
+            other => panic!("unexpected Retry-After: {:?}", other),
+        }
+    }
+
+    fn timed_handler(mut state: State) -> (State, Response) {
+        record_server_timing(&mut state, "db", Duration::from_millis(3));
+        record_server_timing(&mut state, "not a token", Duration::from_millis(1));
+        let res = create_response(&state, StatusCode::Accepted, None);
+        (state, res)
+    }
+
+    fn call_timed(config: ServerConfig) -> Response {
+        let mut core = Core::new().unwrap();
+        let service = GothamService::new(
+            Arc::new(|| Ok(timed_handler)),
+            core.handle(),
+            Arc::new(config),
+        );
+
+        let req = get("http://localhost/");
+        let f = service
+            .connect("127.0.0.1:10000".parse().unwrap())
+            .call(req);
+        core.run(f).unwrap()
+    }
+
+    #[test]
+    fn adds_server_timing_when_enabled() {
+        let response = call_timed(ServerConfig::default().with_server_timing(true));
+        let raw = response.headers().get_raw("Server-Timing").unwrap();
+        let raw = ::std::str::from_utf8(raw.one().unwrap()).unwrap();
+        assert!(raw.starts_with("db;dur=3.000, total;dur="));
+
+        let timing = response.headers().get::<ServerTiming>().unwrap();
+        assert_eq!(timing.0.len(), 2);
+        assert_eq!(timing.0[0].name, "db");
+        assert_eq!(timing.0[0].duration, Some(3.0));
+        assert_eq!(timing.0[1].name, "total");
+        assert!(timing.0[1].duration.is_some());
+    }
+
+    #[test]
+    fn omits_server_timing_by_default() {
+        let response = call_timed(ServerConfig::default());
+        assert!(response.headers().get::<ServerTiming>().is_none());
+    }
+
+    fn call_options_asterisk(config: ServerConfig) -> Response {
+        let router = build_simple_router(|route| {
+            route.get("/").to(handler);
+            route.post("/products").to(handler);
+            route.delete("/products/:id").to(handler);
+            route.get("/products/:id").to(handler);
+        });
+
+        let mut core = Core::new().unwrap();
+        let service = GothamService::new(Arc::new(router), core.handle(), Arc::new(config));
+
+        let mut req = Request::new(Method::Options, "*".parse().unwrap());
+        req.headers_mut().set(Host::new("localhost", None));
+        let f = service
+            .connect("127.0.0.1:10000".parse().unwrap())
+            .call(req);
+        core.run(f).unwrap()
+    }
+
+    #[test]
+    fn answers_options_asterisk_with_capabilities() {
+        let response = call_options_asterisk(ServerConfig::default());
+        assert_eq!(response.status(), StatusCode::Ok);
+        assert_eq!(
+            response.headers().get::<Allow>(),
+            Some(&Allow(vec![
+                Method::Get,
+                Method::Post,
+                Method::Delete,
+                Method::Options,
+            ]))
+        );
+    }
+
+    #[test]
+    fn routes_options_asterisk_when_configured() {
+        let config = ServerConfig::default().with_options_asterisk(OptionsAsterisk::Application);
+        let response = call_options_asterisk(config);
+        assert_eq!(response.status(), StatusCode::NotFound);
+        assert!(response.headers().get::<Allow>().is_none());
+    }
+
+    #[derive(Clone)]
+    struct Visits(Arc<AtomicUsize>);
+
+    impl StateData for Visits {}
+
+    fn count_visit(state: State) -> (State, Response) {
+        let visits = Visits::borrow_from(&state).0.fetch_add(1, Ordering::SeqCst) + 1;
+        let body = Some((visits.to_string().into_bytes(), TEXT_PLAIN));
+        let res = create_response(&state, StatusCode::Ok, body);
+        (state, res)
+    }
+
+    #[test]
+    fn puts_app_data_into_every_request() {
+        let visits = Visits(Arc::new(AtomicUsize::new(0)));
+        let config = ServerConfig::default().with_app_data(visits.clone());
+        let test_server = TestServer::with_config(|| Ok(count_visit), config).unwrap();
+
+        for expected in &["1", "2", "3"] {
+            let response = test_server
+                .client()
+                .get("http://localhost/")
+                .perform()
+                .unwrap();
+            assert_eq!(response.read_utf8_body().unwrap(), *expected);
+        }
+
+        assert_eq!(visits.0.load(Ordering::SeqCst), 3);
+    }
 }
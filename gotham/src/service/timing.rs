@@ -6,6 +6,7 @@ use chrono::prelude::*;
 use hyper::Response;
 
 use state::{request_id, State};
+use state::server_timing::server_timing_header;
 use http::header::XRuntimeMicroseconds;
 
 /// Used by `GothamService` to time requests. The `elapsed` function returns the elapsed time
@@ -64,6 +65,20 @@ impl Timing {
             Timing::Invalid => response,
         }
     }
+
+    /// Converts a `Response` into a new `Response` with the `Server-Timing` header included,
+    /// if it has been enabled for this request.
+    pub(super) fn add_server_timing(&self, state: &State, response: Response) -> Response {
+        let total = match *self {
+            Timing::Microseconds(i) => Some(i as f64 / 1000.0),
+            Timing::Invalid => None,
+        };
+
+        match server_timing_header(state, total) {
+            Some(header) => response.with_header(header),
+            None => response,
+        }
+    }
 }
 
 impl Display for Timing {
@@ -11,7 +11,9 @@ use futures::Async;
 use futures::future::{self, Future, FutureResult};
 
 use handler::{Handler, HandlerError, IntoResponse, NewHandler};
+use handler::logging::response_log_level;
 use service::timing::Timer;
+use service::validate::validate_response;
 use state::{request_id, State};
 
 pub(super) fn call_handler<T>(
@@ -34,8 +36,14 @@ where
                 let AssertUnwindSafe(state) = state;
 
                 let f = handler.handle(state).then(move |result| match result {
-                    Ok((state, res)) => finalize_success_response(timer, state, res),
-                    Err((state, err)) => finalize_error_response(timer, state, err),
+                    Ok((state, res)) => future::Either::A(
+                        validate_response(state, res).and_then(move |(state, res)| {
+                            finalize_success_response(timer, state, res)
+                        }),
+                    ),
+                    Err((state, err)) => {
+                        future::Either::B(finalize_error_response(timer, state, err))
+                    }
                 });
 
                 Box::new(f) as Box<ResponseFuture>
@@ -50,7 +58,7 @@ where
                 .catch_unwind()
                 .then(finalize_catch_unwind_response),
         ),
-        Err(_) => Box::new(finalize_panic_response(timer)),
+        Err(payload) => Box::new(finalize_panic_response(timer, &*payload)),
     }
 }
 
@@ -61,14 +69,18 @@ fn finalize_success_response(
 ) -> FutureResult<Response, hyper::Error> {
     let timing = timer.elapsed(&state);
 
-    info!(
-        "[RESPONSE][{}][{}][{}][{}]",
-        request_id(&state),
-        response.version(),
-        response.status(),
-        timing
-    );
+    if let Some(level) = response_log_level(&state) {
+        log!(
+            level,
+            "[RESPONSE][{}][{}][{}][{}]",
+            request_id(&state),
+            response.version(),
+            response.status(),
+            timing
+        );
+    }
 
+    let response = timing.add_server_timing(&state, response);
     future::ok(timing.add_to_response(response))
 }
 
@@ -94,14 +106,19 @@ fn finalize_error_response(
         );
     }
 
-    future::ok(err.into_response(&state))
+    let response = err.into_response(&state);
+    future::ok(timing.add_server_timing(&state, response))
 }
 
-fn finalize_panic_response(timer: Timer) -> FutureResult<Response, hyper::Error> {
+fn finalize_panic_response(
+    timer: Timer,
+    payload: &(dyn Any + Send),
+) -> FutureResult<Response, hyper::Error> {
     let timing = timer.elapsed_no_logging();
 
     error!(
-        "[PANIC][A panic occurred while invoking the handler][{}]",
+        "[PANIC][A panic occurred while invoking the handler: {}][{}]",
+        panic_message(payload),
         timing
     );
 
@@ -111,23 +128,36 @@ fn finalize_panic_response(timer: Timer) -> FutureResult<Response, hyper::Error>
 fn finalize_catch_unwind_response(
     result: Result<Result<Response, hyper::Error>, Box<Any + Send>>,
 ) -> FutureResult<Response, hyper::Error> {
-    let response = result
-        .unwrap_or_else(|_| {
-            let e = io::Error::new(
-                io::ErrorKind::Other,
-                "Attempting to poll the future caused a panic",
-            );
-
-            Err(hyper::Error::Io(e))
-        })
-        .unwrap_or_else(|_| {
+    let response = match result {
+        Ok(Ok(response)) => response,
+        Ok(Err(_)) => {
             error!("[PANIC][A panic occurred while polling the future]");
             Response::new().with_status(StatusCode::InternalServerError)
-        });
+        }
+        Err(payload) => {
+            error!(
+                "[PANIC][A panic occurred while polling the future: {}]",
+                panic_message(&*payload)
+            );
+            Response::new().with_status(StatusCode::InternalServerError)
+        }
+    };
 
     future::ok(response)
 }
 
+/// Extracts the message given to `panic!`, which is a `&str` or a `String` unless the panic was
+/// raised with some other value, in which case a generic message is used.
+fn panic_message(payload: &(dyn Any + Send)) -> &str {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message
+    } else {
+        "panicked with a value other than a string"
+    }
+}
+
 enum UnwindSafeFuture<F>
 where
     F: Future<Error = hyper::Error>,
@@ -182,6 +212,7 @@ mod tests {
     use http::response::create_response;
     use state::set_request_id;
     use handler::{HandlerFuture, IntoHandlerError};
+    use test::logged;
 
     #[test]
     fn success() {
@@ -304,4 +335,42 @@ mod tests {
         let response = r.wait().unwrap();
         assert_eq!(response.status(), StatusCode::InternalServerError);
     }
+
+    #[test]
+    fn logs_panic_message() {
+        logged::capture();
+
+        let new_handler = || {
+            Ok(|_| -> Box<HandlerFuture> {
+                let id = 42;
+                panic!("unable to load widget {}", id)
+            })
+        };
+        let r = call_handler(&new_handler, AssertUnwindSafe(State::new()));
+        assert_eq!(r.wait().unwrap().status(), StatusCode::InternalServerError);
+
+        let new_handler = || {
+            Ok(|_| {
+                let f = future::lazy(|| -> Result<_, _> { panic!("widget store unavailable") });
+                Box::new(f) as Box<HandlerFuture>
+            })
+        };
+        let r = call_handler(&new_handler, AssertUnwindSafe(State::new()));
+        assert_eq!(r.wait().unwrap().status(), StatusCode::InternalServerError);
+
+        let lines = logged::lines_starting_with("[PANIC]");
+        let logged = |message: &str| lines.iter().any(|(_, line)| line.contains(message));
+        assert!(logged("[A panic occurred while invoking the handler: unable to load widget 42]"));
+        assert!(logged("[A panic occurred while polling the future: widget store unavailable]"));
+    }
+
+    #[test]
+    fn describes_panics_without_a_message() {
+        assert_eq!(panic_message(&"static message"), "static message");
+        assert_eq!(panic_message(&String::from("formatted message")), "formatted message");
+        assert_eq!(
+            panic_message(&42),
+            "panicked with a value other than a string"
+        );
+    }
 }
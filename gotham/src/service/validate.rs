@@ -0,0 +1,409 @@
+//! Defines the checks applied to responses from the application, as configured by
+//! `ResponseValidation`.
+
+use std::{io, mem};
+
+use futures::{future, Async, Future, Poll, Sink, Stream};
+use hyper::{self, Body, Chunk, Headers, Method, Response, StatusCode};
+use hyper::header::{Connection, ContentLength, TransferEncoding};
+use tokio_core::reactor::Handle;
+
+use config::ResponseValidation;
+use http::response::create_response;
+use state::{request_id, FromState, State};
+
+pub(super) type ValidationFuture = dyn Future<Item = (State, Response), Error = hyper::Error>;
+
+/// Checks `res` for contradictions, resolving with the response to send in its place.
+///
+/// Only `ResponseValidation::Reject` buffers a body to compare it with `Content-Length`, as it
+/// must replace the response before it is sent. Otherwise the body is checked as it streams.
+pub(super) fn validate_response(state: State, res: Response) -> Box<ValidationFuture> {
+    let validation = ResponseValidation::try_borrow_from(&state)
+        .cloned()
+        .unwrap_or_default();
+
+    if validation == ResponseValidation::Off {
+        return Box::new(future::ok((state, res)));
+    }
+
+    let has_body = res.body_ref().is_some_and(|body| !body.is_empty());
+    let content_length = res.headers().get::<ContentLength>().map(|len| len.0);
+
+    match (has_body, content_length) {
+        (true, Some(content_length)) if validation == ResponseValidation::Reject => {
+            let mut res = res;
+            let status = res.status();
+            let headers = mem::replace(res.headers_mut(), Headers::new());
+            let f = res.body().concat2().map(move |body| {
+                let mut problems = header_problems(&state, status, &headers, true);
+                let mismatched = body.len() as u64 != content_length;
+                if mismatched {
+                    problems.push(format!(
+                        "Content-Length is {} but the body is {} bytes",
+                        content_length,
+                        body.len()
+                    ));
+                }
+
+                let res = Response::new()
+                    .with_status(status)
+                    .with_headers(headers)
+                    .with_body(body);
+                resolve(validation, state, res, problems, mismatched)
+            });
+            Box::new(f)
+        }
+        (true, Some(content_length)) => {
+            let problems = header_problems(&state, res.status(), res.headers(), true);
+            let (state, res) = resolve(validation, state, res, problems, false);
+            let res = check_length(&state, res, content_length);
+            Box::new(future::ok((state, res)))
+        }
+        (has_body, content_length) => {
+            let mut problems = header_problems(&state, res.status(), res.headers(), has_body);
+            let mut mismatched = false;
+            if let Some(content_length) = content_length {
+                if !has_body && content_length > 0 && !omits_body(&state, res.status()) {
+                    mismatched = true;
+                    problems.push(format!(
+                        "Content-Length is {} but there is no body",
+                        content_length
+                    ));
+                }
+            }
+            Box::new(future::ok(resolve(validation, state, res, problems, mismatched)))
+        }
+    }
+}
+
+/// Finds the problems which are apparent from the status and headers of the response.
+fn header_problems(
+    state: &State,
+    status: StatusCode,
+    headers: &Headers,
+    has_body: bool,
+) -> Vec<String> {
+    let mut problems = Vec::new();
+
+    if has_body {
+        if forbids_body(status) {
+            problems.push(format!("a body is not permitted with status {}", status));
+        } else if Method::try_borrow_from(state) == Some(&Method::Head) {
+            problems.push("a body is not permitted in response to HEAD".to_owned());
+        }
+    }
+
+    if headers.has::<ContentLength>() && headers.has::<TransferEncoding>() {
+        problems.push("both Content-Length and Transfer-Encoding are set".to_owned());
+    }
+
+    problems
+}
+
+/// Returns `true` if a response with `status` must not carry a body.
+fn forbids_body(status: StatusCode) -> bool {
+    status.is_informational()
+        || status == StatusCode::NoContent
+        || status == StatusCode::NotModified
+}
+
+/// Returns `true` if a response with `status` to the request in `state` describes a body which
+/// it does not carry, and so may have a `Content-Length` header without a body.
+fn omits_body(state: &State, status: StatusCode) -> bool {
+    status == StatusCode::NotModified || Method::try_borrow_from(state) == Some(&Method::Head)
+}
+
+/// Streams the body of `res` as given, ending it with an error if it turns out to differ in
+/// length from `content_length`. The error aborts the response, so that hyper closes the
+/// connection rather than leaving the client to misread the next response.
+fn check_length(state: &State, res: Response, content_length: u64) -> Response {
+    let mut res = res;
+    let status = res.status();
+    let headers = mem::replace(res.headers_mut(), Headers::new());
+
+    let checked = CheckedLength {
+        body: res.body(),
+        request_id: request_id(state).to_owned(),
+        content_length,
+        received: 0,
+        finished: false,
+    };
+
+    let (tx, body) = Body::pair();
+    let id = request_id(state).to_owned();
+    let forward = tx
+        .sink_map_err(move |_| trace!("[{}] client disconnected from checked response", id))
+        .send_all(checked.then(Ok))
+        .then(|_| Ok(()));
+    Handle::borrow_from(state).spawn(forward);
+
+    Response::new()
+        .with_status(status)
+        .with_headers(headers)
+        .with_body(body)
+}
+
+/// Passes on the chunks of a response body, failing once they exceed `content_length` in total,
+/// or end short of it.
+struct CheckedLength {
+    body: Body,
+    request_id: String,
+    content_length: u64,
+    received: u64,
+    finished: bool,
+}
+
+impl CheckedLength {
+    fn mismatch(&mut self, description: &str) -> hyper::Error {
+        self.finished = true;
+        let problem = format!(
+            "Content-Length is {} but the body is {} {} bytes",
+            self.content_length, description, self.received
+        );
+        error!("[{}] invalid response: {}", self.request_id, problem);
+        warn!(
+            "[{}] aborting the invalid response, closing the connection",
+            self.request_id
+        );
+        hyper::Error::Io(io::Error::new(io::ErrorKind::InvalidData, problem))
+    }
+}
+
+impl Stream for CheckedLength {
+    type Item = Chunk;
+    type Error = hyper::Error;
+
+    fn poll(&mut self) -> Poll<Option<Chunk>, hyper::Error> {
+        if self.finished {
+            return Ok(Async::Ready(None));
+        }
+
+        match self.body.poll()? {
+            Async::NotReady => Ok(Async::NotReady),
+            Async::Ready(Some(chunk)) => {
+                self.received += chunk.len() as u64;
+                if self.received > self.content_length {
+                    return Err(self.mismatch("at least"));
+                }
+                Ok(Async::Ready(Some(chunk)))
+            }
+            Async::Ready(None) if self.received < self.content_length => {
+                Err(self.mismatch("only"))
+            }
+            Async::Ready(None) => Ok(Async::Ready(None)),
+        }
+    }
+}
+
+/// Logs each problem found with the response, and replaces it if configured to reject it.
+///
+/// A response sent as given when its body does not match its `Content-Length` header (as
+/// indicated by `mismatched`) is marked to close the connection once sent, as the client cannot
+/// know where the next response on the connection would begin.
+fn resolve(
+    validation: ResponseValidation,
+    state: State,
+    res: Response,
+    problems: Vec<String>,
+    mismatched: bool,
+) -> (State, Response) {
+    if problems.is_empty() {
+        return (state, res);
+    }
+
+    for problem in &problems {
+        error!("[{}] invalid response: {}", request_id(&state), problem);
+    }
+
+    match validation {
+        ResponseValidation::Reject => {
+            let res = create_response(&state, StatusCode::InternalServerError, None);
+            (state, res)
+        }
+        _ if mismatched => {
+            warn!(
+                "[{}] closing the connection after the invalid response",
+                request_id(&state)
+            );
+            let mut res = res;
+            res.headers_mut().set(Connection::close());
+            (state, res)
+        }
+        _ => (state, res),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::{Arc, Mutex};
+
+    use config::ServerConfig;
+    use test::TestServer;
+
+    fn inconsistent(state: State) -> (State, Response) {
+        let res = Response::new()
+            .with_status(StatusCode::Ok)
+            .with_header(ContentLength(0))
+            .with_body(Body::from("unexpected"));
+        (state, res)
+    }
+
+    fn consistent(state: State) -> (State, Response) {
+        let res = Response::new()
+            .with_status(StatusCode::Ok)
+            .with_header(ContentLength(8))
+            .with_body(Body::from("expected"));
+        (state, res)
+    }
+
+    fn short_body(state: State) -> (State, Response) {
+        let res = Response::new()
+            .with_status(StatusCode::Ok)
+            .with_header(ContentLength(20))
+            .with_body(Body::from("short"));
+        (state, res)
+    }
+
+    fn no_content_with_body(state: State) -> (State, Response) {
+        let res = Response::new()
+            .with_status(StatusCode::NoContent)
+            .with_body(Body::from("unexpected"));
+        (state, res)
+    }
+
+    fn status(
+        handler: fn(State) -> (State, Response),
+        validation: ResponseValidation,
+    ) -> StatusCode {
+        let config = ServerConfig::default().with_response_validation(validation);
+        let test_server = TestServer::with_config(move || Ok(handler), config).unwrap();
+        test_server
+            .client()
+            .get("http://localhost/")
+            .perform()
+            .unwrap()
+            .status()
+    }
+
+    #[test]
+    fn rejects_inconsistent_response() {
+        assert_eq!(
+            status(inconsistent, ResponseValidation::Reject),
+            StatusCode::InternalServerError
+        );
+        assert_eq!(
+            status(no_content_with_body, ResponseValidation::Reject),
+            StatusCode::InternalServerError
+        );
+    }
+
+    #[test]
+    fn passes_consistent_response() {
+        let config = ServerConfig::default().with_response_validation(ResponseValidation::Reject);
+        let test_server = TestServer::with_config(|| Ok(consistent), config).unwrap();
+        let response = test_server
+            .client()
+            .get("http://localhost/")
+            .perform()
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::Ok);
+        assert_eq!(response.read_utf8_body().unwrap(), "expected");
+    }
+
+    #[test]
+    fn logs_inconsistent_response() {
+        assert_eq!(status(inconsistent, ResponseValidation::Log), StatusCode::Ok);
+    }
+
+    #[test]
+    fn closes_connection_after_body_length_mismatch() {
+        let config = ServerConfig::default().with_response_validation(ResponseValidation::Log);
+        let test_server = TestServer::with_config(|| Ok(short_body), config).unwrap();
+        let client = test_server.client();
+
+        let response = client.clone().get("http://localhost/").perform().unwrap();
+        assert_eq!(response.status(), StatusCode::Ok);
+        assert!(response.read_body().is_err());
+
+        let response = client.clone().get("http://localhost/").perform().unwrap();
+        assert_eq!(response.status(), StatusCode::Ok);
+        assert_eq!(test_server.connections(), 2);
+    }
+
+    #[test]
+    fn streams_body_under_log() {
+        let senders = Arc::new(Mutex::new(Vec::new()));
+        let new_handler = {
+            let senders = senders.clone();
+            move || {
+                let senders = senders.clone();
+                Ok(move |state: State| {
+                    let (tx, body) = Body::pair();
+                    senders.lock().unwrap().push(tx);
+                    let res = Response::new()
+                        .with_status(StatusCode::Ok)
+                        .with_header(ContentLength(8))
+                        .with_body(body);
+                    (state, res)
+                })
+            }
+        };
+
+        let config = ServerConfig::default().with_response_validation(ResponseValidation::Log);
+        let test_server = TestServer::with_config(new_handler, config).unwrap();
+
+        // The status line is sent before the body is complete.
+        let response = test_server
+            .client()
+            .get("http://localhost/")
+            .perform()
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::Ok);
+
+        let mut tx = senders.lock().unwrap().pop().unwrap();
+        tx.try_send(Ok(Chunk::from("expected"))).unwrap();
+        drop(tx);
+        assert_eq!(response.read_utf8_body().unwrap(), "expected");
+    }
+
+    #[test]
+    fn keeps_connection_alive_after_valid_response() {
+        let config = ServerConfig::default().with_response_validation(ResponseValidation::Log);
+        let test_server = TestServer::with_config(|| Ok(consistent), config).unwrap();
+        let client = test_server.client();
+
+        for _ in 0..2 {
+            let response = client.clone().get("http://localhost/").perform().unwrap();
+            assert!(response.headers().get::<Connection>().is_none());
+            assert_eq!(response.read_utf8_body().unwrap(), "expected");
+        }
+        assert_eq!(test_server.connections(), 1);
+    }
+
+    #[test]
+    fn finds_problems() {
+        let mut state = State::new();
+        state.put(Method::Head);
+
+        let mut headers = Headers::new();
+        headers.set(ContentLength(4));
+        headers.set(TransferEncoding::chunked());
+
+        assert_eq!(
+            header_problems(&state, StatusCode::Ok, &headers, true),
+            vec![
+                "a body is not permitted in response to HEAD".to_owned(),
+                "both Content-Length and Transfer-Encoding are set".to_owned(),
+            ]
+        );
+        assert_eq!(
+            header_problems(&state, StatusCode::NotModified, &Headers::new(), true),
+            vec!["a body is not permitted with status 304 Not Modified".to_owned()]
+        );
+        assert!(header_problems(&state, StatusCode::Ok, &Headers::new(), false).is_empty());
+    }
+}
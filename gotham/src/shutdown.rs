@@ -0,0 +1,305 @@
+//! Defines the `Shutdown` handle, which is used to gracefully stop a running Gotham application.
+//!
+//! When a server begins draining, it stops accepting new connections and waits for in-flight
+//! connections to complete, up to the timeout configured via
+//! `ServerConfig::with_shutdown_timeout`. Responses sent while draining carry `Connection: close`
+//! so that clients do not reuse the connection, and idle kept-alive connections are closed as
+//! soon as draining begins, rather than holding the server open until the timeout.
+//!
+//! Combined with `ServerConfig::with_reuse_port`, this allows a zero-downtime restart: the new
+//! process binds the same address and begins accepting, and the old process is then told to
+//! drain (e.g. via `signal_drain`).
+//!
+//! Whether the listening socket is closed while draining is set via
+//! `ServerConfig::with_drain_behavior`.
+
+use std::cell::Cell;
+use std::io;
+use std::rc::Rc;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+use futures::{future, Async, Future, Poll, Stream};
+use futures::future::Shared;
+use hyper;
+use hyper::server::{Connection, Service};
+use hyper::{Request, Response};
+use tokio_core::reactor::{Handle, Interval};
+use tokio_io::{AsyncRead, AsyncWrite};
+
+#[cfg(unix)]
+use libc;
+
+/// How often a running server checks whether draining has begun, or has completed.
+const POLL_INTERVAL_MILLIS: u64 = 50;
+
+/// Set by the signal handler installed via `Shutdown::drain_on_signal`.
+static SIGNALLED: AtomicBool = AtomicBool::new(false);
+
+/// A handle used to tell a running Gotham application to stop accepting connections and drain.
+///
+/// `Shutdown` is cheaply cloneable, and all clones refer to the same server. Pass a clone to the
+/// server via `ServerConfig::with_shutdown` and keep another to trigger draining.
+///
+/// # Examples
+///
+/// ```rust
+/// # extern crate gotham;
+/// #
+/// # use gotham::config::ServerConfig;
+/// # use gotham::shutdown::Shutdown;
+/// #
+/// # fn main() {
+/// let shutdown = Shutdown::new();
+/// let config = ServerConfig::default().with_shutdown(shutdown.clone());
+/// # drop(config);
+///
+/// // Later, from any thread:
+/// shutdown.drain();
+/// assert!(shutdown.is_draining());
+/// # }
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct Shutdown {
+    inner: Arc<ShutdownInner>,
+}
+
+#[derive(Debug, Default)]
+struct ShutdownInner {
+    draining: AtomicBool,
+    on_signal: AtomicBool,
+}
+
+impl Shutdown {
+    /// Creates a new `Shutdown` handle, which has not begun draining.
+    pub fn new() -> Shutdown {
+        Shutdown::default()
+    }
+
+    /// Tells the server to stop accepting new connections and drain existing ones.
+    pub fn drain(&self) {
+        self.inner.draining.store(true, Ordering::SeqCst);
+    }
+
+    /// Returns `true` once draining has begun, either via `drain` or a signal.
+    pub fn is_draining(&self) -> bool {
+        self.inner.draining.load(Ordering::SeqCst)
+            || (self.inner.on_signal.load(Ordering::SeqCst) && SIGNALLED.load(Ordering::SeqCst))
+    }
+
+    /// Begins draining when the process receives `SIGTERM`, as sent by `signal_drain`.
+    ///
+    /// This replaces any existing `SIGTERM` handler for the process.
+    #[cfg(unix)]
+    pub fn drain_on_signal(&self) -> io::Result<()> {
+        extern "C" fn on_sigterm(_: libc::c_int) {
+            SIGNALLED.store(true, Ordering::SeqCst);
+        }
+
+        let handler = on_sigterm as extern "C" fn(libc::c_int) as libc::sighandler_t;
+        if unsafe { libc::signal(libc::SIGTERM, handler) } == libc::SIG_ERR {
+            return Err(io::Error::last_os_error());
+        }
+
+        self.inner.on_signal.store(true, Ordering::SeqCst);
+        Ok(())
+    }
+}
+
+/// What a draining server does with connections which arrive after draining has begun.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DrainBehavior {
+    /// Close the listening socket as soon as draining begins, so that new connections are
+    /// refused. Suits load balancers which detect a closed port, and is the default.
+    #[default]
+    CloseListener,
+
+    /// Keep accepting connections until draining completes, answering every new request with
+    /// `503 Service Unavailable` and `Connection: close`. Suits load balancers which remove a
+    /// backend based on its responses, rather than connection failures.
+    RespondUnavailable,
+}
+
+/// Tells the Gotham application running as process `pid` to begin draining, by sending it
+/// `SIGTERM`. The application must have called `Shutdown::drain_on_signal`.
+#[cfg(unix)]
+pub fn signal_drain(pid: u32) -> io::Result<()> {
+    if unsafe { libc::kill(pid as libc::pid_t, libc::SIGTERM) } == 0 {
+        Ok(())
+    } else {
+        Err(io::Error::last_os_error())
+    }
+}
+
+/// Resolves once `shutdown` has begun draining.
+pub(crate) fn draining(
+    shutdown: Shutdown,
+    handle: &Handle,
+) -> Box<dyn Future<Item = (), Error = io::Error>> {
+    let interval = match Interval::new(Duration::from_millis(POLL_INTERVAL_MILLIS), handle) {
+        Ok(interval) => interval,
+        Err(e) => return Box::new(future::err(e)),
+    };
+
+    Box::new(
+        interval
+            .skip_while(move |_| Ok(!shutdown.is_draining()))
+            .into_future()
+            .map(|_| ())
+            .map_err(|(e, _)| e),
+    )
+}
+
+/// Resolves once draining begins, shared between the connections of a worker thread.
+pub(crate) type DrainSignal = Shared<Box<dyn Future<Item = (), Error = io::Error>>>;
+
+/// Creates the `DrainSignal` for `shutdown`, polling it on the event loop of `handle`.
+pub(crate) fn drain_signal(shutdown: Shutdown, handle: &Handle) -> DrainSignal {
+    draining(shutdown, handle).shared()
+}
+
+/// A connection which can be told to close once the current request has been answered.
+pub(crate) trait KeepAlive {
+    fn disable_keep_alive(&mut self);
+}
+
+impl<I, S, B> KeepAlive for Connection<I, S>
+where
+    S: Service<Request = Request, Response = Response<B>, Error = hyper::Error> + 'static,
+    I: AsyncRead + AsyncWrite + 'static,
+    B: Stream<Error = hyper::Error> + 'static,
+    B::Item: AsRef<[u8]>,
+{
+    fn disable_keep_alive(&mut self) {
+        Connection::disable_keep_alive(self)
+    }
+}
+
+/// Serves a connection until it closes, disabling keep-alive once draining begins.
+///
+/// An idle connection is then closed immediately, and any other once its current request has been
+/// answered, so that kept-alive connections and their timers do not hold the worker open until
+/// the shutdown timeout.
+pub(crate) struct CloseWhenDraining<C> {
+    connection: C,
+    signal: Option<DrainSignal>,
+}
+
+/// Wraps `connection` to disable keep-alive once `signal` resolves. The signal is `None` for a
+/// connection accepted while draining, as its responses carry `Connection: close` instead, and
+/// closing it while idle would refuse its first request.
+pub(crate) fn close_when_draining<C>(
+    connection: C,
+    signal: Option<DrainSignal>,
+) -> CloseWhenDraining<C>
+where
+    C: Future + KeepAlive,
+{
+    CloseWhenDraining { connection, signal }
+}
+
+impl<C> Future for CloseWhenDraining<C>
+where
+    C: Future + KeepAlive,
+{
+    type Item = C::Item;
+    type Error = C::Error;
+
+    fn poll(&mut self) -> Poll<C::Item, C::Error> {
+        let draining = match self.signal.as_mut().map(Future::poll) {
+            Some(Ok(Async::Ready(_))) => true,
+            Some(Ok(Async::NotReady)) | None => false,
+            Some(Err(e)) => {
+                warn!("unable to watch for draining: {}", *e);
+                self.signal = None;
+                false
+            }
+        };
+
+        if draining {
+            trace!(" disabling keep-alive while draining");
+            self.connection.disable_keep_alive();
+            self.signal = None;
+        }
+
+        self.connection.poll()
+    }
+}
+
+/// Resolves once `active` connections reach zero, or `timeout` has elapsed.
+pub(crate) fn drained(
+    active: Rc<Cell<usize>>,
+    timeout: Duration,
+    handle: &Handle,
+) -> Box<dyn Future<Item = (), Error = io::Error>> {
+    let now = Instant::now();
+    let deadline = now + timeout;
+    let interval = match Interval::new_at(now, Duration::from_millis(POLL_INTERVAL_MILLIS), handle)
+    {
+        Ok(interval) => interval,
+        Err(e) => return Box::new(future::err(e)),
+    };
+
+    Box::new(
+        interval
+            .skip_while(move |_| Ok(active.get() > 0 && Instant::now() < deadline))
+            .into_future()
+            .map(|_| ())
+            .map_err(|(e, _)| e),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use tokio_core::reactor::Core;
+
+    #[test]
+    fn drain_is_shared_between_clones() {
+        let shutdown = Shutdown::new();
+        let clone = shutdown.clone();
+        assert!(!clone.is_draining());
+
+        shutdown.drain();
+        assert!(clone.is_draining());
+    }
+
+    #[test]
+    fn draining_resolves_after_drain() {
+        let mut core = Core::new().unwrap();
+        let shutdown = Shutdown::new();
+        shutdown.drain();
+
+        core.run(draining(shutdown, &core.handle())).unwrap();
+    }
+
+    #[test]
+    fn drained_resolves_at_timeout() {
+        let mut core = Core::new().unwrap();
+        let active = Rc::new(Cell::new(1));
+        let start = Instant::now();
+
+        core.run(drained(active, Duration::from_millis(100), &core.handle()))
+            .unwrap();
+        assert!(start.elapsed() >= Duration::from_millis(100));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn drains_on_signal() {
+        let shutdown = Shutdown::new();
+        let ignored = Shutdown::new();
+        shutdown.drain_on_signal().unwrap();
+
+        signal_drain(::std::process::id()).unwrap();
+
+        let start = Instant::now();
+        while !shutdown.is_draining() && start.elapsed() < Duration::from_secs(5) {
+            ::std::thread::yield_now();
+        }
+        assert!(shutdown.is_draining());
+        assert!(!ignored.is_draining());
+    }
+}
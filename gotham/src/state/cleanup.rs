@@ -0,0 +1,169 @@
+//! Defines cleanup which runs once the `Request` is finished with, however it ends.
+
+use state::{State, StateData};
+
+struct Cleanup {
+    tasks: Vec<Box<dyn FnOnce()>>,
+}
+
+impl StateData for Cleanup {}
+
+impl Drop for Cleanup {
+    fn drop(&mut self) {
+        while let Some(task) = self.tasks.pop() {
+            task();
+        }
+    }
+}
+
+/// Registers `f` to be called once the `Request` is finished with, like a `finally` block scoped
+/// to the request. This suits releasing resources acquired part way through handling a request,
+/// such as a connection checked out of a pool.
+///
+/// Cleanup runs when `State` is dropped, which happens however the request ends: once the
+/// response has been handed to Hyper, after an error or panic in the `Handler`, or when the
+/// request is abandoned because the client disconnected or a timeout elapsed. Cleanup registered
+/// more than once runs in the reverse order of registration. A response body which is still being
+/// streamed to the client does not hold `State`, so must not depend on resources released here.
+///
+/// # Examples
+///
+/// ```rust
+/// # extern crate gotham;
+/// # extern crate hyper;
+/// #
+/// # use std::sync::Arc;
+/// # use std::sync::atomic::{AtomicUsize, Ordering};
+/// # use hyper::{Response, StatusCode};
+/// # use gotham::state::{register_cleanup, State};
+/// # use gotham::test::TestServer;
+/// #
+/// # fn main() {
+/// let in_use = Arc::new(AtomicUsize::new(0));
+///
+/// let pool = in_use.clone();
+/// let my_handler = move |mut state: State| {
+///     // Check a connection out of the pool...
+///     pool.fetch_add(1, Ordering::SeqCst);
+///
+///     let pool = pool.clone();
+///     register_cleanup(&mut state, move || {
+///         // ...and return it, however the request ends.
+///         pool.fetch_sub(1, Ordering::SeqCst);
+///     });
+///
+///     (state, Response::new().with_status(StatusCode::Ok))
+/// };
+/// #
+/// # let test_server = TestServer::new(move || Ok(my_handler.clone())).unwrap();
+/// # let response = test_server.client().get("http://localhost/").perform().unwrap();
+/// # assert_eq!(response.status(), StatusCode::Ok);
+/// # assert_eq!(in_use.load(Ordering::SeqCst), 0);
+/// # }
+/// ```
+pub fn register_cleanup<F>(state: &mut State, f: F)
+where
+    F: FnOnce() + 'static,
+{
+    if !state.has::<Cleanup>() {
+        state.put(Cleanup { tasks: Vec::new() });
+    }
+
+    state.borrow_mut::<Cleanup>().tasks.push(Box::new(f));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::io;
+    use std::sync::{Arc, Mutex};
+
+    use futures::{future, Future};
+    use hyper::{Response, StatusCode, Uri};
+
+    use handler::{Handler, HandlerError, HandlerFuture, IntoHandlerError};
+    use state::FromState;
+    use test::TestServer;
+
+    /// A mock pool of resources, recording each checkout and return.
+    #[derive(Clone, Default)]
+    struct Pool {
+        events: Arc<Mutex<Vec<String>>>,
+    }
+
+    impl Pool {
+        fn checkout(&self, state: &mut State, name: &'static str) {
+            self.events.lock().unwrap().push(format!("checked out {}", name));
+
+            let events = self.events.clone();
+            register_cleanup(state, move || {
+                events.lock().unwrap().push(format!("returned {}", name));
+            });
+        }
+
+        fn events(&self) -> Vec<String> {
+            self.events.lock().unwrap().clone()
+        }
+    }
+
+    #[test]
+    fn runs_cleanup_after_success_and_error() {
+        let pool = Pool::default();
+
+        let handler_pool = pool.clone();
+        let handler = move |mut state: State| -> Box<HandlerFuture> {
+            handler_pool.checkout(&mut state, "db");
+
+            if Uri::borrow_from(&state).path() == "/fail" {
+                let e = io::Error::other("failed");
+                Box::new(future::err((state, e.into_handler_error())))
+            } else {
+                let res = Response::new().with_status(StatusCode::Ok);
+                Box::new(future::ok((state, res)))
+            }
+        };
+
+        let test_server = TestServer::new(move || Ok(handler.clone())).unwrap();
+        let expected = [("/", StatusCode::Ok), ("/fail", StatusCode::InternalServerError)];
+        for &(path, status) in &expected {
+            let response = test_server
+                .client()
+                .get(&format!("http://localhost{}", path))
+                .perform()
+                .unwrap();
+            assert_eq!(response.status(), status);
+        }
+
+        assert_eq!(
+            pool.events(),
+            vec!["checked out db", "returned db", "checked out db", "returned db"]
+        );
+    }
+
+    #[test]
+    fn runs_cleanup_in_reverse_when_request_is_abandoned() {
+        let pool = Pool::default();
+
+        let handler_pool = pool.clone();
+        let handler = move |mut state: State| -> Box<HandlerFuture> {
+            handler_pool.checkout(&mut state, "db");
+            handler_pool.checkout(&mut state, "cache");
+
+            // Never completes, as if waiting on a client which has disconnected.
+            let f = future::empty::<(), ()>().then(move |_| {
+                future::ok::<_, (State, HandlerError)>((state, Response::new()))
+            });
+            Box::new(f)
+        };
+
+        let f = handler.handle(State::new());
+        assert_eq!(pool.events(), vec!["checked out db", "checked out cache"]);
+
+        drop(f);
+        assert_eq!(
+            pool.events(),
+            vec!["checked out db", "checked out cache", "returned cache", "returned db"]
+        );
+    }
+}
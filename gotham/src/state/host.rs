@@ -0,0 +1,73 @@
+//! Defines storage for the host requested by the client
+
+use hyper::Uri;
+use hyper::header::{Headers, Host};
+
+use state::{FromState, State, StateData};
+
+struct ForwardedHost {
+    host: String,
+}
+
+impl StateData for ForwardedHost {}
+
+pub(crate) fn put_forwarded_host(state: &mut State, host: String) {
+    state.put(ForwardedHost { host })
+}
+
+/// Returns the host, and port if one was given, which the client requested.
+///
+/// When the server has been configured to trust proxy headers, via
+/// `ServerConfig::with_trusted_proxy_headers`, the host reported by the proxy is used. Otherwise
+/// the host is taken from the `Host` header, or from the request URI when it is in absolute form.
+///
+/// # Examples
+///
+/// ```rust
+/// # extern crate gotham;
+/// # extern crate hyper;
+/// #
+/// # use hyper::{Response, StatusCode};
+/// # use gotham::config::ServerConfig;
+/// # use gotham::http::request::forwarded::ForwardedPrecedence;
+/// # use gotham::state::{State, request_host};
+/// # use gotham::test::TestServer;
+/// #
+/// fn my_handler(state: State) -> (State, Response) {
+///     let body = request_host(&state).unwrap_or_default();
+///     let response = Response::new().with_status(StatusCode::Ok).with_body(body);
+///     (state, response)
+/// }
+/// #
+/// # fn main() {
+/// #   let config = ServerConfig::default()
+/// #       .with_trusted_proxy_headers(ForwardedPrecedence::XForwarded);
+/// #   let test_server = TestServer::with_config(|| Ok(my_handler), config).unwrap();
+/// #   let response = test_server
+/// #       .client()
+/// #       .get("http://localhost/")
+/// #       .with_raw_header("X-Forwarded-Host", "example.com")
+/// #       .perform()
+/// #       .unwrap();
+/// #
+/// #   assert_eq!(response.read_body().unwrap().as_slice(), b"example.com");
+/// # }
+/// ```
+pub fn request_host(state: &State) -> Option<String> {
+    if let Some(forwarded) = ForwardedHost::try_borrow_from(state) {
+        return Some(forwarded.host.clone());
+    }
+
+    if let Some(host) = Headers::try_borrow_from(state).and_then(|h| h.get::<Host>()) {
+        return Some(with_port(host.hostname(), host.port()));
+    }
+
+    Uri::try_borrow_from(state).and_then(|uri| uri.host().map(|host| with_port(host, uri.port())))
+}
+
+fn with_port(host: &str, port: Option<u16>) -> String {
+    match port {
+        Some(port) => format!("{}:{}", host, port),
+        None => host.to_owned(),
+    }
+}
@@ -1,23 +1,42 @@
 //! Defines types for passing request state through `Middleware` and `Handler` implementations
 
+mod cleanup;
 mod data;
 mod from_state;
 pub mod request_id;
 pub(crate) mod client_addr;
+pub(crate) mod host;
+pub(crate) mod request_start;
+pub(crate) mod sampling;
+pub(crate) mod secure;
+pub(crate) mod server_timing;
+pub(crate) mod transfer;
 
 use std::collections::HashMap;
 use std::any::{Any, TypeId};
 
+pub use state::cleanup::register_cleanup;
 pub use state::data::StateData;
 pub use state::from_state::FromState;
-pub use state::request_id::{request_id, set_request_id};
+pub use state::request_id::{request_id, set_request_id, RequestId};
 pub use state::client_addr::client_addr;
+pub use state::host::request_host;
+pub use state::request_start::request_start;
+pub use state::sampling::sampling_value;
+pub use state::secure::is_secure;
+pub use state::server_timing::record_server_timing;
+pub use state::transfer::{transfer_sizes, TransferHook, TransferSizes};
 
 /// Provides storage for request state, and stores one item of each type. The types used for
 /// storage must implement the `gotham::state::StateData` trait to allow its storage.
 ///
 /// Gotham provides `StateData` to ease this implementation via `derive`.
 ///
+/// Before the application is invoked, Gotham populates `State` with the parts of the request:
+/// its `Method`, `Uri`, `HttpVersion`, `Headers` and `Body`, each of which can be borrowed or taken
+/// via `FromState`. The `HttpVersion` is the protocol version the request arrived with, allowing
+/// handlers to account for differences such as HTTP/1.0 clients lacking chunked encoding.
+///
 /// # Examples
 ///
 /// ```rust
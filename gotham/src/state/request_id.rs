@@ -1,5 +1,7 @@
 //! Defines a unique id per `Request` that should be output with all logging
 
+use std::fmt;
+
 use hyper::header::Headers;
 use uuid::Uuid;
 
@@ -7,8 +9,68 @@ use http::header::XRequestId;
 use state::{FromState, State};
 
 /// Holds details about the current Request that are useful for enhancing logging.
+///
+/// Stored in `State` by Gotham before any middleware or handler is invoked, and so available as a
+/// structured value via `RequestId::borrow_from`, e.g. for use as a span id. Formats as the
+/// same string returned by `request_id`.
+///
+/// # Examples
+///
+/// ```rust
+/// # extern crate gotham;
+/// # extern crate hyper;
+/// #
+/// # use hyper::{Response, StatusCode};
+/// # use gotham::state::{FromState, RequestId, State};
+/// # use gotham::test::TestServer;
+/// #
+/// fn my_handler(state: State) -> (State, Response) {
+///     let body = {
+///         let id = RequestId::borrow_from(&state);
+///         match id.uuid() {
+///             Some(uuid) => format!("uuid {}", uuid.hyphenated()),
+///             None => format!("external {}", id),
+///         }
+///     };
+///     let response = Response::new().with_status(StatusCode::Ok).with_body(body);
+///     (state, response)
+/// }
+/// #
+/// # fn main() {
+/// #   let test_server = TestServer::new(|| Ok(my_handler)).unwrap();
+/// #   let response = test_server.client().get("http://localhost/").perform().unwrap();
+/// #   assert!(response.read_utf8_body().unwrap().starts_with("uuid "));
+/// # }
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub struct RequestId {
     val: String,
+    uuid: Option<Uuid>,
+}
+
+impl RequestId {
+    fn new(val: String) -> RequestId {
+        let uuid = Uuid::parse_str(&val).ok();
+        RequestId { val, uuid }
+    }
+
+    /// The request id as a string, as returned by `request_id`.
+    pub fn as_str(&self) -> &str {
+        &self.val
+    }
+
+    /// The request id as a `Uuid`. This is always present when the id was generated by Gotham,
+    /// and present when the id was given via the `X-Request-ID` header only if the header holds
+    /// a valid UUID.
+    pub fn uuid(&self) -> Option<Uuid> {
+        self.uuid
+    }
+}
+
+impl fmt::Display for RequestId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.val)
+    }
 }
 
 /// Sets a unique identifier for the request if it has not already been stored.
@@ -28,14 +90,16 @@ pub fn set_request_id<'a>(state: &'a mut State) -> &'a str {
                     "[{}] RequestId set from external source via X-Request-ID header",
                     ex_req_id.0.clone()
                 );
-                RequestId {
-                    val: ex_req_id.0.clone(),
-                }
+                RequestId::new(ex_req_id.0.clone())
             }
             None => {
-                let val = Uuid::new_v4().hyphenated().to_string();
+                let uuid = Uuid::new_v4();
+                let val = uuid.hyphenated().to_string();
                 trace!("[{}] RequestId generated internally", val);
-                RequestId { val }
+                RequestId {
+                    val,
+                    uuid: Some(uuid),
+                }
             }
         };
         state.put(request_id);
@@ -54,11 +118,16 @@ pub fn set_request_id<'a>(state: &'a mut State) -> &'a str {
 /// prior to handling control to middleware pipelines and application handlers.
 pub fn request_id(state: &State) -> &str {
     match RequestId::try_borrow_from(state) {
-        Some(request_id) => &request_id.val,
+        Some(request_id) => request_id.as_str(),
         None => panic!("RequestId must be populated before application code is invoked"),
     }
 }
 
+/// Copies the `RequestId` of `from` into `to`, so that both are logged with the same id.
+pub(crate) fn copy_request_id(from: &State, to: &mut State) {
+    to.put(RequestId::borrow_from(from).clone());
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -85,6 +154,17 @@ mod tests {
         assert_eq!("1-2-3-4", request_id(&state));
     }
 
+    #[test]
+    fn reads_request_id_header_name_in_any_case() {
+        let mut state = State::new();
+
+        let mut headers = Headers::new();
+        headers.set_raw("x-REQUEST-id", "1-2-3-4");
+        state.put(headers);
+
+        assert_eq!("1-2-3-4", set_request_id(&mut state));
+    }
+
     #[test]
     fn sets_a_unique_request_id() {
         let mut state = State::new();
@@ -105,13 +185,36 @@ mod tests {
     #[test]
     fn does_not_overwrite_existant_request_id() {
         let mut state = State::new();
-        state.put(RequestId {
-            val: "1-2-3-4".to_string(),
-        });
+        state.put(RequestId::new("1-2-3-4".to_string()));
 
         {
             set_request_id(&mut state);
         }
         assert_eq!("1-2-3-4", request_id(&state));
     }
+
+    #[test]
+    fn exposes_typed_request_id() {
+        let mut state = State::new();
+        state.put(Headers::new());
+        set_request_id(&mut state);
+
+        let id = RequestId::borrow_from(&state);
+        let uuid = id.uuid().unwrap();
+        assert_eq!(format!("{}", id), uuid.hyphenated().to_string());
+        assert_eq!(id.as_str(), request_id(&state));
+    }
+
+    #[test]
+    fn parses_external_request_id() {
+        let uuid = "67e55044-10b1-426f-9247-bb680e5fe0c8";
+        assert_eq!(
+            RequestId::new(uuid.to_owned()).uuid(),
+            Some(Uuid::parse_str(uuid).unwrap())
+        );
+
+        let id = RequestId::new("1-2-3-4".to_owned());
+        assert_eq!(id.uuid(), None);
+        assert_eq!(id.to_string(), "1-2-3-4");
+    }
 }
@@ -0,0 +1,89 @@
+//! Defines storage for the time at which Gotham began handling the `Request`
+
+use std::time::Instant;
+
+use state::{FromState, State, StateData};
+
+struct RequestStart {
+    instant: Instant,
+}
+
+impl StateData for RequestStart {}
+
+pub(crate) fn put_request_start(state: &mut State, instant: Instant) {
+    state.put(RequestStart { instant })
+}
+
+/// Returns the time at which Gotham began handling the `Request`, before any middleware or
+/// handler was invoked. Every part of the application measures from the same point by computing
+/// the time elapsed since this `Instant`.
+///
+/// Returns `None` when the `State` was not created by Gotham for a `Request`.
+///
+/// # Examples
+///
+/// ```rust
+/// # extern crate gotham;
+/// # extern crate hyper;
+/// #
+/// # use hyper::{Response, StatusCode};
+/// # use gotham::state::{State, request_start};
+/// # use gotham::test::TestServer;
+/// #
+/// fn my_handler(state: State) -> (State, Response) {
+///     let elapsed = request_start(&state).expect("no request start").elapsed();
+///     let body = format!("{}µs", elapsed.as_micros());
+///     let response = Response::new().with_status(StatusCode::Ok).with_body(body);
+///     (state, response)
+/// }
+/// #
+/// # fn main() {
+/// #   let test_server = TestServer::new(|| Ok(my_handler)).unwrap();
+/// #   let response = test_server.client().get("http://localhost/").perform().unwrap();
+/// #   assert!(response.read_utf8_body().unwrap().ends_with("µs"));
+/// # }
+/// ```
+pub fn request_start(state: &State) -> Option<Instant> {
+    RequestStart::try_borrow_from(state).map(|start| start.instant)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::thread;
+    use std::time::Duration;
+
+    use hyper::{Response, StatusCode};
+
+    use test::TestServer;
+
+    #[test]
+    fn elapsed_since_request_start() {
+        fn handler(state: State) -> (State, Response) {
+            thread::sleep(Duration::from_millis(5));
+            let elapsed = request_start(&state).unwrap().elapsed();
+
+            let status = if elapsed >= Duration::from_millis(5) {
+                StatusCode::Ok
+            } else {
+                StatusCode::InternalServerError
+            };
+            (state, Response::new().with_status(status))
+        }
+
+        let test_server = TestServer::new(|| Ok(handler)).unwrap();
+        let response = test_server
+            .client()
+            .get("http://localhost/")
+            .perform()
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::Ok);
+    }
+
+    #[test]
+    fn absent_outside_request() {
+        assert_eq!(request_start(&State::new()), None);
+    }
+}
@@ -0,0 +1,103 @@
+//! Defines storage for a random value drawn once per `Request`, for use in sampling decisions.
+
+use rand;
+
+use state::{FromState, State, StateData};
+
+struct SamplingValue {
+    value: f64,
+}
+
+impl StateData for SamplingValue {}
+
+pub(crate) fn put_sampling_value(state: &mut State) {
+    state.put(SamplingValue {
+        value: rand::random(),
+    })
+}
+
+/// Returns a value drawn uniformly at random from `[0, 1)` when Gotham began handling the
+/// `Request`.
+///
+/// The value is drawn once per `Request`, so middleware and handlers which sample requests, e.g.
+/// for tracing or detailed logging, reach consistent decisions by comparing it against their
+/// sampling rate, rather than each drawing a random number of their own.
+///
+/// Returns `None` when the `State` was not created by Gotham for a `Request`.
+///
+/// # Examples
+///
+/// ```rust
+/// # extern crate gotham;
+/// # extern crate hyper;
+/// #
+/// # use hyper::{Response, StatusCode};
+/// # use gotham::state::{State, sampling_value};
+/// # use gotham::test::TestServer;
+/// #
+/// const TRACE_RATE: f64 = 0.01;
+///
+/// fn my_handler(state: State) -> (State, Response) {
+///     let traced = sampling_value(&state).map(|v| v < TRACE_RATE).unwrap_or(false);
+///     let body = format!("traced: {}", traced);
+///     let response = Response::new().with_status(StatusCode::Ok).with_body(body);
+///     (state, response)
+/// }
+/// #
+/// # fn main() {
+/// #   let test_server = TestServer::new(|| Ok(my_handler)).unwrap();
+/// #   let response = test_server.client().get("http://localhost/").perform().unwrap();
+/// #   assert!(response.read_utf8_body().unwrap().starts_with("traced: "));
+/// # }
+/// ```
+pub fn sampling_value(state: &State) -> Option<f64> {
+    SamplingValue::try_borrow_from(state).map(|sampling| sampling.value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::collections::HashSet;
+
+    use hyper::{Response, StatusCode};
+
+    use test::TestServer;
+
+    #[test]
+    fn stable_within_request_and_varies_across_requests() {
+        fn handler(state: State) -> (State, Response) {
+            let first = sampling_value(&state).unwrap();
+            let second = sampling_value(&state).unwrap();
+
+            let response = if first == second && (0.0..1.0).contains(&first) {
+                Response::new()
+                    .with_status(StatusCode::Ok)
+                    .with_body(format!("{}", first))
+            } else {
+                Response::new().with_status(StatusCode::InternalServerError)
+            };
+            (state, response)
+        }
+
+        let test_server = TestServer::new(|| Ok(handler)).unwrap();
+        let values: HashSet<String> = (0..10)
+            .map(|_| {
+                let response = test_server
+                    .client()
+                    .get("http://localhost/")
+                    .perform()
+                    .unwrap();
+                assert_eq!(response.status(), StatusCode::Ok);
+                response.read_utf8_body().unwrap()
+            })
+            .collect();
+
+        assert!(values.len() > 1);
+    }
+
+    #[test]
+    fn absent_outside_request() {
+        assert_eq!(sampling_value(&State::new()), None);
+    }
+}
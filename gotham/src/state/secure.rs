@@ -0,0 +1,57 @@
+//! Defines storage for whether the `Request` arrived over a secure connection
+
+use state::{FromState, State, StateData};
+
+struct Secure {
+    secure: bool,
+}
+
+impl StateData for Secure {}
+
+pub(crate) fn put_secure(state: &mut State, secure: bool) {
+    state.put(Secure { secure })
+}
+
+/// Returns `true` if the `Request` arrived over TLS, as resolved by Gotham before the application
+/// is invoked.
+///
+/// Gotham does not terminate TLS itself, so a connection is only considered secure when the
+/// server has been configured to trust proxy headers, via
+/// `ServerConfig::with_trusted_proxy_headers`, and the proxy reports that the client connected
+/// using `https`.
+///
+/// # Examples
+///
+/// ```rust
+/// # extern crate gotham;
+/// # extern crate hyper;
+/// #
+/// # use hyper::{Response, StatusCode};
+/// # use gotham::config::ServerConfig;
+/// # use gotham::http::request::forwarded::ForwardedPrecedence;
+/// # use gotham::state::{State, is_secure};
+/// # use gotham::test::TestServer;
+/// #
+/// fn my_handler(state: State) -> (State, Response) {
+///     let body = if is_secure(&state) { "secure" } else { "insecure" };
+///     let response = Response::new().with_status(StatusCode::Ok).with_body(body);
+///     (state, response)
+/// }
+/// #
+/// # fn main() {
+/// #   let config = ServerConfig::default()
+/// #       .with_trusted_proxy_headers(ForwardedPrecedence::XForwarded);
+/// #   let test_server = TestServer::with_config(|| Ok(my_handler), config).unwrap();
+/// #   let response = test_server
+/// #       .client()
+/// #       .get("http://localhost/")
+/// #       .with_raw_header("X-Forwarded-Proto", "https")
+/// #       .perform()
+/// #       .unwrap();
+/// #
+/// #   assert_eq!(response.read_body().unwrap().as_slice(), b"secure");
+/// # }
+/// ```
+pub fn is_secure(state: &State) -> bool {
+    Secure::try_borrow_from(state).is_some_and(|s| s.secure)
+}
@@ -0,0 +1,104 @@
+//! Defines storage for metrics reported to the client in the `Server-Timing` header
+
+use std::time::Duration;
+
+use http::header::server_timing::is_tchar;
+use http::header::{ServerTiming, ServerTimingMetric};
+use state::{request_id, FromState, State, StateData};
+
+struct ServerTimings {
+    metrics: Vec<(&'static str, Duration)>,
+}
+
+impl StateData for ServerTimings {}
+
+pub(crate) fn enable_server_timing(state: &mut State) {
+    state.put(ServerTimings {
+        metrics: Vec::new(),
+    })
+}
+
+/// Records a metric to be reported to the client in the `Server-Timing` response header, for
+/// display by browser developer tools. Middleware and handlers use this to break down the time
+/// spent handling the request, alongside the `total` metric which Gotham adds itself.
+///
+/// This does nothing unless the server has been configured with
+/// `ServerConfig::with_server_timing`. Metric names are static so that no request data can be
+/// reflected to the client, and names which are not valid HTTP tokens are ignored.
+///
+/// # Examples
+///
+/// ```rust
+/// # extern crate gotham;
+/// # extern crate hyper;
+/// #
+/// # use std::time::Instant;
+/// # use hyper::{Response, StatusCode};
+/// # use gotham::config::ServerConfig;
+/// # use gotham::http::header::ServerTiming;
+/// # use gotham::state::{State, record_server_timing};
+/// # use gotham::test::TestServer;
+/// #
+/// fn my_handler(mut state: State) -> (State, Response) {
+///     let start = Instant::now();
+///     // Query the database...
+///     record_server_timing(&mut state, "db", start.elapsed());
+///
+///     let response = Response::new().with_status(StatusCode::Ok);
+///     (state, response)
+/// }
+/// #
+/// # fn main() {
+/// #   let config = ServerConfig::default().with_server_timing(true);
+/// #   let test_server = TestServer::with_config(|| Ok(my_handler), config).unwrap();
+/// #   let response = test_server
+/// #       .client()
+/// #       .get("http://localhost/")
+/// #       .perform()
+/// #       .unwrap();
+/// #
+/// #   let timing = response.headers().get::<ServerTiming>().unwrap();
+/// #   assert_eq!(timing.0[0].name, "db");
+/// #   assert_eq!(timing.0[1].name, "total");
+/// # }
+/// ```
+pub fn record_server_timing(state: &mut State, name: &'static str, duration: Duration) {
+    if name.is_empty() || !name.chars().all(is_tchar) {
+        warn!(
+            "[{}] ignoring invalid Server-Timing metric name `{}`",
+            request_id(state),
+            name
+        );
+        return;
+    }
+
+    if let Some(timings) = ServerTimings::try_borrow_mut_from(state) {
+        timings.metrics.push((name, duration));
+    }
+}
+
+/// Builds the `Server-Timing` header from the metrics recorded so far, followed by `total`, or
+/// returns `None` when server timing is not enabled.
+pub(crate) fn server_timing_header(state: &State, total: Option<f64>) -> Option<ServerTiming> {
+    ServerTimings::try_borrow_from(state).map(|timings| {
+        let mut metrics: Vec<ServerTimingMetric> = timings
+            .metrics
+            .iter()
+            .map(|&(name, duration)| ServerTimingMetric {
+                name: name.to_owned(),
+                duration: Some(as_millis(duration)),
+            })
+            .collect();
+
+        metrics.push(ServerTimingMetric {
+            name: String::from("total"),
+            duration: total,
+        });
+
+        ServerTiming(metrics)
+    })
+}
+
+fn as_millis(duration: Duration) -> f64 {
+    duration.as_secs() as f64 * 1000.0 + f64::from(duration.subsec_nanos()) / 1_000_000.0
+}
@@ -0,0 +1,283 @@
+//! Defines accounting of the bytes transferred in the request and response bodies, configured via
+//! `ServerConfig::with_transfer_accounting`.
+
+use std::fmt;
+use std::mem;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use futures::{Future, Sink, Stream};
+use hyper::{Body, Headers, Response};
+use tokio_core::reactor::Handle;
+
+use state::{FromState, State, StateData};
+
+/// The number of bytes transferred in the request and response bodies of a single `Request`.
+///
+/// Values are shared between clones, and grow as each body is transferred, so a clone taken
+/// while handling the request, via `transfer_sizes`, reads the final sizes once the response has
+/// been sent. The final sizes are also given to the `TransferHook`, if configured.
+///
+/// Only the bodies are counted, as they are passed between Hyper and the application, excluding
+/// headers and chunked transfer encoding. When a `Middleware` or `Handler` compresses the
+/// response body, the compressed size is counted, which is the size sent to the client.
+#[derive(Clone, Debug, Default)]
+pub struct TransferSizes {
+    request: Arc<AtomicU64>,
+    response: Arc<AtomicU64>,
+}
+
+impl StateData for TransferSizes {}
+
+impl TransferSizes {
+    pub(crate) fn new() -> TransferSizes {
+        TransferSizes::default()
+    }
+
+    /// The number of bytes of the request body read so far.
+    pub fn request_body(&self) -> u64 {
+        self.request.load(Ordering::SeqCst)
+    }
+
+    /// The number of bytes of the response body sent so far.
+    pub fn response_body(&self) -> u64 {
+        self.response.load(Ordering::SeqCst)
+    }
+}
+
+/// Returns the `TransferSizes` of the `Request`, when transfer accounting is enabled via
+/// `ServerConfig::with_transfer_accounting`.
+///
+/// # Examples
+///
+/// ```rust
+/// # extern crate futures;
+/// # extern crate gotham;
+/// # extern crate hyper;
+/// # extern crate mime;
+/// #
+/// # use futures::{Future, Stream};
+/// # use hyper::{Body, StatusCode};
+/// # use gotham::config::ServerConfig;
+/// # use gotham::handler::HandlerFuture;
+/// # use gotham::http::response::create_response;
+/// # use gotham::state::{transfer_sizes, FromState, State};
+/// # use gotham::test::TestServer;
+/// #
+/// fn upload(mut state: State) -> Box<HandlerFuture> {
+///     let f = Body::take_from(&mut state).concat2().then(move |_| {
+///         let received = transfer_sizes(&state).map(|sizes| sizes.request_body());
+///         let body = format!("received {} bytes", received.unwrap_or(0));
+///         let body = Some((body.into_bytes(), mime::TEXT_PLAIN));
+///         let res = create_response(&state, StatusCode::Ok, body);
+///         Ok((state, res))
+///     });
+///
+///     Box::new(f)
+/// }
+/// #
+/// # fn main() {
+/// #   let config = ServerConfig::default().with_transfer_accounting(true);
+/// #   let test_server = TestServer::with_config(|| Ok(upload), config).unwrap();
+/// #   let response = test_server
+/// #       .client()
+/// #       .post("http://localhost/", "0123456789", mime::TEXT_PLAIN)
+/// #       .perform()
+/// #       .unwrap();
+/// #   assert_eq!(response.read_utf8_body().unwrap(), "received 10 bytes");
+/// # }
+/// ```
+pub fn transfer_sizes(state: &State) -> Option<TransferSizes> {
+    TransferSizes::try_borrow_from(state).cloned()
+}
+
+/// Told of the `TransferSizes` of each request once its response body has been passed to Hyper,
+/// along with the request ID. Configured via `ServerConfig::with_transfer_hook`, for use in
+/// billing or logging bandwidth.
+///
+/// # Examples
+///
+/// ```rust
+/// # extern crate gotham;
+/// # #[macro_use]
+/// # extern crate log;
+/// #
+/// # use gotham::config::ServerConfig;
+/// # use gotham::state::TransferHook;
+/// #
+/// # fn main() {
+/// let hook = TransferHook::new(|request_id, sizes| {
+///     info!(
+///         "[{}] received {} bytes, sent {} bytes",
+///         request_id,
+///         sizes.request_body(),
+///         sizes.response_body()
+///     );
+/// });
+/// let config = ServerConfig::default().with_transfer_hook(hook);
+/// assert!(config.transfer_accounting());
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct TransferHook {
+    f: Arc<HookFn>,
+}
+
+type HookFn = dyn Fn(&str, &TransferSizes) + Send + Sync;
+
+impl TransferHook {
+    /// Creates a `TransferHook` which calls `f` with the request ID and final `TransferSizes` of
+    /// each request.
+    pub fn new<F>(f: F) -> TransferHook
+    where
+        F: Fn(&str, &TransferSizes) + Send + Sync + 'static,
+    {
+        TransferHook { f: Arc::new(f) }
+    }
+
+    pub(crate) fn call(&self, request_id: &str, sizes: &TransferSizes) {
+        (self.f)(request_id, sizes)
+    }
+}
+
+impl fmt::Debug for TransferHook {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("TransferHook")
+    }
+}
+
+/// Replaces the request `Body` in `state` with one which forwards its chunks, counting them in
+/// `sizes`.
+pub(crate) fn count_request_body(state: &mut State, sizes: &TransferSizes) {
+    let counter = sizes.request.clone();
+    let chunks = Body::take_from(state).then(move |result| {
+        if let Ok(ref chunk) = result {
+            counter.fetch_add(chunk.len() as u64, Ordering::SeqCst);
+        }
+        Ok::<_, ()>(result)
+    });
+
+    let (tx, body) = Body::pair();
+    let forward = tx.sink_map_err(|_| ()).send_all(chunks).map(|_| ());
+    Handle::borrow_from(state).spawn(forward);
+    state.put(body);
+}
+
+/// Replaces the body of `res` with one which forwards its chunks, counting them in `sizes`, and
+/// calls `done` once the body has been forwarded or the client has gone away.
+pub(crate) fn count_response_body<F>(
+    handle: &Handle,
+    mut res: Response,
+    sizes: TransferSizes,
+    done: F,
+) -> Response
+where
+    F: FnOnce(&TransferSizes) + 'static,
+{
+    if res.body_ref().is_none() {
+        done(&sizes);
+        return res;
+    }
+
+    let status = res.status();
+    let headers = mem::replace(res.headers_mut(), Headers::new());
+
+    let counter = sizes.response.clone();
+    let chunks = res.body().then(move |result| {
+        if let Ok(ref chunk) = result {
+            counter.fetch_add(chunk.len() as u64, Ordering::SeqCst);
+        }
+        Ok::<_, ()>(result)
+    });
+
+    let (tx, body) = Body::pair();
+    let forward = tx.sink_map_err(|_| ()).send_all(chunks).then(move |forwarded| {
+        // The body ends when the sender is dropped, so `done` is called before the client sees
+        // the end of the body.
+        done(&sizes);
+        drop(forwarded);
+        Ok(())
+    });
+    handle.spawn(forward);
+
+    Response::new()
+        .with_status(status)
+        .with_headers(headers)
+        .with_body(body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::Mutex;
+
+    use hyper::StatusCode;
+    use mime;
+
+    use config::ServerConfig;
+    use handler::HandlerFuture;
+    use http::response::create_response;
+    use state::request_id;
+    use test::TestServer;
+
+    fn echo(mut state: State) -> Box<HandlerFuture> {
+        let f = Body::take_from(&mut state).concat2().then(move |body| {
+            let mut body = body.unwrap().to_vec();
+            body.extend_from_slice(b"!");
+            let res = create_response(&state, StatusCode::Ok, Some((body, mime::TEXT_PLAIN)));
+            Ok((state, res))
+        });
+
+        Box::new(f)
+    }
+
+    #[test]
+    fn reports_body_sizes_to_hook() {
+        let reported = Arc::new(Mutex::new(Vec::new()));
+        let hook = {
+            let reported = reported.clone();
+            TransferHook::new(move |request_id, sizes| {
+                reported.lock().unwrap().push((
+                    request_id.to_owned(),
+                    sizes.request_body(),
+                    sizes.response_body(),
+                ));
+            })
+        };
+
+        let config = ServerConfig::default().with_transfer_hook(hook);
+        let test_server = TestServer::with_config(|| Ok(echo), config).unwrap();
+        let response = test_server
+            .client()
+            .post("http://localhost/", "a".repeat(1000), mime::TEXT_PLAIN)
+            .with_header(::http::header::XRequestId("transfer".to_owned()))
+            .perform()
+            .unwrap();
+
+        let body = response.read_body().unwrap();
+        assert_eq!(body.len(), 1001);
+        assert_eq!(
+            *reported.lock().unwrap(),
+            vec![("transfer".to_owned(), 1000, body.len() as u64)]
+        );
+    }
+
+    #[test]
+    fn absent_unless_enabled() {
+        fn handler(state: State) -> (State, ::hyper::Response) {
+            let body = format!("{}:{}", request_id(&state), transfer_sizes(&state).is_some());
+            let body = Some((body.into_bytes(), mime::TEXT_PLAIN));
+            let res = create_response(&state, StatusCode::Ok, body);
+            (state, res)
+        }
+
+        let test_server = TestServer::new(|| Ok(handler)).unwrap();
+        let response = test_server
+            .client()
+            .get("http://localhost/")
+            .perform()
+            .unwrap();
+        assert!(response.read_utf8_body().unwrap().ends_with(":false"));
+    }
+}
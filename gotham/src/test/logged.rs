@@ -0,0 +1,45 @@
+//! Captures the lines logged while the tests run, so that tests can assert upon them. A logger can
+//! only be set once per process, so it is shared by every test which inspects the log.
+
+use std::sync::{Mutex, Once};
+
+use log::{self, LogLevel, LogLevelFilter, LogMetadata, LogRecord};
+
+static CAPTURE: Once = Once::new();
+static LINES: Mutex<Vec<(LogLevel, String)>> = Mutex::new(Vec::new());
+
+struct CapturedLog;
+
+impl log::Log for CapturedLog {
+    fn enabled(&self, metadata: &LogMetadata) -> bool {
+        metadata.target() == "gotham::service::trap"
+    }
+
+    fn log(&self, record: &LogRecord) {
+        if self.enabled(record.metadata()) {
+            let line = record.args().to_string();
+            LINES.lock().unwrap().push((record.level(), line));
+        }
+    }
+}
+
+/// Starts capturing the lines logged by `gotham::service::trap`, if not already started.
+pub(crate) fn capture() {
+    CAPTURE.call_once(|| {
+        log::set_logger(|max_level| {
+            // Not `Trace`, as some tests trace from a `State` without a request id.
+            max_level.set(LogLevelFilter::Debug);
+            Box::new(CapturedLog)
+        }).unwrap();
+    });
+}
+
+/// The lines captured so far which start with `prefix`, with the level each was logged at.
+pub(crate) fn lines_starting_with(prefix: &str) -> Vec<(LogLevel, String)> {
+    let lines = LINES.lock().unwrap();
+    lines
+        .iter()
+        .filter(|(_, line)| line.starts_with(prefix))
+        .cloned()
+        .collect()
+}
@@ -2,8 +2,8 @@
 //!
 //! `TestServer::new(_)` is the most useful entry point.
 
-use std::{cell, io, net, time};
-use std::cell::RefCell;
+use std::{io, net, time};
+use std::cell::{Cell, RefCell};
 use std::net::{IpAddr, SocketAddr, TcpListener, TcpStream};
 use std::ops::{Deref, DerefMut};
 use std::rc::Rc;
@@ -14,15 +14,18 @@ use hyper::{self, Body, Method, Request, Response, Uri};
 use hyper::client::{self, Client};
 use hyper::error::UriError;
 use hyper::header::ContentType;
-use hyper::server::{self, Http};
+use hyper::server::Http;
 use mime;
 use mio;
-use tokio_core::reactor::{Core, PollEvented, Timeout};
+use tokio_core::reactor::{Core, Handle, PollEvented, Timeout};
 
+use config::ServerConfig;
 use handler::NewHandler;
 use service::GothamService;
 use router::Router;
 
+#[cfg(test)]
+pub(crate) mod logged;
 mod request;
 
 pub use self::request::RequestBuilder;
@@ -65,9 +68,11 @@ where
     NH: NewHandler + 'static,
 {
     core: RefCell<Core>,
+    handle: Handle,
     http: Http,
     timeout: u64,
     gotham_service: GothamService<NH>,
+    connections: Cell<usize>,
 }
 
 /// The `TestRequestError` type represents all error states that can result from evaluating a
@@ -116,19 +121,45 @@ where
 
     /// Sets the request timeout to `timeout` seconds and returns a new `TestServer`.
     pub fn with_timeout(new_handler: NH, timeout: u64) -> Result<TestServer<NH>, io::Error> {
-        Core::new().map(|core| {
+        TestServer::with_timeout_and_config(new_handler, timeout, ServerConfig::default())
+    }
+
+    /// Creates a `TestServer` which handles requests according to `config`, in the same way as
+    /// `gotham::start_with_config`. The number of threads in `config` is ignored, as a
+    /// `TestServer` is always single-threaded.
+    ///
+    /// Timeout will be set to 10 seconds.
+    pub fn with_config(new_handler: NH, config: ServerConfig) -> Result<TestServer<NH>, io::Error> {
+        TestServer::with_timeout_and_config(new_handler, 10, config)
+    }
+
+    fn with_timeout_and_config(
+        new_handler: NH,
+        timeout: u64,
+        config: ServerConfig,
+    ) -> Result<TestServer<NH>, io::Error> {
+        Core::new().and_then(|mut core| {
+            if let Some(init) = config.worker_init() {
+                init.run(&mut core)?;
+            }
             let handle = core.handle();
 
             let data = TestServerData {
                 core: RefCell::new(core),
-                http: server::Http::new(),
+                handle: handle.clone(),
+                http: config.protocol(),
                 timeout,
-                gotham_service: GothamService::new(Arc::new(new_handler), handle),
+                gotham_service: GothamService::new(
+                    Arc::new(new_handler),
+                    handle,
+                    Arc::new(config),
+                ),
+                connections: Cell::new(0),
             };
 
-            TestServer {
+            Ok(TestServer {
                 data: Rc::new(data),
-            }
+            })
         })
     }
 
@@ -142,12 +173,97 @@ where
     /// the server will see `client_addr` as the source address for the connection. The
     /// `client_addr` can be any value, and need not be contactable.
     pub fn client_with_address(&self, client_addr: net::SocketAddr) -> TestClient<NH> {
-        self.try_client_with_address(client_addr)
-            .expect("TestServer: unable to spawn client")
+        let connector = {
+            let test_server = self.clone();
+            TestConnect {
+                connect: Rc::new(move || test_server.connect(client_addr)),
+            }
+        };
+
+        TestClient {
+            client: self.build_client(connector.clone(), true),
+            connector,
+            test_server: self.clone(),
+        }
     }
 
-    fn try_client_with_address(&self, client_addr: net::SocketAddr) -> io::Result<TestClient<NH>> {
-        let handle = self.data.core.borrow().handle();
+    /// The number of connections which have been made to the `TestServer` by its clients.
+    ///
+    /// A `TestClient` keeps its connection alive between requests by default, so this is useful
+    /// in asserting whether a connection was reused.
+    pub fn connections(&self) -> usize {
+        self.data.connections.get()
+    }
+
+    /// Sends each of `requests` at once, and awaits all of their responses, which are returned
+    /// in the same order as `requests`. The requests are in flight together on the event loop of
+    /// the `TestServer`, so this is useful in testing behaviour which depends upon concurrency,
+    /// such as rate limiting or shared state.
+    ///
+    /// Requests made with the same `TestClient` share its pool, which opens further connections
+    /// while the others are busy. If any request fails, the first error is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # extern crate hyper;
+    /// # extern crate gotham;
+    /// #
+    /// # use gotham::state::State;
+    /// # use hyper::{Response, StatusCode};
+    /// #
+    /// # fn my_handler(state: State) -> (State, Response) {
+    /// #   (state, Response::new().with_status(StatusCode::Accepted))
+    /// # }
+    /// #
+    /// # fn main() {
+    /// use gotham::test::TestServer;
+    ///
+    /// let test_server = TestServer::new(|| Ok(my_handler)).unwrap();
+    /// let client = test_server.client();
+    ///
+    /// let requests = (0..10).map(|_| client.clone().get("http://localhost/"));
+    /// let responses = test_server.perform_concurrently(requests).unwrap();
+    ///
+    /// assert_eq!(responses.len(), 10);
+    /// for response in responses {
+    ///     assert_eq!(response.status(), StatusCode::Accepted);
+    /// }
+    /// # }
+    /// ```
+    pub fn perform_concurrently<I>(
+        &self,
+        requests: I,
+    ) -> Result<Vec<TestResponse>, TestRequestError>
+    where
+        I: IntoIterator<Item = RequestBuilder<NH>>,
+    {
+        let sent = requests
+            .into_iter()
+            .map(|request| request.send())
+            .collect::<Result<Vec<_>, _>>()?;
+
+        self.run_request(future::join_all(sent)).map(|responses| {
+            responses
+                .into_iter()
+                .map(|response| TestResponse {
+                    response,
+                    reader: Box::new(self.clone()),
+                })
+                .collect()
+        })
+    }
+
+    fn build_client(&self, connector: TestConnect, keep_alive: bool) -> Client<TestConnect> {
+        Client::configure()
+            .connector(connector)
+            .keep_alive(keep_alive)
+            .build(&self.data.handle)
+    }
+
+    /// Opens a new connection to the server, seen by the server as coming from `client_addr`.
+    fn connect(&self, client_addr: SocketAddr) -> io::Result<PollEvented<mio::net::TcpStream>> {
+        let handle = &self.data.handle;
 
         let (cs, ss) = {
             // We're creating a private TCP-based pipe here. Bind to an ephemeral port, connect to
@@ -160,10 +276,10 @@ where
         };
 
         let cs = mio::net::TcpStream::from_stream(cs)?;
-        let cs = PollEvented::new(cs, &handle)?;
+        let cs = PollEvented::new(cs, handle)?;
 
         let ss = mio::net::TcpStream::from_stream(ss)?;
-        let ss = PollEvented::new(ss, &handle)?;
+        let ss = PollEvented::new(ss, handle)?;
 
         let service = self.data.gotham_service.connect(client_addr);
         let f = self.data
@@ -173,17 +289,9 @@ where
             .map_err(|_| ());
 
         handle.spawn(f);
+        self.data.connections.set(self.data.connections.get() + 1);
 
-        let client = Client::configure()
-            .connector(TestConnect {
-                stream: cell::RefCell::new(Some(cs)),
-            })
-            .build(&self.data.core.borrow().handle());
-
-        Ok(TestClient {
-            client,
-            test_server: self.clone(),
-        })
+        Ok(cs)
     }
 
     /// Runs the event loop until the response future is completed.
@@ -232,18 +340,75 @@ where
 }
 
 /// Client interface for issuing requests to a `TestServer`.
+///
+/// Each request consumes the `TestClient`, so a clone is used for each of several requests.
+/// Clones share a pool of connections, and a connection is kept alive and reused once the body of
+/// a response has been read, allowing connection-scoped behavior to be tested. A new connection
+/// is made for each request when keep-alive is disabled via `with_keep_alive`.
+///
+/// # Examples
+///
+/// ```rust
+/// # extern crate hyper;
+/// # extern crate gotham;
+/// #
+/// # use gotham::state::State;
+/// # use hyper::{Response, StatusCode};
+/// #
+/// # fn my_handler(state: State) -> (State, Response) {
+/// #   (state, Response::new().with_status(StatusCode::Ok).with_body("ok"))
+/// # }
+/// #
+/// # fn main() {
+/// use gotham::test::TestServer;
+///
+/// let test_server = TestServer::new(|| Ok(my_handler)).unwrap();
+/// let client = test_server.client();
+///
+/// for _ in 0..3 {
+///     let response = client.clone().get("http://localhost/").perform().unwrap();
+///     assert_eq!(response.read_utf8_body().unwrap(), "ok");
+/// }
+///
+/// assert_eq!(test_server.connections(), 1);
+/// # }
+/// ```
 pub struct TestClient<NH>
 where
     NH: NewHandler + 'static,
 {
     client: Client<TestConnect>,
+    connector: TestConnect,
     test_server: TestServer<NH>,
 }
 
+impl<NH> Clone for TestClient<NH>
+where
+    NH: NewHandler + 'static,
+{
+    fn clone(&self) -> TestClient<NH> {
+        TestClient {
+            client: self.client.clone(),
+            connector: self.connector.clone(),
+            test_server: self.test_server.clone(),
+        }
+    }
+}
+
 impl<NH> TestClient<NH>
 where
     NH: NewHandler + 'static,
 {
+    /// Sets whether connections are kept alive and reused between requests. Defaults to `true`.
+    ///
+    /// The returned `TestClient` and its clones share a new pool of connections.
+    pub fn with_keep_alive(self, keep_alive: bool) -> TestClient<NH> {
+        TestClient {
+            client: self.test_server.build_client(self.connector.clone(), keep_alive),
+            ..self
+        }
+    }
+
     /// Parse the URI and begin constructing a GET request using this `TestClient`.
     pub fn get(self, uri: &str) -> RequestBuilder<NH> {
         self.build_request(Method::Get, uri)
@@ -372,12 +537,15 @@ impl TestResponse {
     }
 }
 
-/// `TestConnect` represents the connection between a test client and the `TestServer` instance
+/// `TestConnect` makes the connections between a test client and the `TestServer` instance
 /// that created it. This type should never be used directly.
+#[derive(Clone)]
 pub struct TestConnect {
-    stream: cell::RefCell<Option<PollEvented<mio::net::TcpStream>>>,
+    connect: Rc<ConnectFn>,
 }
 
+type ConnectFn = dyn Fn() -> io::Result<PollEvented<mio::net::TcpStream>>;
+
 impl client::Service for TestConnect {
     type Request = hyper::Uri;
     type Error = io::Error;
@@ -385,14 +553,7 @@ impl client::Service for TestConnect {
     type Future = future::FutureResult<Self::Response, Self::Error>;
 
     fn call(&self, _req: Self::Request) -> Self::Future {
-        match self.stream.try_borrow_mut().map(|ref mut o| o.take()) {
-            Ok(Some(stream)) => future::ok(stream),
-            Ok(None) => future::err(io::Error::new(io::ErrorKind::Other, "stream already taken")),
-            Err(_) => future::err(io::Error::new(
-                io::ErrorKind::Other,
-                "stream.try_borrow_mut() failed",
-            )),
-        }
+        future::result((self.connect)())
     }
 }
 
@@ -400,10 +561,13 @@ impl client::Service for TestConnect {
 mod tests {
     use super::*;
 
-    use std::time::{SystemTime, UNIX_EPOCH};
+    use std::cmp;
+    use std::sync::Mutex;
+    use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
     use hyper::{Body, StatusCode, Uri};
     use hyper::header::{ContentLength, ContentType};
+    use hyper::server;
     use mime;
 
     use handler::{Handler, HandlerFuture, IntoHandlerError, NewHandler};
@@ -494,6 +658,25 @@ mod tests {
         }
     }
 
+    #[test]
+    fn applies_server_config() {
+        let new_service = || {
+            Ok(TestHandler {
+                response: "".to_owned(),
+            })
+        };
+        let config = ServerConfig::default().with_max_headers(8);
+        let test_server = TestServer::with_config(new_service, config).unwrap();
+
+        let mut request = test_server.client().get("http://localhost/");
+        for i in 0..10 {
+            request = request.with_raw_header(format!("X-Header-{}", i), "value");
+        }
+        let response = request.perform().unwrap();
+
+        assert_eq!(response.status(), StatusCode::RequestHeaderFieldsTooLarge);
+    }
+
     #[test]
     fn sets_client_addr() {
         let ticks = SystemTime::now()
@@ -574,4 +757,73 @@ mod tests {
         assert_eq!(content_length, buf.len() as u64);
         assert_eq!(data, &buf);
     }
+
+    #[test]
+    fn performs_requests_concurrently() {
+        #[derive(Default)]
+        struct Counts {
+            handled: usize,
+            in_flight: usize,
+            max_in_flight: usize,
+        }
+
+        let counts = Arc::new(Mutex::new(Counts::default()));
+        let handler_counts = counts.clone();
+        let handler = move |state: State| -> Box<HandlerFuture> {
+            {
+                let mut counts = handler_counts.lock().unwrap();
+                counts.handled += 1;
+                counts.in_flight += 1;
+                counts.max_in_flight = cmp::max(counts.max_in_flight, counts.in_flight);
+            }
+
+            let counts = handler_counts.clone();
+            let delay = Timeout::new(Duration::from_millis(50), Handle::borrow_from(&state));
+            let f = delay.unwrap().then(move |_| {
+                counts.lock().unwrap().in_flight -= 1;
+                let res = create_response(&state, StatusCode::Ok, None);
+                future::ok((state, res))
+            });
+            Box::new(f)
+        };
+
+        let test_server = TestServer::new(move || Ok(handler.clone())).unwrap();
+        let client = test_server.client();
+        let requests = (0..50).map(|_| client.clone().get("http://localhost/"));
+        let responses = test_server.perform_concurrently(requests).unwrap();
+
+        assert_eq!(responses.len(), 50);
+        for response in responses {
+            assert_eq!(response.status(), StatusCode::Ok);
+        }
+
+        let counts = counts.lock().unwrap();
+        assert_eq!(counts.handled, 50);
+        assert_eq!(counts.max_in_flight, 50);
+        assert_eq!(test_server.connections(), 50);
+    }
+
+    #[test]
+    fn reuses_kept_alive_connection() {
+        let new_service = || {
+            Ok(TestHandler {
+                response: "reused".to_owned(),
+            })
+        };
+
+        let test_server = TestServer::new(new_service).unwrap();
+        let client = test_server.client();
+        for _ in 0..2 {
+            let response = client.clone().get("http://localhost/").perform().unwrap();
+            assert_eq!(response.read_utf8_body().unwrap(), "reused");
+        }
+        assert_eq!(test_server.connections(), 1);
+
+        let client = test_server.client().with_keep_alive(false);
+        for _ in 0..2 {
+            let response = client.clone().get("http://localhost/").perform().unwrap();
+            assert_eq!(response.read_utf8_body().unwrap(), "reused");
+        }
+        assert_eq!(test_server.connections(), 3);
+    }
 }
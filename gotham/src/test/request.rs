@@ -1,6 +1,9 @@
+use std::borrow::Cow;
+
 use hyper::{Body, Method, Request, Uri};
+use hyper::client::FutureResponse;
 use hyper::error::UriError;
-use hyper::header::Header;
+use hyper::header::{Header, Raw};
 
 use handler::NewHandler;
 use test::{TestClient, TestRequestError, TestResponse};
@@ -47,6 +50,23 @@ where
         RequestBuilder { request, ..self }
     }
 
+    /// Appends a raw header line into the underlying `Request`, keeping any existing lines for the
+    /// same header name. Useful for sending headers which Hyper does not model, or values which a
+    /// typed header would refuse to produce.
+    pub fn with_raw_header<K, V>(self, name: K, value: V) -> RequestBuilder<NH>
+    where
+        K: Into<Cow<'static, str>>,
+        V: Into<Raw>,
+    {
+        let mut request = self.request;
+
+        if let Ok(ref mut req) = request {
+            req.headers_mut().append_raw(name, value);
+        }
+
+        RequestBuilder { request, ..self }
+    }
+
     /// Adds the given body into the underlying `Request`, replacing any existing body.
     pub fn with_body<T>(self, body: T) -> RequestBuilder<NH>
     where
@@ -66,4 +86,11 @@ where
     pub fn perform(self) -> Result<TestResponse, TestRequestError> {
         self.client.perform(self.request?)
     }
+
+    /// Sends the constructed request without awaiting the response, so that it can be awaited
+    /// alongside others.
+    pub(super) fn send(self) -> Result<FutureResponse, TestRequestError> {
+        let request = self.request?;
+        Ok(self.client.client.request(request))
+    }
 }
@@ -11,10 +11,17 @@ pub fn bad_request_static_response_extender(ast: &syn::DeriveInput) -> quote::To
             #borrowed #where_clause
         {
             fn extend(state: &mut ::gotham::state::State, res: &mut ::hyper::Response) {
-                ::gotham::http::response::extend_response(state,
-                                                          res,
-                                                          ::hyper::StatusCode::BadRequest,
-                                                          None);
+                match state.try_take::<::gotham::http::request::field_errors::FieldErrors>() {
+                    Some(errors) => {
+                        errors.extend_response(state, res, ::hyper::StatusCode::BadRequest)
+                    }
+                    None => {
+                        ::gotham::http::response::extend_response(state,
+                                                                  res,
+                                                                  ::hyper::StatusCode::BadRequest,
+                                                                  None)
+                    }
+                }
             }
         }
     }
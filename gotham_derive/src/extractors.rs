@@ -1,7 +1,7 @@
 use syn;
 use quote;
 
-use helpers::{ty_fields, ty_params};
+use helpers::{ty_field_bounds, ty_fields, ty_params};
 
 pub fn base_path(ast: &syn::DeriveInput) -> quote::Tokens {
     let (name, borrowed, where_clause) = ty_params(&ast, None);
@@ -9,6 +9,9 @@ pub fn base_path(ast: &syn::DeriveInput) -> quote::Tokens {
     let ofl = optional_field_labels(optional_fields);
     let ofl_len = ofl.len();
     let keys = field_names(&fields);
+    let keys2 = keys.clone();
+    let bindings = field_bindings(&fields);
+    let (bindings2, bindings3, bindings4) = (bindings.clone(), bindings.clone(), bindings.clone());
 
     let struct_name_token = quote!{#name};
     let struct_name = struct_name_token.as_str();
@@ -37,15 +40,15 @@ pub fn base_path(ast: &syn::DeriveInput) -> quote::Tokens {
                                            ::gotham::state::request_id(s), struct_name);
                                     Ok(val)
                                 }
-                                Err(_) => {
+                                Err(e) => {
                                     error!("[{}] unrecoverable error converting request path \
                                             segment(s) into {}",
                                            ::gotham::state::request_id(s), struct_name);
-                                    Err(String::from("unrecoverable error converting request path"))
+                                    Err(e.to_string())
                                 }
                             }
                         }
-                        None => Err(String::from("Error converting Request path values")),
+                        None => Err(String::from("missing value")),
                     }
                 }
 
@@ -65,14 +68,37 @@ pub fn base_path(ast: &syn::DeriveInput) -> quote::Tokens {
                     }
                 }
 
-                let rp = #name {
-                    #(
-                        #fields: parse(s, sm.get(#keys))?,
-                     )*
-                };
+                let mut errors = ::gotham::http::request::field_errors::FieldErrors::new();
+                #(
+                    let #bindings = match parse(s, sm.get(#keys)) {
+                        Ok(val) => Some(val),
+                        Err(message) => {
+                            errors.push(::gotham::http::request::field_errors::FieldError::new(
+                                #keys2,
+                                message,
+                            ));
+                            None
+                        }
+                    };
+                 )*
 
-                s.put(rp);
-                Ok(())
+                match (#(#bindings2,)*) {
+                    (#(Some(#bindings3),)*) => {
+                        let rp = #name {
+                            #(
+                                #fields: #bindings4,
+                             )*
+                        };
+
+                        s.put(rp);
+                        Ok(())
+                    }
+                    _ => {
+                        let e = errors.to_string();
+                        s.put(errors);
+                        Err(e)
+                    }
+                }
             }
         }
     }
@@ -85,6 +111,10 @@ pub fn base_query_string(ast: &syn::DeriveInput) -> quote::Tokens {
     let ofl_len = ofl.len();
     let keys = field_names(&fields);
     let keys2 = keys.clone();
+    let keys3 = keys.clone();
+    let bindings = field_bindings(&fields);
+    let (bindings2, bindings3, bindings4) = (bindings.clone(), bindings.clone(), bindings.clone());
+    let (mins, maxes) = bound_tokens(ty_field_bounds(ast));
 
     let struct_name_token = quote!{#name};
     let struct_name = struct_name_token.as_str();
@@ -97,12 +127,27 @@ pub fn base_query_string(ast: &syn::DeriveInput) -> quote::Tokens {
                 fn parse<T>(
                     s: &::gotham::state::State,
                     key: &str,
-                    values: Option<&Vec<::gotham::http::FormUrlDecoded>>
+                    values: Option<&Vec<::gotham::http::FormUrlDecoded>>,
+                    min: Option<usize>,
+                    max: Option<usize>
                 ) -> Result<T, String>
                 where
                     T: ::gotham::router::request::query_string::FromQueryString,
                 {
                     let struct_name = #struct_name;
+
+                    // Checked before any conversion, so that an excessive number of values is
+                    // rejected cheaply.
+                    let len = values.map(|values| values.len()).unwrap_or(0);
+                    if min.map(|min| len < min).unwrap_or(false)
+                        || max.map(|max| len > max).unwrap_or(false)
+                    {
+                        error!("[{}] query string value `{}` has {} values, outside the bounds \
+                                for {}",
+                               ::gotham::state::request_id(&s), key, len, struct_name);
+                        return Err(format!("has {} values", len));
+                    }
+
                     match values {
                         Some(values) => {
                             match T::from_query_string(key, values.as_slice()) {
@@ -111,14 +156,14 @@ pub fn base_query_string(ast: &syn::DeriveInput) -> quote::Tokens {
                                            ::gotham::state::request_id(&s), struct_name);
                                     Ok(val)
                                 }
-                                Err(_) => {
+                                Err(e) => {
                                     error!("[{}] unrecoverable error converting query string value(s) into {}",
                                            ::gotham::state::request_id(&s), struct_name);
-                                    Err(String::from("unrecoverable error converting query string"))
+                                    Err(e.to_string())
                                 }
                             }
                         }
-                        None => Err(format!("error converting query string value `{}`", key))
+                        None => Err(String::from("missing value"))
                     }
                 }
 
@@ -147,21 +192,60 @@ pub fn base_query_string(ast: &syn::DeriveInput) -> quote::Tokens {
                 trace!("[{}] query string mappings to be parsed: {:?}",
                        ::gotham::state::request_id(s), qsm);
 
-                let qss = #name {
-                    #(
-                        #fields: parse(s, #keys, qsm.get(#keys2))?,
-                     )*
-                };
-                trace!("[{}] query string struct created and stored in state",
-                       ::gotham::state::request_id(s));
+                let mut errors = ::gotham::http::request::field_errors::FieldErrors::new();
+                #(
+                    let #bindings = match parse(s, #keys, qsm.get(#keys2), #mins, #maxes) {
+                        Ok(val) => Some(val),
+                        Err(message) => {
+                            errors.push(::gotham::http::request::field_errors::FieldError::new(
+                                #keys3,
+                                message,
+                            ));
+                            None
+                        }
+                    };
+                 )*
 
-                s.put(qss);
-                Ok(())
+                match (#(#bindings2,)*) {
+                    (#(Some(#bindings3),)*) => {
+                        let qss = #name {
+                            #(
+                                #fields: #bindings4,
+                             )*
+                        };
+                        trace!("[{}] query string struct created and stored in state",
+                               ::gotham::state::request_id(s));
+
+                        s.put(qss);
+                        Ok(())
+                    }
+                    _ => {
+                        let e = errors.to_string();
+                        s.put(errors);
+                        Err(e)
+                    }
+                }
             }
         }
     }
 }
 
+fn bound_tokens(
+    bounds: Vec<(Option<usize>, Option<usize>)>,
+) -> (Vec<quote::Tokens>, Vec<quote::Tokens>) {
+    fn option_tokens(bound: Option<usize>) -> quote::Tokens {
+        match bound {
+            Some(bound) => quote! { Some(#bound) },
+            None => quote! { None },
+        }
+    }
+
+    bounds
+        .into_iter()
+        .map(|(min, max)| (option_tokens(min), option_tokens(max)))
+        .unzip()
+}
+
 fn optional_field_labels<'a>(optional_fields: Vec<&'a syn::Ident>) -> Vec<&'a str> {
     let mut ofl = Vec::new();
     for ident in optional_fields {
@@ -177,3 +261,12 @@ fn field_names<'a>(fields: &'a Vec<&'a syn::Ident>) -> Vec<String> {
     }
     keys
 }
+
+/// Names the local variables holding each parsed field, chosen so as not to collide with the
+/// other locals in the generated `extract` function.
+fn field_bindings(fields: &Vec<&syn::Ident>) -> Vec<syn::Ident> {
+    fields
+        .iter()
+        .map(|ident| syn::Ident::from(format!("__gotham_field_{}", ident.as_ref())))
+        .collect()
+}
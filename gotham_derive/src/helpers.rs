@@ -69,6 +69,45 @@ pub fn ty_fields<'a>(ast: &'a syn::DeriveInput) -> (Vec<&syn::Ident>, Vec<&syn::
     (fields, optional_fields)
 }
 
+/// Returns the `(min, max)` number of values permitted for each named field, as given by a
+/// `#[query_string(min = N, max = N)]` attribute, in the same order as `ty_fields`.
+pub fn ty_field_bounds(ast: &syn::DeriveInput) -> Vec<(Option<usize>, Option<usize>)> {
+    match ast.body {
+        syn::Body::Struct(syn::VariantData::Struct(ref body)) => body.iter()
+            .filter(|field| field.ident.is_some())
+            .map(field_bounds)
+            .collect::<Vec<_>>(),
+        _ => panic!("Not implemented for tuple or unit like structs"),
+    }
+}
+
+fn field_bounds(field: &syn::Field) -> (Option<usize>, Option<usize>) {
+    let mut bounds = (None, None);
+
+    for attr in field.attrs.iter() {
+        let items = match attr.value {
+            syn::MetaItem::List(ref ident, ref items) if ident == "query_string" => items,
+            _ => continue,
+        };
+
+        for item in items.iter() {
+            match *item {
+                syn::NestedMetaItem::MetaItem(syn::MetaItem::NameValue(
+                    ref name,
+                    syn::Lit::Int(value, _),
+                )) if name == "min" => bounds.0 = Some(value as usize),
+                syn::NestedMetaItem::MetaItem(syn::MetaItem::NameValue(
+                    ref name,
+                    syn::Lit::Int(value, _),
+                )) if name == "max" => bounds.1 = Some(value as usize),
+                _ => panic!("Expected `min = N` or `max = N` in `#[query_string(...)]`"),
+            }
+        }
+    }
+
+    bounds
+}
+
 fn is_option(ty: &syn::Ty) -> bool {
     match *ty {
         syn::Ty::Path(_, ref p) => match p.segments.first() {
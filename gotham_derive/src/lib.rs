@@ -18,7 +18,7 @@ pub fn base_path_extractor(input: proc_macro::TokenStream) -> proc_macro::TokenS
     gen.parse().unwrap()
 }
 
-#[proc_macro_derive(QueryStringExtractor)]
+#[proc_macro_derive(QueryStringExtractor, attributes(query_string))]
 pub fn base_query_string_extractor(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
     let ast = syn::parse_macro_input(&input.to_string()).unwrap();
     let gen = extractors::base_query_string(&ast);